@@ -6,13 +6,59 @@ pub struct StorageConfig {
     pub endpoint: Option<String>, // Custom S3 endpoint (for MinIO, etc.)
     pub region: String,
     pub bucket: String,
+    #[serde(serialize_with = "super::mask_secret")]
     pub access_key_id: String,
+    #[serde(serialize_with = "super::mask_secret")]
     pub secret_access_key: String,
     pub use_path_style: bool, // For MinIO compatibility
     pub enable_ssl: bool,
-    pub upload_timeout: u64, // seconds
-    pub max_file_size: u64,  // bytes
+    pub upload_timeout: u64,                   // seconds
+    pub max_file_size: u64,                    // bytes
+    pub max_image_width: u32,                  // pixels, read from the image header
+    pub max_image_height: u32,                 // pixels, read from the image header
+    pub circuit_breaker_threshold: u32, // Consecutive backend failures that open the breaker (0 disables)
+    pub circuit_breaker_cooldown_seconds: u64, // How long an open breaker fails fast before probing
+    pub max_upload_bytes_per_sec: u64,  // Aggregate upload bandwidth cap to S3 (0 = unlimited)
+    pub per_relay_quota_bytes: Option<u64>, // Stored bytes allowed per relay before uploads get 507 (unset = unlimited)
+    pub sign_archives: bool, // Store a detached Ed25519 signature (.sig) next to each ZIP archive
+    #[serde(serialize_with = "super::mask_secret")]
+    pub archive_signing_key: Option<String>, // Base64 32-byte Ed25519 seed, required with sign_archives or issue_receipts
+    pub issue_receipts: bool, // Return a receipt signed with the archive signing key for each stored event
+    pub store_receipts: bool, // Also store each receipt next to the event object (.receipt.json)
+    #[serde(deserialize_with = "super::deserialize_string_list")]
     pub allowed_mime_types: Vec<String>,
+    #[serde(default)]
+    pub compress_annotations: bool, // Gzip stored event JSON (.json.gz)
+    #[serde(default)]
+    pub key_layout: StorageLayout, // How event object keys are laid out in the bucket
+    #[serde(default)]
+    pub verify_after_upload: bool, // Check each stored object against the uploaded bytes, failing on mismatch
+    #[serde(default)]
+    pub verify_content_type: bool, // On read, flag event objects whose stored Content-Type doesn't match their key
+    pub zip_max_entries: usize, // Events whose archive would hold more entries are rejected
+    pub zip_max_concurrent: usize, // ZIP packaging jobs run at once; excess ones queue
+    #[serde(default)]
+    pub migrate: bool, // On startup, bring event objects under older key schemes into the current one
+}
+
+/// Object key layout for stored events
+/// The by-hash marker (`events/by-hash/{hash}.json`) is the same under every layout,
+/// so lookups by hash keep working when the layout changes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageLayout {
+    /// `events/{Y}/{m}/...`, convenient for time-series browsing
+    #[default]
+    DateHierarchy,
+    /// `events/{hash}.{ext}`
+    Flat,
+    /// `events/relays/{relay_id}/{Y}/{m}/{d}/{hash}.{ext}`, for per-relay access
+    RelayHierarchy,
+}
+
+/// One ZIP packaging job per CPU, since deflate is CPU-bound
+pub fn default_zip_max_concurrent() -> usize {
+    std::thread::available_parallelism().map_or(1, usize::from)
 }
 
 impl Default for StorageConfig {
@@ -27,12 +73,29 @@ impl Default for StorageConfig {
             enable_ssl: true,
             upload_timeout: 300,              // 5 minutes
             max_file_size: 100 * 1024 * 1024, // 100MB
+            max_image_width: 16384,
+            max_image_height: 16384,
+            circuit_breaker_threshold: 5,
+            circuit_breaker_cooldown_seconds: 30,
+            max_upload_bytes_per_sec: 0,
+            per_relay_quota_bytes: None,
+            sign_archives: false,
+            archive_signing_key: None,
+            issue_receipts: false,
+            store_receipts: false,
             allowed_mime_types: vec![
                 "image/jpeg".to_string(),
                 "image/png".to_string(),
                 "image/gif".to_string(),
                 "video/mp4".to_string(),
             ],
+            compress_annotations: false,
+            key_layout: StorageLayout::DateHierarchy,
+            verify_after_upload: false,
+            verify_content_type: false,
+            zip_max_entries: 64,
+            zip_max_concurrent: default_zip_max_concurrent(),
+            migrate: false,
         }
     }
 }
@@ -92,15 +155,30 @@ impl StorageConfig {
     }
 
     /// Generate object key for event storage
-    pub fn generate_event_key(&self, event_hash: &str, file_extension: &str) -> String {
+    pub fn generate_event_key(
+        &self,
+        event_hash: &str,
+        relay_id: &str,
+        file_extension: &str,
+    ) -> String {
         let now = chrono::Utc::now();
-        format!(
-            "events/{}/{}/{}.{}",
-            now.format("%Y"),
-            now.format("%m"),
-            event_hash,
-            file_extension
-        )
+        match self.key_layout {
+            StorageLayout::DateHierarchy => format!(
+                "events/{}/{}/{}.{}",
+                now.format("%Y"),
+                now.format("%m"),
+                event_hash,
+                file_extension
+            ),
+            StorageLayout::Flat => format!("events/{event_hash}.{file_extension}"),
+            StorageLayout::RelayHierarchy => format!(
+                "events/relays/{}/{}/{}.{}",
+                path_segment(relay_id),
+                now.format("%Y/%m/%d"),
+                event_hash,
+                file_extension
+            ),
+        }
     }
 
     /// Generate object key for media storage
@@ -121,3 +199,15 @@ impl StorageConfig {
         )
     }
 }
+
+/// Make an untrusted value safe to use as a single key segment
+pub(crate) fn path_segment(value: &str) -> String {
+    let safe: String = value
+        .chars()
+        .map(|c| if c == '/' || c == '\\' { '_' } else { c })
+        .collect();
+    match safe.as_str() {
+        "" | "." | ".." => "unknown".to_string(),
+        _ => safe,
+    }
+}
@@ -0,0 +1,73 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Event schema version produced by current clients
+pub const CURRENT_EVENT_VERSION: &str = "1.0";
+
+/// Event package validation rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationConfig {
+    /// Maximum seconds an annotation timestamp may be ahead of server time (unchecked when unset)
+    pub max_clock_skew_seconds: Option<i64>,
+    /// Earliest accepted annotation timestamp (unchecked when unset)
+    pub min_annotation_timestamp: Option<DateTime<Utc>>,
+    /// Event schema versions accepted for submission
+    #[serde(
+        default = "default_supported_event_versions",
+        deserialize_with = "super::deserialize_string_list"
+    )]
+    pub supported_event_versions: Vec<String>,
+    /// Accept events with no annotations when they carry media
+    #[serde(default)]
+    pub allow_media_only_events: bool,
+    /// Annotation label IDs accepted for submission (any label when empty)
+    #[serde(default, deserialize_with = "super::deserialize_string_list")]
+    pub allowed_label_ids: Vec<String>,
+    /// Event schema versions whose events must carry media
+    #[serde(default, deserialize_with = "super::deserialize_string_list")]
+    pub media_required_versions: Vec<String>,
+    /// Reject events with more than one annotation for the same label ID
+    #[serde(default)]
+    pub reject_duplicate_labels: bool,
+}
+
+fn default_supported_event_versions() -> Vec<String> {
+    vec![CURRENT_EVENT_VERSION.to_string()]
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            max_clock_skew_seconds: None,
+            min_annotation_timestamp: None,
+            supported_event_versions: default_supported_event_versions(),
+            allow_media_only_events: false,
+            allowed_label_ids: Vec::new(),
+            media_required_versions: Vec::new(),
+            reject_duplicate_labels: false,
+        }
+    }
+}
+
+impl ValidationConfig {
+    /// Latest acceptable annotation timestamp relative to `now`, if a skew limit is configured
+    pub fn latest_allowed_timestamp(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.max_clock_skew_seconds
+            .map(|seconds| now + Duration::seconds(seconds))
+    }
+
+    /// Whether annotations with this label ID are accepted
+    pub fn is_allowed_label(&self, label_id: &str) -> bool {
+        self.allowed_label_ids.is_empty() || self.allowed_label_ids.iter().any(|l| l == label_id)
+    }
+
+    /// Whether events with this schema version must carry media
+    pub fn requires_media(&self, version: &str) -> bool {
+        self.media_required_versions.iter().any(|v| v == version)
+    }
+
+    /// Whether events with this schema version are accepted
+    pub fn is_supported_version(&self, version: &str) -> bool {
+        self.supported_event_versions.iter().any(|v| v == version)
+    }
+}
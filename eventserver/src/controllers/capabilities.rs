@@ -0,0 +1,99 @@
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Json},
+};
+
+use crate::state::AppState;
+use crate::types::api::CapabilitiesResponse;
+use crate::types::event::{FieldValue, MediaType};
+
+/// How long clients and proxies may cache the capabilities response
+const CAPABILITIES_MAX_AGE_SECONDS: u32 = 300;
+
+/// List the media types, field value kinds and limits this server accepts
+#[utoipa::path(
+    get,
+    path = "/capabilities",
+    responses(
+        (status = 200, description = "Server capabilities", body = CapabilitiesResponse)
+    ),
+    tag = "health"
+)]
+pub async fn capabilities(State(state): State<AppState>) -> impl IntoResponse {
+    let response = CapabilitiesResponse {
+        media_types: MediaType::ALL
+            .iter()
+            .map(|media_type| media_type.as_str().to_string())
+            .collect(),
+        field_value_kinds: FieldValue::SUPPORTED_KINDS
+            .iter()
+            .map(|kind| kind.to_string())
+            .collect(),
+        max_file_size: state.config.storage.max_file_size,
+        allowed_mime_types: state.config.storage.allowed_mime_types.clone(),
+        supported_event_versions: state.config.validation.supported_event_versions.clone(),
+    };
+
+    (
+        [(
+            header::CACHE_CONTROL,
+            format!("public, max-age={CAPABILITIES_MAX_AGE_SECONDS}"),
+        )],
+        Json(response),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    /// Fails to compile when a MediaType variant is added, as a reminder to update `MediaType::ALL`
+    fn _all_media_types_listed(media_type: MediaType) {
+        match media_type {
+            MediaType::ImageJpeg
+            | MediaType::ImagePng
+            | MediaType::ImageGif
+            | MediaType::VideoMp4 => {}
+        }
+    }
+
+    #[tokio::test]
+    async fn test_capabilities_lists_media_types() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+
+        let response = crate::create_app(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/capabilities")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            "public, max-age=300"
+        );
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            json["mediaTypes"],
+            serde_json::json!(["image/jpeg", "image/png", "image/gif", "video/mp4"])
+        );
+        assert_eq!(
+            json["fieldValueKinds"],
+            serde_json::json!(["string", "number", "boolean", "null"])
+        );
+        assert_eq!(json["maxFileSize"], 100 * 1024 * 1024);
+    }
+}
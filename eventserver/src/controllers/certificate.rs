@@ -0,0 +1,577 @@
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
+    Router,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::error::{AppError, AuthFailure};
+use crate::middleware::crypto::{
+    device_decoding_key, extract_certificate_token, verify_device_jwt,
+};
+use crate::services::tenant::extract_validated_tenant_id;
+use crate::state::AppState;
+use crate::types::api::{
+    CertificateStatusResponse, CrlResponse, IntrospectBatchResponse, KeyRotationResponse,
+    TokenIntrospection,
+};
+
+/// Most tokens accepted in one introspection batch
+const MAX_INTROSPECT_BATCH: usize = 100;
+
+/// Create certificate routes
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/certificates/status", get(certificate_status))
+        .route("/certificates/rotate-key", post(rotate_key))
+        .route("/certificates/crl", get(revocation_list))
+        .route("/certificates/introspect-batch", post(introspect_batch))
+}
+
+/// Key rotation request
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RotateKeyRequest {
+    /// ES256 JWT signed with the current device key, carrying `new_public_key`
+    /// (base64-encoded P-256 JWK), `aud: "event_server"` and `exp`
+    pub rotation_jwt: String,
+}
+
+/// Batch token introspection request
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct IntrospectBatchRequest {
+    /// Certificate tokens to check, at most 100
+    pub tokens: Vec<String>,
+}
+
+/// Claims of the rotation JWT
+#[derive(Debug, Deserialize)]
+struct KeyRotationClaims {
+    new_public_key: String,
+}
+
+/// Check whether the presented certificate is still accepted
+/// Expired, revoked or unknown certificates are reported as `valid: false` rather than rejected,
+/// so relays can refresh proactively before submitting events
+#[utoipa::path(
+    get,
+    path = "/api/v1/certificates/status",
+    responses(
+        (status = 200, description = "Certificate status", body = CertificateStatusResponse),
+        (status = 401, description = "Bearer token missing or not a certificate issued by this server")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "authentication"
+)]
+pub async fn certificate_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<CertificateStatusResponse>, AppError> {
+    let token = extract_certificate_token(&headers).ok_or_else(|| {
+        AppError::auth(
+            AuthFailure::MissingToken,
+            "Missing certificate token in Authorization header",
+        )
+    })?;
+
+    match state.certificate_service.validate_certificate(&token) {
+        Ok(validation) => {
+            let seconds_remaining = (validation.expires_at - Utc::now()).num_seconds().max(0);
+            info!(
+                relay_id = %validation.relay_id,
+                seconds_remaining = seconds_remaining,
+                "Certificate status checked"
+            );
+
+            Ok(Json(CertificateStatusResponse {
+                valid: true,
+                relay_id: Some(validation.relay_id),
+                expires_at: Some(validation.expires_at),
+                seconds_remaining,
+                reason: None,
+            }))
+        }
+        Err(AppError::Authentication { reason, .. })
+            if matches!(
+                reason,
+                AuthFailure::CertExpired
+                    | AuthFailure::CertRevoked
+                    | AuthFailure::CertTooOld
+                    | AuthFailure::CertNotFound
+            ) =>
+        {
+            Ok(Json(CertificateStatusResponse {
+                valid: false,
+                relay_id: None,
+                expires_at: None,
+                seconds_remaining: 0,
+                reason: Some(reason.code().to_string()),
+            }))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Switch the certificate to a new device key without re-running PoW
+/// The rotation must be signed with the current key; events signed with the previous key
+/// keep verifying during the configured grace window
+#[utoipa::path(
+    post,
+    path = "/api/v1/certificates/rotate-key",
+    request_body = RotateKeyRequest,
+    responses(
+        (status = 200, description = "Device key rotated", body = KeyRotationResponse),
+        (status = 400, description = "New key is malformed or already current"),
+        (status = 401, description = "Invalid certificate or rotation JWT not signed by the current key")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "authentication"
+)]
+pub async fn rotate_key(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<RotateKeyRequest>,
+) -> Result<Json<KeyRotationResponse>, AppError> {
+    let token = extract_certificate_token(&headers).ok_or_else(|| {
+        AppError::auth(
+            AuthFailure::MissingToken,
+            "Missing certificate token in Authorization header",
+        )
+    })?;
+    let validation = state.certificate_service.validate_certificate(&token)?;
+
+    let claims: KeyRotationClaims =
+        verify_device_jwt(&request.rotation_jwt, &validation.public_key)?;
+    device_decoding_key(&claims.new_public_key)
+        .map_err(|e| AppError::BadRequest(format!("Invalid new public key: {e}")))?;
+
+    let certificate = state
+        .certificate_service
+        .rotate_key(&validation.certificate_id, &claims.new_public_key)?;
+    if state.config.security.cert_persistence {
+        crate::persist_certificate(&state, &certificate.certificate_id).await;
+    }
+
+    let previous_key_valid_until = certificate
+        .previous_key_expires_at
+        .unwrap_or(certificate.issued_at);
+    info!(
+        relay_id = %certificate.relay_id,
+        previous_key_valid_until = %previous_key_valid_until,
+        "Device key rotated"
+    );
+
+    Ok(Json(KeyRotationResponse {
+        relay_id: certificate.relay_id,
+        previous_key_valid_until,
+    }))
+}
+
+/// List the currently revoked certificate IDs of the caller's tenant, for validating tokens offline
+/// The ETag only changes with the list's contents, so pollers can send `If-None-Match`
+/// and get `304 Not Modified` until the next revocation
+#[utoipa::path(
+    get,
+    path = "/api/v1/certificates/crl",
+    responses(
+        (status = 200, description = "Current revocation list", body = CrlResponse,
+            headers(("ETag" = String, description = "Version of the list"))),
+        (status = 304, description = "The list matches the If-None-Match ETag"),
+        (status = 401, description = "Invalid or missing certificate")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "authentication"
+)]
+pub async fn revocation_list(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let tenant_id = extract_validated_tenant_id(&headers);
+    let (revoked_certificate_ids, updated_at) = state
+        .certificate_service
+        .revocation_list(tenant_id.as_deref());
+    let digest = Sha256::digest(revoked_certificate_ids.join("\n"));
+    let etag = format!("\"{}\"", hex::encode(&digest[..16]));
+    let etag_value = HeaderValue::from_str(&etag).expect("hex ETag is a valid header value");
+
+    let cache_headers = [
+        (header::ETAG, etag_value),
+        (header::CACHE_CONTROL, HeaderValue::from_static("no-cache")),
+    ];
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag));
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+
+    (
+        cache_headers,
+        Json(CrlResponse {
+            revoked_certificate_ids,
+            updated_at,
+        }),
+    )
+        .into_response()
+}
+
+/// Check many certificate tokens at once, e.g. for an intermediary validating device traffic
+/// Each token is checked for signature, expiry and revocation; any token that fails, including
+/// malformed ones, is reported as `active: false` without failing the batch
+#[utoipa::path(
+    post,
+    path = "/api/v1/certificates/introspect-batch",
+    request_body = IntrospectBatchRequest,
+    responses(
+        (status = 200, description = "Per-token results in request order", body = IntrospectBatchResponse),
+        (status = 400, description = "More than 100 tokens in the batch"),
+        (status = 401, description = "Invalid or missing certificate")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "authentication"
+)]
+pub async fn introspect_batch(
+    State(state): State<AppState>,
+    Json(request): Json<IntrospectBatchRequest>,
+) -> Result<Json<IntrospectBatchResponse>, AppError> {
+    if request.tokens.len() > MAX_INTROSPECT_BATCH {
+        return Err(AppError::BadRequest(format!(
+            "At most {MAX_INTROSPECT_BATCH} tokens can be introspected at once"
+        )));
+    }
+
+    // Signature checks are CPU-bound, so tokens are verified in parallel on the blocking pool
+    let checks = request.tokens.into_iter().map(|token| {
+        let certificates = state.certificate_service.clone();
+        tokio::task::spawn_blocking(move || certificates.validate_certificate(&token))
+    });
+    let results: Vec<TokenIntrospection> = futures::future::join_all(checks)
+        .await
+        .into_iter()
+        .map(|result| match result {
+            Ok(Ok(validation)) => TokenIntrospection {
+                active: true,
+                relay_id: Some(validation.relay_id),
+                expires_at: Some(validation.expires_at),
+            },
+            _ => TokenIntrospection {
+                active: false,
+                relay_id: None,
+                expires_at: None,
+            },
+        })
+        .collect();
+
+    info!(
+        tokens = results.len(),
+        active = results.iter().filter(|result| result.active).count(),
+        "Certificate tokens introspected"
+    );
+    Ok(Json(IntrospectBatchResponse { results }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::crypto::{CertificateRequest, CertificateService, MockClock};
+    use crate::test_utils::{sample_event, signed_package_request, DeviceKey};
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn issue_token(service: &CertificateService) -> String {
+        service
+            .issue_certificate(&CertificateRequest {
+                relay_id: "test_relay".to_string(),
+                public_key: "test_public_key".to_string(),
+                ed25519_public_key: None,
+                tenant_id: None,
+            })
+            .unwrap()
+            .cert_token
+    }
+
+    async fn status(state: AppState, token: &str) -> (StatusCode, serde_json::Value) {
+        let response = crate::create_app(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/api/v1/certificates/status")
+                    .header("Authorization", format!("Bearer {token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_valid_certificate_status() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let token = issue_token(&state.certificate_service);
+
+        let (code, json) = status(state, &token).await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(json["valid"], true);
+        assert_eq!(json["relay_id"], "test_relay");
+        assert!(json["expires_at"].is_string());
+        assert!(json["seconds_remaining"].as_i64().unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_expired_certificate_status() {
+        let mut state = AppState::new_mock(AppConfig::default()).await;
+        state.certificate_service =
+            CertificateService::with_params(-1, "test_jwt_secret".to_string());
+        let token = issue_token(&state.certificate_service);
+
+        let (code, json) = status(state, &token).await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(json["valid"], false);
+        assert_eq!(json["reason"], "CERT_EXPIRED");
+        assert_eq!(json["seconds_remaining"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_revoked_certificate_status() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let token = issue_token(&state.certificate_service);
+        let validation = state
+            .certificate_service
+            .validate_certificate(&token)
+            .unwrap();
+        state
+            .certificate_service
+            .revoke_certificate(&validation.certificate_id);
+
+        let (code, json) = status(state, &token).await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(json["valid"], false);
+        assert_eq!(json["reason"], "CERT_REVOKED");
+    }
+
+    async fn crl(state: &AppState, token: &str, if_none_match: Option<&str>) -> Response {
+        let mut request = Request::builder()
+            .uri("/api/v1/certificates/crl")
+            .header("Authorization", format!("Bearer {token}"));
+        if let Some(etag) = if_none_match {
+            request = request.header("If-None-Match", etag);
+        }
+        crate::create_app(state.clone())
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_crl_lists_revocations_with_changing_etag() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let caller = issue_token(&state.certificate_service);
+        let certificate_id = |token: &str| {
+            state
+                .certificate_service
+                .validate_certificate(token)
+                .unwrap()
+                .certificate_id
+        };
+        let first_token = issue_token(&state.certificate_service);
+        let first = certificate_id(&first_token);
+        let second = certificate_id(&issue_token(&state.certificate_service));
+
+        state.certificate_service.revoke_certificate(&first);
+        let response = crl(&state, &caller, None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()["etag"].to_str().unwrap().to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["revokedCertificateIds"], serde_json::json!([first]));
+        assert!(json["updatedAt"].is_string());
+
+        // Unchanged list
+        let response = crl(&state, &caller, Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()["etag"], etag.as_str());
+
+        state.certificate_service.revoke_certificate(&second);
+        let response = crl(&state, &caller, Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()["etag"], etag.as_str());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let mut expected = vec![first, second];
+        expected.sort();
+        assert_eq!(json["revokedCertificateIds"], serde_json::json!(expected));
+
+        // Fetching the list takes a valid certificate
+        assert_eq!(
+            crl(&state, &first_token, None).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    async fn rotate(state: &AppState, token: &str, rotation_jwt: String) -> StatusCode {
+        let body = serde_json::json!({ "rotation_jwt": rotation_jwt });
+        crate::create_app(state.clone())
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/certificates/rotate-key")
+                    .header("Content-Type", "application/json")
+                    .header("Authorization", format!("Bearer {token}"))
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+    }
+
+    async fn submit(state: &AppState, device: &DeviceKey, token: &str) -> StatusCode {
+        crate::create_app(state.clone())
+            .oneshot(signed_package_request(device, token, &sample_event()))
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_introspect_batch() {
+        let clock = MockClock::new();
+        let mut state = AppState::new_mock(AppConfig::default()).await;
+        state.certificate_service =
+            CertificateService::default().with_clock(Arc::new(clock.clone()));
+
+        let expired = issue_token(&state.certificate_service);
+        clock.advance(chrono::Duration::hours(25));
+        let valid = issue_token(&state.certificate_service);
+        let revoked = issue_token(&state.certificate_service);
+        let revoked_id = state
+            .certificate_service
+            .validate_certificate(&revoked)
+            .unwrap()
+            .certificate_id;
+        state.certificate_service.revoke_certificate(&revoked_id);
+
+        let introspect = |tokens: Vec<String>| {
+            let body = serde_json::json!({ "tokens": tokens });
+            crate::create_app(state.clone()).oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/certificates/introspect-batch")
+                    .header("Authorization", format!("Bearer {valid}"))
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+        };
+
+        let tokens = vec![
+            valid.clone(),
+            expired,
+            revoked,
+            "not-a-token".to_string(),
+            String::new(),
+        ];
+        let response = introspect(tokens).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let results = json["results"].as_array().unwrap();
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0]["active"], true);
+        assert_eq!(results[0]["relay_id"], "test_relay");
+        assert!(results[0]["expires_at"].is_string());
+        for result in &results[1..] {
+            assert_eq!(result["active"], false, "{result}");
+            assert!(result["relay_id"].is_null());
+        }
+
+        // Oversized batches are refused outright
+        let response = introspect(vec![valid.clone(); MAX_INTROSPECT_BATCH + 1])
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_old_key_verifies_only_during_grace_window() {
+        let clock = MockClock::new();
+        let mut state = AppState::new_mock(AppConfig::default()).await;
+        state.certificate_service = CertificateService::default()
+            .with_key_rotation_grace(chrono::Duration::minutes(10))
+            .with_clock(Arc::new(clock.clone()));
+
+        let old_key = DeviceKey::generate();
+        let new_key = DeviceKey::generate();
+        let token = crate::test_utils::issue_token(&state, &old_key);
+        let rotation_claims = serde_json::json!({ "new_public_key": new_key.public_key() });
+
+        // The rotation must be signed with the key currently on the certificate
+        assert_eq!(
+            rotate(&state, &token, new_key.sign_claims(rotation_claims.clone())).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            rotate(&state, &token, old_key.sign_claims(rotation_claims)).await,
+            StatusCode::OK
+        );
+
+        // Both keys verify while the grace window is open
+        assert_eq!(submit(&state, &old_key, &token).await, StatusCode::OK);
+        assert_eq!(submit(&state, &new_key, &token).await, StatusCode::OK);
+
+        // Only the new key verifies once it has closed
+        clock.advance(chrono::Duration::minutes(10) + chrono::Duration::seconds(1));
+        assert_eq!(
+            submit(&state, &old_key, &token).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(submit(&state, &new_key, &token).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_rotation_rejects_malformed_key() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let device = DeviceKey::generate();
+        let token = crate::test_utils::issue_token(&state, &device);
+
+        let rotation_jwt = device.sign_claims(serde_json::json!({ "new_public_key": "not-a-jwk" }));
+        assert_eq!(
+            rotate(&state, &token, rotation_jwt).await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(submit(&state, &device, &token).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_token_is_rejected() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+
+        let (code, json) = status(state, "garbage").await;
+        assert_eq!(code, StatusCode::UNAUTHORIZED);
+        assert_eq!(json["code"], "CERT_INVALID");
+    }
+}
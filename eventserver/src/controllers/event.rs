@@ -1,30 +1,73 @@
 use axum::{
-    extract::{Path, Request, State},
-    http::StatusCode,
-    response::Json,
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
     routing::{get, post},
     Router,
 };
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Instant;
 use tracing::{error, info, warn};
 use utoipa;
 
+use crate::crypto::EventReceipt;
 use crate::error::EventServerError;
-use crate::middleware::crypto::extract_validated_relay_id;
+use crate::middleware::crypto::{extract_validated_certificate_id, extract_validated_relay_id};
+use crate::middleware::server_timing::ServerTimings;
+use crate::services::image_header;
+use crate::services::rejection_log::{log_rejected_event, RejectedEventSummary};
+use crate::services::storage::{EventDownload, ObjectDownload, StoredMedia};
+use crate::services::tenant::extract_validated_tenant_id;
 use crate::services::zip_packager::{ZipPackageOptions, ZipPackager};
+use crate::services::StorageService;
 use crate::state::AppState;
-use crate::types::event::{EventPackage, ProcessingResult};
+use crate::types::api::EventStatusResponse;
+use crate::types::event::{EventPackage, FieldValue, ProcessingResult};
+use uuid::Uuid;
+
+/// Response header carrying the stored Content-Type of an event object that didn't match its key
+pub const CONTENT_TYPE_MISMATCH_HEADER: &str = "x-content-type-mismatch";
 
 /// Extract verified event package from request extensions (set by crypto middleware)
 fn extract_verified_event_package(request: &Request) -> Option<EventPackage> {
     request.extensions().get::<EventPackage>().cloned()
 }
 
+/// Log a redacted summary of a rejected event, passing the error through
+fn rejected(
+    relay_id: &str,
+    event_package: &EventPackage,
+    error: EventServerError,
+) -> EventServerError {
+    log_rejected_event(
+        Some(relay_id),
+        &RejectedEventSummary::of(event_package),
+        &error,
+    );
+    error
+}
+
+/// Storage scoped to the caller's tenant; the shared namespace when tenancy is disabled
+fn tenant_storage(state: &AppState, headers: &HeaderMap) -> StorageService {
+    state
+        .storage_service
+        .for_tenant(extract_validated_tenant_id(headers).as_deref())
+}
+
 /// Create event-related routes
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/events", post(receive_event))
         .route("/events/package", post(receive_event_package))
         .route("/events/:hash/verify", get(verify_event_hash))
+        .route("/events/:id/status", get(event_status))
+        .route("/events/:hash/download", get(download_event))
+        .route("/events/:hash/media", get(download_event_media))
+        .route(
+            "/events/:hash/annotations.csv",
+            get(download_annotations_csv),
+        )
 }
 
 /// Receive and process an event from a relay
@@ -37,7 +80,11 @@ pub fn routes() -> Router<AppState> {
         (status = 200, description = "Event processed successfully", body = ProcessingResult),
         (status = 400, description = "Invalid event data or validation failed"),
         (status = 401, description = "Authentication required - Bearer token missing or invalid"),
-        (status = 500, description = "Internal server error during processing")
+        (status = 403, description = "Request names a different tenant than the certificate"),
+        (status = 409, description = "Event JWT was already submitted and has not expired; retries must be signed anew"),
+        (status = 500, description = "Internal server error during processing"),
+        (status = 503, description = "Storage temporarily unavailable - retry after the Retry-After interval"),
+        (status = 507, description = "Relay storage quota exceeded")
     ),
     security(
         ("bearer_auth" = [])
@@ -47,14 +94,11 @@ pub fn routes() -> Router<AppState> {
 async fn receive_event(
     State(state): State<AppState>,
     request: Request,
-) -> Result<Json<ProcessingResult>, (StatusCode, String)> {
+) -> Result<Json<ProcessingResult>, EventServerError> {
     // Extract verified event package from request extensions (set by crypto middleware)
     let event_package = extract_verified_event_package(&request).ok_or_else(|| {
         error!("No verified event package found in request extensions");
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Event data verification failed".to_string(),
-        )
+        EventServerError::Internal("Event data verification failed".to_string())
     })?;
 
     info!(
@@ -66,57 +110,87 @@ async fn receive_event(
     let headers = request.headers();
     let relay_id = extract_validated_relay_id(headers).ok_or_else(|| {
         error!("No validated relay ID found in headers");
-        (
-            StatusCode::UNAUTHORIZED,
-            "Authentication required".to_string(),
-        )
+        EventServerError::Unauthorized("Authentication required".to_string())
     })?;
 
+    let summary = RejectedEventSummary::of(&event_package);
+    let published_package = state.event_bus.is_enabled().then(|| event_package.clone());
     match state
         .event_service
-        .process_event(event_package, relay_id)
+        .with_storage(tenant_storage(&state, headers))
+        .process_event(event_package, relay_id.clone())
         .await
     {
-        Ok(result) => {
+        Ok((result, written)) => {
             info!(
                 event_id = %result.event_id,
                 hash = %result.hash,
                 "Event processed successfully"
             );
+            // A resubmission of an already stored event was announced when first stored
+            if let Some(event_package) = published_package.as_ref().filter(|_| written) {
+                state.event_bus.publish(
+                    event_package,
+                    &result.hash,
+                    &relay_id,
+                    &result.storage_location,
+                );
+            }
             Ok(Json(result))
         }
-        Err(EventServerError::Validation(msg)) => {
-            warn!(error = %msg, "Event validation failed");
-            Err((StatusCode::BAD_REQUEST, msg))
+        Err(e @ EventServerError::Validation(_)) => {
+            log_rejected_event(Some(&relay_id), &summary, &e);
+            Err(e)
         }
         Err(EventServerError::Storage(msg)) => {
             error!(error = %msg, "Storage error during event processing");
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Storage error".to_string(),
+            Err(EventServerError::Storage(
+                "Failed to store event".to_string(),
             ))
         }
+        Err(e @ EventServerError::ServiceUnavailable { .. }) => {
+            warn!(error = %e, "Storage temporarily unavailable during event processing");
+            Err(e)
+        }
+        Err(e @ EventServerError::QuotaExceeded { .. }) => Err(e),
         Err(e) => {
             error!(error = %e, "Unexpected error during event processing");
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
+            Err(EventServerError::Internal(
                 "Internal server error".to_string(),
             ))
         }
     }
 }
 
+/// Query parameters for event package submission
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct PackageParams {
+    /// Storage format: `zip` (default) or `json`; `json` applies only to events without media
+    pub format: Option<String>,
+    /// Respond `202 Accepted` once validated and store in the background (defaults to server config)
+    #[serde(rename = "async")]
+    #[param(rename = "async")]
+    pub accept_async: Option<bool>,
+}
+
 /// Receive and process a SignedEventPackage from frontend
-/// Creates ZIP file and uploads to S3
+/// Creates ZIP file and uploads to S3, or stores plain JSON for media-less `?format=json` submissions
 #[utoipa::path(
     post,
     path = "/api/v1/events/package",
+    params(PackageParams),
     request_body = SignedEventPackage,
     responses(
-        (status = 200, description = "Event package processed and uploaded successfully", body = serde_json::Value),
+        (status = 200, description = "Event package processed and uploaded successfully; includes a signed receipt (EventReceipt) when receipts are enabled", body = serde_json::Value),
+        (status = 202, description = "Event package validated and accepted for background storage; poll statusUrl", body = serde_json::Value),
         (status = 400, description = "Invalid event package or validation failed"),
         (status = 401, description = "Authentication required - Bearer token missing or invalid"),
-        (status = 500, description = "Internal server error during processing or storage")
+        (status = 403, description = "Request names a different tenant than the certificate"),
+        (status = 409, description = "Event JWT was already submitted and has not expired; retries must be signed anew"),
+        (status = 413, description = "Media decodes past the configured maximum file size"),
+        (status = 500, description = "Internal server error during processing or storage"),
+        (status = 503, description = "Storage temporarily unavailable - retry after the Retry-After interval"),
+        (status = 507, description = "Relay storage quota exceeded")
     ),
     security(
         ("bearer_auth" = [])
@@ -125,86 +199,390 @@ async fn receive_event(
 )]
 async fn receive_event_package(
     State(state): State<AppState>,
+    Query(params): Query<PackageParams>,
     request: Request,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<(StatusCode, Json<serde_json::Value>), EventServerError> {
     // Extract verified event package from request extensions (set by crypto middleware)
     let event_package = extract_verified_event_package(&request).ok_or_else(|| {
         error!("No verified event package found in request extensions");
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Event data verification failed".to_string(),
-        )
+        EventServerError::Internal("Event data verification failed".to_string())
     })?;
 
-    // Validate the event package
-    let validation = event_package.validate();
+    let relay_id = extract_validated_relay_id(request.headers()).unwrap_or_default();
+    let timings = request.extensions().get::<ServerTimings>().cloned();
+
+    // Upconvert older schema versions, then validate the event package
+    let event_package = event_package.migrate_to_current();
+    let validation = event_package.validate_with(&state.config.validation);
     if !validation.is_valid {
-        warn!(
-            event_id = %event_package.id,
-            errors = ?validation.errors,
-            "EventPackage validation failed"
-        );
-        return Err((
-            StatusCode::BAD_REQUEST,
-            format!("Invalid event package: {}", validation.errors.join(", ")),
+        return Err(rejected(
+            &relay_id,
+            &event_package,
+            EventServerError::BadRequest(format!(
+                "Invalid event package: {}",
+                validation.errors.join(", ")
+            )),
         ));
     }
+    // Decoded once here; packaging and media storage reuse the bytes
+    let media_data =
+        ZipPackager::decode_event_media(&event_package, state.config.storage.max_file_size)
+            .map_err(|e| rejected(&relay_id, &event_package, e))?
+            .map(Arc::<[u8]>::from);
+    image_header::check_image_dimensions(
+        &event_package,
+        state.config.storage.max_image_width,
+        state.config.storage.max_image_height,
+    )
+    .map_err(|e| rejected(&relay_id, &event_package, e))?;
 
-    // Create ZIP file from EventPackage
-    let zip_options = ZipPackageOptions::default();
-    let zip_data =
-        match ZipPackager::create_zip_from_event_package(&event_package, zip_options).await {
-            Ok(data) => data,
-            Err(e) => {
-                error!(
-                    event_id = %event_package.id,
-                    error = %e,
-                    "Failed to create ZIP package"
-                );
-                return Err((
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to create ZIP package".to_string(),
-                ));
-            }
-        };
-
-    // Upload ZIP file to S3
-    let storage_location = match state
-        .storage_service
-        .upload_zip_file(&event_package, &zip_data)
-        .await
-    {
-        Ok(location) => location,
+    let event_hash = match state.event_service.generate_event_hash(&event_package) {
+        Ok(hash) => hash,
         Err(e) => {
             error!(
                 event_id = %event_package.id,
                 error = %e,
-                "Failed to upload ZIP to S3"
+                "Failed to hash event package"
             );
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to upload to storage".to_string(),
+            return Err(EventServerError::Internal(
+                "Failed to hash event package".to_string(),
             ));
         }
     };
 
+    let storage = tenant_storage(&state, request.headers());
+
+    // Media-less events may skip ZIP packaging and be stored as (optionally gzipped) JSON
+    let json_fast_path = match params.format.as_deref() {
+        None | Some("zip") => false,
+        Some("json") => event_package.media.is_none(),
+        Some(other) => {
+            return Err(EventServerError::BadRequest(format!(
+                "Unsupported format '{other}'; expected 'zip' or 'json'"
+            )));
+        }
+    };
+
+    let accept_async = params
+        .accept_async
+        .unwrap_or(state.config.server.accept_async);
+    // With every background slot taken the package is stored before answering instead
+    let background_slot = accept_async
+        .then(|| state.background_stores.try_reserve())
+        .flatten();
+    if accept_async && background_slot.is_none() {
+        warn!(event_id = %event_package.id, "Background storage at capacity, storing synchronously");
+    }
+    if let Some(slot) = background_slot {
+        // Everything a client could fix was checked above; only storage is deferred
+        let response = serde_json::json!({
+            "status": "accepted",
+            "eventId": event_package.id,
+            "hash": event_hash,
+            "statusUrl": format!("/api/v1/events/{}/status", event_package.id),
+            "acceptedAt": chrono::Utc::now()
+        });
+
+        let event_id = event_package.id;
+        let tenant_id = extract_validated_tenant_id(request.headers());
+        let certificate_id = extract_validated_certificate_id(request.headers());
+        state.jobs.pending(
+            event_id,
+            &event_hash,
+            tenant_id.as_deref(),
+            certificate_id.as_deref(),
+        );
+        let background_stores = state.background_stores.clone();
+        background_stores.spawn(slot, async move {
+            match store_event_package(
+                &state,
+                &storage,
+                ValidatedEvent {
+                    package: &event_package,
+                    media_data,
+                    hash: &event_hash,
+                },
+                &relay_id,
+                json_fast_path,
+                None,
+            )
+            .await
+            {
+                Ok(stored) => {
+                    state
+                        .jobs
+                        .stored(event_id, &event_hash, &stored.storage_location);
+                    info!(
+                        event_id = %event_id,
+                        hash = %event_hash,
+                        storage_location = %stored.storage_location,
+                        "Background EventPackage storage completed"
+                    );
+                }
+                Err(e) => {
+                    state.jobs.failed(event_id, &event_hash);
+                    error!(
+                        event_id = %event_id,
+                        hash = %event_hash,
+                        relay_id = %relay_id,
+                        error = %e,
+                        "Background EventPackage storage failed; the event was accepted but not stored"
+                    );
+                }
+            }
+        });
+
+        info!(event_id = %event_id, "EventPackage accepted for background storage");
+        return Ok((StatusCode::ACCEPTED, Json(response)));
+    }
+
+    let StoredPackage {
+        storage_location,
+        zip_size,
+        stored_media,
+        receipt,
+    } = store_event_package(
+        &state,
+        &storage,
+        ValidatedEvent {
+            package: &event_package,
+            media_data,
+            hash: &event_hash,
+        },
+        &relay_id,
+        json_fast_path,
+        timings.as_ref(),
+    )
+    .await?;
+
     // Create response
-    let response = serde_json::json!({
+    let mut response = serde_json::json!({
         "status": "processed",
         "eventId": event_package.id,
+        "hash": event_hash,
         "storageLocation": storage_location,
-        "zipSize": zip_data.len(),
+        "zipSize": zip_size,
+        "mediaAlreadyStored": stored_media.as_ref().is_some_and(|m| m.already_stored),
+        "mediaDigest": stored_media.as_ref().map(|m| m.digest.clone()),
         "processedAt": chrono::Utc::now()
     });
+    if let Some(receipt) = receipt {
+        response["receipt"] = serde_json::to_value(receipt)?;
+    }
 
     info!(
         event_id = %event_package.id,
         storage_location = %storage_location,
-        zip_size = zip_data.len(),
+        zip_size,
+        json_fast_path,
         "EventPackage processed and uploaded successfully"
     );
 
-    Ok(Json(response))
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Outcome of storing a validated event package
+struct StoredPackage {
+    storage_location: String,
+    zip_size: usize,
+    stored_media: Option<StoredMedia>,
+    receipt: Option<EventReceipt>,
+}
+
+/// An event package that passed validation, with the media decoded while validating it
+struct ValidatedEvent<'a> {
+    package: &'a EventPackage,
+    media_data: Option<Arc<[u8]>>,
+    hash: &'a str,
+}
+
+/// Package (unless `json_fast_path`), upload, index and store media for a validated event
+/// Packaging and storage durations are recorded in `timings` when given
+async fn store_event_package(
+    state: &AppState,
+    storage: &StorageService,
+    event: ValidatedEvent<'_>,
+    relay_id: &str,
+    json_fast_path: bool,
+    timings: Option<&ServerTimings>,
+) -> Result<StoredPackage, EventServerError> {
+    let ValidatedEvent {
+        package: event_package,
+        media_data,
+        hash: event_hash,
+    } = event;
+    let mut started = Instant::now();
+    let (outcome, zip_size) = if json_fast_path {
+        match storage
+            .store_event(event_package, event_hash, relay_id)
+            .await
+        {
+            Ok(outcome) => (outcome, 0),
+            Err(e) => {
+                error!(
+                    event_id = %event_package.id,
+                    error = %e,
+                    "Failed to store event JSON"
+                );
+                return Err(storage_failure(e));
+            }
+        }
+    } else {
+        // Create ZIP file from EventPackage
+        let zip_data = match state
+            .zip_packaging
+            .package(
+                event_package,
+                media_data.clone(),
+                ZipPackageOptions {
+                    max_entries: state.config.storage.zip_max_entries,
+                    ..ZipPackageOptions::default()
+                },
+            )
+            .await
+        {
+            Ok(data) => data,
+            Err(e @ EventServerError::Validation(_)) => {
+                return Err(rejected(relay_id, event_package, e));
+            }
+            Err(e) => {
+                error!(
+                    event_id = %event_package.id,
+                    error = %e,
+                    "Failed to create ZIP package"
+                );
+                return Err(EventServerError::Internal(
+                    "Failed to create ZIP package".to_string(),
+                ));
+            }
+        };
+        if let Some(timings) = timings {
+            timings.record("packaging", started);
+        }
+        started = Instant::now();
+
+        // Upload ZIP file to S3
+        match storage
+            .upload_zip_file(event_package, event_hash, relay_id, &zip_data)
+            .await
+        {
+            Ok(outcome) => (outcome, zip_data.len()),
+            Err(e) => {
+                error!(
+                    event_id = %event_package.id,
+                    error = %e,
+                    "Failed to upload ZIP to S3"
+                );
+                return Err(storage_failure(e));
+            }
+        }
+    };
+    let storage_location = outcome.location;
+
+    // The listing index is a convenience for admin views; don't fail the submission over it
+    if let Err(e) = storage
+        .index_event(event_package, event_hash, relay_id)
+        .await
+    {
+        warn!(event_id = %event_package.id, error = %e, "Failed to update event index");
+    }
+
+    // Also keep a content-addressed copy of the media, stored once per digest
+    let stored_media = match (&event_package.media, &media_data) {
+        (Some(media), Some(media_data)) => {
+            match storage
+                .store_media(media_data, media.media_type.as_str(), relay_id)
+                .await
+            {
+                Ok(stored) => Some(stored),
+                Err(e) => {
+                    error!(
+                        event_id = %event_package.id,
+                        error = %e,
+                        "Failed to store media"
+                    );
+                    return Err(storage_failure(e));
+                }
+            }
+        }
+        _ => None,
+    };
+
+    // Signed only once the event is stored, so a receipt always proves a stored event
+    let receipt = storage
+        .issue_receipt(event_package.id, event_hash, &storage_location)
+        .await;
+    if let Some(timings) = timings {
+        timings.record("storage", started);
+    }
+    // A resubmission of an already stored event was announced when first stored
+    if outcome.written {
+        state
+            .event_bus
+            .publish(event_package, event_hash, relay_id, &storage_location);
+    }
+
+    Ok(StoredPackage {
+        storage_location,
+        zip_size,
+        stored_media,
+        receipt,
+    })
+}
+
+/// Hide storage internals from clients, but keep back-off hints for transient failures
+fn storage_failure(error: EventServerError) -> EventServerError {
+    match error {
+        EventServerError::ServiceUnavailable { .. } | EventServerError::QuotaExceeded { .. } => {
+            error
+        }
+        _ => EventServerError::Storage("Failed to upload to storage".to_string()),
+    }
+}
+
+/// Report the storage progress of an event accepted in async mode
+/// Only the submitting certificate can poll, and only on the instance that accepted the event
+#[utoipa::path(
+    get,
+    path = "/api/v1/events/{id}/status",
+    params(
+        ("id" = Uuid, Path, description = "ID of the event returned when it was accepted")
+    ),
+    responses(
+        (status = 200, description = "Current storage status", body = EventStatusResponse),
+        (status = 401, description = "Authentication required - Bearer token missing or invalid"),
+        (status = 404, description = "No async submission with this ID from the caller's certificate on this instance, or its status has expired")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "events"
+)]
+async fn event_status(
+    State(state): State<AppState>,
+    Path(event_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<Json<EventStatusResponse>, EventServerError> {
+    // Another caller's job is reported as unknown rather than forbidden, so IDs can't be probed
+    let tenant_id = extract_validated_tenant_id(&headers);
+    let certificate_id = extract_validated_certificate_id(&headers);
+    let job = state
+        .jobs
+        .get(&event_id)
+        .filter(|job| job.tenant_id == tenant_id && job.certificate_id == certificate_id)
+        .ok_or_else(|| {
+            EventServerError::NotFound(format!(
+                "No pending or recent submission for event {event_id}"
+            ))
+        })?;
+
+    Ok(Json(EventStatusResponse {
+        event_id,
+        status: job.status,
+        hash: job.hash,
+        storage_location: job.storage_location,
+        updated_at: job.updated_at,
+    }))
 }
 
 /// Verify if an event hash exists in storage
@@ -219,6 +597,7 @@ async fn receive_event_package(
         (status = 200, description = "Hash verification completed", body = HashVerificationResponse),
         (status = 400, description = "Invalid hash format - must be 64 characters"),
         (status = 401, description = "Authentication required - Bearer token missing or invalid"),
+        (status = 403, description = "Request names a different tenant than the certificate"),
         (status = 500, description = "Internal server error during verification")
     ),
     security(
@@ -229,6 +608,7 @@ async fn receive_event_package(
 async fn verify_event_hash(
     State(state): State<AppState>,
     Path(hash): Path<String>,
+    headers: HeaderMap,
 ) -> Result<Json<HashVerificationResponse>, (StatusCode, String)> {
     info!(hash = %hash, "Received hash verification request");
 
@@ -241,7 +621,12 @@ async fn verify_event_hash(
         ));
     }
 
-    match state.event_service.verify_event_hash(&hash).await {
+    match state
+        .event_service
+        .with_storage(tenant_storage(&state, &headers))
+        .verify_event_hash(&hash)
+        .await
+    {
         Ok(exists) => {
             info!(
                 hash = %hash,
@@ -268,6 +653,236 @@ async fn verify_event_hash(
     }
 }
 
+/// Download the stored archive for an event hash
+/// Honors the HTTP `Range` header so interrupted downloads can be resumed
+#[utoipa::path(
+    get,
+    path = "/api/v1/events/{hash}/download",
+    params(
+        ("hash" = String, Path, description = "SHA-256 hash of the event to download (64 characters)"),
+        ("Range" = Option<String>, Header, description = "Optional byte range, e.g. bytes=0-99")
+    ),
+    responses(
+        (status = 200, description = "Full stored object", content_type = "application/zip"),
+        (status = 206, description = "Requested byte range of the stored object", content_type = "application/zip"),
+        (status = 400, description = "Invalid hash format - must be 64 characters"),
+        (status = 401, description = "Authentication required - Bearer token missing or invalid"),
+        (status = 403, description = "Request names a different tenant than the certificate"),
+        (status = 404, description = "No stored event for this hash"),
+        (status = 416, description = "Requested range not satisfiable")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "events"
+)]
+async fn download_event(
+    State(state): State<AppState>,
+    Path(hash): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, EventServerError> {
+    if hash.len() != 64 {
+        warn!(hash = %hash, "Invalid hash format");
+        return Err(EventServerError::BadRequest(
+            "Hash must be 64 characters (SHA-256)".to_string(),
+        ));
+    }
+
+    let range = headers.get(header::RANGE).and_then(|h| h.to_str().ok());
+    let EventDownload {
+        download,
+        content_type_mismatch,
+    } = tenant_storage(&state, &headers)
+        .download_event(&hash, range)
+        .await?;
+
+    let extension = if download.content_type.as_deref() == Some("application/json") {
+        "json"
+    } else {
+        "zip"
+    };
+    let content_type = download
+        .content_type
+        .clone()
+        .unwrap_or_else(|| "application/zip".to_string());
+
+    let mut response = ranged_response(download, &content_type, &format!("{hash}.{extension}"));
+    // Served with the type its key implies; the stored one is reported for investigation
+    if let Some(value) =
+        content_type_mismatch.and_then(|stored| HeaderValue::from_str(&stored).ok())
+    {
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE_MISMATCH_HEADER, value);
+    }
+    Ok(response)
+}
+
+/// Download only the media of a stored event
+/// Honors the HTTP `Range` header, e.g. for seeking within a video
+#[utoipa::path(
+    get,
+    path = "/api/v1/events/{hash}/media",
+    params(
+        ("hash" = String, Path, description = "SHA-256 hash of the event (64 characters)"),
+        ("Range" = Option<String>, Header, description = "Optional byte range, e.g. bytes=0-99")
+    ),
+    responses(
+        (status = 200, description = "Raw media bytes, typed by the event's media type"),
+        (status = 206, description = "Requested byte range of the media"),
+        (status = 400, description = "Invalid hash format - must be 64 characters"),
+        (status = 401, description = "Authentication required - Bearer token missing or invalid"),
+        (status = 403, description = "Request names a different tenant than the certificate"),
+        (status = 404, description = "No stored event for this hash, or the event has no media"),
+        (status = 416, description = "Requested range not satisfiable")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "events"
+)]
+async fn download_event_media(
+    State(state): State<AppState>,
+    Path(hash): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, EventServerError> {
+    if hash.len() != 64 {
+        warn!(hash = %hash, "Invalid hash format");
+        return Err(EventServerError::BadRequest(
+            "Hash must be 64 characters (SHA-256)".to_string(),
+        ));
+    }
+
+    let range = headers.get(header::RANGE).and_then(|h| h.to_str().ok());
+    let media = tenant_storage(&state, &headers)
+        .download_media(&hash, range)
+        .await?;
+
+    let filename = format!(
+        "{hash}.{}",
+        ZipPackager::get_file_extension(media.media_type.as_str())
+    );
+    Ok(ranged_response(
+        media.download,
+        media.media_type.as_str(),
+        &filename,
+    ))
+}
+
+/// Download the annotations of a stored event as CSV
+/// One row per annotation with `label_id`, `value` and `timestamp` columns; null values are
+/// left empty and numbers and booleans are written as their JSON text
+#[utoipa::path(
+    get,
+    path = "/api/v1/events/{hash}/annotations.csv",
+    params(
+        ("hash" = String, Path, description = "SHA-256 hash of the event (64 characters)")
+    ),
+    responses(
+        (status = 200, description = "Annotations as CSV with a header row", content_type = "text/csv"),
+        (status = 400, description = "Invalid hash format - must be 64 characters"),
+        (status = 401, description = "Authentication required - Bearer token missing or invalid"),
+        (status = 403, description = "Request names a different tenant than the certificate"),
+        (status = 404, description = "No stored event for this hash")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "events"
+)]
+async fn download_annotations_csv(
+    State(state): State<AppState>,
+    Path(hash): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, EventServerError> {
+    if hash.len() != 64 {
+        warn!(hash = %hash, "Invalid hash format");
+        return Err(EventServerError::BadRequest(
+            "Hash must be 64 characters (SHA-256)".to_string(),
+        ));
+    }
+
+    let event_package = tenant_storage(&state, &headers)
+        .retrieve_event(&hash)
+        .await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{hash}-annotations.csv\""),
+            ),
+        ],
+        annotations_csv(&event_package),
+    )
+        .into_response())
+}
+
+/// RFC 4180 CSV of an event's annotations, header row first
+fn annotations_csv(event_package: &EventPackage) -> String {
+    let mut csv = String::from("label_id,value,timestamp\r\n");
+    for annotation in &event_package.annotations {
+        let value = match &annotation.value {
+            FieldValue::String(value) => csv_text(value),
+            FieldValue::Number(value) => value.to_string(),
+            FieldValue::Boolean(value) => value.to_string(),
+            FieldValue::Null => String::new(),
+            FieldValue::Unknown(value) => csv_text(&value.to_string()),
+        };
+        let timestamp = annotation
+            .timestamp
+            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        csv.push_str(&format!(
+            "{},{},{timestamp}\r\n",
+            csv_text(&annotation.label_id),
+            value
+        ));
+    }
+    csv
+}
+
+/// Quote a text field when needed; text a spreadsheet would run as a formula is prefixed with `'`
+fn csv_text(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{value}")
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Attachment response for a (possibly ranged) object download
+fn ranged_response(download: ObjectDownload, content_type: &str, filename: &str) -> Response {
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_str(content_type)
+            .unwrap_or(HeaderValue::from_static("application/octet-stream")),
+    );
+    response_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    if let Ok(disposition) = HeaderValue::from_str(&format!("attachment; filename=\"{filename}\""))
+    {
+        response_headers.insert(header::CONTENT_DISPOSITION, disposition);
+    }
+
+    let status = match download.content_range {
+        Some(content_range) => {
+            if let Ok(value) = HeaderValue::from_str(&content_range) {
+                response_headers.insert(header::CONTENT_RANGE, value);
+            }
+            StatusCode::PARTIAL_CONTENT
+        }
+        None => StatusCode::OK,
+    };
+
+    (status, response_headers, download.body).into_response()
+}
+
 /// Response for hash verification
 #[derive(serde::Serialize, utoipa::ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -276,3 +891,746 @@ pub struct HashVerificationResponse {
     pub exists: bool,
     pub verified_at: chrono::DateTime<chrono::Utc>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::crypto::CertificateRequest;
+    use crate::test_utils::{sample_event, sample_media};
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    fn bearer_token(state: &AppState) -> String {
+        let response = state
+            .certificate_service
+            .issue_certificate(&CertificateRequest {
+                relay_id: "test_relay".to_string(),
+                public_key: "test_public_key".to_string(),
+                ed25519_public_key: None,
+                tenant_id: None,
+            })
+            .unwrap();
+        format!("Bearer {}", response.cert_token)
+    }
+
+    #[tokio::test]
+    async fn test_download_event_range() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let hash = "b".repeat(64);
+        let archive: Vec<u8> = (0..1000u32).map(|i| (i % 256) as u8).collect();
+        state
+            .storage_service
+            .upload_zip_file(&sample_event(), &hash, "test_relay", &archive)
+            .await
+            .unwrap();
+        let token = bearer_token(&state);
+
+        let response = crate::create_app(state)
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(format!("/api/v1/events/{hash}/download"))
+                    .header("Authorization", token)
+                    .header(header::RANGE, "bytes=0-99")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 0-99/1000");
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/zip");
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body.len(), 100);
+        assert_eq!(&body[..], &archive[..100]);
+    }
+
+    #[tokio::test]
+    async fn test_json_fast_path_skips_zip() {
+        use crate::services::storage::MockS3Client;
+        use crate::services::StorageService;
+        use crate::test_utils::{issue_token, signed_package_request, DeviceKey};
+        use std::sync::Arc;
+
+        for compress in [false, true] {
+            let mut state = AppState::new_mock(AppConfig::default()).await;
+            let mock = Arc::new(MockS3Client::default());
+            state.storage_service = StorageService::with_mock(mock.clone());
+            state.storage_service.set_compress_annotations(compress);
+            let device = DeviceKey::generate();
+            let token = issue_token(&state, &device);
+
+            let mut request = signed_package_request(&device, &token, &sample_event());
+            *request.uri_mut() = "/api/v1/events/package?format=json".parse().unwrap();
+            let response = crate::create_app(state).oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["status"], "processed");
+            assert_eq!(body["zipSize"], 0);
+
+            let keys = mock.put_log();
+            let extension = if compress { ".json.gz" } else { ".json" };
+            assert!(keys
+                .iter()
+                .any(|key| !key.starts_with("events/by-hash/") && key.ends_with(extension)));
+            assert!(!keys.iter().any(|key| key.ends_with(".zip")));
+            // Found by hash like an archive
+            let marker = format!("events/by-hash/{}.json", body["hash"].as_str().unwrap());
+            assert!(keys.contains(&marker));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_processed_submission_returns_signed_receipt() {
+        use crate::crypto::ArchiveSigner;
+        use crate::services::storage::MockS3Client;
+        use crate::services::StorageService;
+        use crate::test_utils::{issue_token, signed_package_request, DeviceKey};
+        use std::sync::Arc;
+
+        let mut state = AppState::new_mock(AppConfig::default()).await;
+        let mock = Arc::new(MockS3Client::default());
+        let signer = ArchiveSigner::new(None).unwrap();
+        state.storage_service =
+            StorageService::with_mock(mock.clone()).with_receipt_signer(signer.clone(), true);
+        let device = DeviceKey::generate();
+        let token = issue_token(&state, &device);
+        let event = sample_event();
+
+        let request = signed_package_request(&device, &token, &event);
+        let response = crate::create_app(state).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        let receipt: EventReceipt = serde_json::from_value(body["receipt"].clone()).unwrap();
+        assert_eq!(receipt.event_id, event.id);
+        assert_eq!(receipt.hash, body["hash"]);
+        assert_eq!(receipt.storage_location, body["storageLocation"]);
+        assert!(signer.verify_receipt(&receipt));
+        let altered = EventReceipt {
+            hash: "0".repeat(64),
+            ..receipt.clone()
+        };
+        assert!(!signer.verify_receipt(&altered));
+
+        // Stored next to the archive
+        let stored = mock
+            .put_log()
+            .into_iter()
+            .find(|key| key.ends_with(".zip.receipt.json"))
+            .expect("stored receipt");
+        let stored: EventReceipt =
+            serde_json::from_slice(&mock.object(&stored).unwrap().body).unwrap();
+        assert_eq!(stored, receipt);
+    }
+
+    #[tokio::test]
+    async fn test_relay_over_storage_quota_is_rejected() {
+        use crate::test_utils::{issue_token, signed_package_request, DeviceKey};
+
+        // Room for one archive but not two
+        let archive_size = ZipPackager::create_zip_from_event_package(
+            &sample_event(),
+            ZipPackageOptions::default(),
+        )
+        .unwrap()
+        .len() as u64;
+        let mut state = AppState::new_mock(AppConfig::default()).await;
+        state
+            .storage_service
+            .set_relay_quota(Some(archive_size * 3 / 2));
+        let device = DeviceKey::generate();
+        let token = issue_token(&state, &device);
+        let app = crate::create_app(state);
+
+        let request = signed_package_request(&device, &token, &sample_event());
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // A distinct annotation, so it can't hash like the first event and count as stored
+        let mut second = sample_event();
+        second.annotations[0].value = FieldValue::String("second".to_string());
+        let request = signed_package_request(&device, &token, &second);
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INSUFFICIENT_STORAGE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "RELAY_QUOTA_EXCEEDED");
+        assert_eq!(body["quota_bytes"], archive_size * 3 / 2);
+        assert!(body["used_bytes"].as_u64().unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_sync_and_async_package_submission() {
+        use crate::test_utils::{issue_token, signed_package_request, DeviceKey};
+
+        for (accept_async, query, max_background_stores, expected) in [
+            (false, "", 64, StatusCode::OK),
+            (false, "?async=true", 64, StatusCode::ACCEPTED),
+            (true, "", 64, StatusCode::ACCEPTED),
+            (true, "?async=false", 64, StatusCode::OK),
+            // No free background slot, so it's stored before answering
+            (true, "", 0, StatusCode::OK),
+        ] {
+            let mut config = AppConfig::default();
+            config.server.accept_async = accept_async;
+            config.server.max_background_stores = max_background_stores;
+            let state = AppState::new_mock(config).await;
+            let device = DeviceKey::generate();
+            let token = issue_token(&state, &device);
+            let app = crate::create_app(state);
+
+            let mut request = signed_package_request(&device, &token, &sample_event());
+            *request.uri_mut() = format!("/api/v1/events/package{query}").parse().unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), expected);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+            if expected == StatusCode::OK {
+                assert_eq!(body["status"], "processed");
+                continue;
+            }
+            assert_eq!(body["status"], "accepted");
+            assert_eq!(
+                body["statusUrl"],
+                format!(
+                    "/api/v1/events/{}/status",
+                    body["eventId"].as_str().unwrap()
+                )
+            );
+            wait_for_status(&app, &token, body["statusUrl"].as_str().unwrap(), "stored").await;
+        }
+    }
+
+    async fn fetch_status(app: &axum::Router, token: &str, status_url: &str) -> serde_json::Value {
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(status_url)
+                    .header("Authorization", format!("Bearer {token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    async fn wait_for_status(
+        app: &axum::Router,
+        token: &str,
+        status_url: &str,
+        expected: &str,
+    ) -> serde_json::Value {
+        for _ in 0..100 {
+            let body = fetch_status(app, token, status_url).await;
+            if body["status"] == expected {
+                return body;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        panic!("status never became {expected}");
+    }
+
+    #[tokio::test]
+    async fn test_async_status_transitions_to_stored() {
+        use crate::services::storage::MockS3Client;
+        use crate::services::StorageService;
+        use crate::test_utils::{issue_token, signed_package_request, DeviceKey};
+        use std::sync::Arc;
+
+        let mut state = AppState::new_mock(AppConfig::default()).await;
+        state.storage_service = StorageService::with_mock(Arc::new(MockS3Client::with_put_delay(
+            std::time::Duration::from_millis(200),
+        )));
+        let device = DeviceKey::generate();
+        let token = issue_token(&state, &device);
+        let other_token = issue_token(&state, &DeviceKey::generate());
+        let app = crate::create_app(state);
+
+        let mut request = signed_package_request(&device, &token, &sample_event());
+        *request.uri_mut() = "/api/v1/events/package?async=true".parse().unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let accepted: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let status_url = accepted["statusUrl"].as_str().unwrap();
+
+        let pending = fetch_status(&app, &token, status_url).await;
+        assert_eq!(pending["status"], "pending");
+        assert!(pending["storageLocation"].is_null());
+
+        // Another certificate can't see the job
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(status_url)
+                    .header("Authorization", format!("Bearer {other_token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let stored = wait_for_status(&app, &token, status_url, "stored").await;
+        assert_eq!(stored["hash"], accepted["hash"]);
+        assert!(stored["storageLocation"]
+            .as_str()
+            .unwrap()
+            .contains("events/"));
+
+        // Unknown IDs are not found
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(format!("/api/v1/events/{}/status", uuid::Uuid::new_v4()))
+                    .header("Authorization", format!("Bearer {token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_media_is_stored_once() {
+        use crate::test_utils::{issue_token, signed_package_request, DeviceKey};
+        use crate::types::event::MediaType;
+        use sha2::Digest;
+
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let device = DeviceKey::generate();
+        let token = issue_token(&state, &device);
+        let app = crate::create_app(state);
+
+        let with_media = || {
+            let mut event = sample_event();
+            event.media = Some(sample_media(MediaType::ImageJpeg, b"Hello World"));
+            event
+        };
+
+        let mut results = Vec::new();
+        for event in [with_media(), with_media()] {
+            let response = app
+                .clone()
+                .oneshot(signed_package_request(&device, &token, &event))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            results.push(serde_json::from_slice::<serde_json::Value>(&body).unwrap());
+        }
+
+        assert_eq!(results[0]["mediaAlreadyStored"], false);
+        assert_eq!(results[1]["mediaAlreadyStored"], true);
+        assert_eq!(results[0]["mediaDigest"], results[1]["mediaDigest"]);
+        assert_eq!(
+            results[0]["mediaDigest"],
+            hex::encode(sha2::Sha256::digest(b"Hello World"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_media_decoding_past_limit_is_rejected() {
+        use crate::test_utils::{issue_token, signed_package_request, DeviceKey};
+        use crate::types::event::MediaType;
+
+        let mut config = AppConfig::default();
+        config.storage.max_file_size = 1024;
+        let state = AppState::new_mock(config).await;
+        let device = DeviceKey::generate();
+        let token = issue_token(&state, &device);
+
+        // Just over the limit
+        let mut event = sample_event();
+        event.media = Some(sample_media(MediaType::ImageJpeg, &[0; 1026]));
+
+        let response = crate::create_app(state)
+            .oneshot(signed_package_request(&device, &token, &event))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "PAYLOAD_TOO_LARGE");
+        assert!(json["error"].as_str().unwrap().contains("1026 bytes"));
+    }
+
+    #[tokio::test]
+    async fn test_undecodable_media_is_rejected() {
+        use crate::services::storage::MockS3Client;
+        use crate::services::StorageService;
+        use crate::test_utils::{issue_token, signed_package_request, DeviceKey};
+        use crate::types::event::{EventMedia, MediaType};
+
+        let mut state = AppState::new_mock(AppConfig::default()).await;
+        let mock = Arc::new(MockS3Client::default());
+        state.storage_service = StorageService::with_mock(mock.clone());
+        let device = DeviceKey::generate();
+        let token = issue_token(&state, &device);
+
+        let mut event = sample_event();
+        event.media = Some(EventMedia {
+            data: "data:image/jpeg;base64,not base64!".to_string(),
+            ..sample_media(MediaType::ImageJpeg, b"Hello World")
+        });
+
+        let response = crate::create_app(state)
+            .oneshot(signed_package_request(&device, &token, &event))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        // Rejected before anything is stored, rather than stored without its media
+        assert!(mock.put_log().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_archive_over_entry_cap_is_rejected() {
+        use crate::services::storage::MockS3Client;
+        use crate::services::StorageService;
+        use crate::test_utils::{issue_token, signed_package_request, DeviceKey};
+        use crate::types::event::MediaType;
+
+        // metadata.json, annotations.json, the media and media_metadata.json make four
+        let mut config = AppConfig::default();
+        config.storage.zip_max_entries = 3;
+        let mut state = AppState::new_mock(config).await;
+        let mock = Arc::new(MockS3Client::default());
+        state.storage_service = StorageService::with_mock(mock.clone());
+        let device = DeviceKey::generate();
+        let token = issue_token(&state, &device);
+
+        let mut event = sample_event();
+        event.media = Some(sample_media(MediaType::ImageJpeg, b"Hello World"));
+
+        let response = crate::create_app(state)
+            .oneshot(signed_package_request(&device, &token, &event))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(json["error"].as_str().unwrap().contains("3-entry limit"));
+        assert!(mock.put_log().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_download_event_full_and_missing() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let hash = "c".repeat(64);
+        state
+            .storage_service
+            .upload_zip_file(&sample_event(), &hash, "test_relay", b"zip-bytes")
+            .await
+            .unwrap();
+        let token = bearer_token(&state);
+        let app = crate::create_app(state);
+
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(format!("/api/v1/events/{hash}/download"))
+                    .header("Authorization", token.clone())
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ACCEPT_RANGES], "bytes");
+
+        let missing = "d".repeat(64);
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(format!("/api/v1/events/{missing}/download"))
+                    .header("Authorization", token)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_download_annotations_csv() {
+        use crate::types::event::EventAnnotation;
+
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let timestamp = chrono::DateTime::parse_from_rfc3339("2024-05-01T12:30:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let mut event = sample_event();
+        event.annotations = [
+            FieldValue::String("flooded, \"badly\"".to_string()),
+            FieldValue::Number(2.5),
+            FieldValue::Boolean(true),
+            FieldValue::Null,
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| EventAnnotation {
+            label_id: format!("label_{index}"),
+            value,
+            timestamp,
+        })
+        .collect();
+        let hash = "e".repeat(64);
+        state
+            .storage_service
+            .store_event(&event, &hash, "test_relay")
+            .await
+            .unwrap();
+        let token = bearer_token(&state);
+
+        let response = crate::create_app(state)
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(format!("/api/v1/events/{hash}/annotations.csv"))
+                    .header("Authorization", token)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/csv; charset=utf-8"
+        );
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            format!("attachment; filename=\"{hash}-annotations.csv\"").as_str()
+        );
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let csv = String::from_utf8(body.to_vec()).unwrap();
+        let rows: Vec<&str> = csv.split_terminator("\r\n").collect();
+        assert_eq!(
+            rows,
+            [
+                "label_id,value,timestamp",
+                "label_0,\"flooded, \"\"badly\"\"\",2024-05-01T12:30:00.000Z",
+                "label_1,2.5,2024-05-01T12:30:00.000Z",
+                "label_2,true,2024-05-01T12:30:00.000Z",
+                "label_3,,2024-05-01T12:30:00.000Z",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_event_media() {
+        use crate::test_utils::{issue_token, signed_package_request, DeviceKey};
+        use crate::types::event::MediaType;
+
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let device = DeviceKey::generate();
+        let token = issue_token(&state, &device);
+        let app = crate::create_app(state);
+
+        let mut event = sample_event();
+        event.media = Some(sample_media(MediaType::ImagePng, b"Hello World"));
+        let submit = |event| {
+            let app = app.clone();
+            let request = signed_package_request(&device, &token, &event);
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                json["hash"].as_str().unwrap().to_string()
+            }
+        };
+        let hash = submit(event).await;
+
+        let media_request = |hash: &str, range: Option<&str>| {
+            let mut request = axum::http::Request::builder()
+                .uri(format!("/api/v1/events/{hash}/media"))
+                .header("Authorization", format!("Bearer {token}"));
+            if let Some(range) = range {
+                request = request.header(header::RANGE, range);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(media_request(&hash, None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"Hello World");
+
+        let response = app
+            .clone()
+            .oneshot(media_request(&hash, Some("bytes=6-10")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"World");
+
+        // An event stored without media has nothing to serve
+        let plain_hash = submit(sample_event()).await;
+        let response = app.oneshot(media_request(&plain_hash, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_tenants_are_isolated() {
+        use crate::config::TenantSource;
+        use crate::services::storage::MockS3Client;
+        use crate::services::StorageService;
+        use crate::test_utils::{signed_package_request, DeviceKey};
+        use std::sync::Arc;
+
+        let mut config = AppConfig::default();
+        config.security.tenant_source = TenantSource::Certificate;
+        let mut state = AppState::new_mock(config).await;
+        let mock = Arc::new(MockS3Client::default());
+        state.storage_service = StorageService::with_mock(mock.clone());
+        let tenant_token = |device: &DeviceKey, tenant_id: &str| {
+            state
+                .certificate_service
+                .issue_certificate(&CertificateRequest {
+                    relay_id: format!("{tenant_id}_relay"),
+                    public_key: device.public_key(),
+                    ed25519_public_key: None,
+                    tenant_id: Some(tenant_id.to_string()),
+                })
+                .unwrap()
+                .cert_token
+        };
+        let (acme_device, globex_device) = (DeviceKey::generate(), DeviceKey::generate());
+        let acme = tenant_token(&acme_device, "acme");
+        let globex = tenant_token(&globex_device, "globex");
+        let app = crate::create_app(state);
+
+        let get = |uri: String, token: &str, tenant_header: Option<&str>| {
+            let mut request = axum::http::Request::builder()
+                .uri(uri)
+                .header("Authorization", format!("Bearer {token}"));
+            if let Some(tenant_id) = tenant_header {
+                request = request.header("x-tenant-id", tenant_id);
+            }
+            app.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        let event = sample_event();
+        let response = app
+            .clone()
+            .oneshot(signed_package_request(&acme_device, &acme, &event))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let hash = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["hash"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        // Another tenant can neither see nor fetch it
+        let download = format!("/api/v1/events/{hash}/download");
+        let response = get(download.clone(), &globex, None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = get(format!("/api/v1/events/{hash}/verify"), &globex, None)
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["exists"], false);
+
+        // Naming the other tenant explicitly is refused outright
+        let response = get(download.clone(), &globex, Some("acme")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // The same event from the other tenant is stored in its own namespace; the extra
+        // claim only keeps the replay cache from matching the first submission
+        let mut request = signed_package_request(&globex_device, &globex, &event);
+        *request.body_mut() = Body::from(
+            serde_json::json!({
+                "jwtEventData": globex_device
+                    .sign_claims(serde_json::json!({ "payload": event, "jti": "globex" }))
+            })
+            .to_string(),
+        );
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let archives: Vec<String> = mock
+            .put_log()
+            .into_iter()
+            .filter(|key| key.ends_with(&format!("{hash}.zip")))
+            .collect();
+        assert_eq!(archives.len(), 2);
+        assert!(archives[0].starts_with("tenants/acme/events/"));
+        assert!(archives[1].starts_with("tenants/globex/events/"));
+
+        let response = get(download, &acme, Some("acme")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // An async submission's status is only visible to the submitting tenant
+        let mut request = signed_package_request(&acme_device, &acme, &event);
+        *request.uri_mut() = "/api/v1/events/package?async=true".parse().unwrap();
+        *request.body_mut() = Body::from(
+            serde_json::json!({
+                "jwtEventData": acme_device
+                    .sign_claims(serde_json::json!({ "payload": event, "jti": "acme-async" }))
+            })
+            .to_string(),
+        );
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let status_url = format!("/api/v1/events/{}/status", event.id);
+        let response = get(status_url.clone(), &globex, None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = get(status_url, &acme, None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,15 @@
+use axum::http::{Method, Uri};
+
+use crate::error::AppError;
+
+/// Fallback handler for unmatched routes
+/// Returns the same structured JSON error shape as the rest of the API
+pub async fn not_found(uri: Uri) -> AppError {
+    AppError::NotFound(format!("No route found for {}", uri.path()))
+}
+
+/// Fallback handler for known paths hit with an unsupported method
+/// axum adds the `Allow` header listing the methods the path does support
+pub async fn method_not_allowed(method: Method, uri: Uri) -> AppError {
+    AppError::MethodNotAllowed(format!("{method} is not supported for {}", uri.path()))
+}
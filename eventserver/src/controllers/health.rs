@@ -1,5 +1,69 @@
-use crate::types::api::{HealthResponse, ServiceHealthStatus};
-use axum::{http::StatusCode, response::Json};
+use crate::services::circuit_breaker::CircuitState;
+use crate::services::storage::StorageService;
+use crate::state::AppState;
+use crate::types::api::{HealthResponse, ReadinessResponse, ServiceHealthStatus};
+use axum::{extract::State, http::StatusCode, response::Json};
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How long `/health` and `/readyz` reuse a storage probe before listing the bucket again
+const STORAGE_PROBE_TTL: Duration = Duration::from_secs(5);
+
+/// Process start time and the outcome of past storage probes
+#[derive(Clone)]
+pub struct HealthTracker {
+    started_at: Instant,
+    last_storage_ok_at: Arc<RwLock<Option<DateTime<Utc>>>>,
+    last_storage_probe: Arc<tokio::sync::Mutex<Option<(Instant, bool)>>>, // When storage was last probed, and the outcome
+}
+
+impl Default for HealthTracker {
+    fn default() -> Self {
+        Self {
+            started_at: Instant::now(),
+            last_storage_ok_at: Arc::new(RwLock::new(None)),
+            last_storage_probe: Arc::new(tokio::sync::Mutex::new(None)),
+        }
+    }
+}
+
+impl HealthTracker {
+    /// Seconds elapsed since the tracker was created at startup
+    pub fn uptime_seconds(&self) -> f64 {
+        self.started_at.elapsed().as_secs_f64()
+    }
+
+    /// Probe storage, reusing the last outcome while it's younger than `STORAGE_PROBE_TTL`
+    /// Concurrent callers wait for the probe in flight instead of issuing their own
+    pub async fn probe_storage(&self, storage: &StorageService) -> bool {
+        let mut last_probe = self.last_storage_probe.lock().await;
+        if let Some((probed_at, ok)) = *last_probe {
+            if probed_at.elapsed() < STORAGE_PROBE_TTL {
+                return ok;
+            }
+        }
+
+        let ok = match storage.check_health().await {
+            Ok(()) => {
+                *self.last_storage_ok_at.write().unwrap() = Some(Utc::now());
+                true
+            }
+            Err(e) => {
+                warn!(error = %e, "Storage health probe failed");
+                false
+            }
+        };
+        *last_probe = Some((Instant::now(), ok));
+        ok
+    }
+
+    /// Timestamp of the last successful storage probe, if any
+    pub fn last_storage_ok_at(&self) -> Option<DateTime<Utc>> {
+        *self.last_storage_ok_at.read().unwrap()
+    }
+}
 
 /// Health check endpoint
 #[utoipa::path(
@@ -11,12 +75,162 @@ use axum::{http::StatusCode, response::Json};
     ),
     tag = "health"
 )]
-pub async fn health_check() -> Result<Json<HealthResponse>, StatusCode> {
-    // TODO: Implement actual health checks for services
-    let services = ServiceHealthStatus {
-        storage: true, // TODO: Check S3 connectivity
-    };
+pub async fn health_check(
+    State(state): State<AppState>,
+) -> Result<Json<HealthResponse>, StatusCode> {
+    let storage = state.health.probe_storage(&state.storage_service).await;
 
-    let health_response = HealthResponse::new(services);
+    let health_response = HealthResponse::new(
+        ServiceHealthStatus { storage },
+        state.health.uptime_seconds(),
+        state.health.last_storage_ok_at(),
+    );
     Ok(Json(health_response))
 }
+
+/// Readiness endpoint
+/// Follows the storage circuit breaker when it's enabled: not ready while it's open. A
+/// half-open breaker reports ready so that traffic can reach it and run the probe call.
+/// Without a breaker, falls back to the storage probe shared with `/health`
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (status = 200, description = "Ready to take traffic", body = ReadinessResponse),
+        (status = 503, description = "Storage unavailable", body = ReadinessResponse)
+    ),
+    tag = "health"
+)]
+pub async fn readiness(State(state): State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
+    let circuit_breaker = state.storage_service.circuit_breaker_status();
+    let ready = match &circuit_breaker {
+        Some(status) => status.state != CircuitState::Open,
+        None => state.health.probe_storage(&state.storage_service).await,
+    };
+
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(ReadinessResponse {
+            ready,
+            circuit_breaker,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn fetch_health(state: &AppState) -> serde_json::Value {
+        let response = crate::create_app(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/health")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    async fn fetch_readiness(state: &AppState) -> (StatusCode, serde_json::Value) {
+        let response = crate::create_app(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/readyz")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_readiness_follows_circuit_breaker() {
+        use crate::error::EventServerError;
+        use crate::services::storage::StorageService;
+
+        let mut state = AppState::new_mock(AppConfig::default()).await;
+        let (storage, breaker) = StorageService::new_mock()
+            .await
+            .with_circuit_breaker(2, Duration::from_secs(30));
+        state.storage_service = storage;
+
+        let (status, body) = fetch_readiness(&state).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["circuitBreaker"]["state"], "closed");
+
+        for _ in 0..2 {
+            breaker.record(Err(EventServerError::Storage("connection refused".into())));
+        }
+        let (status, body) = fetch_readiness(&state).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["ready"], false);
+        assert_eq!(body["circuitBreaker"]["state"], "open");
+        let until_half_open = body["circuitBreaker"]["secondsUntilHalfOpen"]
+            .as_u64()
+            .unwrap();
+        assert!((1..=30).contains(&until_half_open));
+
+        // A successful backend call closes the breaker
+        breaker.record(Ok(()));
+        let (status, body) = fetch_readiness(&state).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["ready"], true);
+        assert_eq!(body["circuitBreaker"]["state"], "closed");
+        assert!(body["circuitBreaker"]["secondsUntilHalfOpen"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_uptime_and_reuses_storage_probe() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+
+        let first = fetch_health(&state).await;
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        let second = fetch_health(&state).await;
+
+        assert_eq!(second["status"], "healthy");
+        assert!(second["version"].is_string());
+        assert!(
+            second["uptime_seconds"].as_f64().unwrap() > first["uptime_seconds"].as_f64().unwrap()
+        );
+
+        let first_ok: DateTime<Utc> = first["last_storage_ok_at"]
+            .as_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let second_ok: DateTime<Utc> = second["last_storage_ok_at"]
+            .as_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        // The second call within the TTL reuses the first probe
+        assert_eq!(second_ok, first_ok);
+
+        let (status, _) = fetch_readiness(&state).await;
+        assert_eq!(status, StatusCode::OK);
+        let third = fetch_health(&state).await;
+        assert_eq!(third["last_storage_ok_at"], first["last_storage_ok_at"]);
+    }
+}
@@ -0,0 +1,30 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Json},
+};
+
+use crate::state::AppState;
+
+/// Public keys clients can use to verify server signatures on stored archives and event
+/// receipts and, when they are EdDSA-signed, certificate tokens (matched by `kid`)
+#[utoipa::path(
+    get,
+    path = "/api/v1/jwks",
+    responses(
+        (status = 200, description = "JSON Web Key Set; empty when archive signing and receipts are disabled and certificate tokens are HMAC-signed")
+    ),
+    tag = "health"
+)]
+pub async fn jwks(State(state): State<AppState>) -> impl IntoResponse {
+    // Archives and receipts share the server key
+    let server_signer = state
+        .storage_service
+        .archive_signer()
+        .or(state.storage_service.receipt_signer());
+    let keys: Vec<serde_json::Value> = server_signer
+        .map(|signer| signer.jwk())
+        .into_iter()
+        .chain(state.certificate_service.signing_jwks())
+        .collect();
+    Json(serde_json::json!({ "keys": keys }))
+}
@@ -1,4 +1,10 @@
+pub mod admin;
+pub mod capabilities;
+pub mod certificate;
 pub mod event;
+pub mod fallback;
 pub mod health;
+pub mod jwks;
 pub mod openapi;
-// pub mod relay;
+pub mod relay;
+pub mod tools;
@@ -1,9 +1,15 @@
 use axum::{
-    http::StatusCode,
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     routing::get,
     Router,
 };
+use flate2::{write::GzEncoder, Compression};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::sync::OnceLock;
 pub use utoipa::Modify;
 use utoipa::{
     openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
@@ -11,13 +17,25 @@ use utoipa::{
 };
 use utoipa_swagger_ui::SwaggerUi;
 
-use crate::controllers::{event, health};
+use crate::config::{DocsConfig, PowAlgorithm};
+use crate::controllers::{admin, capabilities, certificate, event, health, jwks, tools};
 use crate::crypto::{
-    PowCertificateRequest, PowChallenge, PowChallengeResponse, PowSolution, TokenResponse,
+    EventReceipt, PowCertificateRequest, PowChallenge, PowChallengeRequest, PowChallengeResponse,
+    PowSolution, TokenResponse,
 };
+use crate::services::circuit_breaker::{CircuitBreakerStatus, CircuitState};
+use crate::services::jobs::JobStatus;
+use crate::services::storage::EventIndexEntry;
 use crate::state::AppState;
 use crate::types::{
-    api::{HealthResponse, ServiceHealthStatus},
+    api::{
+        ArchiveDiscrepancy, ArchiveValidationReport, CapabilitiesResponse,
+        CertificateStatusResponse, CrlResponse, EventBusStatsResponse, EventStatusResponse,
+        HealthResponse, IntrospectBatchResponse, KeyRotationResponse, ReadinessResponse,
+        ReindexResponse, ReplayFlushResponse, ReplayStatsResponse, RevocationResult,
+        RevokeBatchResponse, ServiceHealthStatus, SigningKeyRotationResponse,
+        TenantEnrollmentResponse, TokenIntrospection,
+    },
     event::{
         EventAnnotation, EventMedia, EventMetadata, EventPackage, EventPayload, EventSource,
         FieldValue, MediaType, ProcessingResult,
@@ -29,16 +47,41 @@ use crate::types::{
 #[openapi(
     paths(
         health::health_check,
+        health::readiness,
+        capabilities::capabilities,
+        jwks::jwks,
         event::receive_event,
         event::receive_event_package,
         event::verify_event_hash,
+        event::event_status,
+        event::download_event,
+        event::download_event_media,
+        event::download_annotations_csv,
         crate::request_pow_challenge,
         crate::verify_pow_and_issue_certificate,
+        certificate::certificate_status,
+        certificate::rotate_key,
+        certificate::revocation_list,
+        certificate::introspect_batch,
+        tools::validate_archive,
+        admin::export_events,
+        admin::event_index,
+        admin::reindex_event,
+        admin::replay_stats,
+        admin::event_bus_stats,
+        admin::flush_replay_cache,
+        admin::revoke_batch,
+        admin::rotate_signing_key,
+        admin::issue_tenant_enrollment,
+        admin::export_config_env,
     ),
     components(
         schemas(
             HealthResponse,
             ServiceHealthStatus,
+            ReadinessResponse,
+            CircuitBreakerStatus,
+            CircuitState,
             event::HashVerificationResponse,
             EventPackage,
             EventPayload,
@@ -53,13 +96,39 @@ use crate::types::{
             PowChallengeResponse,
             PowSolution,
             PowCertificateRequest,
+            PowChallengeRequest,
+            PowAlgorithm,
             TokenResponse,
+            EventReceipt,
+            CertificateStatusResponse,
+            certificate::RotateKeyRequest,
+            certificate::IntrospectBatchRequest,
+            IntrospectBatchResponse,
+            TokenIntrospection,
+            KeyRotationResponse,
+            CrlResponse,
+            ArchiveValidationReport,
+            ArchiveDiscrepancy,
+            CapabilitiesResponse,
+            ReplayStatsResponse,
+            EventBusStatsResponse,
+            ReplayFlushResponse,
+            ReindexResponse,
+            admin::RevokeBatchRequest,
+            RevokeBatchResponse,
+            RevocationResult,
+            SigningKeyRotationResponse,
+            TenantEnrollmentResponse,
+            EventIndexEntry,
+            EventStatusResponse,
+            JobStatus,
         )
     ),
     tags(
         (name = "health", description = "Health check endpoints"),
         (name = "events", description = "Event processing endpoints"),
-        (name = "authentication", description = "Authentication and PoW challenge endpoints")
+        (name = "authentication", description = "Authentication and PoW challenge endpoints"),
+        (name = "admin", description = "Operator endpoints guarded by the admin token")
     ),
     info(
         title = "EventServer API",
@@ -96,30 +165,126 @@ impl Modify for SecurityAddon {
     }
 }
 
-/// Create OpenAPI documentation routes
-pub fn routes() -> Router<AppState> {
+/// Create OpenAPI documentation routes, with the Swagger UI mounted at the configured path
+/// Nothing is registered when docs are disabled
+pub fn routes(docs: &DocsConfig) -> Router<AppState> {
+    if !docs.enabled {
+        return Router::new();
+    }
+
+    // Serialize the spec now rather than on the first request
+    json_spec();
+
     Router::new()
         .route("/openapi-json", get(openapi_json))
         .route("/openapi-yaml", get(openapi_yaml))
-        .merge(SwaggerUi::new("/docs").url("/openapi.json", ApiDoc::openapi()))
+        .merge(SwaggerUi::new(docs.path.clone()).url("/openapi.json", ApiDoc::openapi()))
 }
 
 /// Serve OpenAPI specification in JSON format
+/// The spec is cached at startup and gzipped for clients that accept it; the plain and gzipped
+/// representations carry different ETags
 #[utoipa::path(
     get,
     path = "/openapi.json",
     responses(
-        (status = 200, description = "OpenAPI specification in JSON format", content_type = "application/json")
+        (status = 200, description = "OpenAPI specification in JSON format", content_type = "application/json"),
+        (status = 304, description = "Spec unchanged since the ETag sent in If-None-Match")
     ),
     tag = "documentation"
 )]
+async fn openapi_json(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let Some(spec) = json_spec() else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
 
-async fn openapi_json() -> Response {
-    let spec = ApiDoc::openapi();
-    match serde_json::to_string_pretty(&spec) {
-        Ok(json) => (StatusCode::OK, [("content-type", "application/json")], json).into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    let gzip = state.config.docs.compress_spec && accepts_gzip(&headers);
+    let (body, etag) = if gzip {
+        (&spec.gzipped, &spec.gzipped_etag)
+    } else {
+        (&spec.json, &spec.etag)
+    };
+    let not_modified = if_none_match(&headers, etag);
+    let etag = HeaderValue::from_str(etag).expect("hex ETag is a valid header value");
+    let vary = (header::VARY, HeaderValue::from_static("accept-encoding"));
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag), vary]).into_response();
+    }
+
+    let mut response = ([(header::ETAG, etag), vary], body.clone()).into_response();
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    if gzip {
+        headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    }
+    response
+}
+
+/// The JSON spec, serialized and compressed once since it never changes at runtime
+struct CachedSpec {
+    json: Bytes,
+    gzipped: Bytes,
+    etag: String,
+    gzipped_etag: String,
+}
+
+static JSON_SPEC: OnceLock<Option<CachedSpec>> = OnceLock::new();
+
+fn json_spec() -> Option<&'static CachedSpec> {
+    JSON_SPEC
+        .get_or_init(|| {
+            let json = serde_json::to_vec_pretty(&ApiDoc::openapi()).ok()?;
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(&json).ok()?;
+            let gzipped = encoder.finish().ok()?;
+            let digest = &hex::encode(Sha256::digest(&json))[..32];
+            Some(CachedSpec {
+                json: json.into(),
+                gzipped: gzipped.into(),
+                etag: format!("\"{digest}\""),
+                gzipped_etag: format!("\"{digest}-gzip\""),
+            })
+        })
+        .as_ref()
+}
+
+/// Whether `If-None-Match` lists `etag` (or `*`)
+fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == etag || tag == "*")
+}
+
+/// Whether `Accept-Encoding` allows gzip
+/// An explicit `gzip` entry takes precedence over `*`, and a q-value of 0 refuses the coding
+fn accepts_gzip(headers: &HeaderMap) -> bool {
+    let (mut gzip, mut any) = (None, None);
+    for coding in headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+    {
+        let mut parts = coding.split(';').map(str::trim);
+        let name = parts.next().unwrap_or_default();
+        // Unparseable weights count as a refusal
+        let q = parts
+            .find_map(|param| param.strip_prefix("q="))
+            .map_or(1.0, |q| q.parse::<f32>().unwrap_or(0.0));
+        if name.eq_ignore_ascii_case("gzip") {
+            gzip = Some(q);
+        } else if name == "*" {
+            any = Some(q);
+        }
     }
+    gzip.or(any).is_some_and(|q| q > 0.0)
 }
 
 /// Serve OpenAPI specification in YAML format
@@ -138,3 +303,140 @@ async fn openapi_yaml() -> Response {
         Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::json_spec;
+    use crate::config::AppConfig;
+    use crate::state::AppState;
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use std::io::Read;
+    use tower::ServiceExt;
+
+    async fn get_status(config: &AppConfig, uri: &str) -> StatusCode {
+        let state = AppState::new_mock(config.clone()).await;
+        crate::create_app(state)
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_disabled_docs_are_not_served() {
+        let mut config = AppConfig::default();
+        assert_eq!(get_status(&config, "/docs/").await, StatusCode::OK);
+
+        config.docs.enabled = false;
+        for uri in ["/docs", "/docs/", "/openapi.json", "/openapi-json"] {
+            assert_eq!(
+                get_status(&config, uri).await,
+                StatusCode::NOT_FOUND,
+                "{uri}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_docs_path_is_configurable() {
+        let mut config = AppConfig::default();
+        config.docs.path = "/internal/api-docs".to_string();
+
+        assert_eq!(
+            get_status(&config, "/internal/api-docs/").await,
+            StatusCode::OK
+        );
+        assert_eq!(get_status(&config, "/docs/").await, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_spec_served_from_cache_with_etag() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let app = crate::create_app(state);
+        let get = |request: axum::http::request::Builder| {
+            app.clone()
+                .oneshot(request.uri("/openapi-json").body(Body::empty()).unwrap())
+        };
+
+        let first = get(Request::builder()).await.unwrap();
+        let second = get(Request::builder()).await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        let etag = first.headers()[header::ETAG].clone();
+        assert_eq!(second.headers()[header::ETAG], etag);
+        let body = axum::body::to_bytes(first.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        // Served straight from the cached bytes
+        assert_eq!(body, json_spec().unwrap().json);
+        assert!(serde_json::from_slice::<serde_json::Value>(&body).is_ok());
+
+        let not_modified = get(Request::builder().header(header::IF_NONE_MATCH, etag.clone()))
+            .await
+            .unwrap();
+        assert_eq!(not_modified.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(not_modified.headers()[header::ETAG], etag);
+
+        let gzipped = get(Request::builder().header(header::ACCEPT_ENCODING, "br, gzip"))
+            .await
+            .unwrap();
+        assert_eq!(gzipped.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(gzipped.headers()[header::VARY], "accept-encoding");
+        // Each representation has its own ETag, and only matches itself
+        let gzipped_etag = gzipped.headers()[header::ETAG].clone();
+        assert_ne!(gzipped_etag, etag);
+        let revalidated = get(Request::builder()
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .header(header::IF_NONE_MATCH, etag.clone()))
+        .await
+        .unwrap();
+        assert_eq!(revalidated.status(), StatusCode::OK);
+        assert_eq!(revalidated.headers()[header::ETAG], gzipped_etag);
+        let compressed = axum::body::to_bytes(gzipped.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(compressed.len() < body.len());
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(&compressed[..])
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, body);
+    }
+
+    #[test]
+    fn test_accept_encoding_negotiation() {
+        let accepts = |value: &str| {
+            let mut headers = axum::http::HeaderMap::new();
+            headers.insert(header::ACCEPT_ENCODING, value.parse().unwrap());
+            super::accepts_gzip(&headers)
+        };
+        assert!(accepts("gzip"));
+        assert!(accepts("br;q=1.0, GZIP;q=0.5"));
+        assert!(accepts("*"));
+        assert!(!accepts("br"));
+        assert!(!accepts("gzip;q=0"));
+        // An explicit refusal wins over the wildcard, in either order
+        assert!(!accepts("*, gzip;q=0"));
+        assert!(!accepts("gzip;q=0, *"));
+        assert!(!accepts("*;q=0"));
+        assert!(!super::accepts_gzip(&axum::http::HeaderMap::new()));
+    }
+
+    #[tokio::test]
+    async fn test_spec_compression_can_be_disabled() {
+        let mut config = AppConfig::default();
+        config.docs.compress_spec = false;
+        let response = crate::create_app(AppState::new_mock(config).await)
+            .oneshot(
+                Request::builder()
+                    .uri("/openapi-json")
+                    .header(header::ACCEPT_ENCODING, "gzip")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+    }
+}
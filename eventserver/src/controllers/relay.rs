@@ -2,20 +2,23 @@ use axum::{
     extract::State,
     http::StatusCode,
     response::Json,
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 use tracing::{error, info, warn};
 
 use crate::error::EventServerError;
 use crate::state::AppState;
-use crate::types::relay::{ProvisionRequest, ProvisionResult, RelayInfo};
+use crate::types::relay::{ProvisionRequest, ProvisionResult, RelayInfo, RelayStatus};
 
-/// Create relay-related routes
+/// Create relay-related routes, mounted under the admin API
+/// The registry they manage is what `enforce_relay_status` checks
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/relays/provision", post(provision_relay))
         .route("/relays", get(list_relays))
+        .route("/relays/:id", delete(decommission_relay))
+        .route("/relays/:id/status", put(set_relay_status))
         .route("/relays/:id/health", get(check_relay_health))
         .route("/relays/stats", get(get_network_stats))
 }
@@ -62,6 +65,40 @@ async fn provision_relay(
     }
 }
 
+/// Register a relay, or change the status of a registered one
+/// Registering a draining relay as active again cancels its removal
+async fn set_relay_status(
+    State(state): State<AppState>,
+    axum::extract::Path(relay_id): axum::extract::Path<String>,
+    Json(request): Json<RelayStatusRequest>,
+) -> Json<RelayStatusResponse> {
+    info!(relay_id = %relay_id, status = ?request.status, "Setting relay status");
+    state
+        .relay_service
+        .set_relay_status(&relay_id, request.status.clone());
+    Json(RelayStatusResponse {
+        relay_id,
+        status: request.status,
+    })
+}
+
+/// Decommission a relay; it keeps submitting until its drain window ends
+async fn decommission_relay(
+    State(state): State<AppState>,
+    axum::extract::Path(relay_id): axum::extract::Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match state.relay_service.decommission_relay(&relay_id).await {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => {
+            error!(relay_id = %relay_id, error = %e, "Error decommissioning relay");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to decommission relay".to_string(),
+            ))
+        }
+    }
+}
+
 /// List approved relays
 /// Stateless - queries external systems for current relay list
 async fn list_relays(
@@ -154,6 +191,21 @@ async fn get_network_stats(
     }
 }
 
+/// Request to set a relay's status
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayStatusRequest {
+    pub status: RelayStatus,
+}
+
+/// Response for a relay status change
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayStatusResponse {
+    pub relay_id: String,
+    pub status: RelayStatus,
+}
+
 /// Response for relay list
 #[derive(serde::Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -186,3 +238,101 @@ pub struct NetworkStatsResponse {
     pub network_uptime_percentage: f64,
     pub retrieved_at: chrono::DateTime<chrono::Utc>,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config::AppConfig;
+    use crate::crypto::MockClock;
+    use crate::state::AppState;
+    use crate::test_utils::{issue_token, sample_event, signed_package_request, DeviceKey};
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    const ADMIN_TOKEN: &str = "test-admin-token";
+
+    fn admin_request(method: &str, uri: &str, body: &str) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {ADMIN_TOKEN}"))
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_relays_registered_through_admin_api_are_enforced() {
+        let mut config = AppConfig::default();
+        config.security.enforce_relay_status = true;
+        config.security.admin_token = Some(ADMIN_TOKEN.to_string());
+        let mut state = AppState::new_mock(config).await;
+        let clock = MockClock::new();
+        state.relay_service = state
+            .relay_service
+            .clone()
+            .with_clock(Arc::new(clock.clone()));
+        let device = DeviceKey::generate();
+        let token = issue_token(&state, &device);
+        let submit = || {
+            crate::create_app(state.clone()).oneshot(signed_package_request(
+                &device,
+                &token,
+                &sample_event(),
+            ))
+        };
+        assert_eq!(submit().await.unwrap().status(), StatusCode::FORBIDDEN);
+
+        let response = crate::create_app(state.clone())
+            .oneshot(admin_request(
+                "PUT",
+                "/api/v1/admin/relays/test_relay/status",
+                r#"{"status":"active"}"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["relayId"], "test_relay");
+        assert_eq!(json["status"], "active");
+        assert_eq!(submit().await.unwrap().status(), StatusCode::OK);
+
+        // Decommissioned relays drain, then are refused
+        let response = crate::create_app(state.clone())
+            .oneshot(admin_request(
+                "DELETE",
+                "/api/v1/admin/relays/test_relay",
+                "",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(submit().await.unwrap().status(), StatusCode::OK);
+        clock.advance(chrono::Duration::seconds(300));
+        assert_eq!(submit().await.unwrap().status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_relay_routes_require_admin_token() {
+        let mut config = AppConfig::default();
+        config.security.admin_token = Some(ADMIN_TOKEN.to_string());
+        let state = AppState::new_mock(config).await;
+
+        let response = crate::create_app(state)
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/api/v1/admin/relays/test_relay/status")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"status":"active"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,129 @@
+use axum::{body::Bytes, extract::State, response::Json, routing::post, Router};
+use tracing::info;
+
+use crate::error::AppError;
+use crate::services::archive_validator;
+use crate::state::AppState;
+use crate::types::api::ArchiveValidationReport;
+
+/// Create developer tool routes
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/tools/validate-archive", post(validate_archive))
+}
+
+/// Check a client-built ZIP against the layout the server produces for an event package
+/// The upload is capped at `server.max_body_bytes`, and each entry is only inflated up to
+/// `storage.max_file_size`. Inflating and hashing share the ZIP packaging slots
+#[utoipa::path(
+    post,
+    path = "/api/v1/tools/validate-archive",
+    request_body(content = Vec<u8>, content_type = "application/zip", description = "ZIP archive to check"),
+    responses(
+        (status = 200, description = "Conformance report", body = ArchiveValidationReport),
+        (status = 401, description = "Missing or invalid certificate"),
+        (status = 413, description = "Archive larger than server.max_body_bytes"),
+        (status = 422, description = "Body is not a readable ZIP archive")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "events"
+)]
+pub async fn validate_archive(
+    State(state): State<AppState>,
+    body: Bytes,
+) -> Result<Json<ArchiveValidationReport>, AppError> {
+    let size = body.len();
+    let max_entry_bytes = state.config.storage.max_file_size;
+    let report = state
+        .zip_packaging
+        .run(move || archive_validator::validate_archive(&body, max_entry_bytes))
+        .await??;
+    info!(
+        size,
+        conformant = report.conformant,
+        discrepancies = report.discrepancies.len(),
+        "Validated client-built archive"
+    );
+    Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::services::zip_packager::{ZipPackageOptions, ZipPackager};
+    use crate::test_utils::{issue_token, sample_event, DeviceKey};
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use std::io::{Cursor, Write};
+    use tower::ServiceExt;
+    use zip::{write::FileOptions, ZipWriter};
+
+    async fn post_archive(
+        state: AppState,
+        token: Option<&str>,
+        archive: Vec<u8>,
+    ) -> (StatusCode, serde_json::Value) {
+        let mut request = Request::post("/api/v1/tools/validate-archive")
+            .header(header::CONTENT_TYPE, "application/zip");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let response = crate::create_app(state)
+            .oneshot(request.body(Body::from(archive)).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_validate_archive_reports_conformance() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let token = issue_token(&state, &DeviceKey::generate());
+
+        let archive = ZipPackager::create_zip_from_event_package(
+            &sample_event(),
+            ZipPackageOptions::default(),
+        )
+        .unwrap();
+        let (status, json) = post_archive(state.clone(), Some(&token), archive).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["conformant"], true);
+
+        // Only metadata.json: annotations.json is reported missing
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("metadata.json", FileOptions::default())
+            .unwrap();
+        zip.write_all(br#"{"id":"x","version":"1.0","createdAt":"2024-01-01T00:00:00Z","createdBy":null,"source":"web","annotationCount":0,"hasMedia":false}"#)
+            .unwrap();
+        let archive = zip.finish().unwrap().into_inner();
+        let (status, json) = post_archive(state.clone(), Some(&token), archive.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["conformant"], false);
+        assert_eq!(json["discrepancies"][0]["entry"], "annotations.json");
+        assert_eq!(
+            json["discrepancies"][0]["problem"],
+            "Missing required entry"
+        );
+
+        let (status, _) = post_archive(state, None, archive).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_validate_archive_bounds_size() {
+        let mut config = AppConfig::default();
+        config.server.max_body_bytes = 64;
+        let state = AppState::new_mock(config).await;
+        let token = issue_token(&state, &DeviceKey::generate());
+
+        let (status, json) = post_archive(state, Some(&token), vec![0u8; 65]).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(json["error"].is_string());
+    }
+}
@@ -0,0 +1,185 @@
+use base64::{engine::general_purpose, Engine as _};
+#[cfg(test)]
+use ed25519_dalek::{Signature, Verifier};
+use ed25519_dalek::{Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::error::EventServerError;
+
+/// Detached signature over a stored archive
+/// The Ed25519 signature covers the raw SHA-256 digest of the archive bytes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveSignature {
+    pub algorithm: String, // Always "Ed25519"
+    pub key_id: String,    // Matches the `kid` of the key published at /api/v1/jwks
+    pub sha256: String,    // Hex digest of the archive
+    pub signature: String, // Base64 signature over the digest bytes
+}
+
+/// Signs stored ZIP archives with the server's Ed25519 key
+#[derive(Clone)]
+pub struct ArchiveSigner {
+    signing_key: SigningKey,
+    key_id: String,
+}
+
+impl std::fmt::Debug for ArchiveSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArchiveSigner")
+            .field("key_id", &self.key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ArchiveSigner {
+    /// Build a signer from a base64 32-byte seed, or a random key when none is configured
+    /// A random key changes on every restart, so signatures only verify against the JWKS
+    /// published by the process that made them
+    pub fn new(seed: Option<&str>) -> Result<Self, EventServerError> {
+        let signing_key = match seed.filter(|seed| !seed.is_empty()) {
+            Some(seed) => {
+                let bytes: [u8; 32] = general_purpose::STANDARD
+                    .decode(seed)
+                    .ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or_else(|| {
+                        EventServerError::Config(
+                            "storage.archive_signing_key must be a base64 32-byte seed".to_string(),
+                        )
+                    })?;
+                SigningKey::from_bytes(&bytes)
+            }
+            None => {
+                warn!("No archive signing key configured, using an ephemeral key");
+                SigningKey::from_bytes(&rand::random())
+            }
+        };
+        Ok(Self::from_signing_key(signing_key))
+    }
+
+    fn from_signing_key(signing_key: SigningKey) -> Self {
+        let key_id = ed25519_key_id(&signing_key.verifying_key());
+        Self {
+            signing_key,
+            key_id,
+        }
+    }
+
+    /// Key ID published as the JWK `kid`
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Sign the SHA-256 digest of `archive`
+    pub fn sign(&self, archive: &[u8]) -> ArchiveSignature {
+        let digest = Sha256::digest(archive);
+        ArchiveSignature {
+            algorithm: "Ed25519".to_string(),
+            key_id: self.key_id.clone(),
+            sha256: hex::encode(digest),
+            signature: self.sign_message(&digest),
+        }
+    }
+
+    /// Check `signature` against `archive` and this signer's public key
+    #[cfg(test)]
+    pub fn verify(&self, archive: &[u8], signature: &ArchiveSignature) -> bool {
+        let digest = Sha256::digest(archive);
+        signature.key_id == self.key_id
+            && signature.sha256 == hex::encode(digest)
+            && self.verify_message(&digest, &signature.signature)
+    }
+
+    /// Base64 Ed25519 signature over `message`
+    pub(crate) fn sign_message(&self, message: &[u8]) -> String {
+        general_purpose::STANDARD.encode(self.signing_key.sign(message).to_bytes())
+    }
+
+    /// Check a base64 signature made by `sign_message`
+    #[cfg(test)]
+    pub(crate) fn verify_message(&self, message: &[u8], signature: &str) -> bool {
+        let Some(bytes) = general_purpose::STANDARD
+            .decode(signature)
+            .ok()
+            .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+        else {
+            return false;
+        };
+        self.signing_key
+            .verifying_key()
+            .verify(message, &Signature::from_bytes(&bytes))
+            .is_ok()
+    }
+
+    /// Public key as a JSON Web Key (RFC 8037 OKP)
+    pub fn jwk(&self) -> serde_json::Value {
+        ed25519_jwk(&self.key_id, &self.signing_key.verifying_key())
+    }
+}
+
+/// Key ID of an Ed25519 public key: the first 16 hex digits of its SHA-256
+pub(crate) fn ed25519_key_id(public_key: &VerifyingKey) -> String {
+    hex::encode(Sha256::digest(public_key.to_bytes()))[..16].to_string()
+}
+
+/// Ed25519 public key as a JSON Web Key (RFC 8037 OKP)
+pub(crate) fn ed25519_jwk(key_id: &str, public_key: &VerifyingKey) -> serde_json::Value {
+    serde_json::json!({
+        "kty": "OKP",
+        "crv": "Ed25519",
+        "use": "sig",
+        "alg": "EdDSA",
+        "kid": key_id,
+        "x": general_purpose::URL_SAFE_NO_PAD.encode(public_key.to_bytes()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_verifies_and_rejects_tampering() {
+        let seed = general_purpose::STANDARD.encode([7u8; 32]);
+        let signer = ArchiveSigner::new(Some(&seed)).unwrap();
+        let archive = b"PK\x03\x04 archive bytes".to_vec();
+        let signature = signer.sign(&archive);
+        assert!(signer.verify(&archive, &signature));
+
+        let mut tampered = archive.clone();
+        tampered[5] ^= 0x01;
+        assert!(!signer.verify(&tampered, &signature));
+
+        // The published JWK alone is enough to verify
+        let jwk = signer.jwk();
+        assert_eq!(jwk["kid"], signature.key_id);
+        let x: [u8; 32] = general_purpose::URL_SAFE_NO_PAD
+            .decode(jwk["x"].as_str().unwrap())
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let sig: [u8; 64] = general_purpose::STANDARD
+            .decode(&signature.signature)
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let key = VerifyingKey::from_bytes(&x).unwrap();
+        let sig = Signature::from_bytes(&sig);
+        assert!(key.verify(&Sha256::digest(&archive), &sig).is_ok());
+        assert!(key.verify(&Sha256::digest(&tampered), &sig).is_err());
+    }
+
+    #[test]
+    fn test_invalid_seed_rejected() {
+        assert!(matches!(
+            ArchiveSigner::new(Some("not-base64!")),
+            Err(EventServerError::Config(_))
+        ));
+        let short = general_purpose::STANDARD.encode([1u8; 16]);
+        assert!(ArchiveSigner::new(Some(&short)).is_err());
+        assert!(ArchiveSigner::new(None).is_ok());
+    }
+}
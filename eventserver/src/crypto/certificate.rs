@@ -1,13 +1,24 @@
 use base64::Engine;
 use chrono::{DateTime, Duration, Utc};
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
+use tracing::warn;
 
-use crate::error::EventServerError;
+use crate::config::CertCapPolicy;
+use crate::crypto::{CertificateKeyring, Clock, StoredKeyring, SystemClock, ValidationCache};
+use crate::error::{AuthFailure, EventServerError};
+
+/// Validations memoized by default; see `with_validation_cache_size`
+const DEFAULT_VALIDATION_CACHE_SIZE: usize = 1024;
+
+/// Retry-After hint when issuance is refused because the certificate cap is reached
+const CAP_REJECT_RETRY_SECONDS: u64 = 60;
 
 /// JWT claims for device certificates
 #[derive(Debug, Serialize, Deserialize)]
@@ -33,6 +44,54 @@ pub struct DeviceCertificate {
     pub issued_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
     pub signature: String, // Server signature of the certificate
+    /// Key replaced by the last rotation, still accepted until `previous_key_expires_at`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_public_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_key_expires_at: Option<DateTime<Utc>>,
+    /// Base64 Ed25519 key that must sign event packages in dual-signature mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ed25519_public_key: Option<String>,
+    /// Tenant the certificate was issued for; its events are stored in that tenant's namespace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+}
+
+impl DeviceCertificate {
+    /// Data covered by the server signature
+    fn signing_input(&self) -> String {
+        certificate_data(
+            &self.certificate_id,
+            &self.relay_id,
+            &self.public_key,
+            self.ed25519_public_key.as_deref(),
+            self.tenant_id.as_deref(),
+            self.expires_at,
+        )
+    }
+}
+
+/// Certificate signing input; the Ed25519 key and tenant are appended only when bound, so
+/// certificates issued without them keep their original signature
+fn certificate_data(
+    certificate_id: &str,
+    relay_id: &str,
+    public_key: &str,
+    ed25519_public_key: Option<&str>,
+    tenant_id: Option<&str>,
+    expires_at: DateTime<Utc>,
+) -> String {
+    let mut data = format!(
+        "{certificate_id}:{relay_id}:{public_key}:{}",
+        expires_at.timestamp()
+    );
+    if let Some(ed25519_public_key) = ed25519_public_key {
+        data = format!("{data}:{ed25519_public_key}");
+    }
+    if let Some(tenant_id) = tenant_id {
+        data = format!("{data}:tenant={tenant_id}");
+    }
+    data
 }
 
 /// Certificate request after PoW verification
@@ -40,29 +99,77 @@ pub struct DeviceCertificate {
 pub struct CertificateRequest {
     pub relay_id: String,
     pub public_key: String, // JWK format P-256 public key
+    #[serde(default)]
+    pub ed25519_public_key: Option<String>, // Base64 Ed25519 key bound for dual-signature mode
+    #[serde(default)]
+    pub tenant_id: Option<String>, // Tenant claim bound into the certificate
 }
 
 /// Certificate response returned to client
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CertificateResponse {
     pub cert_token: String, // JWT-like token for easy validation
+    #[serde(skip)]
+    pub certificate_id: String, // Server-side ID, not exposed to clients
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub expires_in_seconds: i64, // Remaining validity at issuance, so clients needn't diff clocks
 }
 
 /// Certificate validation result
 #[derive(Debug, Clone)]
 #[allow(unused)]
 pub struct CertificateValidation {
+    pub certificate_id: String,
     pub relay_id: String,
     pub public_key: String,
     pub expires_at: DateTime<Utc>,
+    /// Pre-rotation key, only set while its grace window is open
+    pub previous_public_key: Option<String>,
+    /// Ed25519 key bound at issuance, if any
+    pub ed25519_public_key: Option<String>,
+    /// Tenant claim bound at issuance, if any
+    pub tenant_id: Option<String>,
+}
+
+/// A revoked certificate, kept until the token would have expired anyway
+/// Persisted so revocations survive restarts and reach other instances
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Revocation {
+    pub certificate_id: String,
+    pub expires_at: DateTime<Utc>,
+    pub tenant_id: Option<String>, // Tenant the certificate was bound to, so the CRL can be scoped
+}
+
+impl From<DeviceCertificate> for Revocation {
+    fn from(certificate: DeviceCertificate) -> Self {
+        Self {
+            certificate_id: certificate.certificate_id,
+            expires_at: certificate.expires_at,
+            tenant_id: certificate.tenant_id,
+        }
+    }
 }
 
 /// Certificate service for managing device certificates
 #[derive(Debug, Clone)]
 pub struct CertificateService {
     certificates: Arc<Mutex<HashMap<String, DeviceCertificate>>>,
+    revoked: Arc<Mutex<HashMap<String, Revocation>>>, // Revoked certificate ID -> revocation
+    revoked_updated_at: Arc<Mutex<DateTime<Utc>>>,    // Last change to the revocation list
     certificate_lifetime: Duration,
-    jwt_secret: String, // JWT secret for signing tokens
+    jwt_secret: String,         // JWT secret for signing tokens
+    token_algorithm: Algorithm, // Algorithm used to sign and verify certificate tokens
+    signing_keys: Option<Arc<Mutex<CertificateKeyring>>>, // Ed25519 keys when tokens are EdDSA-signed
+    signing_key_updates: Arc<tokio::sync::Mutex<()>>, // Serializes keyring rotations and reloads
+    max_active: usize,                                // Cap on stored certificates (0 = unlimited)
+    cap_policy: CertCapPolicy,                        // Reject or evict once the cap is reached
+    clock: Arc<dyn Clock>,                            // Time source for issuance and expiry
+    key_rotation_grace: Duration, // How long the previous key stays valid after a rotation
+    previous_jwt_secret: Option<(String, DateTime<Utc>)>, // Rotated-out secret and when it stops verifying
+    validation_cache: ValidationCache, // Memoized validations of recently seen tokens
+    max_accepted_age: Option<Duration>, // Older certificates must be renewed even if unexpired
 }
 
 impl CertificateService {
@@ -70,8 +177,20 @@ impl CertificateService {
     pub fn new(jwt_secret: String) -> Self {
         Self {
             certificates: Arc::new(Mutex::new(HashMap::new())),
+            revoked: Arc::new(Mutex::new(HashMap::new())),
+            revoked_updated_at: Arc::new(Mutex::new(SystemClock.now())),
             certificate_lifetime: Duration::hours(24), // Certificates valid for 24 hours
             jwt_secret,
+            token_algorithm: Algorithm::HS256,
+            signing_keys: None,
+            signing_key_updates: Arc::new(tokio::sync::Mutex::new(())),
+            max_active: 0,
+            cap_policy: CertCapPolicy::Reject,
+            clock: Arc::new(SystemClock),
+            key_rotation_grace: Duration::minutes(10),
+            previous_jwt_secret: None,
+            validation_cache: ValidationCache::new(DEFAULT_VALIDATION_CACHE_SIZE),
+            max_accepted_age: None,
         }
     }
 
@@ -80,11 +199,83 @@ impl CertificateService {
     pub fn with_params(lifetime_hours: i64, jwt_secret: String) -> Self {
         Self {
             certificates: Arc::new(Mutex::new(HashMap::new())),
+            revoked: Arc::new(Mutex::new(HashMap::new())),
+            revoked_updated_at: Arc::new(Mutex::new(SystemClock.now())),
             certificate_lifetime: Duration::hours(lifetime_hours),
             jwt_secret,
+            token_algorithm: Algorithm::HS256,
+            signing_keys: None,
+            signing_key_updates: Arc::new(tokio::sync::Mutex::new(())),
+            max_active: 0,
+            cap_policy: CertCapPolicy::Reject,
+            clock: Arc::new(SystemClock),
+            key_rotation_grace: Duration::minutes(10),
+            previous_jwt_secret: None,
+            validation_cache: ValidationCache::new(DEFAULT_VALIDATION_CACHE_SIZE),
+            max_accepted_age: None,
         }
     }
 
+    /// Memoize up to `size` token validations; 0 disables the cache
+    pub fn with_validation_cache_size(mut self, size: usize) -> Self {
+        self.validation_cache = ValidationCache::new(size);
+        self
+    }
+
+    /// Reject certificates issued more than `max_age` ago, even before they expire
+    pub fn with_max_accepted_age(mut self, max_age: Option<Duration>) -> Self {
+        self.max_accepted_age = max_age;
+        self
+    }
+
+    /// Issue certificates valid for `lifetime`
+    pub fn with_certificate_lifetime(mut self, lifetime: Duration) -> Self {
+        self.certificate_lifetime = lifetime;
+        self
+    }
+
+    /// Sign and verify certificate tokens with the given HMAC algorithm
+    pub fn with_token_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.token_algorithm = algorithm;
+        self
+    }
+
+    /// Sign certificate tokens with EdDSA under `keyring`'s primary key, published in the JWKS
+    /// so tokens can be verified without the server secret
+    pub fn with_signing_keys(mut self, keyring: CertificateKeyring) -> Self {
+        self.token_algorithm = Algorithm::EdDSA;
+        self.signing_keys = Some(Arc::new(Mutex::new(keyring)));
+        self
+    }
+
+    /// Bound the number of stored certificates; `max_active` of 0 means unlimited
+    pub fn with_capacity_limit(mut self, max_active: usize, cap_policy: CertCapPolicy) -> Self {
+        self.max_active = max_active;
+        self.cap_policy = cap_policy;
+        self
+    }
+
+    /// Use a custom time source for issuance and expiry checks
+    #[cfg(test)]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.revoked_updated_at = Arc::new(Mutex::new(clock.now()));
+        self.clock = clock;
+        self
+    }
+
+    /// Keep accepting a rotated-out device key for `grace` after the rotation
+    pub fn with_key_rotation_grace(mut self, grace: Duration) -> Self {
+        self.key_rotation_grace = grace;
+        self
+    }
+
+    /// Keep verifying tokens and certificates signed with a rotated-out secret until `valid_until`
+    /// New tokens are always signed with the current secret
+    pub fn with_previous_secret(mut self, secret: String, valid_until: DateTime<Utc>) -> Self {
+        self.previous_jwt_secret = Some((secret, valid_until));
+        self
+    }
+
     /// Issue a new device certificate
     pub fn issue_certificate(
         &self,
@@ -94,16 +285,17 @@ impl CertificateService {
         self.cleanup_expired_certificates();
 
         let certificate_id = self.generate_certificate_id();
-        let now = Utc::now();
+        let now = self.clock.now();
         let expires_at = now + self.certificate_lifetime;
 
         // Create certificate data for signing
-        let cert_data = format!(
-            "{}:{}:{}:{}",
-            certificate_id,
-            request.relay_id,
-            request.public_key,
-            expires_at.timestamp()
+        let cert_data = certificate_data(
+            &certificate_id,
+            &request.relay_id,
+            &request.public_key,
+            request.ed25519_public_key.as_deref(),
+            request.tenant_id.as_deref(),
+            expires_at,
         );
 
         // Sign the certificate with server's private key
@@ -116,21 +308,69 @@ impl CertificateService {
             issued_at: now,
             expires_at,
             signature,
+            previous_public_key: None,
+            previous_key_expires_at: None,
+            ed25519_public_key: request.ed25519_public_key.clone(),
+            tenant_id: request.tenant_id.clone(),
         };
 
         // Generate JWT-like token for easy validation
         let cert_token = self.generate_certificate_token(&certificate)?;
 
-        // Store the certificate
+        // Store the certificate, making room first if the cap is reached
         {
             let mut certificates = self.certificates.lock().unwrap();
-            certificates.insert(certificate_id, certificate.clone());
+            if self.max_active > 0 && certificates.len() >= self.max_active {
+                self.apply_cap_policy(&mut certificates)?;
+            }
+            certificates.insert(certificate_id.clone(), certificate.clone());
         }
 
         // Note: Cleanup of expired certificates is handled during both issuance and validation
         // to ensure optimal memory management and remove stale certificates proactively
 
-        Ok(CertificateResponse { cert_token })
+        Ok(CertificateResponse {
+            cert_token,
+            certificate_id,
+            issued_at: now,
+            expires_at,
+            expires_in_seconds: self.certificate_lifetime.num_seconds(),
+        })
+    }
+
+    /// Enforce the active certificate cap before inserting a new certificate
+    fn apply_cap_policy(
+        &self,
+        certificates: &mut HashMap<String, DeviceCertificate>,
+    ) -> Result<(), EventServerError> {
+        match self.cap_policy {
+            CertCapPolicy::Reject => {
+                warn!(
+                    max_active = self.max_active,
+                    "Active certificate cap reached, rejecting issuance"
+                );
+                Err(EventServerError::ServiceUnavailable {
+                    message: "Certificate capacity reached".to_string(),
+                    retry_after_seconds: Some(CAP_REJECT_RETRY_SECONDS),
+                })
+            }
+            CertCapPolicy::Evict => {
+                let soonest = certificates
+                    .values()
+                    .min_by_key(|certificate| certificate.expires_at)
+                    .map(|certificate| certificate.certificate_id.clone());
+                if let Some(certificate_id) = soonest {
+                    certificates.remove(&certificate_id);
+                    self.validation_cache.invalidate(&certificate_id);
+                    warn!(
+                        max_active = self.max_active,
+                        certificate_id = %certificate_id,
+                        "Active certificate cap reached, evicted certificate closest to expiry"
+                    );
+                }
+                Ok(())
+            }
+        }
     }
 
     /// Validate a certificate token
@@ -138,53 +378,301 @@ impl CertificateService {
         &self,
         token: &str,
     ) -> Result<CertificateValidation, EventServerError> {
+        // Tokens seen recently skip the signature checks, but never revocation or eviction:
+        // those are rechecked on every hit in case an update path missed the cache
+        if let Some(validation) = self.validation_cache.get(token, self.clock.now()) {
+            let revoked = self
+                .revoked
+                .lock()
+                .unwrap()
+                .contains_key(&validation.certificate_id);
+            let present = self
+                .certificates
+                .lock()
+                .unwrap()
+                .contains_key(&validation.certificate_id);
+            if !revoked && present {
+                return Ok(validation);
+            }
+            self.validation_cache.invalidate(&validation.certificate_id);
+        }
+
         // Clean up expired certificates first
         self.cleanup_expired_certificates();
 
         // Parse the token to extract certificate ID
         let certificate_id = self.extract_certificate_id_from_token(token)?;
 
+        if self.revoked.lock().unwrap().contains_key(&certificate_id) {
+            return Err(EventServerError::auth(
+                AuthFailure::CertRevoked,
+                "Certificate has been revoked",
+            ));
+        }
+
         // Get the certificate from storage
         let certificate = {
             let certificates = self.certificates.lock().unwrap();
-            certificates
-                .get(&certificate_id)
-                .cloned()
-                .ok_or_else(|| EventServerError::Validation("Certificate not found".to_string()))?
+            certificates.get(&certificate_id).cloned().ok_or_else(|| {
+                EventServerError::auth(AuthFailure::CertNotFound, "Certificate not found")
+            })?
         };
 
         // Check if certificate is expired
-        if Utc::now() > certificate.expires_at {
+        if self.clock.now() > certificate.expires_at {
             // Remove expired certificate
             {
                 let mut certificates = self.certificates.lock().unwrap();
                 certificates.remove(&certificate_id);
             }
-            return Err(EventServerError::Validation(
-                "Certificate has expired".to_string(),
+            return Err(EventServerError::auth(
+                AuthFailure::CertExpired,
+                "Certificate has expired",
+            ));
+        }
+
+        // Issued before the operator's cut-off; renewing issues a fresh certificate
+        let accepted_until = self
+            .max_accepted_age
+            .map(|max_age| certificate.issued_at + max_age);
+        if accepted_until.is_some_and(|accepted_until| self.clock.now() > accepted_until) {
+            return Err(EventServerError::auth(
+                AuthFailure::CertTooOld,
+                "Certificate too old, renew it",
             ));
         }
 
         // Verify certificate signature
-        let cert_data = format!(
-            "{}:{}:{}:{}",
-            certificate.certificate_id,
-            certificate.relay_id,
-            certificate.public_key,
-            certificate.expires_at.timestamp()
-        );
+        let cert_data = certificate.signing_input();
 
         if !self.verify_certificate_signature(&cert_data, &certificate.signature)? {
-            return Err(EventServerError::Validation(
-                "Invalid certificate signature".to_string(),
+            return Err(EventServerError::auth(
+                AuthFailure::CertInvalid,
+                "Invalid certificate signature",
             ));
         }
 
-        Ok(CertificateValidation {
+        let now = self.clock.now();
+        let previous_public_key = certificate.previous_public_key.filter(|_| {
+            certificate
+                .previous_key_expires_at
+                .is_some_and(|grace_end| now <= grace_end)
+        });
+
+        // Cache no longer than anything the validation depends on stays valid
+        let valid_until = [
+            Some(certificate.expires_at),
+            accepted_until,
+            previous_public_key
+                .as_ref()
+                .and(certificate.previous_key_expires_at),
+            self.previous_jwt_secret
+                .as_ref()
+                .map(|(_, valid_until)| *valid_until),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(certificate.expires_at);
+
+        let validation = CertificateValidation {
+            certificate_id: certificate.certificate_id,
             relay_id: certificate.relay_id,
             public_key: certificate.public_key,
             expires_at: certificate.expires_at,
-        })
+            previous_public_key,
+            ed25519_public_key: certificate.ed25519_public_key,
+            tenant_id: certificate.tenant_id,
+        };
+        self.validation_cache
+            .insert(token, validation.clone(), valid_until);
+        Ok(validation)
+    }
+
+    /// Look up an active certificate by ID
+    pub fn certificate(&self, certificate_id: &str) -> Option<DeviceCertificate> {
+        self.certificates
+            .lock()
+            .unwrap()
+            .get(certificate_id)
+            .cloned()
+    }
+
+    /// Load certificates from the persistence layer into the in-memory map
+    /// Expired, revoked or tampered entries are skipped; returns the number loaded
+    pub fn load_certificates(&self, stored: Vec<DeviceCertificate>) -> usize {
+        let now = self.clock.now();
+        let revoked = self.revoked.lock().unwrap().clone();
+        let mut certificates = self.certificates.lock().unwrap();
+        let mut loaded = 0;
+
+        for certificate in stored {
+            if certificate.expires_at < now || revoked.contains_key(&certificate.certificate_id) {
+                continue;
+            }
+
+            let cert_data = certificate.signing_input();
+            if !matches!(
+                self.verify_certificate_signature(&cert_data, &certificate.signature),
+                Ok(true)
+            ) {
+                warn!(
+                    certificate_id = %certificate.certificate_id,
+                    "Skipping stored certificate with invalid signature"
+                );
+                continue;
+            }
+
+            certificates.insert(certificate.certificate_id.clone(), certificate);
+            loaded += 1;
+        }
+
+        loaded
+    }
+
+    /// Revocation record of a revoked, not yet expired certificate, for persisting
+    pub fn revocation(&self, certificate_id: &str) -> Option<Revocation> {
+        self.revoked.lock().unwrap().get(certificate_id).cloned()
+    }
+
+    /// Apply persisted revocations, e.g. made before a restart or on another instance
+    /// Returns the number of certificates newly revoked here
+    pub fn apply_revocations(&self, revocations: Vec<Revocation>) -> usize {
+        let now = self.clock.now();
+        let mut certificates = self.certificates.lock().unwrap();
+        let mut revoked = self.revoked.lock().unwrap();
+        let mut applied = 0;
+
+        for revocation in revocations {
+            if revocation.expires_at < now || revoked.contains_key(&revocation.certificate_id) {
+                continue;
+            }
+            certificates.remove(&revocation.certificate_id);
+            self.validation_cache.invalidate(&revocation.certificate_id);
+            revoked.insert(revocation.certificate_id.clone(), revocation);
+            applied += 1;
+        }
+        drop((certificates, revoked));
+        if applied > 0 {
+            self.touch_revocations();
+        }
+        applied
+    }
+
+    /// Evict in-memory certificates that are no longer present in the persistence layer
+    /// Returns the number of certificates evicted
+    pub fn retain_certificates(&self, stored_ids: &HashSet<String>) -> usize {
+        let mut certificates = self.certificates.lock().unwrap();
+        let evicted: Vec<String> = certificates
+            .keys()
+            .filter(|id| !stored_ids.contains(*id))
+            .cloned()
+            .collect();
+        for certificate_id in &evicted {
+            certificates.remove(certificate_id);
+            self.validation_cache.invalidate(certificate_id);
+        }
+        evicted.len()
+    }
+
+    /// Revoke an active certificate so later validations fail
+    /// Returns false if the certificate is unknown or already expired
+    pub fn revoke_certificate(&self, certificate_id: &str) -> bool {
+        let removed = self.certificates.lock().unwrap().remove(certificate_id);
+        self.validation_cache.invalidate(certificate_id);
+        match removed {
+            Some(certificate) => {
+                let revocation = Revocation::from(certificate);
+                self.revoked
+                    .lock()
+                    .unwrap()
+                    .insert(revocation.certificate_id.clone(), revocation);
+                self.touch_revocations();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replace a certificate's device key, keeping the old key valid for the grace window
+    /// so events signed before the switch still verify
+    pub fn rotate_key(
+        &self,
+        certificate_id: &str,
+        new_public_key: &str,
+    ) -> Result<DeviceCertificate, EventServerError> {
+        let mut certificates = self.certificates.lock().unwrap();
+        let certificate = certificates.get_mut(certificate_id).ok_or_else(|| {
+            EventServerError::auth(AuthFailure::CertNotFound, "Certificate not found")
+        })?;
+
+        if certificate.public_key == new_public_key {
+            return Err(EventServerError::BadRequest(
+                "New public key matches the current key".to_string(),
+            ));
+        }
+
+        let cert_data = certificate_data(
+            &certificate.certificate_id,
+            &certificate.relay_id,
+            new_public_key,
+            certificate.ed25519_public_key.as_deref(),
+            certificate.tenant_id.as_deref(),
+            certificate.expires_at,
+        );
+        certificate.signature = self.sign_certificate_data(&cert_data)?;
+        certificate.previous_public_key = Some(std::mem::replace(
+            &mut certificate.public_key,
+            new_public_key.to_string(),
+        ));
+        certificate.previous_key_expires_at = Some(self.clock.now() + self.key_rotation_grace);
+        self.validation_cache.invalidate(certificate_id);
+
+        Ok(certificate.clone())
+    }
+
+    /// Revoke every active certificate issued to a relay
+    /// Returns the IDs of the revoked certificates
+    pub fn revoke_relay_certificates(&self, relay_id: &str) -> Vec<String> {
+        let mut certificates = self.certificates.lock().unwrap();
+        let certificate_ids: Vec<String> = certificates
+            .values()
+            .filter(|certificate| certificate.relay_id == relay_id)
+            .map(|certificate| certificate.certificate_id.clone())
+            .collect();
+
+        let mut revoked = self.revoked.lock().unwrap();
+        for certificate_id in &certificate_ids {
+            if let Some(certificate) = certificates.remove(certificate_id) {
+                revoked.insert(certificate_id.clone(), certificate.into());
+            }
+            self.validation_cache.invalidate(certificate_id);
+        }
+        if !certificate_ids.is_empty() {
+            self.touch_revocations();
+        }
+
+        certificate_ids
+    }
+
+    /// IDs of `tenant_id`'s revoked certificates that haven't expired yet, sorted, and when the
+    /// revocation set last changed; other tenants' revocations are never disclosed
+    pub fn revocation_list(&self, tenant_id: Option<&str>) -> (Vec<String>, DateTime<Utc>) {
+        self.cleanup_expired_certificates();
+        let mut certificate_ids: Vec<String> = self
+            .revoked
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, revocation)| revocation.tenant_id.as_deref() == tenant_id)
+            .map(|(certificate_id, _)| certificate_id.clone())
+            .collect();
+        certificate_ids.sort();
+        (certificate_ids, *self.revoked_updated_at.lock().unwrap())
+    }
+
+    fn touch_revocations(&self) {
+        *self.revoked_updated_at.lock().unwrap() = self.clock.now();
     }
 
     /// Generate a unique certificate ID
@@ -194,13 +682,28 @@ impl CertificateService {
         base64::engine::general_purpose::STANDARD.encode(random_bytes)
     }
 
+    /// Secrets accepted for verification: the current one, then the previous one during its overlap window
+    fn verification_secrets(&self) -> impl Iterator<Item = &str> {
+        let now = self.clock.now();
+        let previous = self
+            .previous_jwt_secret
+            .as_ref()
+            .filter(|(_, valid_until)| now <= *valid_until)
+            .map(|(secret, _)| secret.as_str());
+        std::iter::once(self.jwt_secret.as_str()).chain(previous)
+    }
+
     /// Sign certificate data with JWT secret
     fn sign_certificate_data(&self, data: &str) -> Result<String, EventServerError> {
+        Ok(Self::sign_certificate_data_with(data, &self.jwt_secret))
+    }
+
+    fn sign_certificate_data_with(data: &str, secret: &str) -> String {
         let mut hasher = Sha256::new();
         hasher.update(data.as_bytes());
-        hasher.update(self.jwt_secret.as_bytes());
+        hasher.update(secret.as_bytes());
         let hash = hasher.finalize();
-        Ok(base64::engine::general_purpose::STANDARD.encode(hash))
+        base64::engine::general_purpose::STANDARD.encode(hash)
     }
 
     /// Verify certificate signature
@@ -209,8 +712,9 @@ impl CertificateService {
         data: &str,
         signature: &str,
     ) -> Result<bool, EventServerError> {
-        let expected_signature = self.sign_certificate_data(data)?;
-        Ok(expected_signature == signature)
+        Ok(self
+            .verification_secrets()
+            .any(|secret| Self::sign_certificate_data_with(data, secret) == signature))
     }
 
     /// Generate a JWT token for the certificate
@@ -226,8 +730,16 @@ impl CertificateService {
             exp: certificate.expires_at.timestamp(),
         };
 
-        let header = Header::new(Algorithm::HS256);
-        let encoding_key = EncodingKey::from_secret(self.jwt_secret.as_bytes());
+        let mut header = Header::new(self.token_algorithm);
+        let encoding_key = match &self.signing_keys {
+            Some(keys) => {
+                let keys = keys.lock().unwrap();
+                let (encoding_key, key_id) = keys.encoding_key();
+                header.kid = Some(key_id.to_string());
+                encoding_key
+            }
+            None => EncodingKey::from_secret(self.jwt_secret.as_bytes()),
+        };
 
         encode(&header, &claims, &encoding_key)
             .map_err(|e| EventServerError::Validation(format!("Failed to generate JWT token: {e}")))
@@ -235,20 +747,151 @@ impl CertificateService {
 
     /// Extract certificate ID from JWT token
     fn extract_certificate_id_from_token(&self, token: &str) -> Result<String, EventServerError> {
-        let decoding_key = DecodingKey::from_secret(self.jwt_secret.as_bytes());
-        let validation = Validation::new(Algorithm::HS256);
+        let mut validation = Validation::new(self.token_algorithm);
+        // Expiry is checked below against the service clock rather than the system time
+        validation.validate_exp = false;
 
-        let token_data = decode::<DeviceClaims>(token, &decoding_key, &validation)
-            .map_err(|e| EventServerError::Validation(format!("Invalid JWT token: {e}")))?;
+        let token_data = if let Some(keys) = &self.signing_keys {
+            self.decode_signed_token(token, keys, &validation)?
+        } else {
+            self.decode_hmac_token(token, &validation)?
+        };
+
+        if self.clock.now().timestamp() > token_data.claims.exp {
+            return Err(EventServerError::auth(
+                AuthFailure::CertExpired,
+                "Certificate has expired",
+            ));
+        }
 
         Ok(token_data.claims.certificate_id)
     }
 
+    /// Decode an EdDSA token with the key its `kid` names
+    fn decode_signed_token(
+        &self,
+        token: &str,
+        keys: &Mutex<CertificateKeyring>,
+        validation: &Validation,
+    ) -> Result<jsonwebtoken::TokenData<DeviceClaims>, EventServerError> {
+        let invalid = |reason: String| {
+            EventServerError::auth(
+                AuthFailure::CertInvalid,
+                format!("Invalid certificate token: {reason}"),
+            )
+        };
+        let header = decode_header(token).map_err(|e| invalid(e.to_string()))?;
+        let key_id = header
+            .kid
+            .ok_or_else(|| invalid("missing key ID".to_string()))?;
+        let decoding_key = keys
+            .lock()
+            .unwrap()
+            .decoding_key(&key_id, self.clock.now())
+            .ok_or_else(|| invalid(format!("unknown signing key {key_id}")))?;
+        decode::<DeviceClaims>(token, &decoding_key, validation).map_err(|e| invalid(e.to_string()))
+    }
+
+    /// Decode an HMAC token with the current secret, or the previous one during its overlap window
+    fn decode_hmac_token(
+        &self,
+        token: &str,
+        validation: &Validation,
+    ) -> Result<jsonwebtoken::TokenData<DeviceClaims>, EventServerError> {
+        // Report the current secret's error; the previous secret is only a fallback
+        let mut first_error = None;
+        self.verification_secrets()
+            .find_map(|secret| {
+                let decoding_key = DecodingKey::from_secret(secret.as_bytes());
+                decode::<DeviceClaims>(token, &decoding_key, validation)
+                    .map_err(|e| {
+                        first_error.get_or_insert(e);
+                    })
+                    .ok()
+            })
+            .ok_or_else(|| {
+                EventServerError::auth(
+                    AuthFailure::CertInvalid,
+                    format!(
+                        "Invalid certificate token: {}",
+                        first_error.expect("at least the current secret is tried")
+                    ),
+                )
+            })
+    }
+
     /// Clean up expired certificates from memory
     fn cleanup_expired_certificates(&self) {
-        let now = Utc::now();
+        let now = self.clock.now();
         let mut certificates = self.certificates.lock().unwrap();
-        certificates.retain(|_, cert| cert.expires_at > now);
+        certificates.retain(|_, cert| cert.expires_at >= now);
+
+        // Revoked entries only matter until the token would have expired anyway
+        let mut revoked = self.revoked.lock().unwrap();
+        let before = revoked.len();
+        revoked.retain(|_, revocation| revocation.expires_at >= now);
+        if revoked.len() != before {
+            drop(revoked);
+            self.touch_revocations();
+        }
+    }
+
+    /// `kid` signing new certificate tokens, when they are EdDSA-signed
+    pub fn signing_key_id(&self) -> Option<String> {
+        self.signing_keys
+            .as_ref()
+            .map(|keys| keys.lock().unwrap().primary_key_id().to_string())
+    }
+
+    /// Exclusive right to change the keyring; held across a rotation's persist-then-install, or
+    /// a reload, so concurrent updates can't overwrite each other's keys
+    pub async fn lock_signing_key_updates(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.signing_key_updates.lock().await
+    }
+
+    /// Install the persisted keyring, e.g. one rotated by another instance
+    /// Returns whether the primary key changed; a no-op when tokens are HMAC-signed
+    pub fn restore_signing_keys(&self, stored: &StoredKeyring) -> Result<bool, EventServerError> {
+        let Some(keys) = &self.signing_keys else {
+            return Ok(false);
+        };
+        let mut keys = keys.lock().unwrap();
+        let restored = keys.restore(stored)?;
+        let changed = restored.primary_key_id() != keys.primary_key_id();
+        *keys = restored;
+        Ok(changed)
+    }
+
+    /// Current keyring with a new primary key; outstanding certificates stay verifiable with
+    /// the old one until they expire. Takes effect once passed to `install_signing_keys`, with
+    /// `lock_signing_key_updates` held throughout
+    pub fn rotated_signing_keys(&self) -> Result<CertificateKeyring, EventServerError> {
+        let keys = self.signing_keys.as_ref().ok_or_else(|| {
+            EventServerError::Conflict(
+                "Certificate tokens are HMAC-signed; set security.cert_token_alg to EdDSA to use rotatable signing keys"
+                    .to_string(),
+            )
+        })?;
+        let rotated = keys
+            .lock()
+            .unwrap()
+            .rotated(self.clock.now(), self.certificate_lifetime);
+        Ok(rotated)
+    }
+
+    /// Replace the signing keyring, e.g. with one from `rotated_signing_keys`
+    pub fn install_signing_keys(&self, keyring: CertificateKeyring) {
+        if let Some(keys) = &self.signing_keys {
+            *keys.lock().unwrap() = keyring;
+        }
+    }
+
+    /// Public keys verifying certificate tokens, empty when tokens are HMAC-signed
+    pub fn signing_jwks(&self) -> Vec<serde_json::Value> {
+        self.signing_keys
+            .as_ref()
+            .map(|keys| keys.lock().unwrap().jwks(self.clock.now()))
+            .unwrap_or_default()
     }
 
     /// Get the number of active certificates (for testing/monitoring)
@@ -257,6 +900,12 @@ impl CertificateService {
         let certificates = self.certificates.lock().unwrap();
         certificates.len()
     }
+
+    /// Validations answered from the cache (for testing)
+    #[cfg(test)]
+    pub fn validation_cache_hits(&self) -> u64 {
+        self.validation_cache.hits()
+    }
 }
 
 impl Default for CertificateService {
@@ -281,6 +930,8 @@ mod tests {
         let request = CertificateRequest {
             relay_id: "test_relay".to_string(),
             public_key: "test_public_key".to_string(),
+            ed25519_public_key: None,
+            tenant_id: None,
         };
 
         let response = service.issue_certificate(&request).unwrap();
@@ -290,18 +941,372 @@ mod tests {
         assert_eq!(validation.public_key, "test_public_key");
     }
 
+    #[test]
+    fn test_configured_token_algorithms() {
+        let mut config = crate::config::AppConfig::default().security;
+
+        for name in ["HS256", "HS384", "hs512"] {
+            config.cert_token_alg = name.to_string();
+            let algorithm = config.cert_token_algorithm().unwrap();
+            let service =
+                CertificateService::new("test_secret".to_string()).with_token_algorithm(algorithm);
+            let request = CertificateRequest {
+                relay_id: "test_relay".to_string(),
+                public_key: "test_public_key".to_string(),
+                ed25519_public_key: None,
+                tenant_id: None,
+            };
+
+            let response = service.issue_certificate(&request).unwrap();
+            let header = jsonwebtoken::decode_header(&response.cert_token).unwrap();
+            assert_eq!(header.alg, algorithm);
+            assert!(service.validate_certificate(&response.cert_token).is_ok());
+
+            // A service verifying with a different algorithm must reject the token
+            let other = CertificateService::new("test_secret".to_string()).with_token_algorithm(
+                if algorithm == Algorithm::HS256 {
+                    Algorithm::HS512
+                } else {
+                    Algorithm::HS256
+                },
+            );
+            assert!(matches!(
+                other.validate_certificate(&response.cert_token),
+                Err(EventServerError::Authentication {
+                    reason: AuthFailure::CertInvalid,
+                    ..
+                })
+            ));
+        }
+
+        for name in ["RS256", "none", ""] {
+            config.cert_token_alg = name.to_string();
+            assert!(config.cert_token_algorithm().is_err());
+        }
+    }
+
+    #[test]
+    fn test_signing_key_rotation_keeps_outstanding_certificates_valid() {
+        let service = CertificateService::new("test_secret".to_string()).with_signing_keys(
+            CertificateKeyring::generate(crate::test_utils::seed_cipher()),
+        );
+        let kid = |token: &str| jsonwebtoken::decode_header(token).unwrap().kid.unwrap();
+
+        let old_token = service
+            .issue_certificate(&relay_request("relay-a"))
+            .unwrap()
+            .cert_token;
+        assert_eq!(
+            jsonwebtoken::decode_header(&old_token).unwrap().alg,
+            Algorithm::EdDSA
+        );
+        service.install_signing_keys(service.rotated_signing_keys().unwrap());
+        let new_token = service
+            .issue_certificate(&relay_request("relay-a"))
+            .unwrap()
+            .cert_token;
+
+        assert_ne!(kid(&old_token), kid(&new_token));
+        assert!(service.validate_certificate(&old_token).is_ok());
+        assert!(service.validate_certificate(&new_token).is_ok());
+        let published: Vec<_> = service
+            .signing_jwks()
+            .iter()
+            .map(|jwk| jwk["kid"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(published, [kid(&new_token), kid(&old_token)]);
+
+        // HMAC-signed tokens have no keypair to rotate
+        assert!(matches!(
+            CertificateService::default().rotated_signing_keys(),
+            Err(EventServerError::Conflict(_))
+        ));
+    }
+
+    fn relay_request(relay_id: &str) -> CertificateRequest {
+        CertificateRequest {
+            relay_id: relay_id.to_string(),
+            public_key: "test_public_key".to_string(),
+            ed25519_public_key: None,
+            tenant_id: None,
+        }
+    }
+
+    #[test]
+    fn test_cap_reject_policy() {
+        let service = CertificateService::new("test_secret".to_string())
+            .with_capacity_limit(2, CertCapPolicy::Reject);
+
+        let first = service.issue_certificate(&relay_request("a")).unwrap();
+        service.issue_certificate(&relay_request("b")).unwrap();
+        assert!(matches!(
+            service.issue_certificate(&relay_request("c")),
+            Err(EventServerError::ServiceUnavailable {
+                retry_after_seconds: Some(CAP_REJECT_RETRY_SECONDS),
+                ..
+            })
+        ));
+
+        // Existing certificates are unaffected
+        assert_eq!(service.active_certificate_count(), 2);
+        assert!(service.validate_certificate(&first.cert_token).is_ok());
+    }
+
+    #[test]
+    fn test_cap_evict_policy() {
+        let service = CertificateService::new("test_secret".to_string())
+            .with_capacity_limit(2, CertCapPolicy::Evict);
+
+        let first = service.issue_certificate(&relay_request("a")).unwrap();
+        let second = service.issue_certificate(&relay_request("b")).unwrap();
+        // Make the second certificate the one closest to expiry
+        service
+            .certificates
+            .lock()
+            .unwrap()
+            .get_mut(&second.certificate_id)
+            .unwrap()
+            .expires_at -= Duration::minutes(5);
+
+        let third = service.issue_certificate(&relay_request("c")).unwrap();
+        assert_eq!(service.active_certificate_count(), 2);
+        assert!(service.certificate(&second.certificate_id).is_none());
+        assert!(service.certificate(&first.certificate_id).is_some());
+        assert!(service.validate_certificate(&third.cert_token).is_ok());
+    }
+
+    #[test]
+    fn test_revocation_list_is_stamped_from_the_clock() {
+        let clock = crate::crypto::MockClock::new();
+        clock.advance(Duration::days(3));
+        let service = CertificateService::with_params(1, "test_secret".to_string())
+            .with_clock(Arc::new(clock.clone()));
+
+        let (_, updated_at) = service.revocation_list(None);
+        assert_eq!(updated_at, clock.now());
+    }
+
+    #[test]
+    fn test_repeated_validation_hits_cache() {
+        let clock = crate::crypto::MockClock::new();
+        let service = CertificateService::with_params(1, "test_secret".to_string())
+            .with_clock(Arc::new(clock.clone()));
+        let request = CertificateRequest {
+            relay_id: "test_relay".to_string(),
+            public_key: "test_public_key".to_string(),
+            ed25519_public_key: None,
+            tenant_id: None,
+        };
+        let token = service.issue_certificate(&request).unwrap().cert_token;
+
+        let first = service.validate_certificate(&token).unwrap();
+        assert_eq!(service.validation_cache_hits(), 0);
+        let second = service.validate_certificate(&token).unwrap();
+        assert_eq!(service.validation_cache_hits(), 1);
+        assert_eq!(first.certificate_id, second.certificate_id);
+
+        // Cached entries don't outlive the certificate
+        clock.advance(Duration::hours(1) + Duration::seconds(1));
+        let result = service.validate_certificate(&token);
+        assert!(matches!(
+            result,
+            Err(EventServerError::Authentication {
+                reason: AuthFailure::CertExpired,
+                ..
+            })
+        ));
+        assert_eq!(service.validation_cache_hits(), 1);
+
+        // Revocation drops the cached validation
+        let token = service.issue_certificate(&request).unwrap().cert_token;
+        let validation = service.validate_certificate(&token).unwrap();
+        assert!(service.validate_certificate(&token).is_ok());
+        assert_eq!(service.validation_cache_hits(), 2);
+        assert!(service.revoke_certificate(&validation.certificate_id));
+        let result = service.validate_certificate(&token);
+        assert!(matches!(
+            result,
+            Err(EventServerError::Authentication {
+                reason: AuthFailure::CertRevoked,
+                ..
+            })
+        ));
+
+        // So does eviction by a reconcile with storage
+        let token = service.issue_certificate(&request).unwrap().cert_token;
+        assert!(service.validate_certificate(&token).is_ok());
+        assert!(service.validate_certificate(&token).is_ok());
+        assert_eq!(service.retain_certificates(&HashSet::new()), 1);
+        let result = service.validate_certificate(&token);
+        assert!(matches!(
+            result,
+            Err(EventServerError::Authentication {
+                reason: AuthFailure::CertNotFound,
+                ..
+            })
+        ));
+
+        // Disabled cache always re-validates
+        let uncached =
+            CertificateService::new("test_secret".to_string()).with_validation_cache_size(0);
+        let token = uncached.issue_certificate(&request).unwrap().cert_token;
+        assert!(uncached.validate_certificate(&token).is_ok());
+        assert!(uncached.validate_certificate(&token).is_ok());
+        assert_eq!(uncached.validation_cache_hits(), 0);
+    }
+
     #[test]
     fn test_expired_certificate() {
-        let service = CertificateService::with_params(-1, "test_secret".to_string()); // Expired 1 hour ago
+        let clock = crate::crypto::MockClock::new();
+        let service = CertificateService::with_params(1, "test_secret".to_string())
+            .with_clock(Arc::new(clock.clone()));
         let request = CertificateRequest {
             relay_id: "test_relay".to_string(),
             public_key: "test_public_key".to_string(),
+            ed25519_public_key: None,
+            tenant_id: None,
         };
 
         let response = service.issue_certificate(&request).unwrap();
 
-        // Certificate should be expired immediately
+        // Still valid at the exact expiry instant
+        clock.advance(Duration::hours(1));
+        assert!(service.validate_certificate(&response.cert_token).is_ok());
+
+        // Expired one second later
+        clock.advance(Duration::seconds(1));
         let result = service.validate_certificate(&response.cert_token);
-        assert!(result.is_err());
+        assert!(matches!(
+            result,
+            Err(EventServerError::Authentication {
+                reason: AuthFailure::CertExpired,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_certificate_beyond_max_accepted_age() {
+        let clock = crate::crypto::MockClock::new();
+        let service = CertificateService::with_params(24, "test_secret".to_string())
+            .with_clock(Arc::new(clock.clone()))
+            .with_max_accepted_age(Some(Duration::hours(2)));
+        let request = CertificateRequest {
+            relay_id: "test_relay".to_string(),
+            public_key: "test_public_key".to_string(),
+            ed25519_public_key: None,
+            tenant_id: None,
+        };
+        let token = service.issue_certificate(&request).unwrap().cert_token;
+
+        // Within the accepted age, including from the validation cache
+        clock.advance(Duration::hours(2));
+        assert!(service.validate_certificate(&token).is_ok());
+        assert!(service.validate_certificate(&token).is_ok());
+
+        // Unexpired, but too old
+        clock.advance(Duration::seconds(1));
+        let result = service.validate_certificate(&token);
+        assert!(matches!(
+            result,
+            Err(EventServerError::Authentication {
+                reason: AuthFailure::CertTooOld,
+                ..
+            })
+        ));
+
+        // A renewed certificate is accepted again
+        let token = service.issue_certificate(&request).unwrap().cert_token;
+        assert!(service.validate_certificate(&token).is_ok());
+    }
+
+    #[test]
+    fn test_previous_secret_verifies_during_overlap_window() {
+        let clock = crate::crypto::MockClock::new();
+        let old = CertificateService::new("old_secret".to_string());
+        let response = old.issue_certificate(&relay_request("relay_a")).unwrap();
+        let certificate_id = old
+            .validate_certificate(&response.cert_token)
+            .unwrap()
+            .certificate_id;
+
+        // Restart with a rotated secret, keeping the old one for an hour
+        let rotated = CertificateService::new("new_secret".to_string())
+            .with_clock(Arc::new(clock.clone()))
+            .with_previous_secret("old_secret".to_string(), clock.now() + Duration::hours(1));
+        assert_eq!(
+            rotated.load_certificates(vec![old.certificate(&certificate_id).unwrap()]),
+            1
+        );
+        assert!(rotated.validate_certificate(&response.cert_token).is_ok());
+
+        // Newly issued tokens use the current secret
+        let fresh = rotated
+            .issue_certificate(&relay_request("relay_b"))
+            .unwrap();
+        assert!(old.validate_certificate(&fresh.cert_token).is_err());
+
+        clock.advance(Duration::hours(1) + Duration::seconds(1));
+        let result = rotated.validate_certificate(&response.cert_token);
+        assert!(matches!(
+            result,
+            Err(EventServerError::Authentication {
+                reason: AuthFailure::CertInvalid,
+                ..
+            })
+        ));
+        assert!(rotated.validate_certificate(&fresh.cert_token).is_ok());
+    }
+
+    #[test]
+    fn test_revoked_certificate() {
+        let service = CertificateService::new("test_secret".to_string());
+        let request = CertificateRequest {
+            relay_id: "test_relay".to_string(),
+            public_key: "test_public_key".to_string(),
+            ed25519_public_key: None,
+            tenant_id: None,
+        };
+
+        let response = service.issue_certificate(&request).unwrap();
+        let validation = service.validate_certificate(&response.cert_token).unwrap();
+
+        assert!(service.revoke_certificate(&validation.certificate_id));
+        assert!(!service.revoke_certificate(&validation.certificate_id));
+
+        let result = service.validate_certificate(&response.cert_token);
+        assert!(matches!(
+            result,
+            Err(EventServerError::Authentication {
+                reason: AuthFailure::CertRevoked,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_revocation_list_is_scoped_to_tenant() {
+        let service = CertificateService::new("test_secret".to_string());
+        let revoke = |tenant_id: Option<&str>| {
+            let response = service
+                .issue_certificate(&CertificateRequest {
+                    relay_id: "test_relay".to_string(),
+                    public_key: "test_public_key".to_string(),
+                    ed25519_public_key: None,
+                    tenant_id: tenant_id.map(str::to_string),
+                })
+                .unwrap();
+            let certificate_id = service
+                .validate_certificate(&response.cert_token)
+                .unwrap()
+                .certificate_id;
+            assert!(service.revoke_certificate(&certificate_id));
+            certificate_id
+        };
+        let (acme, globex, shared) = (revoke(Some("acme")), revoke(Some("globex")), revoke(None));
+
+        assert_eq!(service.revocation_list(Some("acme")).0, vec![acme]);
+        assert_eq!(service.revocation_list(Some("globex")).0, vec![globex]);
+        assert_eq!(service.revocation_list(None).0, vec![shared]);
     }
 }
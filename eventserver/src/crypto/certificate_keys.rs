@@ -0,0 +1,351 @@
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use jsonwebtoken::{DecodingKey, EncodingKey};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::archive_signing::{ed25519_jwk, ed25519_key_id};
+use crate::error::EventServerError;
+
+/// PKCS#8 v1 prefix of an Ed25519 private key; the 32-byte seed follows
+const ED25519_PKCS8_PREFIX: [u8; 16] = [
+    0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+];
+
+/// Associated data binding sealed seeds to their purpose
+const SEED_AAD: &[u8] = b"eventserver/certificate-signing-key";
+
+/// AES-256-GCM key sealing the primary seed before the keyring is written to the bucket,
+/// so read access to storage alone doesn't yield the certificate signing key
+#[derive(Clone)]
+pub struct SeedCipher {
+    key: [u8; 32],
+}
+
+impl std::fmt::Debug for SeedCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SeedCipher").finish_non_exhaustive()
+    }
+}
+
+/// A seed encrypted with a `SeedCipher`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SealedSeed {
+    pub nonce: String,      // Base64 96-bit nonce
+    pub ciphertext: String, // Base64 seed plus GCM tag
+}
+
+impl SeedCipher {
+    /// Cipher from `security.cert_keys_encryption_key`, a base64 32-byte key
+    pub fn new(key: &str) -> Result<Self, EventServerError> {
+        general_purpose::STANDARD
+            .decode(key.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(|key| Self { key })
+            .ok_or_else(|| {
+                EventServerError::Config(
+                    "security.cert_keys_encryption_key must be a base64 32-byte key".to_string(),
+                )
+            })
+    }
+
+    fn aead_key(&self) -> LessSafeKey {
+        LessSafeKey::new(
+            UnboundKey::new(&AES_256_GCM, &self.key).expect("32-byte key is valid for AES-256"),
+        )
+    }
+
+    fn seal(&self, seed: &[u8; 32]) -> SealedSeed {
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let mut in_out = seed.to_vec();
+        self.aead_key()
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(SEED_AAD),
+                &mut in_out,
+            )
+            .expect("sealing a 32-byte seed cannot overflow");
+        SealedSeed {
+            nonce: general_purpose::STANDARD.encode(nonce),
+            ciphertext: general_purpose::STANDARD.encode(in_out),
+        }
+    }
+
+    fn open(&self, sealed: &SealedSeed) -> Result<[u8; 32], EventServerError> {
+        let invalid = || {
+            EventServerError::Config(
+                "Stored certificate signing key can't be decrypted with security.cert_keys_encryption_key"
+                    .to_string(),
+            )
+        };
+        let nonce: [u8; NONCE_LEN] = general_purpose::STANDARD
+            .decode(&sealed.nonce)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(invalid)?;
+        let mut in_out = general_purpose::STANDARD
+            .decode(&sealed.ciphertext)
+            .map_err(|_| invalid())?;
+        let seed = self
+            .aead_key()
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(SEED_AAD),
+                &mut in_out,
+            )
+            .map_err(|_| invalid())?;
+        (*seed).try_into().map_err(|_| invalid())
+    }
+}
+
+/// Ed25519 keys signing certificate tokens
+/// New tokens are signed with the primary key. Keys replaced by a rotation stay available
+/// for verification until every certificate they could have signed has expired
+#[derive(Clone)]
+pub struct CertificateKeyring {
+    primary: SigningKey,
+    primary_key_id: String,
+    retired: Vec<RetiredKey>,
+    cipher: SeedCipher, // Seals the primary seed whenever the keyring is persisted
+}
+
+/// Public half of a rotated-out signing key
+#[derive(Debug, Clone)]
+struct RetiredKey {
+    key_id: String,
+    public_key: VerifyingKey,
+    verify_until: DateTime<Utc>, // Retirement plus the certificate lifetime
+}
+
+impl std::fmt::Debug for CertificateKeyring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CertificateKeyring")
+            .field("primary_key_id", &self.primary_key_id)
+            .field("retired", &self.retired)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Persisted form of a keyring; the primary seed is sealed and retired keys are kept
+/// without their private half
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredKeyring {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_seed: Option<String>, // Legacy plaintext base64 seed, re-sealed on load
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sealed_primary_seed: Option<SealedSeed>,
+    #[serde(default)]
+    pub retired: Vec<StoredRetiredKey>,
+}
+
+impl StoredKeyring {
+    /// Whether the seed is still stored in plaintext and should be re-saved sealed
+    pub fn is_plaintext(&self) -> bool {
+        self.sealed_primary_seed.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredRetiredKey {
+    pub public_key: String, // Base64 32-byte Ed25519 public key
+    pub verify_until: DateTime<Utc>,
+}
+
+impl CertificateKeyring {
+    /// Keyring holding a single freshly generated key
+    pub fn generate(cipher: SeedCipher) -> Self {
+        Self::with_primary(SigningKey::from_bytes(&rand::random()), Vec::new(), cipher)
+    }
+
+    fn with_primary(primary: SigningKey, retired: Vec<RetiredKey>, cipher: SeedCipher) -> Self {
+        Self {
+            primary_key_id: ed25519_key_id(&primary.verifying_key()),
+            primary,
+            retired,
+            cipher,
+        }
+    }
+
+    /// Restore a persisted keyring, unsealing its seed with `cipher`
+    pub fn from_stored(
+        stored: &StoredKeyring,
+        cipher: SeedCipher,
+    ) -> Result<Self, EventServerError> {
+        let decode = |value: &str| -> Result<[u8; 32], EventServerError> {
+            general_purpose::STANDARD
+                .decode(value)
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| {
+                    EventServerError::Config(
+                        "Stored certificate signing key is invalid".to_string(),
+                    )
+                })
+        };
+
+        let seed = match (&stored.sealed_primary_seed, &stored.primary_seed) {
+            (Some(sealed), _) => cipher.open(sealed)?,
+            (None, Some(seed)) => decode(seed)?,
+            (None, None) => {
+                return Err(EventServerError::Config(
+                    "Stored certificate keyring has no primary key".to_string(),
+                ))
+            }
+        };
+        let primary = SigningKey::from_bytes(&seed);
+        let retired = stored
+            .retired
+            .iter()
+            .map(|key| {
+                let public_key = VerifyingKey::from_bytes(&decode(&key.public_key)?)
+                    .map_err(|e| EventServerError::Config(format!("Invalid retired key: {e}")))?;
+                Ok(RetiredKey {
+                    key_id: ed25519_key_id(&public_key),
+                    public_key,
+                    verify_until: key.verify_until,
+                })
+            })
+            .collect::<Result<_, EventServerError>>()?;
+        Ok(Self::with_primary(primary, retired, cipher))
+    }
+
+    /// Another persisted keyring, unsealed with this keyring's cipher
+    pub fn restore(&self, stored: &StoredKeyring) -> Result<Self, EventServerError> {
+        Self::from_stored(stored, self.cipher.clone())
+    }
+
+    /// Form persisted in storage, with the primary seed sealed
+    pub fn to_stored(&self) -> StoredKeyring {
+        StoredKeyring {
+            primary_seed: None,
+            sealed_primary_seed: Some(self.cipher.seal(&self.primary.to_bytes())),
+            retired: self
+                .retired
+                .iter()
+                .map(|key| StoredRetiredKey {
+                    public_key: general_purpose::STANDARD.encode(key.public_key.to_bytes()),
+                    verify_until: key.verify_until,
+                })
+                .collect(),
+        }
+    }
+
+    /// Keyring with a new primary key, keeping the current one verifiable for `certificate_lifetime`
+    /// Retired keys past their window are dropped
+    pub fn rotated(&self, now: DateTime<Utc>, certificate_lifetime: Duration) -> Self {
+        let mut retired: Vec<RetiredKey> = self
+            .retired
+            .iter()
+            .filter(|key| key.verify_until > now)
+            .cloned()
+            .collect();
+        retired.insert(
+            0,
+            RetiredKey {
+                key_id: self.primary_key_id.clone(),
+                public_key: self.primary.verifying_key(),
+                verify_until: now + certificate_lifetime,
+            },
+        );
+        Self::with_primary(
+            SigningKey::from_bytes(&rand::random()),
+            retired,
+            self.cipher.clone(),
+        )
+    }
+
+    /// `kid` of the key signing new tokens
+    pub fn primary_key_id(&self) -> &str {
+        &self.primary_key_id
+    }
+
+    /// Key and `kid` for signing a new token
+    pub fn encoding_key(&self) -> (EncodingKey, &str) {
+        let mut der = ED25519_PKCS8_PREFIX.to_vec();
+        der.extend_from_slice(&self.primary.to_bytes());
+        (EncodingKey::from_ed_der(&der), &self.primary_key_id)
+    }
+
+    /// Key that verifies tokens signed under `key_id`, if it's still accepted at `now`
+    pub fn decoding_key(&self, key_id: &str, now: DateTime<Utc>) -> Option<DecodingKey> {
+        self.verification_keys(now)
+            .find(|(id, _)| *id == key_id)
+            .map(|(_, public_key)| DecodingKey::from_ed_der(&public_key.to_bytes()))
+    }
+
+    /// Verification keys as JSON Web Keys, primary first
+    pub fn jwks(&self, now: DateTime<Utc>) -> Vec<serde_json::Value> {
+        self.verification_keys(now)
+            .map(|(key_id, public_key)| ed25519_jwk(key_id, &public_key))
+            .collect()
+    }
+
+    fn verification_keys(
+        &self,
+        now: DateTime<Utc>,
+    ) -> impl Iterator<Item = (&str, VerifyingKey)> + '_ {
+        std::iter::once((self.primary_key_id.as_str(), self.primary.verifying_key())).chain(
+            self.retired
+                .iter()
+                .filter(move |key| key.verify_until > now)
+                .map(|key| (key.key_id.as_str(), key.public_key)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher() -> SeedCipher {
+        SeedCipher::new(&general_purpose::STANDARD.encode([7u8; 32])).unwrap()
+    }
+
+    #[test]
+    fn test_rotated_keys_expire_after_certificate_lifetime() {
+        let now = Utc::now();
+        let keyring = CertificateKeyring::generate(cipher());
+        let rotated = keyring.rotated(now, Duration::hours(1));
+        let old_key_id = keyring.primary_key_id();
+        assert_ne!(rotated.primary_key_id(), old_key_id);
+
+        assert!(rotated.decoding_key(old_key_id, now).is_some());
+        assert!(rotated
+            .decoding_key(old_key_id, now + Duration::hours(2))
+            .is_none());
+        assert_eq!(rotated.jwks(now).len(), 2);
+
+        // Survives a round trip through storage, without the retired private key
+        let stored = rotated.to_stored();
+        let restored = CertificateKeyring::from_stored(&stored, cipher()).unwrap();
+        assert_eq!(restored.primary_key_id(), rotated.primary_key_id());
+        assert!(restored.decoding_key(old_key_id, now).is_some());
+    }
+
+    #[test]
+    fn test_stored_seed_is_sealed() {
+        let keyring = CertificateKeyring::generate(cipher());
+        let stored = keyring.to_stored();
+        let seed = general_purpose::STANDARD.encode(keyring.primary.to_bytes());
+        assert!(!serde_json::to_string(&stored).unwrap().contains(&seed));
+
+        // Another key can't open it
+        let other = SeedCipher::new(&general_purpose::STANDARD.encode([8u8; 32])).unwrap();
+        assert!(CertificateKeyring::from_stored(&stored, other).is_err());
+
+        // Legacy plaintext keyrings still load, so they can be re-saved sealed
+        let legacy = StoredKeyring {
+            primary_seed: Some(seed),
+            sealed_primary_seed: None,
+            retired: Vec::new(),
+        };
+        assert!(legacy.is_plaintext());
+        let restored = CertificateKeyring::from_stored(&legacy, cipher()).unwrap();
+        assert_eq!(restored.primary_key_id(), keyring.primary_key_id());
+    }
+}
@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+
+/// Source of the current time for expiry checks
+/// Injected into services so tests can control time instead of sleeping
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Wall-clock time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Manually advanced clock for deterministic tests
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: std::sync::Arc<std::sync::Mutex<DateTime<Utc>>>,
+}
+
+#[cfg(test)]
+impl MockClock {
+    /// Start the clock at the current wall-clock time
+    pub fn new() -> Self {
+        Self {
+            now: std::sync::Arc::new(std::sync::Mutex::new(Utc::now())),
+        }
+    }
+
+    /// Move the clock forward by `duration`
+    pub fn advance(&self, duration: chrono::Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
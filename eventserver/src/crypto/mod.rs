@@ -1,5 +1,17 @@
+pub mod archive_signing;
 pub mod certificate;
+pub mod certificate_keys;
+pub mod clock;
 pub mod pow;
+pub mod receipt;
+pub mod replay;
+pub mod validation_cache;
 
+pub use archive_signing::*;
 pub use certificate::*;
+pub use certificate_keys::*;
+pub use clock::*;
 pub use pow::*;
+pub use receipt::*;
+pub use replay::*;
+pub use validation_cache::*;
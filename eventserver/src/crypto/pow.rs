@@ -2,21 +2,44 @@ use base64::Engine;
 use chrono::{DateTime, Duration, Utc};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+use tracing::{info, warn};
 use utoipa::ToSchema;
 
+use crate::config::{PowAlgorithm, SecurityConfig};
+use crate::crypto::{Clock, SystemClock};
 use crate::error::EventServerError;
 
+/// Number of solve-time samples averaged before each difficulty adjustment
+const AUTOTUNE_WINDOW: usize = 10;
+/// Retry-After hint sent when verification is shed because all slots are busy
+const VERIFY_BUSY_RETRY_SECONDS: u64 = 1;
+
 /// Proof of Work challenge
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PowChallenge {
     pub challenge_id: String,
     pub challenge_data: String, // Base64 encoded random data
     pub difficulty: u32,        // Number of leading zeros required
+    #[serde(default)]
+    pub algorithm: PowAlgorithm, // Hash function the solution must be computed with
     pub expires_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
+    /// Relay the challenge was requested for; only that relay may redeem it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relay_id: Option<String>,
+}
+
+/// Optional body of a PoW challenge request
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct PowChallengeRequest {
+    /// Bind the challenge to this relay so no other relay can redeem it
+    #[serde(default)]
+    pub relay_id: Option<String>,
 }
 
 /// Proof of Work solution
@@ -31,31 +54,99 @@ pub struct PowSolution {
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PowCertificateRequest {
     pub solution: PowSolution,
-    pub public_key: String, // Base64 encoded Ed25519 public key
+    pub public_key: String, // Base64-encoded P-256 JWK
     pub relay_id: String,
+    #[serde(default)]
+    pub ed25519_public_key: Option<String>, // Base64 Ed25519 key, required for dual-signature mode
 }
 
 /// Response for PoW challenge request
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct PowChallengeResponse {
     pub challenge_id: String,
     pub challenge_data: String,
     pub difficulty: u32,
+    pub algorithm: PowAlgorithm, // Hash function to solve with
     pub expires_at: DateTime<Utc>,
+    pub challenge_lifetime: i64, // Seconds a challenge stays valid after issuance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relay_id: Option<String>, // Relay the challenge is bound to, if requested
 }
 
-/// Response for PoW verification (token only)
+/// Response for PoW verification: the certificate token and its validity window
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct TokenResponse {
     pub token: String,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub expires_in_seconds: i64, // Seconds until expiry, computed server-side
+}
+
+/// Difficulty auto-tuning controller
+/// Tracks a rolling window of solve times and nudges difficulty towards a target
+#[derive(Debug)]
+pub struct PowAutotuner {
+    target_solve_time: std::time::Duration,
+    min_difficulty: u32,
+    max_difficulty: u32,
+    samples: Mutex<VecDeque<std::time::Duration>>,
+}
+
+impl PowAutotuner {
+    /// Create a new auto-tuner with a target solve time and difficulty bounds
+    pub fn new(
+        target_solve_time: std::time::Duration,
+        min_difficulty: u32,
+        max_difficulty: u32,
+    ) -> Self {
+        Self {
+            target_solve_time,
+            min_difficulty,
+            max_difficulty,
+            samples: Mutex::new(VecDeque::with_capacity(AUTOTUNE_WINDOW)),
+        }
+    }
+
+    /// Record a solve time and return the adjusted difficulty once a full window is collected
+    fn record(&self, solve_time: std::time::Duration, current: u32) -> Option<u32> {
+        let average = {
+            let mut samples = self.samples.lock().unwrap();
+            samples.push_back(solve_time);
+            if samples.len() < AUTOTUNE_WINDOW {
+                return None;
+            }
+            let total: std::time::Duration = samples.drain(..).sum();
+            total / AUTOTUNE_WINDOW as u32
+        };
+
+        // Only adjust when the average is well outside the target to avoid oscillation
+        let adjusted = if average < self.target_solve_time / 2 {
+            current.saturating_add(1)
+        } else if average > self.target_solve_time * 2 {
+            current.saturating_sub(1)
+        } else {
+            current
+        };
+
+        let adjusted = adjusted.clamp(self.min_difficulty, self.max_difficulty);
+        (adjusted != current).then_some(adjusted)
+    }
 }
 
 /// Proof of Work service for managing challenges and verification
 #[derive(Debug, Clone)]
 pub struct PowService {
     challenges: Arc<Mutex<HashMap<String, PowChallenge>>>,
-    default_difficulty: u32,
+    default_difficulty: Arc<AtomicU32>,
     challenge_lifetime: Duration,
+    autotuner: Option<Arc<PowAutotuner>>,
+    verify_permits: Option<Arc<Semaphore>>, // Bounds concurrent verifications (None = unlimited)
+    max_active_challenges: usize, // Outstanding challenges before issuance is shed (0 = unlimited)
+    reject_grandfathered: bool,   // Reject challenges issued below the current difficulty
+    algorithm: PowAlgorithm,      // Hash function recorded on newly issued challenges
+    clock: Arc<dyn Clock>,        // Time source for issuance and expiry
 }
 
 impl PowService {
@@ -63,8 +154,36 @@ impl PowService {
     pub fn new() -> Self {
         Self {
             challenges: Arc::new(Mutex::new(HashMap::new())),
-            default_difficulty: 4, // Require 4 leading zeros (moderate difficulty)
-            challenge_lifetime: Duration::minutes(10), // Challenges expire in 10 minutes
+            default_difficulty: Arc::new(AtomicU32::new(4)), // Require 4 leading zeros (moderate difficulty)
+            challenge_lifetime: Duration::minutes(10),       // Challenges expire in 10 minutes
+            autotuner: None,
+            verify_permits: None,
+            max_active_challenges: 0,
+            reject_grandfathered: false,
+            algorithm: PowAlgorithm::Sha256,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Create a new PoW service from the security configuration
+    pub fn from_config(config: &SecurityConfig) -> Self {
+        let autotuner = config.pow_autotune.then(|| {
+            Arc::new(PowAutotuner::new(
+                std::time::Duration::from_millis(config.pow_target_solve_ms),
+                config.pow_min_difficulty,
+                config.pow_max_difficulty,
+            ))
+        });
+
+        Self {
+            default_difficulty: Arc::new(AtomicU32::new(config.pow_difficulty)),
+            autotuner,
+            verify_permits: (config.pow_max_concurrent_verify > 0)
+                .then(|| Arc::new(Semaphore::new(config.pow_max_concurrent_verify))),
+            max_active_challenges: config.pow_max_active_challenges,
+            reject_grandfathered: config.pow_reject_grandfathered,
+            algorithm: config.pow_algorithm,
+            ..Self::new()
         }
     }
 
@@ -73,36 +192,126 @@ impl PowService {
     pub fn with_params(difficulty: u32, lifetime_minutes: i64) -> Self {
         Self {
             challenges: Arc::new(Mutex::new(HashMap::new())),
-            default_difficulty: difficulty,
+            default_difficulty: Arc::new(AtomicU32::new(difficulty)),
             challenge_lifetime: Duration::minutes(lifetime_minutes),
+            autotuner: None,
+            verify_permits: None,
+            max_active_challenges: 0,
+            reject_grandfathered: false,
+            algorithm: PowAlgorithm::Sha256,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Use a custom time source for issuance and expiry checks
+    #[cfg(test)]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// How long issued challenges remain valid, in seconds
+    pub fn challenge_lifetime_seconds(&self) -> i64 {
+        self.challenge_lifetime.num_seconds()
+    }
+
+    /// Current difficulty applied to newly issued challenges
+    pub fn current_difficulty(&self) -> u32 {
+        self.default_difficulty.load(Ordering::Relaxed)
+    }
+
+    /// Feed an observed solve time into the auto-tuner (no-op when disabled)
+    pub fn record_solve_time(&self, solve_time: std::time::Duration) {
+        let Some(autotuner) = &self.autotuner else {
+            return;
+        };
+
+        let current = self.current_difficulty();
+        if let Some(adjusted) = autotuner.record(solve_time, current) {
+            self.default_difficulty.store(adjusted, Ordering::Relaxed);
+            info!(
+                previous_difficulty = current,
+                new_difficulty = adjusted,
+                target_solve_ms = autotuner.target_solve_time.as_millis() as u64,
+                "PoW difficulty auto-tuned"
+            );
         }
     }
 
-    /// Generate a new PoW challenge
+    /// Generate a new PoW challenge not bound to any relay
+    #[cfg(test)]
     pub fn generate_challenge(&self) -> Result<PowChallenge, EventServerError> {
+        self.generate_challenge_for(None)
+    }
+
+    /// Generate a challenge, optionally bound to the relay that will redeem it
+    /// At the active-challenge cap expired challenges are dropped first; if the cap is
+    /// still reached, issuance is shed with a 503 until one is redeemed or expires
+    pub fn generate_challenge_for(
+        &self,
+        relay_id: Option<String>,
+    ) -> Result<PowChallenge, EventServerError> {
         let challenge_id = self.generate_challenge_id();
         let challenge_data = self.generate_challenge_data();
-        let now = Utc::now();
+        let now = self.clock.now();
 
         let challenge = PowChallenge {
             challenge_id: challenge_id.clone(),
             challenge_data,
-            difficulty: self.default_difficulty,
+            difficulty: self.current_difficulty(),
+            algorithm: self.algorithm,
             expires_at: now + self.challenge_lifetime,
             created_at: now,
+            relay_id,
         };
 
         // Store the challenge
         {
             let mut challenges = self.challenges.lock().unwrap();
+            if self.max_active_challenges > 0 && challenges.len() >= self.max_active_challenges {
+                challenges.retain(|_, challenge| challenge.expires_at > now);
+                if challenges.len() >= self.max_active_challenges {
+                    let next_expiry = challenges.values().map(|c| c.expires_at).min();
+                    warn!(
+                        active_challenges = challenges.len(),
+                        max_active_challenges = self.max_active_challenges,
+                        "Active PoW challenge cap reached, shedding challenge request"
+                    );
+                    return Err(EventServerError::ServiceUnavailable {
+                        message: "Too many active PoW challenges".to_string(),
+                        retry_after_seconds: next_expiry
+                            .map(|expiry| (expiry - now).num_seconds().max(1) as u64),
+                    });
+                }
+            }
             challenges.insert(challenge_id, challenge.clone());
         }
 
         Ok(challenge)
     }
 
-    /// Verify a PoW solution
-    pub fn verify_solution(&self, solution: &PowSolution) -> Result<(), EventServerError> {
+    /// Verify a PoW solution redeemed by `relay_id`
+    /// When a concurrency limit is configured, excess verifications are shed with a 503
+    /// instead of queueing, so a flood of submissions can't exhaust CPU
+    pub fn verify_solution(
+        &self,
+        solution: &PowSolution,
+        relay_id: &str,
+    ) -> Result<(), EventServerError> {
+        let _permit = match &self.verify_permits {
+            Some(permits) => Some(permits.try_acquire().map_err(|_| {
+                warn!(
+                    challenge_id = %solution.challenge_id,
+                    "All PoW verification slots busy, shedding request"
+                );
+                EventServerError::ServiceUnavailable {
+                    message: "Too many concurrent PoW verifications".to_string(),
+                    retry_after_seconds: Some(VERIFY_BUSY_RETRY_SECONDS),
+                }
+            })?),
+            None => None,
+        };
+
         // Get the challenge
         let challenge = {
             let challenges = self.challenges.lock().unwrap();
@@ -118,22 +327,76 @@ impl PowService {
         };
 
         // Check if challenge is expired
-        if Utc::now() > challenge.expires_at {
+        if self.clock.now() > challenge.expires_at {
             // Remove expired challenge
             {
                 let mut challenges = self.challenges.lock().unwrap();
                 challenges.remove(&solution.challenge_id);
             }
-            return Err(EventServerError::Validation(
-                "Challenge has expired".to_string(),
-            ));
+            return Err(EventServerError::ChallengeExpired {
+                lifetime_seconds: self.challenge_lifetime_seconds(),
+            });
+        }
+
+        // A bound challenge can only be redeemed by its relay; it stays usable by that relay
+        if let Some(bound_relay) = challenge.relay_id.as_deref() {
+            if bound_relay != relay_id {
+                warn!(
+                    challenge_id = %solution.challenge_id,
+                    bound_relay = %bound_relay,
+                    relay_id = %relay_id,
+                    "PoW challenge redeemed by a different relay"
+                );
+                return Err(EventServerError::Validation(format!(
+                    "Challenge {} is bound to another relay",
+                    solution.challenge_id
+                )));
+            }
+        }
+
+        // Optionally stop honouring challenges issued before the difficulty was raised
+        let minimum_difficulty = self.current_difficulty();
+        if self.reject_grandfathered && challenge.difficulty < minimum_difficulty {
+            {
+                let mut challenges = self.challenges.lock().unwrap();
+                challenges.remove(&solution.challenge_id);
+            }
+            return Err(EventServerError::Validation(format!(
+                "Challenge difficulty {} is below the current minimum of {minimum_difficulty}; request a new challenge",
+                challenge.difficulty
+            )));
         }
 
         // Verify the solution
-        let computed_hash = self.compute_hash(&challenge.challenge_data, solution.nonce)?;
+        let computed_hash = self.compute_hash(
+            &challenge.challenge_data,
+            solution.nonce,
+            challenge.algorithm,
+        )?;
 
         // Check if the computed hash matches the provided hash
         if computed_hash != solution.hash {
+            // A hash that checks out under another algorithm was solved for the wrong one;
+            // name the expected algorithm and keep the challenge so the client can re-solve it
+            let actual = PowAlgorithm::ALL
+                .into_iter()
+                .filter(|algorithm| *algorithm != challenge.algorithm)
+                .find(|algorithm| {
+                    self.compute_hash(&challenge.challenge_data, solution.nonce, *algorithm)
+                        .is_ok_and(|hash| hash == solution.hash)
+                });
+            if let Some(actual) = actual {
+                warn!(
+                    challenge_id = %solution.challenge_id,
+                    expected = challenge.algorithm.as_str(),
+                    actual = actual.as_str(),
+                    "PoW solution computed with the wrong algorithm"
+                );
+                return Err(EventServerError::PowAlgorithmMismatch {
+                    expected: challenge.algorithm,
+                    actual,
+                });
+            }
             return Err(EventServerError::Validation(
                 "Invalid hash in solution".to_string(),
             ));
@@ -153,6 +416,11 @@ impl PowService {
             challenges.remove(&solution.challenge_id);
         }
 
+        // Feed the time-to-solve into difficulty auto-tuning
+        if let Ok(solve_time) = (self.clock.now() - challenge.created_at).to_std() {
+            self.record_solve_time(solve_time);
+        }
+
         Ok(())
     }
 
@@ -170,13 +438,25 @@ impl PowService {
         base64::engine::general_purpose::STANDARD.encode(random_bytes)
     }
 
-    /// Compute hash for challenge data and nonce
-    fn compute_hash(&self, challenge_data: &str, nonce: u64) -> Result<String, EventServerError> {
-        let mut hasher = Sha256::new();
+    /// Compute hash for challenge data and nonce with the given algorithm
+    fn compute_hash(
+        &self,
+        challenge_data: &str,
+        nonce: u64,
+        algorithm: PowAlgorithm,
+    ) -> Result<String, EventServerError> {
+        let hash = match algorithm {
+            PowAlgorithm::Sha256 => Self::digest::<Sha256>(challenge_data, nonce),
+            PowAlgorithm::Sha512 => Self::digest::<Sha512>(challenge_data, nonce),
+        };
+        Ok(base64::engine::general_purpose::STANDARD.encode(hash))
+    }
+
+    fn digest<D: Digest>(challenge_data: &str, nonce: u64) -> Vec<u8> {
+        let mut hasher = D::new();
         hasher.update(challenge_data.as_bytes());
         hasher.update(nonce.to_le_bytes());
-        let hash = hasher.finalize();
-        Ok(base64::engine::general_purpose::STANDARD.encode(hash))
+        hasher.finalize().to_vec()
     }
 
     /// Check if hash meets difficulty requirement (number of leading zeros)
@@ -230,7 +510,7 @@ mod tests {
     #[test]
     fn test_pow_service_creation() {
         let service = PowService::new();
-        assert_eq!(service.default_difficulty, 4);
+        assert_eq!(service.current_difficulty(), 4);
         assert_eq!(service.active_challenge_count(), 0);
     }
 
@@ -252,14 +532,20 @@ mod tests {
         let challenge_data = "test_data";
         let nonce = 12345u64;
 
-        let hash1 = service.compute_hash(challenge_data, nonce).unwrap();
-        let hash2 = service.compute_hash(challenge_data, nonce).unwrap();
+        let hash1 = service
+            .compute_hash(challenge_data, nonce, PowAlgorithm::Sha256)
+            .unwrap();
+        let hash2 = service
+            .compute_hash(challenge_data, nonce, PowAlgorithm::Sha256)
+            .unwrap();
 
         // Same input should produce same hash
         assert_eq!(hash1, hash2);
 
         // Different nonce should produce different hash
-        let hash3 = service.compute_hash(challenge_data, nonce + 1).unwrap();
+        let hash3 = service
+            .compute_hash(challenge_data, nonce + 1, PowAlgorithm::Sha256)
+            .unwrap();
         assert_ne!(hash1, hash3);
     }
 
@@ -291,7 +577,9 @@ mod tests {
         let mut valid_hash = String::new();
 
         for i in 0..10000 {
-            let hash = service.compute_hash(&challenge.challenge_data, i).unwrap();
+            let hash = service
+                .compute_hash(&challenge.challenge_data, i, challenge.algorithm)
+                .unwrap();
             if service.meets_difficulty(&hash, 1).unwrap() {
                 nonce = i;
                 valid_hash = hash;
@@ -308,12 +596,228 @@ mod tests {
         };
 
         // Valid solution should pass
-        assert!(service.verify_solution(&solution).is_ok());
+        assert!(service.verify_solution(&solution, "test_relay").is_ok());
 
         // Challenge should be removed after successful verification
         assert!(service.get_challenge(&challenge.challenge_id).is_none());
     }
 
+    #[test]
+    fn test_concurrent_verifications_are_bounded() {
+        let config = SecurityConfig {
+            pow_difficulty: 1,
+            pow_max_concurrent_verify: 1,
+            ..crate::config::AppConfig::default().security
+        };
+        let service = PowService::from_config(&config);
+        let challenge = service.generate_challenge().unwrap();
+        let (nonce, hash) = (0..10000)
+            .map(|i| {
+                (
+                    i,
+                    service
+                        .compute_hash(&challenge.challenge_data, i, challenge.algorithm)
+                        .unwrap(),
+                )
+            })
+            .find(|(_, hash)| service.meets_difficulty(hash, 1).unwrap())
+            .unwrap();
+        let solution = PowSolution {
+            challenge_id: challenge.challenge_id,
+            nonce,
+            hash,
+        };
+
+        // While another verification holds the only slot, this one is shed
+        let in_flight = service
+            .verify_permits
+            .as_ref()
+            .unwrap()
+            .try_acquire()
+            .unwrap();
+        assert!(matches!(
+            service.verify_solution(&solution, "test_relay"),
+            Err(EventServerError::ServiceUnavailable {
+                retry_after_seconds: Some(VERIFY_BUSY_RETRY_SECONDS),
+                ..
+            })
+        ));
+
+        // Once it finishes, the shed request can be retried with the same challenge
+        drop(in_flight);
+        assert!(service.verify_solution(&solution, "test_relay").is_ok());
+    }
+
+    #[test]
+    fn test_active_challenges_are_capped() {
+        let config = SecurityConfig {
+            pow_difficulty: 1,
+            pow_max_active_challenges: 2,
+            ..crate::config::AppConfig::default().security
+        };
+        let clock = crate::crypto::MockClock::new();
+        let service = PowService::from_config(&config).with_clock(Arc::new(clock.clone()));
+
+        let first = service.generate_challenge().unwrap();
+        service.generate_challenge().unwrap();
+        assert!(matches!(
+            service.generate_challenge(),
+            Err(EventServerError::ServiceUnavailable {
+                retry_after_seconds: Some(600),
+                ..
+            })
+        ));
+
+        // Redeeming a challenge frees its slot
+        service
+            .verify_solution(&solve(&service, &first), "test_relay")
+            .unwrap();
+        service.generate_challenge().unwrap();
+        assert!(service.generate_challenge().is_err());
+
+        // So does expiry
+        clock.advance(Duration::minutes(10));
+        service.generate_challenge().unwrap();
+        assert_eq!(service.active_challenge_count(), 1);
+    }
+
+    /// Brute-force a solution for a challenge (test difficulties are low)
+    fn solve(service: &PowService, challenge: &PowChallenge) -> PowSolution {
+        solve_with(service, challenge, challenge.algorithm)
+    }
+
+    fn solve_with(
+        service: &PowService,
+        challenge: &PowChallenge,
+        algorithm: PowAlgorithm,
+    ) -> PowSolution {
+        let (nonce, hash) = (0..1_000_000)
+            .map(|i| {
+                (
+                    i,
+                    service
+                        .compute_hash(&challenge.challenge_data, i, algorithm)
+                        .unwrap(),
+                )
+            })
+            .find(|(_, hash)| {
+                service
+                    .meets_difficulty(hash, challenge.difficulty)
+                    .unwrap()
+            })
+            .unwrap();
+        PowSolution {
+            challenge_id: challenge.challenge_id.clone(),
+            nonce,
+            hash,
+        }
+    }
+
+    #[test]
+    fn test_solution_for_the_wrong_algorithm_names_the_expected_one() {
+        let config = SecurityConfig {
+            pow_difficulty: 1,
+            pow_algorithm: PowAlgorithm::Sha512,
+            ..crate::config::AppConfig::default().security
+        };
+        let service = PowService::from_config(&config);
+        let challenge = service.generate_challenge().unwrap();
+        assert_eq!(challenge.algorithm, PowAlgorithm::Sha512);
+
+        // Solved with SHA-256 against a SHA-512 challenge
+        let solution = solve_with(&service, &challenge, PowAlgorithm::Sha256);
+        let err = service
+            .verify_solution(&solution, "test_relay")
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            EventServerError::PowAlgorithmMismatch {
+                expected: PowAlgorithm::Sha512,
+                actual: PowAlgorithm::Sha256,
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "PoW solution was computed with sha256, but the challenge requires sha512"
+        );
+
+        // A hash that matches no algorithm is still just invalid
+        let garbled = PowSolution {
+            hash: base64::engine::general_purpose::STANDARD.encode([0u8; 32]),
+            ..solution
+        };
+        assert!(matches!(
+            service.verify_solution(&garbled, "test_relay"),
+            Err(EventServerError::Validation(_))
+        ));
+
+        // The challenge survives so the client can re-solve it with the right algorithm
+        let solution = solve(&service, &challenge);
+        assert!(service.verify_solution(&solution, "test_relay").is_ok());
+    }
+
+    #[test]
+    fn test_challenge_bound_to_relay() {
+        let config = SecurityConfig {
+            pow_difficulty: 1,
+            ..crate::config::AppConfig::default().security
+        };
+        let service = PowService::from_config(&config);
+
+        let bound = service
+            .generate_challenge_for(Some("relay_a".to_string()))
+            .unwrap();
+        assert_eq!(bound.relay_id.as_deref(), Some("relay_a"));
+        let solution = solve(&service, &bound);
+
+        // A different relay can't redeem it, and the challenge stays usable
+        let err = service.verify_solution(&solution, "relay_b").unwrap_err();
+        assert!(err.to_string().contains("bound to another relay"));
+        assert!(service.get_challenge(&bound.challenge_id).is_some());
+
+        assert!(service.verify_solution(&solution, "relay_a").is_ok());
+
+        // Unbound challenges are redeemable by any relay
+        let unbound = service.generate_challenge().unwrap();
+        assert!(unbound.relay_id.is_none());
+        let solution = solve(&service, &unbound);
+        assert!(service.verify_solution(&solution, "relay_b").is_ok());
+    }
+
+    #[test]
+    fn test_grandfathered_challenges() {
+        for reject_grandfathered in [false, true] {
+            let config = SecurityConfig {
+                pow_difficulty: 1,
+                pow_reject_grandfathered: reject_grandfathered,
+                ..crate::config::AppConfig::default().security
+            };
+            let service = PowService::from_config(&config);
+            let challenge = service.generate_challenge().unwrap();
+            let solution = solve(&service, &challenge);
+
+            // Difficulty is raised at runtime after the challenge was issued
+            service.default_difficulty.store(2, Ordering::Relaxed);
+
+            let result = service.verify_solution(&solution, "test_relay");
+            if reject_grandfathered {
+                assert!(result
+                    .unwrap_err()
+                    .to_string()
+                    .contains("below the current minimum of 2"));
+                assert!(service.get_challenge(&challenge.challenge_id).is_none());
+            } else {
+                assert!(result.is_ok());
+            }
+
+            // Challenges issued at the new difficulty are accepted either way
+            let fresh = service.generate_challenge().unwrap();
+            assert!(service
+                .verify_solution(&solve(&service, &fresh), "test_relay")
+                .is_ok());
+        }
+    }
+
     #[test]
     fn test_invalid_solution() {
         let service = PowService::new();
@@ -326,25 +830,98 @@ mod tests {
         };
 
         // Invalid solution should fail
-        assert!(service.verify_solution(&invalid_solution).is_err());
+        assert!(service
+            .verify_solution(&invalid_solution, "test_relay")
+            .is_err());
+    }
+
+    #[test]
+    fn test_autotune_raises_difficulty_for_fast_solves() {
+        let config = SecurityConfig {
+            pow_difficulty: 2,
+            pow_autotune: true,
+            pow_target_solve_ms: 2000,
+            pow_min_difficulty: 1,
+            pow_max_difficulty: 4,
+            ..crate::config::AppConfig::default().security
+        };
+        let service = PowService::from_config(&config);
+
+        // Three full windows of consistently fast solves
+        for round in 1..=3 {
+            for _ in 0..AUTOTUNE_WINDOW {
+                service.record_solve_time(std::time::Duration::from_millis(50));
+            }
+            assert_eq!(service.current_difficulty(), (2 + round).min(4));
+        }
+
+        // Newly issued challenges carry the tuned difficulty
+        assert_eq!(service.generate_challenge().unwrap().difficulty, 4);
+    }
+
+    #[test]
+    fn test_autotune_disabled_keeps_difficulty() {
+        let service = PowService::with_params(3, 10);
+
+        for _ in 0..AUTOTUNE_WINDOW * 2 {
+            service.record_solve_time(std::time::Duration::from_millis(50));
+        }
+
+        assert_eq!(service.current_difficulty(), 3);
     }
 
     #[test]
     fn test_expired_challenge() {
-        let service = PowService::with_params(1, 0); // Expire immediately
+        let clock = crate::crypto::MockClock::new();
+        let service = PowService::with_params(1, 10).with_clock(Arc::new(clock.clone()));
         let challenge = service.generate_challenge().unwrap();
 
-        // Wait a bit to ensure expiration (challenge expires immediately but we need time for clock to advance)
-        std::thread::sleep(std::time::Duration::from_millis(1));
-
         let solution = PowSolution {
             challenge_id: challenge.challenge_id,
             nonce: 0,
             hash: "any_hash".to_string(),
         };
 
-        let result = service.verify_solution(&solution);
-        assert!(result.is_err());
+        // Exactly at the expiry instant the challenge is still live (only the hash is wrong)
+        clock.advance(Duration::minutes(10));
+        assert!(matches!(
+            service.verify_solution(&solution, "test_relay"),
+            Err(EventServerError::Validation(_))
+        ));
+
+        // One second later it has expired
+        clock.advance(Duration::seconds(1));
+        let result = service.verify_solution(&solution, "test_relay");
+        assert!(matches!(
+            result,
+            Err(EventServerError::ChallengeExpired {
+                lifetime_seconds: 600
+            })
+        ));
         assert!(result.unwrap_err().to_string().contains("expired"));
     }
+
+    #[test]
+    fn test_solve_time_uses_injected_clock() {
+        let clock = crate::crypto::MockClock::new();
+        let config = SecurityConfig {
+            pow_difficulty: 2,
+            pow_autotune: true,
+            pow_target_solve_ms: 2000,
+            pow_min_difficulty: 1,
+            pow_max_difficulty: 4,
+            ..crate::config::AppConfig::default().security
+        };
+        let service = PowService::from_config(&config).with_clock(Arc::new(clock.clone()));
+
+        // Every challenge takes exactly 5s on the mock clock, well above the target
+        for _ in 0..AUTOTUNE_WINDOW {
+            let challenge = service.generate_challenge().unwrap();
+            let solution = solve(&service, &challenge);
+            clock.advance(Duration::seconds(5));
+            service.verify_solution(&solution, "test_relay").unwrap();
+        }
+
+        assert_eq!(service.current_difficulty(), 1);
+    }
 }
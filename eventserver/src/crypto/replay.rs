@@ -0,0 +1,150 @@
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Most tokens remembered at once; the ones closest to expiry are evicted to make room
+const MAX_REPLAY_ENTRIES: usize = 100_000;
+
+/// Remembers signed event JWTs until they expire so the same submission can't be replayed
+/// Entries are keyed by the JWT signing input (header and payload), so re-encoding the
+/// signature of an already seen token does not get past the check.
+/// A replayed token is refused with `409 Conflict`
+#[derive(Debug, Clone)]
+pub struct ReplayCache {
+    max_entries: usize,
+    seen: Arc<Mutex<SeenTokens>>,
+}
+
+#[derive(Debug, Default)]
+struct SeenTokens {
+    expiry_by_key: HashMap<String, i64>, // Digest of signing input -> JWT `exp` (unix seconds)
+    by_expiry: BTreeSet<(i64, String)>,  // Same entries ordered by `exp`, soonest first
+}
+
+impl SeenTokens {
+    /// Drop entries that expired before `now`, then the soonest-expiring ones until one more fits
+    fn make_room(&mut self, max_entries: usize, now: i64) {
+        let mut evicted = 0;
+        while let Some((expires_at, _)) = self.by_expiry.first() {
+            if *expires_at >= now && self.by_expiry.len() < max_entries {
+                break;
+            }
+            if *expires_at >= now {
+                evicted += 1;
+            }
+            let (_, key) = self.by_expiry.pop_first().unwrap();
+            self.expiry_by_key.remove(&key);
+        }
+        if evicted > 0 {
+            // An evicted token could be replayed until it expires
+            warn!(evicted, "Replay cache full, evicted unexpired tokens");
+        }
+    }
+}
+
+impl Default for ReplayCache {
+    fn default() -> Self {
+        Self::with_max_entries(MAX_REPLAY_ENTRIES)
+    }
+}
+
+impl ReplayCache {
+    fn with_max_entries(max_entries: usize) -> Self {
+        Self {
+            max_entries: max_entries.max(1),
+            seen: Arc::default(),
+        }
+    }
+
+    /// Record a verified JWT; returns false if it was already seen and has not yet expired
+    pub fn check_and_record(&self, jwt_token: &str, expires_at: i64) -> bool {
+        let signing_input = jwt_token
+            .rsplit_once('.')
+            .map_or(jwt_token, |(input, _)| input);
+        let key = hex::encode(Sha256::digest(signing_input.as_bytes()));
+
+        let now = Utc::now().timestamp();
+        let mut seen = self.seen.lock().unwrap();
+        match seen.expiry_by_key.get(&key) {
+            Some(exp) if *exp >= now => return false,
+            Some(exp) => {
+                let stale = (*exp, key.clone());
+                seen.by_expiry.remove(&stale);
+                seen.expiry_by_key.remove(&key);
+            }
+            None => {}
+        }
+
+        seen.make_room(self.max_entries, now);
+        seen.by_expiry.insert((expires_at, key.clone()));
+        seen.expiry_by_key.insert(key, expires_at);
+        true
+    }
+
+    /// Number of tracked tokens, including ones that expired since the last check
+    pub fn len(&self) -> usize {
+        self.seen.lock().unwrap().expiry_by_key.len()
+    }
+
+    /// Forget every tracked token; returns how many were dropped
+    pub fn flush(&self) -> usize {
+        let mut seen = self.seen.lock().unwrap();
+        let count = seen.expiry_by_key.len();
+        *seen = SeenTokens::default();
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replayed_token_is_rejected() {
+        let cache = ReplayCache::default();
+        let exp = Utc::now().timestamp() + 60;
+
+        assert!(cache.check_and_record("header.payload.sig1", exp));
+        assert!(!cache.check_and_record("header.payload.sig1", exp));
+        // Same signed content with a different signature encoding is still a replay
+        assert!(!cache.check_and_record("header.payload.sig2", exp));
+        assert!(cache.check_and_record("header.other.sig1", exp));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_expired_entries_are_pruned() {
+        let cache = ReplayCache::default();
+        let past = Utc::now().timestamp() - 1;
+
+        assert!(cache.check_and_record("header.payload.sig", past));
+        assert!(cache.check_and_record("header.payload.sig", past));
+    }
+
+    #[test]
+    fn test_soonest_expiring_tokens_are_evicted_when_full() {
+        let cache = ReplayCache::with_max_entries(2);
+        let now = Utc::now().timestamp();
+
+        assert!(cache.check_and_record("h.soon.s", now + 10));
+        assert!(cache.check_and_record("h.late.s", now + 60));
+        assert!(cache.check_and_record("h.later.s", now + 120));
+        assert_eq!(cache.len(), 2);
+
+        // The soonest-expiring token made room, the others are still refused
+        assert!(!cache.check_and_record("h.late.s", now + 60));
+        assert!(!cache.check_and_record("h.later.s", now + 120));
+        assert!(cache.check_and_record("h.soon.s", now + 10));
+    }
+
+    #[test]
+    fn test_flush_empties_cache() {
+        let cache = ReplayCache::default();
+        cache.check_and_record("a.b.c", Utc::now().timestamp() + 60);
+
+        assert_eq!(cache.flush(), 1);
+        assert_eq!(cache.len(), 0);
+    }
+}
@@ -0,0 +1,166 @@
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::crypto::CertificateValidation;
+
+/// Small LRU cache of successful certificate validations, keyed by a digest of the token
+/// Entries never outlive the validation they memoize: the caller bounds each one by the
+/// certificate's expiry, and revocation or key rotation drops them explicitly
+#[derive(Debug, Clone)]
+pub struct ValidationCache {
+    state: Arc<Mutex<CacheState>>,
+    capacity: usize, // 0 disables caching
+    hits: Arc<AtomicU64>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<String, CachedValidation>,
+    tick: u64, // Monotonic use counter for LRU ordering
+}
+
+#[derive(Debug)]
+struct CachedValidation {
+    validation: CertificateValidation,
+    valid_until: DateTime<Utc>,
+    last_used: u64,
+}
+
+impl ValidationCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(CacheState::default())),
+            capacity,
+            hits: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Cached validation for `token`, if one is still valid at `now`
+    pub fn get(&self, token: &str, now: DateTime<Utc>) -> Option<CertificateValidation> {
+        if self.capacity == 0 {
+            return None;
+        }
+
+        let key = Self::key(token);
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+
+        let entry = state.entries.get_mut(&key)?;
+        if now > entry.valid_until {
+            state.entries.remove(&key);
+            return None;
+        }
+        entry.last_used = tick;
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(entry.validation.clone())
+    }
+
+    /// Remember a successful validation until `valid_until`, evicting the least recently used
+    /// entry when full
+    pub fn insert(
+        &self,
+        token: &str,
+        validation: CertificateValidation,
+        valid_until: DateTime<Utc>,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let key = Self::key(token);
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+
+        if state.entries.len() >= self.capacity && !state.entries.contains_key(&key) {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                state.entries.remove(&oldest);
+            }
+        }
+
+        state.entries.insert(
+            key,
+            CachedValidation {
+                validation,
+                valid_until,
+                last_used: tick,
+            },
+        );
+    }
+
+    /// Drop every cached validation of a certificate
+    pub fn invalidate(&self, certificate_id: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .entries
+            .retain(|_, entry| entry.validation.certificate_id != certificate_id);
+    }
+
+    /// Number of lookups answered from the cache
+    #[cfg(test)]
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    fn key(token: &str) -> String {
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn validation(certificate_id: &str) -> CertificateValidation {
+        CertificateValidation {
+            certificate_id: certificate_id.to_string(),
+            relay_id: "test_relay".to_string(),
+            public_key: "test_public_key".to_string(),
+            expires_at: Utc::now() + Duration::hours(1),
+            previous_public_key: None,
+            ed25519_public_key: None,
+            tenant_id: None,
+        }
+    }
+
+    #[test]
+    fn test_lru_eviction_and_invalidation() {
+        let cache = ValidationCache::new(2);
+        let now = Utc::now();
+        let until = now + Duration::minutes(5);
+
+        cache.insert("a", validation("cert_a"), until);
+        cache.insert("b", validation("cert_b"), until);
+        assert!(cache.get("a", now).is_some()); // "b" is now least recently used
+        cache.insert("c", validation("cert_c"), until);
+
+        assert!(cache.get("b", now).is_none());
+        assert!(cache.get("a", now).is_some());
+        assert!(cache.get("c", now).is_some());
+        assert_eq!(cache.hits(), 3);
+
+        cache.invalidate("cert_a");
+        assert!(cache.get("a", now).is_none());
+        assert!(cache.get("a", until + Duration::seconds(1)).is_none());
+        assert!(cache.get("c", until + Duration::seconds(1)).is_none());
+    }
+
+    #[test]
+    fn test_zero_capacity_disables_cache() {
+        let cache = ValidationCache::new(0);
+        let now = Utc::now();
+        cache.insert("a", validation("cert_a"), now + Duration::minutes(5));
+        assert!(cache.get("a", now).is_none());
+    }
+}
@@ -1,17 +1,23 @@
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
 use thiserror::Error;
 
+use crate::config::PowAlgorithm;
+
 /// Type alias for EventServer errors - uses the main AppError type
 pub type EventServerError = AppError;
 
+/// Response extension marking bodies built from an `AppError`,
+/// so they can be reshaped to the configured error format
+#[derive(Debug, Clone, Copy)]
+pub struct AppErrorResponse;
+
 /// Application-wide error types
 #[derive(Error, Debug)]
-#[allow(dead_code)]
 pub enum AppError {
     #[error("Validation error: {0}")]
     Validation(String),
@@ -26,7 +32,7 @@ pub enum AppError {
     Config(String),
 
     #[error("Rate limit exceeded")]
-    RateLimit,
+    RateLimit(crate::types::api::RateLimitInfo),
 
     #[error("Resource not found: {0}")]
     NotFound(String),
@@ -37,8 +43,107 @@ pub enum AppError {
     #[error("Bad request: {0}")]
     BadRequest(String),
 
-    #[error("Service unavailable: {0}")]
-    ServiceUnavailable(String),
+    #[error("Service unavailable: {message}")]
+    ServiceUnavailable {
+        message: String,
+        /// Sent as `Retry-After` so clients back off instead of retrying immediately
+        retry_after_seconds: Option<u64>,
+    },
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    #[error("Request headers too large: {0}")]
+    HeadersTooLarge(String),
+
+    #[error(
+        "Challenge has expired; challenges are valid for {lifetime_seconds} seconds after issuance"
+    )]
+    ChallengeExpired { lifetime_seconds: i64 },
+
+    #[error(
+        "PoW solution was computed with {}, but the challenge requires {}",
+        actual.as_str(),
+        expected.as_str()
+    )]
+    PowAlgorithmMismatch {
+        expected: PowAlgorithm,
+        actual: PowAlgorithm,
+    },
+
+    #[error("Method not allowed: {0}")]
+    MethodNotAllowed(String),
+
+    #[error("Request timed out: {0}")]
+    Timeout(String),
+
+    #[error("Range not satisfiable: {0}")]
+    RangeNotSatisfiable(String),
+
+    #[error("Scan stopped after {scanned} objects without a match; narrow it with a date")]
+    ScanTruncated { scanned: usize },
+
+    #[error("Relay {relay_id} has used {used_bytes} of its {quota_bytes}-byte storage quota")]
+    QuotaExceeded {
+        relay_id: String,
+        used_bytes: u64,
+        quota_bytes: u64,
+    },
+
+    #[error("Authentication failed: {message}")]
+    Authentication {
+        reason: AuthFailure,
+        message: String,
+    },
+}
+
+/// Specific reasons a request can fail certificate/JWT authentication
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthFailure {
+    /// No `Authorization: Bearer` header was sent
+    MissingToken,
+    /// The certificate token could not be decoded or its signature is wrong
+    CertInvalid,
+    /// The certificate token is well-formed but unknown to this server
+    CertNotFound,
+    /// The certificate has passed its expiry time
+    CertExpired,
+    /// The certificate was revoked before its expiry time
+    CertRevoked,
+    /// The certificate is unexpired but older than the configured maximum accepted age
+    CertTooOld,
+    /// The signed event JWT failed verification
+    JwtInvalid,
+    /// The device public key bound to the certificate is not a valid P-256 JWK
+    JwkInvalid,
+    /// The Ed25519 package signature is missing, wrong, or not bound to the certificate
+    SignatureInvalid,
+}
+
+impl AuthFailure {
+    /// Machine-readable code returned in the error body
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuthFailure::MissingToken => "MISSING_TOKEN",
+            AuthFailure::CertInvalid => "CERT_INVALID",
+            AuthFailure::CertNotFound => "CERT_NOT_FOUND",
+            AuthFailure::CertExpired => "CERT_EXPIRED",
+            AuthFailure::CertRevoked => "CERT_REVOKED",
+            AuthFailure::CertTooOld => "CERT_TOO_OLD",
+            AuthFailure::JwtInvalid => "JWT_INVALID",
+            AuthFailure::JwkInvalid => "JWK_INVALID",
+            AuthFailure::SignatureInvalid => "SIGNATURE_INVALID",
+        }
+    }
 }
 
 impl IntoResponse for AppError {
@@ -65,7 +170,7 @@ impl IntoResponse for AppError {
                 self.to_string(),
                 "CONFIG_ERROR",
             ),
-            AppError::RateLimit => (
+            AppError::RateLimit(_) => (
                 StatusCode::TOO_MANY_REQUESTS,
                 self.to_string(),
                 "RATE_LIMIT_EXCEEDED",
@@ -77,20 +182,111 @@ impl IntoResponse for AppError {
                 "INTERNAL_ERROR",
             ),
             AppError::BadRequest(_) => (StatusCode::BAD_REQUEST, self.to_string(), "BAD_REQUEST"),
-            AppError::ServiceUnavailable(_) => (
+            AppError::ServiceUnavailable { .. } => (
                 StatusCode::SERVICE_UNAVAILABLE,
                 self.to_string(),
                 "SERVICE_UNAVAILABLE",
             ),
+            AppError::Unauthorized(_) => {
+                (StatusCode::UNAUTHORIZED, self.to_string(), "UNAUTHORIZED")
+            }
+            AppError::Forbidden(_) => (StatusCode::FORBIDDEN, self.to_string(), "FORBIDDEN"),
+            AppError::Conflict(_) => (StatusCode::CONFLICT, self.to_string(), "CONFLICT"),
+            AppError::PayloadTooLarge(_) => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                self.to_string(),
+                "PAYLOAD_TOO_LARGE",
+            ),
+            AppError::HeadersTooLarge(_) => (
+                StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+                self.to_string(),
+                "HEADERS_TOO_LARGE",
+            ),
+            AppError::ChallengeExpired { .. } => {
+                (StatusCode::GONE, self.to_string(), "CHALLENGE_EXPIRED")
+            }
+            AppError::PowAlgorithmMismatch { .. } => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                self.to_string(),
+                "POW_ALGORITHM_MISMATCH",
+            ),
+            AppError::MethodNotAllowed(_) => (
+                StatusCode::METHOD_NOT_ALLOWED,
+                self.to_string(),
+                "METHOD_NOT_ALLOWED",
+            ),
+            AppError::Timeout(_) => (
+                StatusCode::GATEWAY_TIMEOUT,
+                self.to_string(),
+                "REQUEST_TIMEOUT",
+            ),
+            AppError::RangeNotSatisfiable(_) => (
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                self.to_string(),
+                "RANGE_NOT_SATISFIABLE",
+            ),
+            AppError::ScanTruncated { .. } => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                self.to_string(),
+                "SCAN_TRUNCATED",
+            ),
+            AppError::QuotaExceeded { .. } => (
+                StatusCode::INSUFFICIENT_STORAGE,
+                self.to_string(),
+                "RELAY_QUOTA_EXCEEDED",
+            ),
+            AppError::Authentication { reason, .. } => {
+                (StatusCode::UNAUTHORIZED, self.to_string(), reason.code())
+            }
         };
 
-        let body = Json(json!({
+        let mut body = json!({
             "error": error_message,
             "code": error_code,
             "timestamp": chrono::Utc::now(),
-        }));
+        });
+        // Throttled clients get the limit details in the body for precise backoff
+        if let AppError::RateLimit(info) = &self {
+            if let (Some(body), Ok(serde_json::Value::Object(info))) =
+                (body.as_object_mut(), serde_json::to_value(info))
+            {
+                body.extend(info);
+            }
+        }
+        // Let the relay see how far over budget it is
+        if let AppError::QuotaExceeded {
+            used_bytes,
+            quota_bytes,
+            ..
+        } = &self
+        {
+            body["used_bytes"] = (*used_bytes).into();
+            body["quota_bytes"] = (*quota_bytes).into();
+        }
+        // Tell the client which algorithm to re-solve with
+        if let AppError::PowAlgorithmMismatch { expected, .. } = &self {
+            body["expected_algorithm"] = expected.as_str().into();
+        }
 
-        (status, body).into_response()
+        let mut response = (status, Json(body)).into_response();
+        response.extensions_mut().insert(AppErrorResponse);
+        if let AppError::RateLimit(info) = &self {
+            info.insert_headers(response.headers_mut());
+            response.headers_mut().insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(info.seconds_until_reset()),
+            );
+        }
+        if let AppError::ServiceUnavailable {
+            retry_after_seconds: Some(seconds),
+            ..
+        } = &self
+        {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(*seconds));
+        }
+        response
     }
 }
 
@@ -134,18 +330,13 @@ impl From<anyhow::Error> for AppError {
     }
 }
 
-/// Validation error details for structured error responses
-#[derive(Debug, serde::Serialize)]
-pub struct ValidationErrorDetails {
-    pub field: String,
-    pub message: String,
-}
-
 impl AppError {
-    /// Create a validation error with multiple field errors
-    pub fn _validation_with_details(message: &str, details: Vec<ValidationErrorDetails>) -> Self {
-        let details_json = serde_json::to_value(details).unwrap_or_default();
-        AppError::Validation(format!("{message}: {details_json}"))
+    /// Create an authentication error for the given failure reason
+    pub fn auth(reason: AuthFailure, message: impl Into<String>) -> Self {
+        AppError::Authentication {
+            reason,
+            message: message.into(),
+        }
     }
 
     /// Create a storage error with operation context
@@ -177,4 +368,33 @@ mod tests {
         // Response should have BAD_REQUEST status
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
+
+    #[test]
+    fn test_service_unavailable_sets_retry_after() {
+        let response = AppError::ServiceUnavailable {
+            message: "Storage quota exceeded".to_string(),
+            retry_after_seconds: Some(300),
+        }
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "300");
+    }
+
+    #[tokio::test]
+    async fn test_pow_algorithm_mismatch_names_expected_algorithm() {
+        let response = AppError::PowAlgorithmMismatch {
+            expected: PowAlgorithm::Sha512,
+            actual: PowAlgorithm::Sha256,
+        }
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "POW_ALGORITHM_MISMATCH");
+        assert_eq!(json["expected_algorithm"], "sha512");
+    }
 }
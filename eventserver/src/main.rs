@@ -1,4 +1,4 @@
-use axum::{middleware as axum_middleware, routing::get, Router};
+use axum::{extract::DefaultBodyLimit, middleware as axum_middleware, routing::get, Router};
 use tower_http::{cors::CorsLayer, trace::TraceLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -7,14 +7,31 @@ mod controllers;
 mod crypto;
 mod error;
 mod middleware;
+mod server;
 mod services;
 mod state;
+#[cfg(test)]
+mod test_utils;
+mod tls;
 mod types;
 
 use crate::config::AppConfig;
-use crate::crypto::{CertificateRequest, CertificateService, PowCertificateRequest, PowService};
+use crate::crypto::{
+    CertificateRequest, CertificateService, PowCertificateRequest, PowChallengeRequest,
+    PowChallengeResponse, PowService, TokenResponse,
+};
+use crate::error::AppError;
+use crate::middleware::access_log::access_log_middleware;
+use crate::middleware::admin::admin_auth_middleware;
 use crate::middleware::crypto::crypto_validation_middleware;
-use crate::services::{EventService, StorageService};
+use crate::middleware::error_format::error_format_middleware;
+use crate::middleware::header_limits::header_limits_middleware;
+use crate::middleware::rate_limit::rate_limit_middleware;
+use crate::middleware::request_span::{request_span_middleware, RequestLogSampling};
+use crate::middleware::server_instance::server_instance_middleware;
+use crate::middleware::server_timing::server_timing_middleware;
+use crate::middleware::timeout::request_timeout_middleware;
+use crate::services::{certificate_sync, EventService, StorageService};
 use crate::state::AppState;
 
 #[tokio::main]
@@ -29,6 +46,7 @@ async fn main() -> anyhow::Result<()> {
                 .unwrap_or_else(|_| "eventserver=debug,tower_http=debug".into()),
         )
         .with(tracing_subscriber::fmt::layer())
+        .with(RequestLogSampling)
         .init();
 
     // Load configuration
@@ -37,9 +55,80 @@ async fn main() -> anyhow::Result<()> {
 
     // Initialize services
     let storage_service = StorageService::new(config.storage.clone()).await?;
-    let event_service = EventService::new(storage_service.clone());
-    let pow_service = PowService::new();
-    let certificate_service = CertificateService::new(config.security.jwt_secret.clone());
+    let event_service = EventService::new(storage_service.clone(), config.validation.clone());
+    if config.storage.migrate {
+        storage_service
+            .migrate_legacy_keys(|event_package| event_service.generate_event_hash(event_package))
+            .await?;
+    }
+    let pow_service = PowService::from_config(&config.security);
+    let certificate_service = CertificateService::new(config.security.jwt_secret.clone())
+        .with_certificate_lifetime(chrono::Duration::hours(
+            config.security.certificate_validity_hours as i64,
+        ))
+        .with_capacity_limit(
+            config.security.cert_max_active,
+            config.security.cert_cap_policy,
+        )
+        .with_key_rotation_grace(chrono::Duration::seconds(
+            config.security.cert_key_rotation_grace_seconds as i64,
+        ))
+        .with_validation_cache_size(config.security.cert_validation_cache_size)
+        .with_max_accepted_age(
+            config
+                .security
+                .cert_max_accepted_age_hours
+                .map(|hours| chrono::Duration::hours(hours as i64)),
+        );
+    let certificate_service = match config.security.cert_token_algorithm()? {
+        jsonwebtoken::Algorithm::EdDSA => certificate_service.with_signing_keys(
+            certificate_sync::load_signing_keys(&storage_service, config.security.seed_cipher()?)
+                .await?,
+        ),
+        algorithm => certificate_service.with_token_algorithm(algorithm),
+    };
+    let certificate_service = match (
+        config
+            .security
+            .previous_jwt_secret
+            .clone()
+            .filter(|secret| !secret.is_empty()),
+        config.security.previous_jwt_secret_expires_at,
+    ) {
+        (Some(previous), Some(overlap_ends)) if overlap_ends > chrono::Utc::now() => {
+            tracing::info!(%overlap_ends, "Accepting the previous JWT secret during rotation overlap");
+            certificate_service.with_previous_secret(previous, overlap_ends)
+        }
+        (Some(_), _) => {
+            tracing::info!("Rotation overlap has ended, the previous JWT secret is ignored");
+            certificate_service
+        }
+        (None, _) => certificate_service,
+    };
+
+    if config.security.cert_persistence {
+        if let Err(e) = certificate_sync::warm_up_certificates(
+            &certificate_service,
+            &storage_service,
+            config.security.cert_warmup_limit,
+        )
+        .await
+        {
+            tracing::warn!(error = %e, "Certificate warm-up failed, starting with an empty cache");
+        }
+    }
+    // Reconciling also reloads EdDSA keyrings, so rotations on other instances are picked up
+    let signs_with_keyring = certificate_service.signing_key_id().is_some();
+    if config.security.cert_reconcile_interval_seconds > 0
+        && (config.security.cert_persistence || signs_with_keyring)
+    {
+        certificate_sync::spawn_certificate_reconciler(
+            certificate_service.clone(),
+            storage_service.clone(),
+            std::time::Duration::from_secs(config.security.cert_reconcile_interval_seconds),
+            config.security.cert_persistence,
+        );
+    }
 
     // Create an application state
     let app_state = AppState::new(
@@ -47,86 +136,247 @@ async fn main() -> anyhow::Result<()> {
         storage_service,
         pow_service,
         certificate_service,
+        config.clone(),
+    );
+
+    let event_bus = app_state.event_bus.clone();
+    let background_stores = app_state.background_stores.clone();
+    let app = create_app(app_state);
+
+    // Refuse to start rather than serve with a weaker TLS policy than configured
+    let tls_config = tls::load_tls_config(&config.server)?;
+
+    // Start server
+    let bind_address = format!("{}:{}", config.server.host, config.server.port);
+    let listener = tokio::net::TcpListener::bind(&bind_address).await?;
+
+    tracing::info!(
+        tls = tls_config.is_some(),
+        "EventServer listening on {}",
+        listener.local_addr()?
+    );
+    tracing::info!(
+        "Server started successfully - Stateless EventServer v{} with cryptographic validation",
+        env!("CARGO_PKG_VERSION")
     );
 
-    // Build application router with separate public and protected routes
-    let app = Router::new()
+    server::serve(
+        listener,
+        app,
+        tls_config,
+        server::ConnectionLimits::from_config(&config.server),
+        shutdown_signal(),
+    )
+    .await;
+
+    tracing::info!("Shutting down, finishing background stores");
+    let unfinished = background_stores
+        .drain(BACKGROUND_STORE_DRAIN_TIMEOUT)
+        .await;
+    if unfinished > 0 {
+        tracing::warn!(
+            unfinished,
+            "Shutdown timed out with background stores still running; those events were accepted but not stored"
+        );
+    }
+    tracing::info!("Delivering buffered event notifications");
+    event_bus.shutdown(EVENT_BUS_DRAIN_TIMEOUT).await;
+
+    Ok(())
+}
+
+/// How long shutdown waits for async-accepted event packages to finish storing
+const BACKGROUND_STORE_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// How long shutdown waits for buffered event bus notifications to be delivered
+const EVENT_BUS_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Resolves on Ctrl+C, or SIGTERM on Unix
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %e, "Failed to listen for Ctrl+C");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Build the application router with separate public and protected routes
+fn create_app(app_state: AppState) -> Router {
+    Router::new()
         // Public routes (no authentication required)
         .route("/health", get(controllers::health::health_check))
-        .merge(controllers::openapi::routes())
-        // PoW routes (public endpoints for authentication)
+        .route("/readyz", get(controllers::health::readiness))
+        .route(
+            "/capabilities",
+            get(controllers::capabilities::capabilities),
+        )
+        .merge(controllers::openapi::routes(&app_state.config.docs))
+        .route("/api/v1/jwks", get(controllers::jwks::jwks))
+        // PoW routes (public endpoints for authentication), with bodies capped well
+        // below the event package limit since they only ever carry a small solution
         .route(
             "/api/v1/pow/challenge",
-            axum::routing::post(request_pow_challenge),
+            axum::routing::post(request_pow_challenge).layer(DefaultBodyLimit::max(
+                app_state.config.server.pow_max_body_bytes,
+            )),
         )
         .route(
             "/api/v1/pow/verify",
-            axum::routing::post(verify_pow_and_issue_certificate),
+            axum::routing::post(verify_pow_and_issue_certificate).layer(DefaultBodyLimit::max(
+                app_state.config.server.pow_max_body_bytes,
+            )),
         )
         // Protected routes (require authentication)
         .nest(
             "/api/v1",
             api_routes()
+                // Rate limit per validated relay (runs after crypto validation)
+                .layer(axum_middleware::from_fn_with_state(
+                    app_state.clone(),
+                    rate_limit_middleware,
+                ))
                 // Apply crypto validation middleware only to protected routes
                 .layer(axum_middleware::from_fn_with_state(
                     app_state.clone(),
                     crypto_validation_middleware,
                 )),
         )
+        // Admin routes (require the configured admin token)
+        .nest(
+            "/api/v1/admin",
+            controllers::admin::routes()
+                .merge(controllers::relay::routes())
+                .layer(axum_middleware::from_fn_with_state(
+                    app_state.clone(),
+                    admin_auth_middleware,
+                )),
+        )
+        // Structured JSON 405 (with Allow) for known paths hit with an unsupported method;
+        // must come after every route so it reaches all of them
+        .method_not_allowed_fallback(controllers::fallback::method_not_allowed)
+        // Structured JSON 404 for any unmatched path
+        .fallback(controllers::fallback::not_found)
+        // Default body limit for extractors, matching the crypto middleware's own cap;
+        // routes with a tighter limit set their own
+        .layer(DefaultBodyLimit::max(
+            app_state.config.server.max_body_bytes,
+        ))
+        // Answer requests exceeding server.request_timeout with a structured 504
+        .layer(axum_middleware::from_fn_with_state(
+            app_state.clone(),
+            request_timeout_middleware,
+        ))
+        // Per-request span carrying request_id, relay_id and event_id for all nested logs
+        .layer(axum_middleware::from_fn_with_state(
+            app_state.clone(),
+            request_span_middleware,
+        ))
+        // Reject oversized or too numerous headers with 431 before anything parses them
+        .layer(axum_middleware::from_fn_with_state(
+            app_state.clone(),
+            header_limits_middleware,
+        ))
+        // Reshape error bodies to the configured error format
+        .layer(axum_middleware::from_fn_with_state(
+            app_state.clone(),
+            error_format_middleware,
+        ))
+        // Name the handling instance on every response, including errors and 404s
+        .layer(axum_middleware::from_fn_with_state(
+            app_state.clone(),
+            server_instance_middleware,
+        ))
+        // Phase durations in a Server-Timing header, when enabled
+        .layer(axum_middleware::from_fn_with_state(
+            app_state.clone(),
+            server_timing_middleware,
+        ))
+        // One access log line per request, in the configured format
+        .layer(axum_middleware::from_fn_with_state(
+            app_state.clone(),
+            access_log_middleware,
+        ))
         .layer(TraceLayer::new_for_http())
         .layer(CorsLayer::permissive())
-        .with_state(app_state);
-
-    // Start server
-    let bind_address = format!("{}:{}", config.server.host, config.server.port);
-    let listener = tokio::net::TcpListener::bind(&bind_address).await?;
-
-    tracing::info!("EventServer listening on {}", listener.local_addr()?);
-    tracing::info!(
-        "Server started successfully - Stateless EventServer v{} with cryptographic validation",
-        env!("CARGO_PKG_VERSION")
-    );
-
-    axum::serve(listener, app).await?;
-
-    Ok(())
+        .with_state(app_state)
 }
 
 fn api_routes() -> Router<AppState> {
-    Router::new().merge(controllers::event::routes())
+    Router::new()
+        .merge(controllers::event::routes())
+        .merge(controllers::certificate::routes())
+        .merge(controllers::tools::routes())
 }
 
 /// Request a new PoW challenge (public endpoint)
 #[utoipa::path(
     post,
     path = "/api/v1/pow/challenge",
+    request_body(content = Option<PowChallengeRequest>, description = "Optionally bind the challenge to a relay_id"),
     responses(
         (status = 200, description = "PoW challenge generated successfully", body = PowChallengeResponse),
-        (status = 500, description = "Failed to generate PoW challenge")
+        (status = 400, description = "Malformed challenge request body"),
+        (status = 500, description = "Failed to generate PoW challenge"),
+        (status = 503, description = "Too many active challenges - retry after the Retry-After interval")
     ),
     tag = "authentication"
 )]
 async fn request_pow_challenge(
     axum::extract::State(state): axum::extract::State<AppState>,
-) -> Result<axum::Json<serde_json::Value>, axum::http::StatusCode> {
-    match state.pow_service.generate_challenge() {
+    body: axum::body::Bytes,
+) -> Result<axum::Json<PowChallengeResponse>, AppError> {
+    // The body is optional; an empty one requests an unbound challenge
+    let request = if body.trim_ascii().is_empty() {
+        PowChallengeRequest::default()
+    } else {
+        serde_json::from_slice::<PowChallengeRequest>(&body).map_err(|e| {
+            tracing::warn!(error = %e, "Malformed PoW challenge request");
+            AppError::BadRequest(format!("Malformed PoW challenge request: {e}"))
+        })?
+    };
+
+    match state.pow_service.generate_challenge_for(request.relay_id) {
         Ok(challenge) => {
             tracing::info!(
                 challenge_id = %challenge.challenge_id,
                 difficulty = challenge.difficulty,
+                relay_id = ?challenge.relay_id,
                 "PoW challenge generated"
             );
 
-            Ok(axum::Json(serde_json::json!({
-                "challenge_id": challenge.challenge_id,
-                "challenge_data": challenge.challenge_data,
-                "difficulty": challenge.difficulty,
-                "expires_at": challenge.expires_at
-            })))
+            Ok(axum::Json(PowChallengeResponse {
+                challenge_id: challenge.challenge_id,
+                challenge_data: challenge.challenge_data,
+                difficulty: challenge.difficulty,
+                algorithm: challenge.algorithm,
+                expires_at: challenge.expires_at,
+                challenge_lifetime: state.pow_service.challenge_lifetime_seconds(),
+                relay_id: challenge.relay_id,
+            }))
         }
         Err(e) => {
             tracing::error!(error = %e, "Failed to generate PoW challenge");
-            Err(axum::http::StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e)
         }
     }
 }
@@ -139,17 +389,28 @@ async fn request_pow_challenge(
     responses(
         (status = 200, description = "PoW verified and certificate issued successfully", body = TokenResponse),
         (status = 400, description = "Invalid PoW solution or request data"),
-        (status = 401, description = "PoW verification failed"),
-        (status = 500, description = "Failed to issue certificate")
+        (status = 401, description = "PoW verification failed, or missing/invalid tenant enrollment token"),
+        (status = 403, description = "Tenant header differs from the enrollment token's tenant"),
+        (status = 410, description = "PoW challenge expired - request a new challenge"),
+        (status = 422, description = "PoW solution computed with the wrong algorithm - re-solve with expected_algorithm"),
+        (status = 500, description = "Failed to issue certificate"),
+        (status = 503, description = "Too many concurrent verifications - retry after the Retry-After interval")
     ),
     tag = "authentication"
 )]
 async fn verify_pow_and_issue_certificate(
     axum::extract::State(state): axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
     axum::Json(request): axum::Json<PowCertificateRequest>,
-) -> Result<axum::Json<serde_json::Value>, axum::http::StatusCode> {
+) -> Result<axum::Json<TokenResponse>, AppError> {
+    // The tenant comes from an admin-issued enrollment token and is bound into the certificate
+    let tenant_id = services::tenant::issuance_tenant(&state.config.security, &headers)?;
+
     // First, verify the PoW solution
-    match state.pow_service.verify_solution(&request.solution) {
+    match state
+        .pow_service
+        .verify_solution(&request.solution, &request.relay_id)
+    {
         Ok(()) => {
             tracing::info!(
                 relay_id = %request.relay_id,
@@ -161,21 +422,37 @@ async fn verify_pow_and_issue_certificate(
             let cert_request = CertificateRequest {
                 relay_id: request.relay_id.clone(),
                 public_key: request.public_key.clone(),
+                ed25519_public_key: request.ed25519_public_key.clone(),
+                tenant_id,
             };
 
             // Issue the certificate
             match state.certificate_service.issue_certificate(&cert_request) {
-                Ok(certificate_response) => Ok(axum::Json(serde_json::json!({
-
-                    "token": certificate_response.cert_token
-                }))),
+                // At the certificate cap with the reject policy; the client should back off
+                Err(e @ AppError::ServiceUnavailable { .. }) => {
+                    tracing::warn!(relay_id = %request.relay_id, "Certificate issuance refused at capacity");
+                    Err(e)
+                }
+                Ok(certificate_response) => {
+                    if state.config.security.cert_persistence {
+                        persist_certificate(&state, &certificate_response.certificate_id).await;
+                    }
+                    Ok(axum::Json(TokenResponse {
+                        token: certificate_response.cert_token,
+                        issued_at: certificate_response.issued_at,
+                        expires_at: certificate_response.expires_at,
+                        expires_in_seconds: certificate_response.expires_in_seconds,
+                    }))
+                }
                 Err(e) => {
                     tracing::error!(
                         error = %e,
                         relay_id = %request.relay_id,
                         "Failed to issue certificate after PoW verification"
                     );
-                    Err(axum::http::StatusCode::INTERNAL_SERVER_ERROR)
+                    Err(AppError::Internal(
+                        "Failed to issue certificate".to_string(),
+                    ))
                 }
             }
         }
@@ -186,7 +463,444 @@ async fn verify_pow_and_issue_certificate(
                 challenge_id = %request.solution.challenge_id,
                 "PoW solution verification failed for certificate request"
             );
-            Err(axum::http::StatusCode::UNAUTHORIZED)
+            match e {
+                // Tell the client how long challenges live so it can budget solve time
+                AppError::ChallengeExpired { .. } => Err(e),
+                // Name the expected algorithm so the client can re-solve
+                AppError::PowAlgorithmMismatch { .. } => Err(e),
+                // Verification was shed under load; the client should back off and retry
+                AppError::ServiceUnavailable { .. } => Err(e),
+                _ => Err(AppError::Unauthorized(
+                    "PoW verification failed".to_string(),
+                )),
+            }
         }
     }
 }
+
+/// Best-effort write of a certificate revocation to storage
+/// Failures are logged; the revocation then only holds on this instance until it restarts
+async fn persist_revocation(state: &AppState, certificate_id: &str) {
+    let Some(revocation) = state.certificate_service.revocation(certificate_id) else {
+        return;
+    };
+    if let Err(e) = state.storage_service.save_revocation(&revocation).await {
+        tracing::error!(
+            error = %e,
+            certificate_id = %certificate_id,
+            "Failed to persist certificate revocation"
+        );
+    }
+}
+
+/// Best-effort write of a newly issued certificate to storage
+/// Failures are only logged; an unpersisted certificate is evicted at the next reconcile
+async fn persist_certificate(state: &AppState, certificate_id: &str) {
+    let Some(certificate) = state.certificate_service.certificate(certificate_id) else {
+        return;
+    };
+    if let Err(e) = state.storage_service.save_certificate(&certificate).await {
+        tracing::warn!(
+            error = %e,
+            relay_id = %certificate.relay_id,
+            "Failed to persist issued certificate"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use tower::ServiceExt;
+
+    async fn test_app() -> Router {
+        create_app(AppState::new_mock(AppConfig::default()).await)
+    }
+
+    /// Check a response body parses as `T` and has exactly the fields its OpenAPI schema documents
+    fn assert_matches_schema<T: serde::de::DeserializeOwned>(
+        json: &serde_json::Value,
+        schema: &str,
+    ) {
+        serde_json::from_value::<T>(json.clone()).unwrap();
+
+        let spec =
+            serde_json::to_value(<controllers::openapi::ApiDoc as utoipa::OpenApi>::openapi())
+                .unwrap();
+        let schema = &spec["components"]["schemas"][schema];
+        let documented: Vec<&String> = schema["properties"].as_object().unwrap().keys().collect();
+        let fields = json.as_object().unwrap();
+        for field in fields.keys() {
+            assert!(documented.contains(&field), "{field} is not documented");
+        }
+        for required in schema["required"].as_array().unwrap() {
+            assert!(
+                fields.contains_key(required.as_str().unwrap()),
+                "{required} is missing"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_route_returns_structured_404() {
+        let app = test_app().await;
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/definitely/not/a/route")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "NOT_FOUND");
+        assert!(json["error"]
+            .as_str()
+            .unwrap()
+            .contains("/definitely/not/a/route"));
+        assert!(json["timestamp"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_method_returns_structured_405() {
+        let app = test_app().await;
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/health")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        let allow = response.headers()[axum::http::header::ALLOW]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(allow.contains("GET"));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "METHOD_NOT_ALLOWED");
+        assert!(json["error"].as_str().unwrap().contains("DELETE"));
+
+        // Supported methods on the same path are unaffected
+        let response = app
+            .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_jwks_publishes_archive_signing_key() {
+        let mut state = AppState::new_mock(AppConfig::default()).await;
+        let jwks = |state: AppState| async move {
+            let response = create_app(state)
+                .oneshot(Request::get("/api/v1/jwks").body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        assert_eq!(jwks(state.clone()).await["keys"], serde_json::json!([]));
+
+        let signer = crate::crypto::ArchiveSigner::new(None).unwrap();
+        state.storage_service = state.storage_service.with_archive_signer(signer.clone());
+        let json = jwks(state).await;
+        assert_eq!(json["keys"][0]["kid"], signer.key_id());
+        assert_eq!(json["keys"][0]["crv"], "Ed25519");
+    }
+
+    #[tokio::test]
+    async fn test_challenge_response_includes_lifetime() {
+        let app = test_app().await;
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/pow/challenge")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["challengeLifetime"], 600);
+    }
+
+    #[tokio::test]
+    async fn test_challenge_response_matches_schema() {
+        for body in ["", r#"{"relay_id":"relay_a"}"#] {
+            let response = test_app()
+                .await
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/api/v1/pow/challenge")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_matches_schema::<PowChallengeResponse>(&json, "PowChallengeResponse");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_challenge_request_binds_relay() {
+        let response = test_app()
+            .await
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/pow/challenge")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"relay_id":"relay_a"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["relayId"], "relay_a");
+    }
+
+    #[tokio::test]
+    async fn test_challenge_response_keys_are_camel_case() {
+        let response = test_app()
+            .await
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/pow/challenge")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let mut keys: Vec<&str> = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort_unstable();
+        assert_eq!(
+            keys,
+            [
+                "algorithm",
+                "challengeData",
+                "challengeId",
+                "challengeLifetime",
+                "difficulty",
+                "expiresAt"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_issuance_response_includes_expires_in_seconds() {
+        use base64::Engine;
+        use sha2::{Digest, Sha256};
+
+        let mut config = AppConfig::default();
+        config.security.certificate_validity_hours = 2;
+        let mut state = AppState::new_mock(config.clone()).await;
+        state.pow_service = PowService::with_params(1, 10);
+        state.certificate_service = CertificateService::default().with_certificate_lifetime(
+            chrono::Duration::hours(config.security.certificate_validity_hours as i64),
+        );
+
+        // Difficulty 1 only needs a leading zero nibble
+        let challenge = state.pow_service.generate_challenge().unwrap();
+        let (nonce, digest) = (0u64..)
+            .map(|nonce| {
+                let mut hasher = Sha256::new();
+                hasher.update(challenge.challenge_data.as_bytes());
+                hasher.update(nonce.to_le_bytes());
+                (nonce, hasher.finalize())
+            })
+            .find(|(_, digest)| digest[0] < 16)
+            .unwrap();
+
+        let request_body = serde_json::json!({
+            "solution": {
+                "challenge_id": challenge.challenge_id,
+                "nonce": nonce,
+                "hash": base64::engine::general_purpose::STANDARD.encode(digest)
+            },
+            "public_key": "test_public_key",
+            "relay_id": "test_relay"
+        });
+
+        let response = create_app(state)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/pow/verify")
+                    .header("content-type", "application/json")
+                    .body(Body::from(request_body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_matches_schema::<TokenResponse>(&json, "TokenResponse");
+        assert!(json["token"].is_string());
+        assert!(json["issuedAt"].is_string());
+        assert!(json["expiresAt"].is_string());
+
+        let expected = (config.security.certificate_validity_hours * 3600) as i64;
+        let expires_in = json["expiresInSeconds"].as_i64().unwrap();
+        assert!((expected - 5..=expected).contains(&expires_in));
+    }
+
+    #[tokio::test]
+    async fn test_expired_challenge_returns_gone_with_hint() {
+        let mut state = AppState::new_mock(AppConfig::default()).await;
+        state.pow_service = PowService::with_params(1, 0); // Expire immediately
+        let challenge = state.pow_service.generate_challenge().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(1));
+
+        let request_body = serde_json::json!({
+            "solution": {
+                "challenge_id": challenge.challenge_id,
+                "nonce": 0,
+                "hash": "any_hash"
+            },
+            "public_key": "test_public_key",
+            "relay_id": "test_relay"
+        });
+
+        let response = create_app(state)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/pow/verify")
+                    .header("content-type", "application/json")
+                    .body(Body::from(request_body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::GONE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "CHALLENGE_EXPIRED");
+        assert!(json["error"]
+            .as_str()
+            .unwrap()
+            .contains("valid for 0 seconds"));
+    }
+
+    #[tokio::test]
+    async fn test_per_route_body_limits() {
+        use crate::test_utils::{issue_token, sample_event, signed_package_request, DeviceKey};
+        use crate::types::event::FieldValue;
+
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let pow_limit = state.config.server.pow_max_body_bytes;
+        let app = create_app(state.clone());
+
+        // Well under the global limit, but too large for a PoW solution
+        let request_body = serde_json::json!({
+            "solution": { "challenge_id": "any", "nonce": 0, "hash": "any_hash" },
+            "public_key": "test_public_key",
+            "relay_id": "r".repeat(pow_limit * 2),
+        });
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/pow/verify")
+                    .header("content-type", "application/json")
+                    .body(Body::from(request_body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // An event package of the same size is accepted
+        let device = DeviceKey::generate();
+        let token = issue_token(&state, &device);
+        let mut event = sample_event();
+        event.annotations[0].value = FieldValue::String("v".repeat(pow_limit * 2));
+        let response = app
+            .oneshot(signed_package_request(&device, &token, &event))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_does_not_shadow_docs() {
+        let app = test_app().await;
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/docs/")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,218 @@
+use axum::{
+    body::HttpBody,
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use std::net::SocketAddr;
+use std::time::Instant;
+use tracing::info;
+
+use crate::config::AccessLogFormat;
+use crate::state::AppState;
+
+/// Tracing target of access log lines, so pipelines can route them separately
+const ACCESS_LOG_TARGET: &str = "access_log";
+
+/// What is logged about one request
+struct AccessLogEntry {
+    remote_addr: Option<SocketAddr>,
+    method: String,
+    target: String, // Path and query
+    version: String,
+    status: u16,
+    bytes: Option<u64>, // None when the body length isn't known up front
+    duration_ms: u128,
+    referer: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl AccessLogEntry {
+    /// NCSA Common Log Format line, with the duration in milliseconds appended
+    fn common(&self) -> String {
+        format!(
+            "{} - - [{}] \"{} {} {}\" {} {} {}",
+            self.remote_addr
+                .map_or_else(|| "-".to_string(), |addr| addr.ip().to_string()),
+            Utc::now().format("%d/%b/%Y:%H:%M:%S %z"),
+            self.method,
+            self.target,
+            self.version,
+            self.status,
+            self.bytes
+                .map_or_else(|| "-".to_string(), |bytes| bytes.to_string()),
+            self.duration_ms,
+        )
+    }
+
+    /// NCSA Combined Log Format line, with the duration in milliseconds appended
+    fn combined(&self) -> String {
+        let quoted = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map_or_else(|| "-".to_string(), |value| value.replace('"', "\\\""))
+        };
+        let common = self.common();
+        let (request, duration) = common.rsplit_once(' ').unwrap_or((&common, ""));
+        format!(
+            "{request} \"{}\" \"{}\" {duration}",
+            quoted(&self.referer),
+            quoted(&self.user_agent)
+        )
+    }
+}
+
+fn header_value(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Access log middleware
+/// Logs one line per request in the configured `server.access_log_format`, covering the
+/// response as the client receives it
+pub async fn access_log_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let remote_addr = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
+    let method = request.method().to_string();
+    let target = request
+        .uri()
+        .path_and_query()
+        .map_or_else(|| request.uri().path().to_string(), |pq| pq.to_string());
+    let version = format!("{:?}", request.version());
+    let referer = header_value(request.headers(), header::REFERER);
+    let user_agent = header_value(request.headers(), header::USER_AGENT);
+
+    let response = next.run(request).await;
+
+    let entry = AccessLogEntry {
+        remote_addr,
+        method,
+        target,
+        version,
+        status: response.status().as_u16(),
+        bytes: response.body().size_hint().exact(),
+        duration_ms: started.elapsed().as_millis(),
+        referer,
+        user_agent,
+    };
+    match state.config.server.access_log_format {
+        AccessLogFormat::Json => info!(
+            target: ACCESS_LOG_TARGET,
+            remote_addr = entry.remote_addr.map(|addr| addr.ip().to_string()),
+            method = %entry.method,
+            path = %entry.target,
+            status = entry.status,
+            bytes = entry.bytes,
+            duration_ms = entry.duration_ms as u64,
+            "Request completed"
+        ),
+        AccessLogFormat::Clf => info!(target: ACCESS_LOG_TARGET, "{}", entry.common()),
+        AccessLogFormat::Combined => info!(target: ACCESS_LOG_TARGET, "{}", entry.combined()),
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_utils::CapturedLogs;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    /// Access log line written for one `GET /health?probe=1`
+    async fn access_line(format: AccessLogFormat) -> String {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .without_time()
+            .with_level(false)
+            .with_target(false)
+            .with_writer(move || writer.clone())
+            .with_max_level(tracing::Level::INFO)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let mut config = AppConfig::default();
+        config.server.access_log_format = format;
+        let state = AppState::new_mock(config).await;
+        let mut request = Request::builder()
+            .uri("/health?probe=1")
+            .header(header::USER_AGENT, "probe/1.0")
+            .body(Body::empty())
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([203, 0, 113, 7], 50000))));
+        crate::create_app(state).oneshot(request).await.unwrap();
+
+        let output = logs.contents();
+        output
+            .lines()
+            .find(|line| line.contains("/health?probe=1"))
+            .expect("access log line")
+            .trim()
+            .to_string()
+    }
+
+    /// `host ident user [dd/Mon/yyyy:HH:MM:SS +zzzz] "request" status bytes`, returning the rest
+    fn strip_common_prefix(line: &str) -> &str {
+        let rest = line
+            .strip_prefix("203.0.113.7 - - [")
+            .unwrap_or_else(|| panic!("{line}"));
+        let (timestamp, rest) = rest.split_once("] ").unwrap();
+        assert!(
+            chrono::DateTime::parse_from_str(timestamp, "%d/%b/%Y:%H:%M:%S %z").is_ok(),
+            "{timestamp}"
+        );
+        let rest = rest
+            .strip_prefix("\"GET /health?probe=1 HTTP/1.1\" 200 ")
+            .unwrap_or_else(|| panic!("{line}"));
+        let (bytes, rest) = rest.split_once(' ').unwrap();
+        assert!(bytes.parse::<u64>().unwrap() > 0, "{line}");
+        rest
+    }
+
+    #[tokio::test]
+    async fn test_clf_access_log() {
+        let line = access_line(AccessLogFormat::Clf).await;
+        let duration = strip_common_prefix(&line);
+        assert!(duration.parse::<u64>().is_ok(), "{line}");
+    }
+
+    #[tokio::test]
+    async fn test_combined_access_log() {
+        let line = access_line(AccessLogFormat::Combined).await;
+        let rest = strip_common_prefix(&line);
+        let duration = rest
+            .strip_prefix("\"-\" \"probe/1.0\" ")
+            .unwrap_or_else(|| panic!("{line}"));
+        assert!(duration.parse::<u64>().is_ok(), "{line}");
+    }
+
+    #[tokio::test]
+    async fn test_json_access_log_by_default() {
+        let line = access_line(AccessLogFormat::default()).await;
+        assert!(line.contains("Request completed"), "{line}");
+        for field in [
+            "remote_addr=\"203.0.113.7\"",
+            "method=GET",
+            "status=200",
+            "duration_ms=",
+        ] {
+            assert!(line.contains(field), "{line}");
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// Admin authorization middleware
+/// Requires `Authorization: Bearer <admin_token>` matching the configured admin token.
+/// Admin routes are disabled entirely when no token is configured.
+pub async fn admin_auth_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let path = request.uri().path().to_string();
+
+    let Some(admin_token) = state.config.security.admin_token.as_deref() else {
+        warn!(path = %path, "Admin endpoint requested but no admin token is configured");
+        return Err(AppError::Forbidden("Admin API is disabled".to_string()));
+    };
+
+    let provided = request
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|auth_header| auth_header.strip_prefix("Bearer "))
+        .ok_or_else(|| AppError::Unauthorized("Admin token required".to_string()))?;
+
+    if !tokens_match(provided, admin_token) {
+        warn!(path = %path, "Admin request rejected: invalid admin token");
+        return Err(AppError::Forbidden("Invalid admin token".to_string()));
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Compare tokens by digest so the comparison time does not depend on the token contents
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    Sha256::digest(provided.as_bytes()) == Sha256::digest(expected.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secret", "Secret"));
+        assert!(!tokens_match("", "secret"));
+    }
+}
@@ -1,17 +1,27 @@
 use axum::{
-    extract::{Request, State},
-    http::{HeaderMap, StatusCode},
+    extract::{OriginalUri, Request, State},
+    http::{header, HeaderMap, Method},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
 };
 use base64::Engine;
-use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
 use p256::elliptic_curve::sec1::FromEncodedPoint;
 use p256::{EncodedPoint, PublicKey};
-use serde::{Deserialize, Serialize};
-use tracing::{error, info, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Instant;
+use tracing::{debug, error, info, warn};
 
-use crate::error::EventServerError;
+use crate::crypto::CertificateValidation;
+use crate::error::{AuthFailure, EventServerError};
+use crate::middleware::request_span::{RequestId, RequestSpan};
+use crate::middleware::server_timing::ServerTimings;
+use crate::middleware::with_json_field;
+use crate::services::rejection_log::{log_rejected_event, RejectedEventSummary};
+use crate::services::request_dedup::DedupOutcome;
+use crate::services::tenant;
 use crate::state::AppState;
 use crate::types::event::{EventPackage, SignedEventPackage};
 
@@ -20,6 +30,8 @@ use crate::types::event::{EventPackage, SignedEventPackage};
 struct EventJwtClaims {
     /// The event package payload
     payload: EventPackage,
+    /// Expiry (unix seconds); replay protection remembers the token until then
+    exp: i64,
 }
 
 /// JWK (JSON Web Key) structure for P-256 elliptic curve keys
@@ -32,25 +44,186 @@ struct JwkKey {
     d: Option<String>, // Private key component (optional)
 }
 
+/// Request captured when crypto validation fails, for forensic analysis
+/// Only the content type is kept from the headers, so credentials are never stored, and the
+/// body is redacted (see `redact_body`)
+#[derive(Debug, Serialize)]
+struct FailedBodyCapture {
+    correlation_id: String, // Generated by the server, so clients can't pick capture keys
+    request_id: Option<String>, // The request's x-request-id, as sent by the client or generated
+    captured_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>, // Captures should be purged after this (see README)
+    method: String,
+    path: String,
+    content_type: Option<String>,
+    status: u16,
+    error: String,
+    body_size: usize,
+    body_sha256: String, // Of the exact received bytes, to match them against a client's copy
+    body: Option<serde_json::Value>, // Redacted JSON body; `None` if it isn't JSON or exceeds `capture_max_bytes`
+}
+
+/// Strings up to this many bytes are kept in captured bodies; longer ones are redacted
+const CAPTURE_KEPT_STRING_BYTES: usize = 64;
+
+/// Budget key shared by all captures
+const CAPTURE_BUDGET_KEY: &str = "captures";
+
 /// Cryptographic validation middleware
 /// This middleware ensures all incoming requests are cryptographically signed
 /// and authenticated before processing. It uses certificate-based authentication
 /// with JWT verification of event data using device public keys.
+/// With `capture_failed_bodies` enabled, the redacted body of a rejected request is written
+/// to storage, up to `capture_max_per_minute` times a minute, and the error carries a
+/// `correlation_id` pointing at the capture.
 pub async fn crypto_validation_middleware(
     State(state): State<AppState>,
     request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
-    let path = request.uri().path().to_string();
+) -> Result<Response, EventServerError> {
+    let path = request_path(&request);
+    if !state.config.security.capture_failed_bodies || is_public_path(&path, &state) {
+        return validate_request(state, request, next).await;
+    }
+
+    // Buffer the body up front so it is still available if validation fails
+    let (parts, body) = request.into_parts();
+    let body_bytes = read_body(body, state.config.server.max_body_bytes, &path).await?;
+    let request_id = parts.extensions.get::<RequestId>().map(|id| id.0.clone());
+    let method = parts.method.to_string();
+    let content_type = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let request = Request::from_parts(parts, axum::body::Body::from(body_bytes.clone()));
+    let error = match validate_request(state.clone(), request, next).await {
+        Ok(response) => return Ok(response),
+        Err(error) => error,
+    };
+
+    let message = error.to_string();
+    let response = error.into_response();
+    let limiter = &state.capture_limiter;
+    if limiter.is_enabled() && limiter.check(CAPTURE_BUDGET_KEY).is_err() {
+        debug!(path = %path, "Capture budget spent, not capturing failed request");
+        return Ok(response);
+    }
+
+    let security = &state.config.security;
+    let correlation_id = uuid::Uuid::new_v4().to_string();
+    let captured_at = Utc::now();
+    let capture = FailedBodyCapture {
+        correlation_id: correlation_id.clone(),
+        request_id,
+        captured_at,
+        expires_at: captured_at + chrono::Duration::hours(security.capture_ttl_hours as i64),
+        method,
+        path: path.clone(),
+        content_type,
+        status: response.status().as_u16(),
+        error: message,
+        body_size: body_bytes.len(),
+        body_sha256: hex::encode(Sha256::digest(&body_bytes)),
+        body: redact_body(&body_bytes, security.capture_max_bytes),
+    };
+
+    match state
+        .storage_service
+        .save_debug_capture(&correlation_id, &capture)
+        .await
+    {
+        Ok(key) => {
+            warn!(path = %path, correlation_id = %correlation_id, key = %key, "Captured body of failed request")
+        }
+        Err(e) => {
+            warn!(path = %path, error = %e, "Failed to store debug capture of failed request")
+        }
+    }
+
+    Ok(with_correlation_id(response, &correlation_id).await)
+}
+
+/// JSON body with its structure and short values kept, for storing in a capture
+/// JWTs keep only their header, which names the algorithm and key; other strings longer than
+/// `CAPTURE_KEPT_STRING_BYTES` (payloads, signatures, keys, media) are replaced by their length.
+/// `None` for bodies that aren't JSON or whose redacted form is over `max_bytes`
+fn redact_body(body: &[u8], max_bytes: usize) -> Option<serde_json::Value> {
+    fn redact(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(text) => *text = redact_string(text),
+            serde_json::Value::Array(items) => items.iter_mut().for_each(redact),
+            serde_json::Value::Object(fields) => fields.values_mut().for_each(redact),
+            _ => {}
+        }
+    }
+
+    let mut value: serde_json::Value = serde_json::from_slice(body).ok()?;
+    redact(&mut value);
+    let size = serde_json::to_vec(&value).ok()?.len();
+    (size <= max_bytes).then_some(value)
+}
+
+fn redact_string(text: &str) -> String {
+    let mut segments = text.split('.');
+    if let (Some(header), Some(_), Some(_), None) = (
+        segments.next(),
+        segments.next(),
+        segments.next(),
+        segments.next(),
+    ) {
+        let is_jwt_header = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(header)
+            .ok()
+            .and_then(|header| serde_json::from_slice::<serde_json::Value>(&header).ok())
+            .is_some_and(|header| header.get("alg").is_some());
+        if is_jwt_header {
+            return format!(
+                "{header}.<redacted {} bytes>",
+                text.len() - header.len() - 1
+            );
+        }
+    }
+    if text.len() <= CAPTURE_KEPT_STRING_BYTES {
+        text.to_string()
+    } else {
+        format!("<redacted {} bytes>", text.len())
+    }
+}
+
+/// Authenticate the request and verify any signed event data it carries
+async fn validate_request(
+    state: AppState,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, EventServerError> {
+    let started = Instant::now();
+    let path = request_path(&request);
+
+    // Only this middleware may vouch for a request, on public paths too
+    strip_validated_headers(request.headers_mut());
 
     // Skip validation for public endpoints
-    if should_skip_validation(&path) {
+    if is_public_path(&path, &state) {
         info!(path = %path, "Skipping crypto validation for public endpoint");
         return Ok(next.run(request).await);
     }
 
     info!(path = %path, "Applying cryptographic validation");
 
+    // Reject bodies declared too large before reading a single byte;
+    // chunked or undeclared bodies are still capped while streaming below
+    let max_body_bytes = state.config.server.max_body_bytes;
+    if let Some(declared) = declared_content_length(request.headers()) {
+        if declared > max_body_bytes as u64 {
+            warn!(path = %path, declared, limit = max_body_bytes, "Rejecting request with oversized Content-Length");
+            return Err(EventServerError::PayloadTooLarge(format!(
+                "Request body exceeds {max_body_bytes} bytes"
+            )));
+        }
+    }
+
     // Extract headers for certificate token check
     let headers = request.headers().clone();
 
@@ -63,6 +236,9 @@ pub async fn crypto_validation_middleware(
             .validate_certificate(&certificate_token)
         {
             Ok(validation) => {
+                if let Some(span) = request.extensions().get::<RequestSpan>() {
+                    span.record_relay_id(&validation.relay_id);
+                }
                 info!(
                     relay_id = %validation.relay_id,
                     expires_at = %validation.expires_at,
@@ -70,19 +246,58 @@ pub async fn crypto_validation_middleware(
                     "Certificate validated successfully"
                 );
 
+                // A valid certificate isn't enough once the relay is suspended or decommissioned
+                if state.config.security.enforce_relay_status {
+                    state.relay_service.ensure_active(&validation.relay_id)?;
+                }
+
+                // Refuse cross-tenant attempts before reading the body
+                let tenant_id = tenant::resolve_tenant(
+                    &state.config.security,
+                    &headers,
+                    validation.tenant_id.as_deref(),
+                )?;
+
                 // Extract request body to verify JWT event data
                 let (parts, body) = request.into_parts();
-                let body_bytes = match axum::body::to_bytes(body, usize::MAX).await {
-                    Ok(bytes) => bytes.to_vec(),
-                    Err(e) => {
-                        error!(error = %e, "Failed to read request body for JWT verification");
-                        return Err(StatusCode::BAD_REQUEST);
+                let body_bytes = read_body(body, max_body_bytes, &path).await?;
+
+                // Catch the common "forgot to set the body" bug before JSON parsing
+                if expects_body(&parts.method)
+                    && body_bytes.trim_ascii().len() < state.config.server.min_body_bytes
+                {
+                    warn!(path = %path, size = body_bytes.len(), "Rejecting empty request body");
+                    return Err(EventServerError::BadRequest(
+                        "Empty request body".to_string(),
+                    ));
+                }
+
+                // A byte-identical retry gets the first response, before replay protection
+                // would reject it and before any further processing; one arriving while the
+                // first is still processed waits for its response
+                let dedup_key = expects_body(&parts.method)
+                    .then(|| {
+                        state.request_dedup.key(
+                            &parts.method,
+                            &path,
+                            &validation.certificate_id,
+                            &body_bytes,
+                        )
+                    })
+                    .flatten();
+                let in_flight = match state.request_dedup.claim(dedup_key).await {
+                    DedupOutcome::Replay(response) => {
+                        info!(path = %path, relay_id = %validation.relay_id, "Answering duplicate request from the dedup cache");
+                        return Ok(response);
                     }
+                    DedupOutcome::Process(in_flight) => in_flight,
                 };
 
                 // Try to parse body as SignedEventPackage for JWT verification
-                info!("Attempting to parse request body as SignedEventPackage");
-                info!("Request body: {}", String::from_utf8_lossy(&body_bytes));
+                info!(
+                    size = body_bytes.len(),
+                    "Attempting to parse request body as SignedEventPackage"
+                );
                 if let Ok(signed_package) =
                     serde_json::from_slice::<SignedEventPackage>(&body_bytes)
                 {
@@ -91,21 +306,50 @@ pub async fn crypto_validation_middleware(
                         signed_package.jwt_event_data.len()
                     );
 
+                    // Defense in depth: a stolen certificate alone must not be enough
+                    if state.config.security.require_dual_signature {
+                        if let Err(e) = verify_package_signature(&signed_package, &validation) {
+                            log_rejected_event(
+                                Some(&validation.relay_id),
+                                &RejectedEventSummary::from_unverified_jwt(
+                                    &signed_package.jwt_event_data,
+                                ),
+                                &e,
+                            );
+                            return Err(e);
+                        }
+                    }
+
                     // Verify JWT event data using device public key from certificate
                     info!("Starting JWT verification with device public key");
-                    match verify_jwt_event_data(
-                        &signed_package.jwt_event_data,
-                        &validation.public_key,
-                    ) {
-                        Ok(event_package) => {
-                            // Print the event package for debugging
+                    match verify_with_certificate_keys(&signed_package.jwt_event_data, &validation)
+                    {
+                        Ok(claims) => {
+                            if !state
+                                .replay_cache
+                                .check_and_record(&signed_package.jwt_event_data, claims.exp)
+                            {
+                                let e = EventServerError::Conflict(
+                                    "Event JWT has already been submitted".to_string(),
+                                );
+                                log_rejected_event(
+                                    Some(&validation.relay_id),
+                                    &RejectedEventSummary::of(&claims.payload),
+                                    &e,
+                                );
+                                return Err(e);
+                            }
+
+                            let event_package = claims.payload;
+                            if let Some(span) = parts.extensions.get::<RequestSpan>() {
+                                span.record_event_id(&event_package.id);
+                            }
                             info!(
                                 event_id = %event_package.id,
                                 event_version = %event_package.version,
                                 annotations_count = %event_package.annotations.len(),
                                 has_media = %event_package.media.is_some(),
-                                "Received and verified event package: {:?}",
-                                event_package
+                                "Received and verified event package"
                             );
 
                             // Add validated relay ID to request headers and event data to extensions
@@ -118,19 +362,33 @@ pub async fn crypto_validation_middleware(
                                     .parse()
                                     .unwrap_or_else(|_| "unknown".parse().unwrap()),
                             );
+                            set_validated_certificate_id(
+                                request.headers_mut(),
+                                &validation.certificate_id,
+                            );
+                            tenant::set_validated_tenant(
+                                request.headers_mut(),
+                                tenant_id.as_deref(),
+                            );
 
                             // Add the verified event package to request extensions for controllers to use
                             request.extensions_mut().insert(event_package);
 
-                            return Ok(next.run(request).await);
+                            if let Some(timings) = request.extensions().get::<ServerTimings>() {
+                                timings.record("crypto", started);
+                            }
+                            let response = next.run(request).await;
+                            return Ok(state.request_dedup.remember(in_flight, response).await);
                         }
                         Err(e) => {
-                            error!(
-                                error = %e,
-                                relay_id = %validation.relay_id,
-                                "JWT event data verification failed"
+                            log_rejected_event(
+                                Some(&validation.relay_id),
+                                &RejectedEventSummary::from_unverified_jwt(
+                                    &signed_package.jwt_event_data,
+                                ),
+                                &e,
                             );
-                            return Err(StatusCode::UNAUTHORIZED);
+                            return Err(e);
                         }
                     }
                 } else {
@@ -148,8 +406,14 @@ pub async fn crypto_validation_middleware(
                             .parse()
                             .unwrap_or_else(|_| "unknown".parse().unwrap()),
                     );
+                    set_validated_certificate_id(request.headers_mut(), &validation.certificate_id);
+                    tenant::set_validated_tenant(request.headers_mut(), tenant_id.as_deref());
 
-                    return Ok(next.run(request).await);
+                    if let Some(timings) = request.extensions().get::<ServerTimings>() {
+                        timings.record("crypto", started);
+                    }
+                    let response = next.run(request).await;
+                    return Ok(state.request_dedup.remember(in_flight, response).await);
                 }
             }
             Err(e) => {
@@ -158,7 +422,7 @@ pub async fn crypto_validation_middleware(
                     path = %path,
                     "Certificate validation failed"
                 );
-                return Err(StatusCode::UNAUTHORIZED);
+                return Err(e);
             }
         }
     }
@@ -168,16 +432,104 @@ pub async fn crypto_validation_middleware(
         path = %path,
         "Request missing certificate token in Authorization header - authentication required"
     );
-    Err(StatusCode::UNAUTHORIZED)
+    Err(EventServerError::auth(
+        AuthFailure::MissingToken,
+        "Missing certificate token in Authorization header",
+    ))
+}
+
+/// Verify event data against the certificate's current key, falling back to the
+/// pre-rotation key while its grace window is open
+fn verify_with_certificate_keys(
+    jwt_token: &str,
+    validation: &CertificateValidation,
+) -> Result<EventJwtClaims, EventServerError> {
+    match verify_jwt_event_data(jwt_token, &validation.public_key) {
+        Err(e) => match &validation.previous_public_key {
+            Some(previous_key) => {
+                let claims = verify_jwt_event_data(jwt_token, previous_key).map_err(|_| e)?;
+                info!(
+                    relay_id = %validation.relay_id,
+                    "Event JWT verified with the pre-rotation device key"
+                );
+                Ok(claims)
+            }
+            None => Err(e),
+        },
+        verified => verified,
+    }
 }
 
 /// Verify JWT event data using device public key from certificate
 fn verify_jwt_event_data(
     jwt_token: &str,
     device_public_key: &str,
-) -> Result<EventPackage, EventServerError> {
+) -> Result<EventJwtClaims, EventServerError> {
+    verify_device_jwt(jwt_token, device_public_key)
+}
+
+/// The only algorithm device JWTs may be signed with
+/// Pinned so `none`, HMAC (keyed with the public JWK) or any other algorithm named in a
+/// token header is never honoured
+const DEVICE_JWT_ALGORITHM: Algorithm = Algorithm::ES256;
+
+/// Verify an ES256 JWT signed by a device key (base64-encoded P-256 JWK)
+pub(crate) fn verify_device_jwt<T: DeserializeOwned>(
+    jwt_token: &str,
+    device_public_key: &str,
+) -> Result<T, EventServerError> {
     info!("Starting JWT verification process");
     info!("JWT token length: {}", jwt_token.len());
+    let decoding_key = device_decoding_key(device_public_key)?;
+
+    // Unknown algorithms such as `none` fail to parse here
+    let header = decode_header(jwt_token).map_err(|e| {
+        EventServerError::auth(
+            AuthFailure::JwtInvalid,
+            format!("JWT verification failed: {e}"),
+        )
+    })?;
+    if header.alg != DEVICE_JWT_ALGORITHM {
+        warn!(alg = ?header.alg, "Rejected device JWT with a disallowed algorithm");
+        return Err(EventServerError::auth(
+            AuthFailure::JwtInvalid,
+            format!(
+                "JWT verification failed: algorithm {:?} is not allowed, expected {DEVICE_JWT_ALGORITHM:?}",
+                header.alg
+            ),
+        ));
+    }
+
+    // Set up JWT validation parameters for ES256
+    let mut validation = Validation::new(DEVICE_JWT_ALGORITHM);
+    validation.algorithms = vec![DEVICE_JWT_ALGORITHM];
+    validation.validate_exp = true;
+    validation.set_audience(&["event_server"]); // Match the audience from frontend
+    info!("Set up JWT validation with ES256 algorithm and audience 'event_server'");
+
+    // Decode and verify the JWT
+    info!("Attempting to decode and verify JWT token");
+    let token_data = decode::<T>(jwt_token, &decoding_key, &validation).map_err(|e| {
+        error!("JWT verification failed: {}", e);
+        error!(
+            "JWT token (first 50 chars): {}",
+            &jwt_token[..std::cmp::min(50, jwt_token.len())]
+        );
+        EventServerError::auth(
+            AuthFailure::JwtInvalid,
+            format!("JWT verification failed: {e}"),
+        )
+    })?;
+
+    info!("Successfully verified JWT token");
+
+    Ok(token_data.claims)
+}
+
+/// Build an ES256 decoding key from a device public key (base64-encoded P-256 JWK)
+pub(crate) fn device_decoding_key(
+    device_public_key: &str,
+) -> Result<DecodingKey, EventServerError> {
     info!("Device public key: {}", device_public_key);
 
     // Decode the base64 encoded public key first
@@ -185,12 +537,18 @@ fn verify_jwt_event_data(
         .decode(device_public_key)
         .map_err(|e| {
             error!("Failed to decode base64 public key: {}", e);
-            EventServerError::Validation(format!("Invalid base64 encoding: {e}"))
+            EventServerError::auth(
+                AuthFailure::JwkInvalid,
+                format!("Invalid base64 encoding: {e}"),
+            )
         })?;
 
     let decoded_key_str = String::from_utf8(decoded_key).map_err(|e| {
         error!("Failed to convert decoded key to UTF-8: {}", e);
-        EventServerError::Validation(format!("Invalid UTF-8 in decoded key: {e}"))
+        EventServerError::auth(
+            AuthFailure::JwkInvalid,
+            format!("Invalid UTF-8 in decoded key: {e}"),
+        )
     })?;
 
     info!("Decoded public key: {}", decoded_key_str);
@@ -199,7 +557,7 @@ fn verify_jwt_event_data(
     let jwk: JwkKey = serde_json::from_str(&decoded_key_str).map_err(|e| {
         error!("Failed to parse decoded public key as JWK: {}", e);
         error!("Decoded key content: '{}'", decoded_key_str);
-        EventServerError::Validation(format!("Invalid JWK format: {e}"))
+        EventServerError::auth(AuthFailure::JwkInvalid, format!("Invalid JWK format: {e}"))
     })?;
 
     info!(
@@ -209,17 +567,17 @@ fn verify_jwt_event_data(
 
     // Validate that this is an EC P-256 key
     if jwk.kty != "EC" {
-        return Err(EventServerError::Validation(format!(
-            "Invalid key type: expected 'EC', got '{}'",
-            jwk.kty
-        )));
+        return Err(EventServerError::auth(
+            AuthFailure::JwkInvalid,
+            format!("Invalid key type: expected 'EC', got '{}'", jwk.kty),
+        ));
     }
 
     if jwk.crv != "P-256" {
-        return Err(EventServerError::Validation(format!(
-            "Invalid curve: expected 'P-256', got '{}'",
-            jwk.crv
-        )));
+        return Err(EventServerError::auth(
+            AuthFailure::JwkInvalid,
+            format!("Invalid curve: expected 'P-256', got '{}'", jwk.crv),
+        ));
     }
 
     // Decode x and y coordinates from base64url
@@ -228,14 +586,20 @@ fn verify_jwt_event_data(
         .decode(&jwk.x)
         .map_err(|e| {
             error!("Failed to decode x coordinate '{}': {}", jwk.x, e);
-            EventServerError::Validation(format!("Invalid x coordinate: {e}"))
+            EventServerError::auth(
+                AuthFailure::JwkInvalid,
+                format!("Invalid x coordinate: {e}"),
+            )
         })?;
 
     let y_bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
         .decode(&jwk.y)
         .map_err(|e| {
             error!("Failed to decode y coordinate '{}': {}", jwk.y, e);
-            EventServerError::Validation(format!("Invalid y coordinate: {e}"))
+            EventServerError::auth(
+                AuthFailure::JwkInvalid,
+                format!("Invalid y coordinate: {e}"),
+            )
         })?;
 
     info!(
@@ -246,17 +610,23 @@ fn verify_jwt_event_data(
 
     // Validate coordinate lengths for P-256 (32 bytes each)
     if x_bytes.len() != 32 {
-        return Err(EventServerError::Validation(format!(
-            "Invalid x coordinate length: expected 32 bytes, got {}",
-            x_bytes.len()
-        )));
+        return Err(EventServerError::auth(
+            AuthFailure::JwkInvalid,
+            format!(
+                "Invalid x coordinate length: expected 32 bytes, got {}",
+                x_bytes.len()
+            ),
+        ));
     }
 
     if y_bytes.len() != 32 {
-        return Err(EventServerError::Validation(format!(
-            "Invalid y coordinate length: expected 32 bytes, got {}",
-            y_bytes.len()
-        )));
+        return Err(EventServerError::auth(
+            AuthFailure::JwkInvalid,
+            format!(
+                "Invalid y coordinate length: expected 32 bytes, got {}",
+                y_bytes.len()
+            ),
+        ));
     }
 
     // Create uncompressed point format: 0x04 || x || y
@@ -269,14 +639,14 @@ fn verify_jwt_event_data(
     // Create P-256 public key from the point
     let encoded_point = EncodedPoint::from_bytes(&point_bytes).map_err(|e| {
         error!("Failed to create encoded point from bytes: {}", e);
-        EventServerError::Validation(format!("Invalid EC point: {e}"))
+        EventServerError::auth(AuthFailure::JwkInvalid, format!("Invalid EC point: {e}"))
     })?;
 
     let public_key = PublicKey::from_encoded_point(&encoded_point)
         .into_option()
         .ok_or_else(|| {
             error!("Failed to create P-256 public key from encoded point");
-            EventServerError::Validation("Invalid P-256 public key point".to_string())
+            EventServerError::auth(AuthFailure::JwkInvalid, "Invalid P-256 public key point")
         })?;
     info!("Successfully created P-256 public key");
 
@@ -291,52 +661,179 @@ fn verify_jwt_event_data(
     let decoding_key = DecodingKey::from_ec_der(&der_bytes);
     info!("Successfully created JWT decoding key");
 
-    // Set up JWT validation parameters for ES256
-    let mut validation = Validation::new(Algorithm::ES256);
-    validation.validate_exp = true;
-    validation.set_audience(&["event_server"]); // Match the audience from frontend
-    info!("Set up JWT validation with ES256 algorithm and audience 'event_server'");
+    Ok(decoding_key)
+}
 
-    // Decode and verify the JWT
-    info!("Attempting to decode and verify JWT token");
-    let token_data =
-        decode::<EventJwtClaims>(jwt_token, &decoding_key, &validation).map_err(|e| {
-            error!("JWT verification failed: {}", e);
-            error!(
-                "JWT token (first 50 chars): {}",
-                &jwt_token[..std::cmp::min(50, jwt_token.len())]
-            );
-            EventServerError::Validation(format!("JWT verification failed: {e}"))
-        })?;
+/// Request path, matched against the full path rather than the one stripped by `Router::nest`
+fn request_path(request: &Request) -> String {
+    request
+        .extensions()
+        .get::<OriginalUri>()
+        .map(|uri| uri.path().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string())
+}
 
-    info!("Successfully verified JWT token");
-    info!("Event package payload: {:?}", token_data.claims.payload);
+/// Buffer a request body, rejecting it with 413 once it exceeds `max_body_bytes`
+async fn read_body(
+    body: axum::body::Body,
+    max_body_bytes: usize,
+    path: &str,
+) -> Result<Vec<u8>, EventServerError> {
+    match axum::body::to_bytes(body, max_body_bytes).await {
+        Ok(bytes) => Ok(bytes.to_vec()),
+        Err(e) if is_length_limit_error(&e) => {
+            warn!(path = %path, limit = max_body_bytes, "Rejecting oversized request body");
+            Err(EventServerError::PayloadTooLarge(format!(
+                "Request body exceeds {max_body_bytes} bytes"
+            )))
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to read request body for JWT verification");
+            Err(EventServerError::BadRequest(format!(
+                "Failed to read request body: {e}"
+            )))
+        }
+    }
+}
 
-    Ok(token_data.claims.payload)
+/// Add the debug capture's correlation ID to a JSON error body
+async fn with_correlation_id(response: Response, correlation_id: &str) -> Response {
+    with_json_field(response, "correlation_id", correlation_id).await
 }
 
+/// Verify the package's Ed25519 signature over `jwt_event_data`, requiring the signing key
+/// to be the one bound to the certificate
+fn verify_package_signature(
+    signed_package: &SignedEventPackage,
+    validation: &CertificateValidation,
+) -> Result<(), EventServerError> {
+    let invalid = |message: &str| EventServerError::auth(AuthFailure::SignatureInvalid, message);
+
+    let bound_key = validation
+        .ed25519_public_key
+        .as_deref()
+        .ok_or_else(|| invalid("Certificate has no bound Ed25519 public key"))?;
+    let (Some(public_key), Some(signature)) =
+        (&signed_package.public_key, &signed_package.signature)
+    else {
+        return Err(invalid(
+            "Event package must carry an Ed25519 signature and public key",
+        ));
+    };
+    if public_key != bound_key {
+        return Err(invalid(
+            "Event package Ed25519 public key does not match the certificate",
+        ));
+    }
+
+    let engine = base64::engine::general_purpose::STANDARD;
+    let key_bytes: [u8; 32] = engine
+        .decode(public_key)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| invalid("Ed25519 public key must be 32 base64-encoded bytes"))?;
+    let verifying_key = ed25519_dalek::VerifyingKey::from_bytes(&key_bytes)
+        .map_err(|_| invalid("Ed25519 public key is not a valid curve point"))?;
+    let signature_bytes: [u8; 64] = engine
+        .decode(signature)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| invalid("Ed25519 signature must be 64 base64-encoded bytes"))?;
+
+    verifying_key
+        .verify_strict(
+            signed_package.jwt_event_data.as_bytes(),
+            &ed25519_dalek::Signature::from_bytes(&signature_bytes),
+        )
+        .map_err(|_| invalid("Ed25519 signature verification failed"))
+}
+
+/// Body size declared by the `Content-Length` header, if present and well-formed
+fn declared_content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Whether a body read failed because it exceeded the configured size limit
+fn is_length_limit_error(error: &axum::Error) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(error);
+    while let Some(err) = source {
+        if err.is::<http_body_util::LengthLimitError>() {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// Whether requests with this method are expected to carry a body
+fn expects_body(method: &Method) -> bool {
+    matches!(*method, Method::POST | Method::PUT | Method::PATCH)
+}
+
+/// Built-in public endpoints that never require authentication
+const DEFAULT_PUBLIC_PATHS: &[&str] = &[
+    "/health",
+    "/capabilities",
+    "/openapi-json",
+    "/openapi-yaml",
+    // PoW challenge endpoint for obtaining challenges
+    "/api/v1/pow/challenge",
+    // PoW verification endpoint for obtaining certificates
+    "/api/v1/pow/verify",
+    // Certificate status check reads and reports on the token itself
+    "/api/v1/certificates/status",
+    // Server verification keys for signed archives
+    "/api/v1/jwks",
+];
+
 /// Determine if cryptographic validation should be skipped for a given path
-pub fn should_skip_validation(path: &str) -> bool {
-    // Public endpoints that don't require authentication
-    let public_paths = [
-        "/health",
-        "/docs",
-        "/openapi-json",
-        "/openapi-yaml",
-        // PoW challenge endpoint for obtaining challenges
-        "/api/v1/pow/challenge",
-        // PoW verification endpoint for obtaining certificates
-        "/api/v1/pow/verify",
-    ];
-
-    public_paths
+/// A path is public if it equals, or is nested under, a built-in or configured public path
+/// `docs_path` is the configured Swagger UI path, or `None` when docs are disabled
+pub fn should_skip_validation(
+    path: &str,
+    extra_public_paths: &[String],
+    docs_path: Option<&str>,
+) -> bool {
+    DEFAULT_PUBLIC_PATHS
         .iter()
-        .any(|&public_path| path == public_path || path.starts_with(&format!("{public_path}/")))
+        .copied()
+        .chain(docs_path)
+        .chain(extra_public_paths.iter().map(String::as_str))
+        .any(|public_path| path_matches(path, public_path))
+}
+
+/// Whether a request path is public under the running configuration
+fn is_public_path(path: &str, state: &AppState) -> bool {
+    let docs = &state.config.docs;
+    should_skip_validation(
+        path,
+        &state.config.security.public_paths,
+        docs.enabled.then_some(docs.path.as_str()),
+    )
+}
+
+/// Prefix match on whole path segments, ignoring trailing slashes on the configured path
+fn path_matches(path: &str, public_path: &str) -> bool {
+    let public_path = public_path.trim_end_matches('/');
+    if public_path.is_empty() {
+        return false;
+    }
+
+    match path.strip_prefix(public_path) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
 }
 
 /// Extract certificate token from Authorization header
 /// Expected format: "Bearer <certificate_token>"
-fn extract_certificate_token(headers: &HeaderMap) -> Option<String> {
+pub fn extract_certificate_token(headers: &HeaderMap) -> Option<String> {
     headers
         .get("Authorization")
         .and_then(|h| h.to_str().ok())
@@ -347,6 +844,40 @@ fn extract_certificate_token(headers: &HeaderMap) -> Option<String> {
         })
 }
 
+/// Prefix of every header this middleware sets on an authenticated request
+const VALIDATED_HEADER_PREFIX: &str = "x-validated-";
+
+/// Drop client-supplied `X-Validated-*` headers, which later layers trust as authenticated
+fn strip_validated_headers(headers: &mut HeaderMap) {
+    let spoofed: Vec<_> = headers
+        .keys()
+        .filter(|name| name.as_str().starts_with(VALIDATED_HEADER_PREFIX))
+        .cloned()
+        .collect();
+    for name in spoofed {
+        headers.remove(name);
+    }
+}
+
+/// Header the crypto middleware sets to the certificate ID of an authenticated request
+const VALIDATED_CERTIFICATE_HEADER: &str = "X-Validated-Certificate-ID";
+
+/// Record the validated certificate ID on a request, replacing whatever the client sent
+fn set_validated_certificate_id(headers: &mut HeaderMap, certificate_id: &str) {
+    headers.remove(VALIDATED_CERTIFICATE_HEADER);
+    if let Ok(value) = certificate_id.parse() {
+        headers.insert(VALIDATED_CERTIFICATE_HEADER, value);
+    }
+}
+
+/// Extract certificate ID from validated request headers
+pub fn extract_validated_certificate_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(VALIDATED_CERTIFICATE_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.to_string())
+}
+
 /// Extract relay ID from validated request headers
 pub fn extract_validated_relay_id(headers: &HeaderMap) -> Option<String> {
     headers
@@ -358,18 +889,674 @@ pub fn extract_validated_relay_id(headers: &HeaderMap) -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::AppConfig;
+    use crate::crypto::{CertificateRequest, CertificateService};
+    use crate::test_utils::{sample_media, DeviceKey};
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use tower::ServiceExt;
+
+    fn issue_token(service: &CertificateService, public_key: &str) -> String {
+        service
+            .issue_certificate(&CertificateRequest {
+                relay_id: "test_relay".to_string(),
+                public_key: public_key.to_string(),
+                ed25519_public_key: None,
+                tenant_id: None,
+            })
+            .unwrap()
+            .cert_token
+    }
+
+    fn signed_body() -> Body {
+        let package = SignedEventPackage {
+            jwt_event_data: "not.a.jwt".to_string(),
+            signature: None,
+            public_key: None,
+        };
+        Body::from(serde_json::to_vec(&package).unwrap())
+    }
+
+    async fn rejection_code(state: AppState, token: Option<String>, body: Body) -> String {
+        let mut builder = Request::builder()
+            .method("POST")
+            .uri("/api/v1/events/package")
+            .header("Content-Type", "application/json");
+        if let Some(token) = token {
+            builder = builder.header("Authorization", format!("Bearer {token}"));
+        }
+
+        let response = crate::create_app(state)
+            .oneshot(builder.body(body).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        json["code"].as_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_empty_body_is_rejected() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let token = issue_token(&state.certificate_service, "test_public_key");
+
+        for body in ["", "  \n"] {
+            let response = crate::create_app(state.clone())
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/api/v1/events/package")
+                        .header("Authorization", format!("Bearer {token}"))
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["error"], "Bad request: Empty request body");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_body_limit_is_configurable() {
+        use crate::test_utils::{sample_event, signed_package_request};
+        use crate::types::event::MediaType;
+
+        let mut event = sample_event();
+        event.media = Some(sample_media(
+            MediaType::ImageJpeg,
+            &vec![0; 3 * 1024 * 1024],
+        ));
+        let device = DeviceKey::generate();
+
+        for (max_body_bytes, expected) in [
+            (2 * 1024 * 1024, StatusCode::PAYLOAD_TOO_LARGE),
+            (8 * 1024 * 1024, StatusCode::OK),
+        ] {
+            let mut config = AppConfig::default();
+            config.server.max_body_bytes = max_body_bytes;
+            let state = AppState::new_mock(config).await;
+            let token = issue_token(&state.certificate_service, &device.public_key());
+
+            let response = crate::create_app(state)
+                .oneshot(signed_package_request(&device, &token, &event))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), expected);
+
+            if expected == StatusCode::PAYLOAD_TOO_LARGE {
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                assert_eq!(json["code"], "PAYLOAD_TOO_LARGE");
+            }
+        }
+    }
+
+    async fn oversized_response(max_body_bytes: usize, request: Request) -> serde_json::Value {
+        let mut config = AppConfig::default();
+        config.server.max_body_bytes = max_body_bytes;
+        let state = AppState::new_mock(config).await;
+        let token = issue_token(&state.certificate_service, "test_public_key");
+
+        let mut request = request;
+        request.headers_mut().insert(
+            header::AUTHORIZATION,
+            format!("Bearer {token}").parse().unwrap(),
+        );
+        let response = crate::create_app(state).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_declared_content_length_rejected_before_reading() {
+        // The body itself is tiny: only the declared length can trigger the rejection
+        let request = Request::builder()
+            .method("POST")
+            .uri("/api/v1/events/package")
+            .header(header::CONTENT_LENGTH, "1048576")
+            .body(Body::from("{}"))
+            .unwrap();
+
+        let json = oversized_response(1024, request).await;
+        assert_eq!(json["code"], "PAYLOAD_TOO_LARGE");
+    }
+
+    #[tokio::test]
+    async fn test_chunked_body_rejected_mid_stream() {
+        let chunks = (0..16).map(|_| Ok::<_, std::io::Error>(vec![b' '; 256]));
+        let request = Request::builder()
+            .method("POST")
+            .uri("/api/v1/events/package")
+            .header(header::TRANSFER_ENCODING, "chunked")
+            .body(Body::from_stream(futures::stream::iter(chunks)))
+            .unwrap();
+        assert!(declared_content_length(request.headers()).is_none());
+
+        let json = oversized_response(1024, request).await;
+        assert_eq!(json["code"], "PAYLOAD_TOO_LARGE");
+    }
+
+    #[tokio::test]
+    async fn test_failed_validation_body_is_captured() {
+        use crate::services::storage::{MockS3Client, StorageService};
+        use crate::test_utils::{sample_event, signed_package_request};
+        use std::sync::Arc;
+
+        let captures = |mock: &MockS3Client| -> Vec<String> {
+            mock.put_log()
+                .into_iter()
+                .filter(|key| key.starts_with("debug/"))
+                .collect()
+        };
+
+        for capture_failed_bodies in [false, true] {
+            let mock = Arc::new(MockS3Client::default());
+            let mut config = AppConfig::default();
+            config.security.capture_failed_bodies = capture_failed_bodies;
+            let mut state = AppState::new_mock(config).await;
+            state.storage_service = StorageService::with_mock(mock.clone());
+            let device = DeviceKey::generate();
+            let token = crate::test_utils::issue_token(&state, &device);
+
+            // A rejected submission
+            let body = serde_json::to_vec(&SignedEventPackage {
+                jwt_event_data: "not.a.jwt".to_string(),
+                signature: None,
+                public_key: None,
+            })
+            .unwrap();
+            let response = crate::create_app(state.clone())
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/api/v1/events/package")
+                        .header("Content-Type", "application/json")
+                        .header("Authorization", format!("Bearer {token}"))
+                        .body(Body::from(body.clone()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            let json: serde_json::Value = serde_json::from_slice(
+                &axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap(),
+            )
+            .unwrap();
+            assert_eq!(json["code"], "JWT_INVALID");
+
+            if !capture_failed_bodies {
+                assert!(json.get("correlation_id").is_none());
+                assert!(captures(&mock).is_empty());
+                continue;
+            }
+
+            let correlation_id = json["correlation_id"].as_str().unwrap();
+            let keys = captures(&mock);
+            assert_eq!(keys.len(), 1);
+            assert!(keys[0].ends_with(&format!("/{correlation_id}.json")));
+
+            let stored = mock.object(&keys[0]).unwrap();
+            let capture: serde_json::Value = serde_json::from_slice(&stored.body).unwrap();
+            // Short values survive redaction
+            assert_eq!(
+                capture["body"],
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            );
+            assert_eq!(capture["body_sha256"], hex::encode(Sha256::digest(&body)));
+            assert_eq!(capture["status"], 401);
+            assert!(!String::from_utf8_lossy(&stored.body).contains(&token));
+
+            // A successful submission is never captured
+            let response = crate::create_app(state)
+                .oneshot(signed_package_request(&device, &token, &sample_event()))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(captures(&mock).len(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_captures_are_server_keyed_redacted_and_budgeted() {
+        use crate::services::storage::{MockS3Client, StorageService};
+        use std::sync::Arc;
+
+        let mock = Arc::new(MockS3Client::default());
+        let mut config = AppConfig::default();
+        config.security.capture_failed_bodies = true;
+        config.security.capture_max_per_minute = 1;
+        let mut state = AppState::new_mock(config).await;
+        state.storage_service = StorageService::with_mock(mock.clone());
+        let device = DeviceKey::generate();
+        let token = crate::test_utils::issue_token(&state, &device);
+
+        // Signed with a key the certificate isn't bound to, so it's rejected
+        let other = DeviceKey::generate();
+        let mut request = crate::test_utils::signed_package_request(
+            &other,
+            &token,
+            &crate::test_utils::sample_event(),
+        );
+        request
+            .headers_mut()
+            .insert("x-request-id", "chosen-by-client".parse().unwrap());
+        let (parts, body) = request.into_parts();
+        let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        let send = |body: axum::body::Bytes| {
+            let mut request = Request::builder()
+                .method(parts.method.clone())
+                .uri(parts.uri.clone());
+            for (name, value) in &parts.headers {
+                request = request.header(name, value);
+            }
+            crate::create_app(state.clone()).oneshot(request.body(Body::from(body)).unwrap())
+        };
+
+        let response = send(body.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let json: serde_json::Value = serde_json::from_slice(
+            &axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        let correlation_id = json["correlation_id"].as_str().unwrap();
+        assert_ne!(correlation_id, "chosen-by-client");
+
+        let keys: Vec<_> = mock
+            .put_log()
+            .into_iter()
+            .filter(|key| key.starts_with("debug/"))
+            .collect();
+        assert_eq!(keys.len(), 1);
+        assert!(keys[0].ends_with(&format!("/{correlation_id}.json")));
+        let stored = mock.object(&keys[0]).unwrap();
+        let capture: serde_json::Value = serde_json::from_slice(&stored.body).unwrap();
+        assert_eq!(capture["request_id"], "chosen-by-client");
+        // The JWT keeps its header only
+        let jwt = capture["body"]["jwtEventData"].as_str().unwrap();
+        assert!(jwt.ends_with(" bytes>"), "{jwt}");
+        // Nothing of the event payload is stored
+        let signed: SignedEventPackage = serde_json::from_slice(&body).unwrap();
+        let payload = signed.jwt_event_data.split('.').nth(1).unwrap();
+        assert!(!String::from_utf8_lossy(&stored.body).contains(payload));
+
+        // Over the budget the failure is answered but not captured
+        let response = send(body).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let json: serde_json::Value = serde_json::from_slice(
+            &axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(json.get("correlation_id").is_none());
+        assert_eq!(
+            mock.put_log()
+                .iter()
+                .filter(|key| key.starts_with("debug/"))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_redact_body_drops_oversized_and_non_json_bodies() {
+        assert!(redact_body(b"not json", 1024).is_none());
+        let body = serde_json::to_vec(&serde_json::json!({ "field": "x".repeat(32) })).unwrap();
+        assert!(redact_body(&body, 1024).is_some());
+        assert!(redact_body(&body, 8).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_missing_token_code() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+        assert_eq!(
+            rejection_code(state, None, Body::empty()).await,
+            "MISSING_TOKEN"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_malformed_certificate_code() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let token = Some("garbage".to_string());
+        assert_eq!(
+            rejection_code(state, token, Body::empty()).await,
+            "CERT_INVALID"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_certificate_code() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+        // Same signing secret, but the certificate was never registered with this service
+        let token = issue_token(&CertificateService::default(), "test_public_key");
+        assert_eq!(
+            rejection_code(state, Some(token), Body::empty()).await,
+            "CERT_NOT_FOUND"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_certificate_code() {
+        let mut state = AppState::new_mock(AppConfig::default()).await;
+        state.certificate_service =
+            CertificateService::with_params(-1, "test_jwt_secret".to_string());
+        let token = issue_token(&state.certificate_service, "test_public_key");
+        assert_eq!(
+            rejection_code(state, Some(token), Body::empty()).await,
+            "CERT_EXPIRED"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_malformed_jwk_code() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let token = issue_token(&state.certificate_service, "test_public_key");
+        assert_eq!(
+            rejection_code(state, Some(token), signed_body()).await,
+            "JWK_INVALID"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_event_jwt_code() {
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let token = issue_token(
+            &state.certificate_service,
+            &DeviceKey::generate().public_key(),
+        );
+        assert_eq!(
+            rejection_code(state, Some(token), signed_body()).await,
+            "JWT_INVALID"
+        );
+    }
+
+    #[test]
+    fn test_device_jwt_algorithm_is_pinned() {
+        let device = DeviceKey::generate();
+        let public_key = device.public_key();
+        let signed = device.sign(&crate::test_utils::sample_event());
+        assert!(verify_device_jwt::<EventJwtClaims>(&signed, &public_key).is_ok());
+
+        // Same claims, unsigned
+        let claims = signed.split('.').nth(1).unwrap();
+        let none_header = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .encode(r#"{"alg":"none","typ":"JWT"}"#);
+        let unsigned = format!("{none_header}.{claims}.");
+
+        // Same claims, HMAC-signed with the public JWK as the secret
+        let claims_json: serde_json::Value = serde_json::from_slice(
+            &base64::engine::general_purpose::URL_SAFE_NO_PAD
+                .decode(claims)
+                .unwrap(),
+        )
+        .unwrap();
+        let hmac = jsonwebtoken::encode(
+            &jsonwebtoken::Header::new(Algorithm::HS256),
+            &claims_json,
+            &jsonwebtoken::EncodingKey::from_secret(public_key.as_bytes()),
+        )
+        .unwrap();
+
+        for token in [unsigned, hmac] {
+            assert!(
+                matches!(
+                    verify_device_jwt::<EventJwtClaims>(&token, &public_key),
+                    Err(EventServerError::Authentication {
+                        reason: AuthFailure::JwtInvalid,
+                        ..
+                    })
+                ),
+                "{token}"
+            );
+        }
+    }
 
     #[test]
     fn test_should_skip_validation() {
-        assert!(should_skip_validation("/health"));
-        assert!(should_skip_validation("/docs"));
-        assert!(should_skip_validation("/openapi-json"));
-        assert!(should_skip_validation("/openapi-yaml"));
-        assert!(should_skip_validation("/api/v1/pow/challenge"));
+        assert!(should_skip_validation("/health", &[], None));
+        assert!(should_skip_validation("/docs", &[], Some("/docs")));
+        assert!(!should_skip_validation("/docs", &[], None));
+        assert!(should_skip_validation("/openapi-json", &[], None));
+        assert!(should_skip_validation("/openapi-yaml", &[], None));
+        assert!(should_skip_validation("/api/v1/pow/challenge", &[], None));
 
-        assert!(!should_skip_validation("/api/v1/events"));
-        assert!(!should_skip_validation("/api/v1/events/package"));
-        assert!(!should_skip_validation("/some/other/path"));
+        assert!(!should_skip_validation("/api/v1/events", &[], None));
+        assert!(!should_skip_validation("/api/v1/events/package", &[], None));
+        assert!(!should_skip_validation("/some/other/path", &[], None));
+    }
+
+    #[test]
+    fn test_configured_public_paths() {
+        let extra = vec!["/metrics".to_string(), "/api/v1/jwks/".to_string()];
+
+        assert!(should_skip_validation("/metrics", &extra, None));
+        assert!(should_skip_validation("/metrics/prometheus", &extra, None));
+        assert!(should_skip_validation("/api/v1/jwks", &extra, None));
+        assert!(should_skip_validation("/api/v1/jwks/current", &extra, None));
+
+        // Prefix matching respects segment boundaries
+        assert!(!should_skip_validation("/metricsx", &extra, None));
+        assert!(!should_skip_validation("/api/v1/events", &extra, None));
+
+        // Configured paths add to the defaults rather than replacing them
+        assert!(should_skip_validation("/health", &extra, None));
+        assert!(should_skip_validation("/api/v1/pow/verify", &extra, None));
+    }
+
+    #[test]
+    fn test_empty_public_path_matches_nothing() {
+        let extra = vec!["/".to_string()];
+        assert!(!should_skip_validation("/api/v1/events", &extra, None));
+    }
+
+    #[tokio::test]
+    async fn test_configured_public_path_skips_auth() {
+        let mut config = AppConfig::default();
+        config.security.public_paths = vec!["/api/v1/events".to_string()];
+        let state = AppState::new_mock(config).await;
+
+        let response = crate::create_app(state)
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/v1/events/{}/verify", "a".repeat(64)))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_ne!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_dual_signature_mode() {
+        use crate::test_utils::sample_event;
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let mut config = AppConfig::default();
+        config.security.require_dual_signature = true;
+        let state = AppState::new_mock(config).await;
+        let device = DeviceKey::generate();
+        let ed25519 = SigningKey::from_bytes(&rand::random());
+        let encode = |bytes: &[u8]| base64::engine::general_purpose::STANDARD.encode(bytes);
+        let token = state
+            .certificate_service
+            .issue_certificate(&CertificateRequest {
+                relay_id: "test_relay".to_string(),
+                public_key: device.public_key(),
+                ed25519_public_key: Some(encode(ed25519.verifying_key().as_bytes())),
+                tenant_id: None,
+            })
+            .unwrap()
+            .cert_token;
+
+        // Signs `signed_data` with `signing_key`, claiming `signing_key`'s public key
+        let submit = |signing_key: &SigningKey, signed_data: Option<&str>| {
+            let jwt_event_data = device.sign(&sample_event());
+            let signature = signing_key.sign(signed_data.unwrap_or(&jwt_event_data).as_bytes());
+            let body = SignedEventPackage {
+                signature: Some(encode(&signature.to_bytes())),
+                public_key: Some(encode(signing_key.verifying_key().as_bytes())),
+                jwt_event_data,
+            };
+            crate::create_app(state.clone()).oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/events/package")
+                    .header("Content-Type", "application/json")
+                    .header("Authorization", format!("Bearer {token}"))
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+        };
+        let rejection = |response: Response| async move {
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            json["code"].as_str().unwrap().to_string()
+        };
+
+        // Matching keys
+        let response = submit(&ed25519, None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Valid signature from a key the certificate does not bind
+        let other = SigningKey::from_bytes(&rand::random());
+        let response = submit(&other, None).await.unwrap();
+        assert_eq!(rejection(response).await, "SIGNATURE_INVALID");
+
+        // Bound key, but the signature covers different data
+        let response = submit(&ed25519, Some("other data")).await.unwrap();
+        assert_eq!(rejection(response).await, "SIGNATURE_INVALID");
+
+        // A certificate-only submission is not enough
+        let response = crate::create_app(state.clone())
+            .oneshot(crate::test_utils::signed_package_request(
+                &device,
+                &token,
+                &sample_event(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(rejection(response).await, "SIGNATURE_INVALID");
+    }
+
+    #[tokio::test]
+    async fn test_enforce_relay_status() {
+        use crate::test_utils::{sample_event, signed_package_request};
+        use crate::types::relay::RelayStatus;
+
+        let mut config = AppConfig::default();
+        config.security.enforce_relay_status = true;
+        let state = AppState::new_mock(config).await;
+        let device = DeviceKey::generate();
+        let token = issue_token(&state.certificate_service, &device.public_key());
+        let submit = || {
+            crate::create_app(state.clone()).oneshot(signed_package_request(
+                &device,
+                &token,
+                &sample_event(),
+            ))
+        };
+
+        // Unknown relay
+        let response = submit().await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        state
+            .relay_service
+            .set_relay_status("test_relay", RelayStatus::Active);
+        let response = submit().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        state
+            .relay_service
+            .set_relay_status("test_relay", RelayStatus::Suspended);
+        let response = submit().await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "FORBIDDEN");
+        assert!(json["error"].as_str().unwrap().contains("not active"));
+    }
+
+    #[tokio::test]
+    async fn test_decommissioned_relay_submits_until_drained() {
+        use crate::crypto::MockClock;
+        use crate::test_utils::{sample_event, signed_package_request};
+        use crate::types::relay::RelayStatus;
+
+        let mut config = AppConfig::default();
+        config.security.enforce_relay_status = true;
+        let mut state = AppState::new_mock(config).await;
+        let clock = MockClock::new();
+        state.relay_service = state
+            .relay_service
+            .clone()
+            .with_clock(std::sync::Arc::new(clock.clone()));
+        let device = DeviceKey::generate();
+        let token = issue_token(&state.certificate_service, &device.public_key());
+        let submit = || {
+            crate::create_app(state.clone()).oneshot(signed_package_request(
+                &device,
+                &token,
+                &sample_event(),
+            ))
+        };
+
+        state
+            .relay_service
+            .set_relay_status("test_relay", RelayStatus::Active);
+        state
+            .relay_service
+            .decommission_relay("test_relay")
+            .await
+            .unwrap();
+        let response = submit().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        clock.advance(chrono::Duration::seconds(300));
+        let response = submit().await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_relay_status_not_enforced_by_default() {
+        use crate::test_utils::{sample_event, signed_package_request};
+
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let device = DeviceKey::generate();
+        let token = issue_token(&state.certificate_service, &device.public_key());
+        let response = crate::create_app(state)
+            .oneshot(signed_package_request(&device, &token, &sample_event()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
     }
 
     #[test]
@@ -386,4 +1573,21 @@ mod tests {
             Some("test_relay".to_string())
         );
     }
+
+    #[test]
+    fn test_strip_validated_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Validated-Relay-ID", "relay".parse().unwrap());
+        headers.insert(VALIDATED_CERTIFICATE_HEADER, "certificate".parse().unwrap());
+        headers.insert(
+            crate::services::tenant::VALIDATED_TENANT_HEADER,
+            "acme".parse().unwrap(),
+        );
+        headers.insert("X-Request-ID", "kept".parse().unwrap());
+
+        strip_validated_headers(&mut headers);
+        assert_eq!(extract_validated_relay_id(&headers), None);
+        assert_eq!(extract_validated_certificate_id(&headers), None);
+        assert_eq!(headers.len(), 1);
+    }
 }
@@ -0,0 +1,124 @@
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::json;
+
+use crate::config::ErrorFormat;
+use crate::error::AppErrorResponse;
+use crate::state::AppState;
+
+/// Content type of RFC 7807 error bodies
+pub const PROBLEM_JSON_CONTENT_TYPE: &str = "application/problem+json";
+
+/// Error format middleware
+/// With `server.error_format = problem_json`, rewrites `AppError` bodies as RFC 7807 problem
+/// details; `code`, `timestamp` and any variant-specific fields are kept as extension members
+pub async fn error_format_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if state.config.server.error_format != ErrorFormat::ProblemJson {
+        return next.run(request).await;
+    }
+
+    let instance = request.uri().path().to_string();
+    let response = next.run(request).await;
+    if response.extensions().get::<AppErrorResponse>().is_none() {
+        return response;
+    }
+    to_problem_json(response, &instance).await
+}
+
+async fn to_problem_json(response: Response, instance: &str) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let bytes = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .unwrap_or_default();
+    let Ok(serde_json::Value::Object(mut fields)) = serde_json::from_slice(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    // No per-error type URIs are published, so the status title is the problem type
+    let detail = fields.remove("error").unwrap_or_default();
+    let mut problem = json!({
+        "type": "about:blank",
+        "title": parts.status.canonical_reason().unwrap_or("Error"),
+        "status": parts.status.as_u16(),
+        "detail": detail,
+        "instance": instance,
+    });
+    if let Some(problem) = problem.as_object_mut() {
+        problem.extend(fields);
+    }
+
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(PROBLEM_JSON_CONTENT_TYPE),
+    );
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(problem.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use axum::http::StatusCode;
+    use tower::ServiceExt;
+
+    async fn get(config: AppConfig, uri: &str) -> Response {
+        crate::create_app(AppState::new_mock(config).await)
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn json_body(response: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_problem_json_errors() {
+        let mut config = AppConfig::default();
+        config.server.error_format = ErrorFormat::ProblemJson;
+        config.server.instance_id = "eu-west-1a".to_string();
+
+        let response = get(config, "/no-such-route").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            PROBLEM_JSON_CONTENT_TYPE
+        );
+        let json = json_body(response).await;
+        assert_eq!(json["type"], "about:blank");
+        assert_eq!(json["title"], "Not Found");
+        assert_eq!(json["status"], 404);
+        assert_eq!(
+            json["detail"],
+            "Resource not found: No route found for /no-such-route"
+        );
+        assert_eq!(json["instance"], "/no-such-route");
+        assert_eq!(json["code"], "NOT_FOUND");
+        assert_eq!(json["server_instance"], "eu-west-1a");
+        assert!(json.get("error").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_errors_by_default() {
+        let response = get(AppConfig::default(), "/no-such-route").await;
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        let json = json_body(response).await;
+        assert_eq!(json["code"], "NOT_FOUND");
+        assert!(json["error"].is_string());
+        assert!(json["timestamp"].is_string());
+        assert!(json.get("type").is_none());
+    }
+}
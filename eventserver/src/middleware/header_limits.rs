@@ -0,0 +1,122 @@
+use axum::{
+    extract::{Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::warn;
+
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// Header limits middleware
+/// Rejects requests with more than `server.max_header_count` headers, or whose headers take
+/// more than `server.max_header_bytes`, with 431 before authentication or routing parses them.
+/// Hyper already refuses heads past twice these limits while reading them (see `server.rs`)
+pub async fn header_limits_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let server = &state.config.server;
+    let headers = request.headers();
+
+    if server.max_header_count > 0 && headers.len() > server.max_header_count {
+        warn!(
+            count = headers.len(),
+            "Rejecting request with too many headers"
+        );
+        return AppError::HeadersTooLarge(format!(
+            "{} headers sent; at most {} are accepted",
+            headers.len(),
+            server.max_header_count
+        ))
+        .into_response();
+    }
+
+    let size = header_bytes(headers);
+    if server.max_header_bytes > 0 && size > server.max_header_bytes {
+        warn!(size, "Rejecting request with oversized headers");
+        return AppError::HeadersTooLarge(format!(
+            "Headers take {size} bytes; at most {} are accepted",
+            server.max_header_bytes
+        ))
+        .into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Size of the header lines as sent, counting `: ` and the CRLF of each
+fn header_bytes(headers: &HeaderMap) -> usize {
+    headers
+        .iter()
+        .map(|(name, value)| name.as_str().len() + value.len() + 4)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use axum::body::Body;
+    use axum::http::{HeaderName, HeaderValue, StatusCode};
+    use tower::ServiceExt;
+
+    async fn send(request: Request) -> Response {
+        let mut config = AppConfig::default();
+        config.server.max_header_bytes = 1024;
+        config.server.max_header_count = 10;
+        crate::create_app(AppState::new_mock(config).await)
+            .oneshot(request)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_oversized_header_is_rejected() {
+        let response = send(
+            Request::post("/api/v1/events")
+                .header("Authorization", format!("Bearer {}", "a".repeat(2048)))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(
+            response.status(),
+            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+        );
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "HEADERS_TOO_LARGE");
+    }
+
+    #[tokio::test]
+    async fn test_header_count_is_limited() {
+        let mut request = Request::get("/health").body(Body::empty()).unwrap();
+        for i in 0..11 {
+            let name = HeaderName::try_from(format!("x-extra-{i}")).unwrap();
+            request
+                .headers_mut()
+                .insert(name, HeaderValue::from_static("1"));
+        }
+        let response = send(request).await;
+        assert_eq!(
+            response.status(),
+            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+        );
+
+        // Within both limits the request goes through
+        let response = send(
+            Request::get("/health")
+                .header("x-extra", "1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
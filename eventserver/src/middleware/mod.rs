@@ -1 +1,30 @@
+pub mod access_log;
+pub mod admin;
 pub mod crypto;
+pub mod error_format;
+pub mod header_limits;
+pub mod rate_limit;
+pub mod request_span;
+pub mod server_instance;
+pub mod server_timing;
+pub mod timeout;
+
+use axum::{http::header, response::Response};
+
+/// Add a top-level field to a JSON response body; non-JSON bodies are returned unchanged
+pub(crate) async fn with_json_field(response: Response, field: &str, value: &str) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let bytes = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .unwrap_or_default();
+    let Ok(serde_json::Value::Object(mut json)) = serde_json::from_slice(&bytes) else {
+        return Response::from_parts(parts, axum::body::Body::from(bytes));
+    };
+
+    json.insert(field.to_string(), value.into());
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(
+        parts,
+        axum::body::Body::from(serde_json::Value::Object(json).to_string()),
+    )
+}
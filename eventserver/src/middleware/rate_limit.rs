@@ -1,21 +1,302 @@
 use axum::{
-    extract::Request,
+    extract::{ConnectInfo, Request, State},
     middleware::Next,
     response::Response,
-    http::StatusCode,
 };
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+use crate::config::RateLimitKey;
+use crate::error::EventServerError;
+use crate::middleware::crypto::{extract_validated_certificate_id, extract_validated_relay_id};
+use crate::state::AppState;
+use crate::types::api::RateLimitInfo;
+
+/// Key used for requests with neither a validated ID nor a known peer address
+const ANONYMOUS_KEY: &str = "anonymous";
+
+/// Request count within the current one-minute window of a key
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    started_at: DateTime<Utc>,
+    count: u32,
+}
+
+/// Windows of every key seen in the last minute or so
+#[derive(Debug)]
+struct Windows {
+    by_key: HashMap<String, Window>,
+    swept_at: DateTime<Utc>, // Stale windows are dropped at most once a minute
+}
+
+/// Fixed-window request limiter, keyed per relay or per certificate
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    limit_per_minute: u32, // 0 disables limiting
+    key: RateLimitKey,
+    windows: Arc<Mutex<Windows>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit_per_minute: u32, key: RateLimitKey) -> Self {
+        Self {
+            limit_per_minute,
+            key,
+            windows: Arc::new(Mutex::new(Windows {
+                by_key: HashMap::new(),
+                swept_at: Utc::now(),
+            })),
+        }
+    }
+
+    /// Count a request for `key`
+    /// Returns the remaining budget, or `Err` with the same info once the limit is exceeded
+    pub fn check(&self, key: &str) -> Result<RateLimitInfo, RateLimitInfo> {
+        self.check_at(key, Utc::now())
+    }
+
+    fn check_at(&self, key: &str, now: DateTime<Utc>) -> Result<RateLimitInfo, RateLimitInfo> {
+        let mut guard = self.windows.lock().unwrap();
+        let Windows { by_key, swept_at } = &mut *guard;
+
+        // Sweeping on a schedule keeps the per-request cost constant however many keys there are
+        if now >= *swept_at + Duration::minutes(1) {
+            by_key.retain(|_, window| now < window.started_at + Duration::minutes(1));
+            *swept_at = now;
+        }
+
+        let window = match by_key.get(key) {
+            Some(window) if now < window.started_at + Duration::minutes(1) => *window,
+            _ => Window {
+                started_at: now,
+                count: 0,
+            },
+        };
+
+        let info = |count: u32| RateLimitInfo {
+            requests_remaining: self.limit_per_minute.saturating_sub(count),
+            reset_time: window.started_at + Duration::minutes(1),
+            limit_per_minute: self.limit_per_minute,
+            scope: self.key,
+        };
+
+        if window.count >= self.limit_per_minute {
+            by_key.insert(key.to_string(), window);
+            return Err(info(window.count));
+        }
+
+        let count = window.count + 1;
+        by_key.insert(key.to_string(), Window { count, ..window });
+        Ok(info(count))
+    }
+
+    /// Whether limiting is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.limit_per_minute > 0
+    }
+
+    /// Budget key of a request that passed the crypto middleware
+    /// Unauthenticated requests are counted per peer IP, so they can't exhaust one shared budget
+    fn request_key(&self, request: &Request) -> String {
+        let headers = request.headers();
+        let validated = match self.key {
+            RateLimitKey::Relay => extract_validated_relay_id(headers),
+            RateLimitKey::Certificate => extract_validated_certificate_id(headers),
+        };
+        validated.unwrap_or_else(|| {
+            request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map_or_else(
+                    || ANONYMOUS_KEY.to_string(),
+                    |ConnectInfo(addr)| format!("ip:{}", addr.ip()),
+                )
+        })
+    }
+}
 
 /// Rate limiting middleware
-/// TODO: Implement rate limiting
+/// Runs after crypto validation so requests are counted per validated relay or certificate; every
+/// response carries `X-RateLimit-*` headers and over-limit requests get a `429`
 pub async fn rate_limit_middleware(
+    State(state): State<AppState>,
     request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
-    // TODO: Extract relay ID from request
-    // TODO: Check rate limit in storage/cache
-    // TODO: Increment counter and set expiration
-    // TODO: Return 429 Too Many Requests if limit exceeded
-    
-    // For now, just pass through all requests
-    Ok(next.run(request).await)
-}
\ No newline at end of file
+) -> Result<Response, EventServerError> {
+    if !state.rate_limiter.is_enabled() {
+        return Ok(next.run(request).await);
+    }
+
+    let key = state.rate_limiter.request_key(&request);
+
+    match state.rate_limiter.check(&key) {
+        Ok(info) => {
+            let mut response = next.run(request).await;
+            info.insert_headers(response.headers_mut());
+            Ok(response)
+        }
+        Err(info) => {
+            warn!(
+                key = %key,
+                scope = info.scope.as_str(),
+                limit_per_minute = info.limit_per_minute,
+                reset_time = %info.reset_time,
+                "Rate limit exceeded"
+            );
+            Err(EventServerError::RateLimit(info))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_utils::{issue_token, sample_event, signed_package_request, DeviceKey};
+    use axum::http::StatusCode;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_limit_is_per_key() {
+        let limiter = RateLimiter::new(2, RateLimitKey::Relay);
+
+        assert_eq!(limiter.check("a").unwrap().requests_remaining, 1);
+        assert_eq!(limiter.check("a").unwrap().requests_remaining, 0);
+        assert!(limiter.check("a").is_err());
+        assert_eq!(limiter.check("b").unwrap().requests_remaining, 1);
+    }
+
+    #[test]
+    fn test_stale_windows_are_swept_once_a_minute() {
+        let limiter = RateLimiter::new(2, RateLimitKey::Relay);
+        let start = Utc::now();
+        let tracked = || limiter.windows.lock().unwrap().by_key.len();
+
+        for key in ["a", "b", "c"] {
+            limiter.check_at(key, start).unwrap();
+        }
+        // A new key within the minute doesn't trigger a sweep
+        limiter
+            .check_at("d", start + Duration::seconds(30))
+            .unwrap();
+        assert_eq!(tracked(), 4);
+
+        // The first request after a minute drops the expired windows, keeping the live one
+        let info = limiter
+            .check_at("e", start + Duration::seconds(61))
+            .unwrap();
+        assert_eq!(info.requests_remaining, 1);
+        assert_eq!(tracked(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_headers_and_throttled_body() {
+        let mut config = AppConfig::default();
+        config.security.rate_limit_per_minute = 2;
+        let state = AppState::new_mock(config).await;
+        let device = DeviceKey::generate();
+        let token = issue_token(&state, &device);
+        let submit = || {
+            crate::create_app(state.clone()).oneshot(signed_package_request(
+                &device,
+                &token,
+                &sample_event(),
+            ))
+        };
+
+        let response = submit().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(headers["x-ratelimit-limit"], "2");
+        assert_eq!(headers["x-ratelimit-remaining"], "1");
+        assert_eq!(headers["x-ratelimit-scope"], "relay");
+        let reset: i64 = headers["x-ratelimit-reset"]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(reset > Utc::now().timestamp());
+
+        assert_eq!(submit().await.unwrap().status(), StatusCode::OK);
+
+        let response = submit().await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["x-ratelimit-remaining"], "0");
+        assert!(response.headers().contains_key("retry-after"));
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "RATE_LIMIT_EXCEEDED");
+        assert_eq!(json["requestsRemaining"], 0);
+        assert_eq!(json["limitPerMinute"], 2);
+        assert!(json["resetTime"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_unauthenticated_requests_are_keyed_by_peer() {
+        let mut config = AppConfig::default();
+        config.security.rate_limit_per_minute = 1;
+        let state = AppState::new_mock(config).await;
+        let status = |peer: [u8; 4]| {
+            let mut request = axum::http::Request::builder()
+                .uri("/api/v1/certificates/status")
+                // A forged relay ID must not pick, or share, anyone's budget
+                .header("X-Validated-Relay-ID", "victim_relay")
+                .body(axum::body::Body::empty())
+                .unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from((peer, 50000))));
+            crate::create_app(state.clone()).oneshot(request)
+        };
+
+        let first = status([203, 0, 113, 7]).await.unwrap();
+        assert_ne!(first.status(), StatusCode::TOO_MANY_REQUESTS);
+        let other_peer = status([203, 0, 113, 8]).await.unwrap();
+        assert_ne!(other_peer.status(), StatusCode::TOO_MANY_REQUESTS);
+        let again = status([203, 0, 113, 7]).await.unwrap();
+        assert_eq!(again.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn test_certificate_mode_gives_each_device_a_budget() {
+        for (key, second_device_status) in [
+            (RateLimitKey::Certificate, StatusCode::OK),
+            (RateLimitKey::Relay, StatusCode::TOO_MANY_REQUESTS),
+        ] {
+            let mut config = AppConfig::default();
+            config.security.rate_limit_per_minute = 1;
+            config.security.rate_limit_key = key;
+            let state = AppState::new_mock(config).await;
+            // Both certificates are issued to the same relay
+            let first = DeviceKey::generate();
+            let first_token = issue_token(&state, &first);
+            let second = DeviceKey::generate();
+            let second_token = issue_token(&state, &second);
+            let submit = |device: &DeviceKey, token: &str| {
+                crate::create_app(state.clone()).oneshot(signed_package_request(
+                    device,
+                    token,
+                    &sample_event(),
+                ))
+            };
+
+            let response = submit(&first, &first_token).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["x-ratelimit-scope"], key.as_str());
+            assert_eq!(
+                submit(&first, &first_token).await.unwrap().status(),
+                StatusCode::TOO_MANY_REQUESTS
+            );
+
+            let response = submit(&second, &second_token).await.unwrap();
+            assert_eq!(response.status(), second_device_status, "{key:?}");
+            assert_eq!(response.headers()["x-ratelimit-scope"], key.as_str());
+        }
+    }
+}
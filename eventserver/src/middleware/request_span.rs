@@ -0,0 +1,244 @@
+use axum::{
+    extract::{Request, State},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use sha2::{Digest, Sha256};
+use tracing::{field, span, Event, Instrument, Level, Span, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+use uuid::Uuid;
+
+use crate::state::AppState;
+
+/// Header carrying the request ID; a valid incoming value is reused so callers can correlate
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest incoming request ID that is reused rather than replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Request ID, stored in request extensions for correlating later artifacts with the request
+#[derive(Clone)]
+pub struct RequestId(pub String);
+
+/// Per-request span, stored in request extensions so later middleware can enrich it
+#[derive(Clone)]
+pub struct RequestSpan(pub Span);
+
+impl RequestSpan {
+    /// Record the authenticated relay on the request span
+    pub fn record_relay_id(&self, relay_id: &str) {
+        self.0.record("relay_id", field::display(relay_id));
+    }
+
+    /// Record the submitted event on the request span
+    pub fn record_event_id(&self, event_id: &Uuid) {
+        self.0.record("event_id", field::display(event_id));
+    }
+}
+
+/// Whether a request's verbose log lines are kept at `sample_rate`
+/// Decided by the request ID's hash, so every line of a request shares the same fate
+fn is_sampled(request_id: &str, sample_rate: f64) -> bool {
+    if sample_rate >= 1.0 {
+        return true;
+    }
+    let digest = Sha256::digest(request_id.as_bytes());
+    let position = u64::from_be_bytes(digest[..8].try_into().unwrap()) as f64 / u64::MAX as f64;
+    position < sample_rate
+}
+
+/// Tracing layer dropping info and more verbose events inside request spans that weren't
+/// sampled; warnings and errors are always kept
+pub struct RequestLogSampling;
+
+/// Marks a request span whose verbose events are dropped
+struct Unsampled;
+
+impl<S> Layer<S> for RequestLogSampling
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let mut sampled = SampledField(true);
+        attrs.record(&mut sampled);
+        if !sampled.0 {
+            if let Some(span) = ctx.span(id) {
+                span.extensions_mut().insert(Unsampled);
+            }
+        }
+    }
+
+    fn event_enabled(&self, event: &Event<'_>, ctx: Context<'_, S>) -> bool {
+        if *event.metadata().level() <= Level::WARN {
+            return true;
+        }
+        ctx.event_scope(event).is_none_or(|mut scope| {
+            !scope.any(|span| span.extensions().get::<Unsampled>().is_some())
+        })
+    }
+}
+
+/// Reads the `sampled` field of a new span
+struct SampledField(bool);
+
+impl field::Visit for SampledField {
+    fn record_bool(&mut self, field: &field::Field, value: bool) {
+        if field.name() == "sampled" {
+            self.0 = value;
+        }
+    }
+
+    fn record_debug(&mut self, _: &field::Field, _: &dyn std::fmt::Debug) {}
+}
+
+/// Request span middleware
+/// Wraps each request in a `request` span with `request_id`, and empty `relay_id`/`event_id`
+/// fields that are filled in once known, so every downstream log line carries them
+/// Only `server.debug_log_sample_rate` of requests keep their info and debug lines
+pub async fn request_span_middleware(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= MAX_REQUEST_ID_LEN)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+        sampled = is_sampled(&request_id, state.config.server.debug_log_sample_rate),
+        relay_id = field::Empty,
+        event_id = field::Empty,
+    );
+    request.extensions_mut().insert(RequestSpan(span.clone()));
+    request
+        .extensions_mut()
+        .insert(RequestId(request_id.clone()));
+
+    let mut response = next.run(request).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::state::AppState;
+    use crate::test_utils::{
+        issue_token, sample_event, signed_package_request, CapturedLogs, DeviceKey,
+    };
+    use axum::http::StatusCode;
+    use tower::ServiceExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[tokio::test]
+    async fn test_span_fields_propagate_to_nested_logs() {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let device = DeviceKey::generate();
+        let token = issue_token(&state, &device);
+        let event = sample_event();
+
+        let mut request = signed_package_request(&device, &token, &event);
+        request
+            .headers_mut()
+            .insert("x-request-id", "req-123".parse().unwrap());
+        let response = crate::create_app(state).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-request-id"], "req-123");
+
+        let output = logs.contents();
+        let nested = output
+            .lines()
+            .find(|line| line.contains("EventPackage processed and uploaded successfully"))
+            .expect("handler log line");
+        // Fields are inherited from the span context, not logged by the handler itself
+        let span_fields = nested
+            .split_once("request{")
+            .and_then(|(_, rest)| rest.split_once("}:"))
+            .map(|(fields, _)| fields)
+            .expect("request span context");
+        assert!(span_fields.contains("request_id=req-123"));
+        assert!(span_fields.contains("relay_id=test_relay"));
+        assert!(span_fields.contains(&format!("event_id={}", event.id)));
+    }
+
+    /// Log output of one accepted and one rejected submission at `sample_rate`
+    async fn sampled_logs(sample_rate: f64) -> String {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish()
+            .with(RequestLogSampling);
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let mut config = AppConfig::default();
+        config.server.debug_log_sample_rate = sample_rate;
+        let state = AppState::new_mock(config).await;
+        let device = DeviceKey::generate();
+        let token = issue_token(&state, &device);
+        let app = crate::create_app(state);
+
+        let request = signed_package_request(&device, &token, &sample_event());
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let request = signed_package_request(&DeviceKey::generate(), &token, &sample_event());
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        logs.contents()
+    }
+
+    #[tokio::test]
+    async fn test_unsampled_requests_keep_only_warnings() {
+        let output = sampled_logs(0.0).await;
+        let request_lines: Vec<&str> = output
+            .lines()
+            .filter(|line| line.contains("request{"))
+            .collect();
+        assert!(!request_lines.is_empty(), "{output}");
+        for line in request_lines {
+            assert!(line.contains("WARN") || line.contains("ERROR"), "{line}");
+        }
+        assert!(output.contains("Rejected event"));
+    }
+
+    #[tokio::test]
+    async fn test_fully_sampled_requests_keep_every_line() {
+        let output = sampled_logs(1.0).await;
+        assert!(output.contains("EventPackage processed and uploaded successfully"));
+        assert!(output.contains("Applying cryptographic validation"));
+        assert!(output.contains("Rejected event"));
+    }
+
+    #[test]
+    fn test_sampling_is_deterministic_per_request_id() {
+        let sampled: Vec<bool> = (0..1000)
+            .map(|i| is_sampled(&format!("req-{i}"), 0.25))
+            .collect();
+        let kept = sampled.iter().filter(|sampled| **sampled).count();
+        assert!((150..350).contains(&kept), "{kept}");
+        assert!((0..1000).all(|i| is_sampled(&format!("req-{i}"), 0.25) == sampled[i]));
+    }
+}
@@ -0,0 +1,76 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::middleware::error_format::PROBLEM_JSON_CONTENT_TYPE;
+use crate::middleware::with_json_field;
+use crate::state::AppState;
+
+/// Header naming the server instance that handled the request
+pub const SERVER_INSTANCE_HEADER: &str = "x-server-instance";
+
+/// Server instance middleware
+/// Tags every response with `X-Server-Instance`, and JSON error bodies with `server_instance`,
+/// so requests can be traced to an instance behind a load balancer
+pub async fn server_instance_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let instance_id = &state.config.server.instance_id;
+    let mut response = next.run(request).await;
+
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value.starts_with("application/json") || value.starts_with(PROBLEM_JSON_CONTENT_TYPE)
+        });
+    if is_json && (response.status().is_client_error() || response.status().is_server_error()) {
+        response = with_json_field(response, "server_instance", instance_id).await;
+    }
+
+    if let Ok(value) = HeaderValue::from_str(instance_id) {
+        response.headers_mut().insert(SERVER_INSTANCE_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_responses_carry_configured_instance_id() {
+        let mut config = AppConfig::default();
+        config.server.instance_id = "eu-west-1a".to_string();
+        let app = crate::create_app(AppState::new_mock(config).await);
+
+        let response = app
+            .clone()
+            .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[SERVER_INSTANCE_HEADER], "eu-west-1a");
+
+        let response = app
+            .oneshot(Request::get("/no-such-route").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[SERVER_INSTANCE_HEADER], "eu-west-1a");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["server_instance"], "eu-west-1a");
+    }
+}
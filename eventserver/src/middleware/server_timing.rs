@@ -0,0 +1,106 @@
+use axum::{
+    extract::{Request, State},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::state::AppState;
+
+/// Response header listing the recorded phase durations
+pub const SERVER_TIMING_HEADER: &str = "server-timing";
+
+/// Durations of the phases of one request
+/// Placed in the request extensions by the middleware; each stage records its own phase
+#[derive(Debug, Clone, Default)]
+pub struct ServerTimings(Arc<Mutex<Vec<(&'static str, Duration)>>>);
+
+impl ServerTimings {
+    /// Record phase `name` as having run from `started` until now
+    pub fn record(&self, name: &'static str, started: Instant) {
+        self.0.lock().unwrap().push((name, started.elapsed()));
+    }
+
+    /// `Server-Timing` value, e.g. `crypto;dur=1.204, storage;dur=8.730`
+    fn header_value(&self) -> Option<HeaderValue> {
+        let phases = self.0.lock().unwrap();
+        if phases.is_empty() {
+            return None;
+        }
+        let value = phases
+            .iter()
+            .map(|(name, duration)| format!("{name};dur={:.3}", duration.as_secs_f64() * 1000.0))
+            .collect::<Vec<_>>()
+            .join(", ");
+        HeaderValue::from_str(&value).ok()
+    }
+}
+
+/// Server-Timing middleware
+/// With `server.server_timing_enabled`, reports the crypto validation, packaging and storage
+/// durations of a request in a `Server-Timing` header for browser devtools
+pub async fn server_timing_middleware(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !state.config.server.server_timing_enabled {
+        return next.run(request).await;
+    }
+
+    let timings = ServerTimings::default();
+    request.extensions_mut().insert(timings.clone());
+    let mut response = next.run(request).await;
+    if let Some(value) = timings.header_value() {
+        response.headers_mut().insert(SERVER_TIMING_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_utils::{issue_token, sample_event, signed_package_request, DeviceKey};
+    use axum::http::StatusCode;
+    use tower::ServiceExt;
+
+    async fn submit_event(server_timing_enabled: bool) -> Response {
+        let mut config = AppConfig::default();
+        config.server.server_timing_enabled = server_timing_enabled;
+        let state = AppState::new_mock(config).await;
+        let device = DeviceKey::generate();
+        let token = issue_token(&state, &device);
+
+        crate::create_app(state)
+            .oneshot(signed_package_request(&device, &token, &sample_event()))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_submission_reports_phase_timings() {
+        let response = submit_event(true).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let header = response.headers()[SERVER_TIMING_HEADER].to_str().unwrap();
+        let names: Vec<&str> = header
+            .split(", ")
+            .map(|segment| {
+                let (name, duration) = segment.split_once(";dur=").unwrap();
+                assert!(duration.parse::<f64>().is_ok(), "{header}");
+                name
+            })
+            .collect();
+        assert_eq!(names, ["crypto", "packaging", "storage"], "{header}");
+    }
+
+    #[tokio::test]
+    async fn test_no_header_unless_enabled() {
+        let response = submit_event(false).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key(SERVER_TIMING_HEADER));
+    }
+}
@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use tracing::warn;
+
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// Request timeout middleware
+/// Requests running past `server.request_timeout` seconds are dropped and answered with a
+/// structured 504 instead of hanging the connection; unset or 0 disables the limit
+pub async fn request_timeout_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let timeout_seconds = match state.config.server.request_timeout {
+        Some(seconds) if seconds > 0 => seconds,
+        _ => return Ok(next.run(request).await),
+    };
+
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    match tokio::time::timeout(Duration::from_secs(timeout_seconds), next.run(request)).await {
+        Ok(response) => Ok(response),
+        Err(_) => {
+            warn!(%method, path = %path, timeout_seconds, "Request timed out");
+            Err(AppError::Timeout(format!(
+                "Request did not complete within {timeout_seconds} seconds"
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use axum::{routing::get, Router};
+    use tower::ServiceExt;
+
+    async fn app(request_timeout: Option<u64>) -> Router {
+        let mut config = AppConfig::default();
+        config.server.request_timeout = request_timeout;
+        let state = AppState::new_mock(config).await;
+        Router::new()
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_millis(1500)).await;
+                    "done"
+                }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                state,
+                request_timeout_middleware,
+            ))
+    }
+
+    #[tokio::test]
+    async fn test_slow_request_gets_structured_504() {
+        let response = app(Some(1))
+            .await
+            .oneshot(Request::get("/slow").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "REQUEST_TIMEOUT");
+        assert!(json["error"].as_str().unwrap().contains("1 seconds"));
+        assert!(json["timestamp"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_timeout_disabled() {
+        let response = app(Some(0))
+            .await
+            .oneshot(Request::get("/slow").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
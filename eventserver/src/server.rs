@@ -0,0 +1,255 @@
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{ConnectInfo, Request};
+use axum::Router;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::server::graceful::{GracefulShutdown, Watcher};
+use hyper_util::service::TowerToHyperService;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::Semaphore;
+use tokio_rustls::TlsAcceptor;
+use tower::util::MapRequest;
+use tracing::{debug, warn};
+
+use crate::config::ServerConfig;
+
+/// How long shutdown waits for open connections to finish their requests
+const CONNECTION_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Smallest read buffer hyper accepts
+const MIN_HEAD_BUFFER_BYTES: usize = 8192;
+
+/// Per-connection limits applied by the accept loop
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionLimits {
+    pub handshake_timeout: Duration, // TLS clients that haven't finished the handshake by then are dropped
+    pub max_connections: usize,      // Further connections wait in the listen backlog
+    pub max_headers: Option<usize>,  // Header lines hyper parses before refusing with 431
+    pub max_head_bytes: Option<usize>, // Bytes hyper buffers for a request head before refusing with 431
+}
+
+impl ConnectionLimits {
+    /// Limits from the server settings
+    /// Hyper refuses heads at twice the configured header limits before buffering them all;
+    /// `header_limits_middleware` answers requests in between with the exact, structured 431
+    pub fn from_config(server: &ServerConfig) -> Self {
+        Self {
+            handshake_timeout: Duration::from_secs(server.tls_handshake_timeout_seconds.max(1)),
+            max_connections: server
+                .max_connections
+                .filter(|max| *max > 0)
+                .map_or(Semaphore::MAX_PERMITS, |max| max as usize),
+            max_headers: (server.max_header_count > 0)
+                .then(|| server.max_header_count.saturating_mul(2)),
+            max_head_bytes: (server.max_header_bytes > 0).then(|| {
+                server
+                    .max_header_bytes
+                    .saturating_mul(2)
+                    .max(MIN_HEAD_BUFFER_BYTES)
+            }),
+        }
+    }
+}
+
+/// Serve `app` until `shutdown` resolves, over TLS when `tls` is set and plain HTTP otherwise
+/// Failed handshakes only drop that connection. On shutdown no more connections are accepted,
+/// and open ones get up to `CONNECTION_DRAIN_TIMEOUT` to finish their requests
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    tls: Option<Arc<rustls::ServerConfig>>,
+    limits: ConnectionLimits,
+    shutdown: impl Future<Output = ()>,
+) {
+    let acceptor = tls.map(TlsAcceptor::from);
+    let connections = Arc::new(Semaphore::new(limits.max_connections));
+    let mut builder = Builder::new(TokioExecutor::new());
+    if let Some(max_headers) = limits.max_headers {
+        builder.http1().max_headers(max_headers);
+    }
+    if let Some(max_head_bytes) = limits.max_head_bytes {
+        builder.http1().max_buf_size(max_head_bytes);
+    }
+    let graceful = GracefulShutdown::new();
+
+    tokio::pin!(shutdown);
+    loop {
+        // Held for the connection's lifetime, so at the cap nothing more is accepted
+        let permit = tokio::select! {
+            permit = connections.clone().acquire_owned() => permit.expect("semaphore never closed"),
+            _ = &mut shutdown => break,
+        };
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = &mut shutdown => break,
+        };
+        let (stream, peer) = match accepted {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                // Typically out of file descriptors; back off rather than spin
+                warn!(error = %e, "Failed to accept connection");
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+
+        let acceptor = acceptor.clone();
+        let (builder, app, watcher) = (builder.clone(), app.clone(), graceful.watcher());
+        tokio::spawn(async move {
+            let _permit = permit;
+            let Some(acceptor) = acceptor else {
+                serve_connection(&builder, watcher, stream, app, peer).await;
+                return;
+            };
+            match tokio::time::timeout(limits.handshake_timeout, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => serve_connection(&builder, watcher, stream, app, peer).await,
+                Ok(Err(e)) => debug!(%peer, error = %e, "TLS handshake refused"),
+                Err(_) => debug!(%peer, "TLS handshake timed out"),
+            }
+        });
+    }
+
+    drop(listener);
+    if tokio::time::timeout(CONNECTION_DRAIN_TIMEOUT, graceful.shutdown())
+        .await
+        .is_err()
+    {
+        warn!("Timed out waiting for open connections to finish");
+    }
+}
+
+async fn serve_connection<S>(
+    builder: &Builder<TokioExecutor>,
+    watcher: Watcher,
+    stream: S,
+    app: Router,
+    peer: SocketAddr,
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = TowerToHyperService::new(MapRequest::new(app, with_peer(peer)));
+    let connection = builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+    if let Err(e) = watcher.watch(connection.into_owned()).await {
+        debug!(%peer, error = %e, "Connection closed with error");
+    }
+}
+
+/// Expose the peer address to handlers as `axum::serve` does with connect info
+/// Applied per request around the shared router, so no per-connection router is built
+fn with_peer<B>(peer: SocketAddr) -> impl FnMut(Request<B>) -> Request<B> + Clone {
+    move |mut request| {
+        request.extensions_mut().insert(ConnectInfo(peer));
+        request
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    /// Serve `app` over plain HTTP on a local port until the returned sender fires
+    async fn spawn_server(
+        app: Router,
+        limits: ConnectionLimits,
+    ) -> (
+        SocketAddr,
+        tokio::sync::oneshot::Sender<()>,
+        tokio::task::JoinHandle<()>,
+    ) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(listener, app, None, limits, async {
+            let _ = stopped.await;
+        }));
+        (address, stop, server)
+    }
+
+    async fn raw_request(address: SocketAddr, request: &[u8]) -> String {
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream.write_all(request).await.unwrap();
+        let mut response = Vec::new();
+        let _ = stream.read_to_end(&mut response).await;
+        String::from_utf8_lossy(&response).into_owned()
+    }
+
+    fn limits(server: &ServerConfig) -> ConnectionLimits {
+        ConnectionLimits::from_config(server)
+    }
+
+    #[tokio::test]
+    async fn test_oversized_heads_are_refused_before_reaching_the_app() {
+        let mut server = crate::config::AppConfig::default().server;
+        server.max_header_bytes = 1024;
+        server.max_header_count = 8;
+        let app = Router::new().route(
+            "/peer",
+            get(|ConnectInfo(peer): ConnectInfo<SocketAddr>| async move { peer.ip().to_string() }),
+        );
+        let (address, _stop, _server) = spawn_server(app, limits(&server)).await;
+
+        let response = raw_request(
+            address,
+            b"GET /peer HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.ends_with("127.0.0.1"), "{response}");
+
+        // Past twice the header count
+        let mut request = String::from("GET /peer HTTP/1.1\r\nHost: localhost\r\n");
+        for i in 0..20 {
+            request.push_str(&format!("X-Filler-{i}: x\r\n"));
+        }
+        request.push_str("Connection: close\r\n\r\n");
+        let response = raw_request(address, request.as_bytes()).await;
+        assert!(response.starts_with("HTTP/1.1 431"), "{response}");
+
+        // Past the head buffer, which is never smaller than hyper's minimum
+        let request = format!(
+            "GET /peer HTTP/1.1\r\nHost: localhost\r\nX-Filler: {}\r\nConnection: close\r\n\r\n",
+            "x".repeat(MIN_HEAD_BUFFER_BYTES * 2)
+        );
+        let response = raw_request(address, request.as_bytes()).await;
+        assert!(response.starts_with("HTTP/1.1 431"), "{response}");
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_lets_open_requests_finish() {
+        let app = Router::new().route(
+            "/slow",
+            get(|| async {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                "done"
+            }),
+        );
+        let server_config = crate::config::AppConfig::default().server;
+        let (address, stop, server) = spawn_server(app, limits(&server_config)).await;
+
+        let request = tokio::spawn(raw_request(
+            address,
+            b"GET /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        ));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        stop.send(()).unwrap();
+
+        let response = request.await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.ends_with("done"), "{response}");
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // No longer accepting
+        assert!(TcpStream::connect(address).await.is_err());
+    }
+}
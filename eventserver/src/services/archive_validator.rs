@@ -0,0 +1,301 @@
+use sha2::{Digest, Sha256};
+use std::io::{self, Cursor, Read};
+use zip::ZipArchive;
+
+use crate::error::EventServerError;
+use crate::services::zip_packager::{
+    ZipPackager, ANNOTATIONS_ENTRY, MEDIA_METADATA_ENTRY, METADATA_ENTRY, METADATA_KEYS,
+};
+use crate::types::api::{ArchiveDiscrepancy, ArchiveValidationReport};
+use crate::types::event::EventAnnotation;
+
+/// Compare a client-built archive against the layout `ZipPackager` produces
+/// Entries are read through a `max_entry_bytes` cap, so a small archive that inflates
+/// enormously is reported rather than decompressed in full. This is CPU-bound; request
+/// handlers run it through `ZipPackagingLimiter`
+pub fn validate_archive(
+    data: &[u8],
+    max_entry_bytes: u64,
+) -> Result<ArchiveValidationReport, EventServerError> {
+    let mut archive = ZipArchive::new(Cursor::new(data))
+        .map_err(|e| EventServerError::Validation(format!("Not a readable ZIP archive: {e}")))?;
+    let entries: Vec<String> = archive.file_names().map(str::to_string).collect();
+    let mut report = Report::default();
+
+    let annotations = match read_entry(&mut archive, ANNOTATIONS_ENTRY, max_entry_bytes) {
+        Ok(Some(bytes)) => match serde_json::from_slice::<Vec<EventAnnotation>>(&bytes) {
+            Ok(annotations) => Some(annotations),
+            Err(e) => {
+                report.add(
+                    ANNOTATIONS_ENTRY,
+                    format!("Not a valid annotation list: {e}"),
+                );
+                None
+            }
+        },
+        Ok(None) => {
+            report.add(ANNOTATIONS_ENTRY, "Missing required entry");
+            None
+        }
+        Err(problem) => {
+            report.add(ANNOTATIONS_ENTRY, problem);
+            None
+        }
+    };
+
+    let media_entries: Vec<&String> = entries
+        .iter()
+        .filter(|name| name.starts_with("media.") && name.as_str() != MEDIA_METADATA_ENTRY)
+        .collect();
+    if media_entries.len() > 1 {
+        for name in &media_entries[1..] {
+            report.add(name, "More than one media entry");
+        }
+    }
+    let media_entry = media_entries.first().map(|name| name.to_string());
+
+    match read_json(&mut archive, METADATA_ENTRY, max_entry_bytes) {
+        Ok(Some(metadata)) => {
+            for key in METADATA_KEYS {
+                if metadata.get(key).is_none() {
+                    report.add(METADATA_ENTRY, format!("Missing key \"{key}\""));
+                }
+            }
+            if let (Some(count), Some(annotations)) =
+                (metadata["annotationCount"].as_u64(), &annotations)
+            {
+                if count != annotations.len() as u64 {
+                    report.add(
+                        METADATA_ENTRY,
+                        format!(
+                            "annotationCount is {count} but annotations.json has {} entries",
+                            annotations.len()
+                        ),
+                    );
+                }
+            }
+            if let Some(has_media) = metadata["hasMedia"].as_bool() {
+                if has_media != media_entry.is_some() {
+                    report.add(
+                        METADATA_ENTRY,
+                        format!(
+                            "hasMedia is {has_media} but the archive {} a media entry",
+                            if media_entry.is_some() {
+                                "has"
+                            } else {
+                                "has no"
+                            }
+                        ),
+                    );
+                }
+            }
+        }
+        Ok(None) => report.add(METADATA_ENTRY, "Missing required entry"),
+        Err(problem) => report.add(METADATA_ENTRY, problem),
+    }
+
+    match (
+        &media_entry,
+        read_json(&mut archive, MEDIA_METADATA_ENTRY, max_entry_bytes),
+    ) {
+        (Some(media_entry), Ok(Some(media_metadata))) => check_media(
+            &mut archive,
+            media_entry,
+            &media_metadata,
+            max_entry_bytes,
+            &mut report,
+        ),
+        (Some(_), Ok(None)) => report.add(MEDIA_METADATA_ENTRY, "Missing for the media entry"),
+        (None, Ok(Some(_))) => report.add(MEDIA_METADATA_ENTRY, "Present without a media entry"),
+        (_, Err(problem)) => report.add(MEDIA_METADATA_ENTRY, problem),
+        (None, Ok(None)) => {}
+    }
+
+    for name in &entries {
+        let expected = [ANNOTATIONS_ENTRY, METADATA_ENTRY, MEDIA_METADATA_ENTRY]
+            .contains(&name.as_str())
+            || media_entries.contains(&name);
+        if !expected {
+            report.add(name, "Unexpected entry");
+        }
+    }
+
+    Ok(ArchiveValidationReport {
+        conformant: report.0.is_empty(),
+        entries,
+        discrepancies: report.0,
+    })
+}
+
+/// Check the media entry's extension and digest against media_metadata.json
+fn check_media(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    media_entry: &str,
+    media_metadata: &serde_json::Value,
+    max_entry_bytes: u64,
+    report: &mut Report,
+) {
+    match media_metadata["type"].as_str() {
+        Some(media_type) => {
+            let expected = format!("media.{}", ZipPackager::get_file_extension(media_type));
+            if media_entry != expected {
+                report.add(
+                    media_entry,
+                    format!("Media of type {media_type} should be named {expected}"),
+                );
+            }
+        }
+        None => report.add(MEDIA_METADATA_ENTRY, "Missing key \"type\""),
+    }
+
+    let Some(expected) = media_metadata["sha256"].as_str() else {
+        return;
+    };
+    match entry_digest(archive, media_entry, max_entry_bytes) {
+        Ok(actual) if !actual.eq_ignore_ascii_case(expected) => report.add(
+            media_entry,
+            format!("sha256 is {actual} but media_metadata.json declares {expected}"),
+        ),
+        Ok(_) => {}
+        Err(problem) => report.add(media_entry, problem),
+    }
+}
+
+/// Bytes of an entry, `None` when absent; errors are reported as discrepancy text
+fn read_entry(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+    max_entry_bytes: u64,
+) -> Result<Option<Vec<u8>>, String> {
+    let file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(format!("Unreadable entry: {e}")),
+    };
+    let mut bytes = Vec::new();
+    file.take(max_entry_bytes + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Unreadable entry: {e}"))?;
+    if bytes.len() as u64 > max_entry_bytes {
+        return Err(format!("Inflates past the {max_entry_bytes}-byte limit"));
+    }
+    Ok(Some(bytes))
+}
+
+fn read_json(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+    max_entry_bytes: u64,
+) -> Result<Option<serde_json::Value>, String> {
+    match read_entry(archive, name, max_entry_bytes)? {
+        Some(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| format!("Not valid JSON: {e}")),
+        None => Ok(None),
+    }
+}
+
+/// Hex SHA-256 of an entry, streamed rather than buffered
+fn entry_digest(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+    max_entry_bytes: u64,
+) -> Result<String, String> {
+    let file = archive
+        .by_name(name)
+        .map_err(|e| format!("Unreadable entry: {e}"))?;
+    let mut hasher = Sha256::new();
+    let copied = io::copy(&mut file.take(max_entry_bytes + 1), &mut hasher)
+        .map_err(|e| format!("Unreadable entry: {e}"))?;
+    if copied > max_entry_bytes {
+        return Err(format!("Inflates past the {max_entry_bytes}-byte limit"));
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[derive(Default)]
+struct Report(Vec<ArchiveDiscrepancy>);
+
+impl Report {
+    fn add(&mut self, entry: &str, problem: impl Into<String>) {
+        self.0.push(ArchiveDiscrepancy {
+            entry: entry.to_string(),
+            problem: problem.into(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::zip_packager::ZipPackageOptions;
+    use crate::test_utils::{sample_event, sample_media};
+    use crate::types::event::MediaType;
+    use std::io::Write;
+    use zip::{write::FileOptions, ZipWriter};
+
+    async fn server_archive() -> Vec<u8> {
+        let mut event = sample_event();
+        event.media = Some(sample_media(MediaType::ImagePng, b"\x89PNG\r\n\x1a\n"));
+        ZipPackager::create_zip_from_event_package(&event, ZipPackageOptions::default()).unwrap()
+    }
+
+    /// Rebuild `archive` without the named entries
+    fn without(archive: &[u8], dropped: &[&str]) -> Vec<u8> {
+        let mut source = ZipArchive::new(Cursor::new(archive)).unwrap();
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for i in 0..source.len() {
+            let mut file = source.by_index(i).unwrap();
+            if dropped.contains(&file.name()) {
+                continue;
+            }
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes).unwrap();
+            zip.start_file(file.name(), FileOptions::default()).unwrap();
+            zip.write_all(&bytes).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_server_built_archive_is_conformant() {
+        let archive = server_archive().await;
+        let report = validate_archive(&archive, 1024 * 1024).unwrap();
+        assert!(report.conformant, "{:?}", report.discrepancies);
+        assert_eq!(report.entries.len(), 4);
+
+        // Dropping the media leaves metadata.json claiming it
+        let archive = without(&archive, &["media.png"]);
+        let report = validate_archive(&archive, 1024 * 1024).unwrap();
+        let problems: Vec<&str> = report
+            .discrepancies
+            .iter()
+            .map(|d| d.entry.as_str())
+            .collect();
+        assert_eq!(problems, [METADATA_ENTRY, MEDIA_METADATA_ENTRY]);
+    }
+
+    #[tokio::test]
+    async fn test_missing_annotations_reported() {
+        let archive = without(&server_archive().await, &[ANNOTATIONS_ENTRY]);
+        let report = validate_archive(&archive, 1024 * 1024).unwrap();
+        assert!(!report.conformant);
+        assert_eq!(report.discrepancies.len(), 1);
+        assert_eq!(report.discrepancies[0].entry, ANNOTATIONS_ENTRY);
+        assert_eq!(report.discrepancies[0].problem, "Missing required entry");
+    }
+
+    #[tokio::test]
+    async fn test_oversized_entry_and_non_zip() {
+        let report = validate_archive(&server_archive().await, 8).unwrap();
+        assert!(report
+            .discrepancies
+            .iter()
+            .any(|d| d.problem == "Inflates past the 8-byte limit"));
+
+        assert!(matches!(
+            validate_archive(b"not a zip", 1024),
+            Err(EventServerError::Validation(_))
+        ));
+    }
+}
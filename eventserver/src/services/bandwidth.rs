@@ -0,0 +1,86 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Global token bucket over uploaded bytes
+/// The bucket holds one second's worth of bytes. Each upload takes its size from the bucket
+/// up front, going into debt if needed, and sleeps until the debt is repaid. Nothing is held
+/// across the sleep, so concurrent uploads queue fairly in arrival order and can't deadlock,
+/// and an upload larger than the bucket is simply paced rather than blocked forever
+#[derive(Debug, Clone)]
+pub struct UploadThrottle {
+    bytes_per_second: u64, // 0 means unlimited
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64, // Negative while uploads are waiting for earlier ones to be paid off
+    refilled_at: Instant,
+}
+
+impl UploadThrottle {
+    pub fn new(bytes_per_second: u64) -> Self {
+        Self {
+            bytes_per_second,
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: bytes_per_second as f64,
+                refilled_at: Instant::now(),
+            })),
+        }
+    }
+
+    /// Wait until `bytes` may be sent without exceeding the configured rate
+    pub async fn acquire(&self, bytes: u64) {
+        let wait = self.reserve(bytes);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Take `bytes` from the bucket and return how long the caller must wait for them
+    fn reserve(&self, bytes: u64) -> Duration {
+        if self.bytes_per_second == 0 {
+            return Duration::ZERO;
+        }
+
+        let rate = self.bytes_per_second as f64;
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(rate);
+        bucket.refilled_at = now;
+
+        bucket.tokens -= bytes as f64;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited_never_waits() {
+        let throttle = UploadThrottle::new(0);
+        assert_eq!(throttle.reserve(u64::MAX), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_reservations_queue_behind_debt() {
+        let throttle = UploadThrottle::new(1000);
+        assert_eq!(throttle.reserve(1000), Duration::ZERO);
+
+        // Each further reservation waits for every byte reserved before it
+        let first = throttle.reserve(500);
+        let second = throttle.reserve(500);
+        assert!(first > Duration::from_millis(450) && first <= Duration::from_millis(500));
+        assert!(second > Duration::from_millis(950) && second <= Duration::from_millis(1000));
+
+        // Larger than the bucket: paced, not refused
+        assert!(throttle.reserve(5000) > Duration::from_secs(5));
+    }
+}
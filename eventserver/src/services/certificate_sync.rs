@@ -0,0 +1,303 @@
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::crypto::{CertificateKeyring, CertificateService, SeedCipher};
+use crate::error::EventServerError;
+use crate::services::StorageService;
+
+/// Load persisted, non-expired certificates into the in-memory map
+/// At most `limit` certificates are read so a huge store cannot stall startup
+pub async fn warm_up_certificates(
+    certificates: &CertificateService,
+    storage: &StorageService,
+    limit: usize,
+) -> Result<usize, EventServerError> {
+    // Revocations first, so revoked certificates aren't loaded back
+    certificates.apply_revocations(storage.load_revocations().await?);
+    let stored = storage.load_certificates(limit).await?;
+    let found = stored.len();
+    let loaded = certificates.load_certificates(stored);
+
+    info!(
+        found,
+        loaded, limit, "Certificate cache warmed up from storage"
+    );
+    Ok(loaded)
+}
+
+/// Certificate signing keys persisted in storage, or a new keyring saved there on first start
+/// Keeping them in the store means restarts and other instances verify the same tokens; the
+/// signing seed is sealed with `cipher`, and a legacy plaintext keyring is re-saved sealed
+pub async fn load_signing_keys(
+    storage: &StorageService,
+    cipher: SeedCipher,
+) -> Result<CertificateKeyring, EventServerError> {
+    if let Some(stored) = storage.load_certificate_keys().await? {
+        let keyring = CertificateKeyring::from_stored(&stored, cipher)?;
+        if stored.is_plaintext() {
+            storage.save_certificate_keys(&keyring.to_stored()).await?;
+            warn!(
+                key_id = keyring.primary_key_id(),
+                "Re-saved plaintext certificate signing keys sealed"
+            );
+        }
+        info!(
+            key_id = keyring.primary_key_id(),
+            "Loaded certificate signing keys from storage"
+        );
+        return Ok(keyring);
+    }
+
+    // Instances starting together race to create the first keyring; only one write succeeds
+    // and the others adopt it, so every instance signs with the same key
+    let keyring = CertificateKeyring::generate(cipher);
+    if !storage
+        .create_certificate_keys(&keyring.to_stored())
+        .await?
+    {
+        let stored = storage.load_certificate_keys().await?.ok_or_else(|| {
+            EventServerError::Storage(
+                "Certificate signing keys vanished after creation".to_string(),
+            )
+        })?;
+        let keyring = keyring.restore(&stored)?;
+        info!(
+            key_id = keyring.primary_key_id(),
+            "Adopted certificate signing keys created by another instance"
+        );
+        return Ok(keyring);
+    }
+    info!(
+        key_id = keyring.primary_key_id(),
+        "Generated certificate signing key"
+    );
+    Ok(keyring)
+}
+
+/// Install the stored signing keyring, picking up rotations made on other instances
+/// Returns whether the primary key changed
+pub async fn reload_signing_keys(
+    certificates: &CertificateService,
+    storage: &StorageService,
+) -> Result<bool, EventServerError> {
+    let _updates = certificates.lock_signing_key_updates().await;
+    let Some(stored) = storage.load_certificate_keys().await? else {
+        return Ok(false);
+    };
+    let changed = certificates.restore_signing_keys(&stored)?;
+    if changed {
+        info!(
+            key_id = certificates.signing_key_id().unwrap_or_default(),
+            "Reloaded rotated certificate signing keys from storage"
+        );
+    }
+    Ok(changed)
+}
+
+/// Evict in-memory certificates that were deleted or revoked in the store
+pub async fn reconcile_certificates(
+    certificates: &CertificateService,
+    storage: &StorageService,
+) -> Result<usize, EventServerError> {
+    let revoked = certificates.apply_revocations(storage.load_revocations().await?);
+    if revoked > 0 {
+        info!(revoked, "Applied certificate revocations from storage");
+    }
+    let stored_ids = storage.list_certificate_ids().await?;
+    let evicted = certificates.retain_certificates(&stored_ids);
+
+    if evicted > 0 {
+        info!(evicted, "Evicted certificates no longer present in storage");
+    }
+    Ok(evicted)
+}
+
+/// Run `reconcile_certificates` (when certificates are persisted) and `reload_signing_keys`
+/// (when tokens are EdDSA-signed) on a fixed interval in the background
+pub fn spawn_certificate_reconciler(
+    certificates: CertificateService,
+    storage: StorageService,
+    interval: Duration,
+    reconcile: bool,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick completes immediately; warm-up has just run, so skip it
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            if reconcile {
+                if let Err(e) = reconcile_certificates(&certificates, &storage).await {
+                    warn!(error = %e, "Certificate reconcile failed");
+                }
+            }
+            if certificates.signing_key_id().is_some() {
+                if let Err(e) = reload_signing_keys(&certificates, &storage).await {
+                    warn!(error = %e, "Certificate signing key reload failed");
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CertificateRequest;
+
+    fn issue(service: &CertificateService, relay_id: &str) -> String {
+        service
+            .issue_certificate(&CertificateRequest {
+                relay_id: relay_id.to_string(),
+                public_key: "test_public_key".to_string(),
+                ed25519_public_key: None,
+                tenant_id: None,
+            })
+            .unwrap()
+            .certificate_id
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_populates_cache_from_store() {
+        let storage = StorageService::new_mock().await;
+
+        // A previous instance issued and persisted two certificates
+        let previous = CertificateService::default();
+        for relay_id in ["relay_a", "relay_b"] {
+            let id = issue(&previous, relay_id);
+            let certificate = previous.certificate(&id).unwrap();
+            storage.save_certificate(&certificate).await.unwrap();
+        }
+
+        let fresh = CertificateService::default();
+        assert_eq!(fresh.active_certificate_count(), 0);
+
+        let loaded = warm_up_certificates(&fresh, &storage, 100).await.unwrap();
+        assert_eq!(loaded, 2);
+        assert_eq!(fresh.active_certificate_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_respects_limit() {
+        let storage = StorageService::new_mock().await;
+        let previous = CertificateService::default();
+        for relay_id in ["relay_a", "relay_b", "relay_c"] {
+            let id = issue(&previous, relay_id);
+            let certificate = previous.certificate(&id).unwrap();
+            storage.save_certificate(&certificate).await.unwrap();
+        }
+
+        let fresh = CertificateService::default();
+        let loaded = warm_up_certificates(&fresh, &storage, 2).await.unwrap();
+        assert_eq!(loaded, 2);
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_skips_certificates_signed_with_another_secret() {
+        let storage = StorageService::new_mock().await;
+        let other = CertificateService::new("other_secret".to_string());
+        let id = issue(&other, "relay_a");
+        storage
+            .save_certificate(&other.certificate(&id).unwrap())
+            .await
+            .unwrap();
+
+        let fresh = CertificateService::default();
+        assert_eq!(
+            warm_up_certificates(&fresh, &storage, 100).await.unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_evicts_certificates_missing_from_store() {
+        let storage = StorageService::new_mock().await;
+        let service = CertificateService::default();
+
+        let kept = issue(&service, "relay_a");
+        storage
+            .save_certificate(&service.certificate(&kept).unwrap())
+            .await
+            .unwrap();
+        // Issued in memory but deleted from the store by another instance
+        let deleted = issue(&service, "relay_b");
+
+        let evicted = reconcile_certificates(&service, &storage).await.unwrap();
+        assert_eq!(evicted, 1);
+        assert!(service.certificate(&kept).is_some());
+        assert!(service.certificate(&deleted).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_instances_share_one_signing_keyring() {
+        use crate::test_utils::seed_cipher;
+
+        let storage = StorageService::new_mock().await;
+        let first = load_signing_keys(&storage, seed_cipher()).await.unwrap();
+        let second = load_signing_keys(&storage, seed_cipher()).await.unwrap();
+        assert_eq!(first.primary_key_id(), second.primary_key_id());
+
+        // A keyring created while this instance was generating its own wins
+        let racing = CertificateKeyring::generate(seed_cipher());
+        assert!(!storage
+            .create_certificate_keys(&racing.to_stored())
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_reload_picks_up_rotation_from_another_instance() {
+        use crate::test_utils::seed_cipher;
+
+        let storage = StorageService::new_mock().await;
+        let keyring = load_signing_keys(&storage, seed_cipher()).await.unwrap();
+        let local = CertificateService::default().with_signing_keys(keyring.clone());
+        assert!(!reload_signing_keys(&local, &storage).await.unwrap());
+
+        let other = CertificateService::default().with_signing_keys(keyring);
+        let rotated = other.rotated_signing_keys().unwrap();
+        storage
+            .save_certificate_keys(&rotated.to_stored())
+            .await
+            .unwrap();
+
+        assert!(reload_signing_keys(&local, &storage).await.unwrap());
+        assert_eq!(local.signing_key_id().unwrap(), rotated.primary_key_id());
+    }
+
+    #[tokio::test]
+    async fn test_revocations_survive_restart_and_reach_other_instances() {
+        let storage = StorageService::new_mock().await;
+        let service = CertificateService::default();
+        let id = issue(&service, "relay_a");
+        storage
+            .save_certificate(&service.certificate(&id).unwrap())
+            .await
+            .unwrap();
+        let peer = CertificateService::default();
+        warm_up_certificates(&peer, &storage, 100).await.unwrap();
+        assert!(peer.certificate(&id).is_some());
+
+        assert!(service.revoke_certificate(&id));
+        storage
+            .save_revocation(&service.revocation(&id).unwrap())
+            .await
+            .unwrap();
+
+        // Another instance drops it at the next reconcile
+        reconcile_certificates(&peer, &storage).await.unwrap();
+        assert!(peer.certificate(&id).is_none());
+        assert_eq!(peer.revocation_list(None).0, vec![id.clone()]);
+
+        // A restarted instance neither loads it nor forgets the revocation
+        let restarted = CertificateService::default();
+        assert_eq!(
+            warm_up_certificates(&restarted, &storage, 100)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(restarted.revocation_list(None).0, vec![id]);
+    }
+}
@@ -0,0 +1,417 @@
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::error::EventServerError;
+use crate::services::storage::{ObjectDownload, S3Operations};
+
+/// Circuit breaker around an S3 backend
+/// After `threshold` consecutive backend failures, calls fail fast with a 503 whose
+/// `Retry-After` is the remaining cooldown. Once the cooldown elapses a single call is let
+/// through as a probe while everyone else keeps failing fast, so clients honouring the
+/// header don't pile back onto a backend that is still struggling
+pub struct CircuitBreakerS3 {
+    inner: Arc<dyn S3Operations>,
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Position of the breaker as seen by the next call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,     // Calls fail fast until the cooldown elapses
+    HalfOpen, // The next call goes through as a probe
+}
+
+/// Snapshot of the breaker for readiness reporting
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitBreakerStatus {
+    pub state: CircuitState,
+    pub seconds_until_half_open: Option<u64>, // Set while open
+}
+
+impl CircuitBreakerS3 {
+    pub fn new(inner: Arc<dyn S3Operations>, threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            inner,
+            threshold,
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Current state, without claiming the probe
+    pub fn status(&self) -> CircuitBreakerStatus {
+        let state = self.state.lock().unwrap();
+        match state.open_until {
+            None => CircuitBreakerStatus {
+                state: CircuitState::Closed,
+                seconds_until_half_open: None,
+            },
+            Some(open_until) => match open_until.checked_duration_since(Instant::now()) {
+                Some(remaining) if !remaining.is_zero() => CircuitBreakerStatus {
+                    state: CircuitState::Open,
+                    seconds_until_half_open: Some(remaining.as_millis().div_ceil(1000) as u64),
+                },
+                _ => CircuitBreakerStatus {
+                    state: CircuitState::HalfOpen,
+                    seconds_until_half_open: None,
+                },
+            },
+        }
+    }
+
+    /// Count a backend call outcome as if it had gone through the breaker
+    #[cfg(test)]
+    pub fn record(&self, result: Result<(), EventServerError>) {
+        let _ = self.after_call(result);
+    }
+
+    /// Fail fast while open; past the cooldown, claim the probe by re-arming the breaker
+    fn before_call(&self) -> Result<(), EventServerError> {
+        let mut state = self.state.lock().unwrap();
+        let Some(open_until) = state.open_until else {
+            return Ok(());
+        };
+
+        let now = Instant::now();
+        if now < open_until {
+            let remaining = open_until - now;
+            return Err(EventServerError::ServiceUnavailable {
+                message: "Storage backend unavailable (circuit open), retry later".to_string(),
+                retry_after_seconds: Some(remaining.as_millis().div_ceil(1000).max(1) as u64),
+            });
+        }
+
+        state.open_until = Some(now + self.cooldown);
+        Ok(())
+    }
+
+    /// Record the outcome; only backend failures count, not missing objects and the like
+    fn after_call<T>(&self, result: Result<T, EventServerError>) -> Result<T, EventServerError> {
+        let mut state = self.state.lock().unwrap();
+        match &result {
+            Err(EventServerError::Storage(_) | EventServerError::ServiceUnavailable { .. }) => {
+                state.consecutive_failures += 1;
+                if state.consecutive_failures >= self.threshold {
+                    if state.consecutive_failures == self.threshold {
+                        warn!(
+                            failures = state.consecutive_failures,
+                            cooldown_seconds = self.cooldown.as_secs(),
+                            "Storage circuit breaker opened"
+                        );
+                    }
+                    state.open_until = Some(Instant::now() + self.cooldown);
+                }
+            }
+            _ => *state = BreakerState::default(),
+        }
+        result
+    }
+}
+
+#[async_trait::async_trait]
+impl S3Operations for CircuitBreakerS3 {
+    async fn put_object_with_encoding(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+        content_encoding: Option<&str>,
+    ) -> Result<Option<String>, EventServerError> {
+        self.before_call()?;
+        let result = self
+            .inner
+            .put_object_with_encoding(bucket, key, body, content_type, content_encoding)
+            .await;
+        self.after_call(result)
+    }
+
+    async fn put_object_if_absent(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+    ) -> Result<bool, EventServerError> {
+        self.before_call()?;
+        let result = self
+            .inner
+            .put_object_if_absent(bucket, key, body, content_type)
+            .await;
+        self.after_call(result)
+    }
+
+    async fn put_object_if_match(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+        etag: &str,
+    ) -> Result<bool, EventServerError> {
+        self.before_call()?;
+        let result = self
+            .inner
+            .put_object_if_match(bucket, key, body, content_type, etag)
+            .await;
+        self.after_call(result)
+    }
+
+    async fn head_object(&self, bucket: &str, key: &str) -> Result<bool, EventServerError> {
+        self.before_call()?;
+        let result = self.inner.head_object(bucket, key).await;
+        self.after_call(result)
+    }
+
+    async fn get_object(&self, bucket: &str, key: &str) -> Result<Vec<u8>, EventServerError> {
+        self.before_call()?;
+        let result = self.inner.get_object(bucket, key).await;
+        self.after_call(result)
+    }
+
+    async fn delete_object(&self, bucket: &str, key: &str) -> Result<(), EventServerError> {
+        self.before_call()?;
+        let result = self.inner.delete_object(bucket, key).await;
+        self.after_call(result)
+    }
+
+    async fn delete_object_if_match(
+        &self,
+        bucket: &str,
+        key: &str,
+        etag: &str,
+    ) -> Result<bool, EventServerError> {
+        self.before_call()?;
+        let result = self.inner.delete_object_if_match(bucket, key, etag).await;
+        self.after_call(result)
+    }
+
+    async fn get_object_range(
+        &self,
+        bucket: &str,
+        key: &str,
+        range: Option<&str>,
+    ) -> Result<ObjectDownload, EventServerError> {
+        self.before_call()?;
+        let result = self.inner.get_object_range(bucket, key, range).await;
+        self.after_call(result)
+    }
+
+    async fn list_objects_after(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        start_after: Option<&str>,
+        max_keys: usize,
+    ) -> Result<Vec<String>, EventServerError> {
+        self.before_call()?;
+        let result = self
+            .inner
+            .list_objects_after(bucket, prefix, start_after, max_keys)
+            .await;
+        self.after_call(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::storage::MockS3Client;
+    use axum::response::IntoResponse;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+    /// Backend that fails every call until told to recover
+    #[derive(Default)]
+    struct FlakyS3 {
+        healthy: AtomicBool,
+        calls: AtomicU32,
+        inner: MockS3Client,
+    }
+
+    impl FlakyS3 {
+        fn check(&self) -> Result<(), EventServerError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.healthy.load(Ordering::SeqCst) {
+                Ok(())
+            } else {
+                Err(EventServerError::Storage("connection refused".to_string()))
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl S3Operations for FlakyS3 {
+        async fn put_object_with_encoding(
+            &self,
+            bucket: &str,
+            key: &str,
+            body: Vec<u8>,
+            content_type: &str,
+            content_encoding: Option<&str>,
+        ) -> Result<Option<String>, EventServerError> {
+            self.check()?;
+            self.inner
+                .put_object_with_encoding(bucket, key, body, content_type, content_encoding)
+                .await
+        }
+
+        async fn put_object_if_absent(
+            &self,
+            bucket: &str,
+            key: &str,
+            body: Vec<u8>,
+            content_type: &str,
+        ) -> Result<bool, EventServerError> {
+            self.check()?;
+            self.inner
+                .put_object_if_absent(bucket, key, body, content_type)
+                .await
+        }
+
+        async fn put_object_if_match(
+            &self,
+            bucket: &str,
+            key: &str,
+            body: Vec<u8>,
+            content_type: &str,
+            etag: &str,
+        ) -> Result<bool, EventServerError> {
+            self.check()?;
+            self.inner
+                .put_object_if_match(bucket, key, body, content_type, etag)
+                .await
+        }
+
+        async fn head_object(&self, bucket: &str, key: &str) -> Result<bool, EventServerError> {
+            self.check()?;
+            self.inner.head_object(bucket, key).await
+        }
+
+        async fn get_object(&self, bucket: &str, key: &str) -> Result<Vec<u8>, EventServerError> {
+            self.check()?;
+            self.inner.get_object(bucket, key).await
+        }
+
+        async fn delete_object(&self, bucket: &str, key: &str) -> Result<(), EventServerError> {
+            self.check()?;
+            self.inner.delete_object(bucket, key).await
+        }
+
+        async fn delete_object_if_match(
+            &self,
+            bucket: &str,
+            key: &str,
+            etag: &str,
+        ) -> Result<bool, EventServerError> {
+            self.check()?;
+            self.inner.delete_object_if_match(bucket, key, etag).await
+        }
+
+        async fn get_object_range(
+            &self,
+            bucket: &str,
+            key: &str,
+            range: Option<&str>,
+        ) -> Result<ObjectDownload, EventServerError> {
+            self.check()?;
+            self.inner.get_object_range(bucket, key, range).await
+        }
+
+        async fn list_objects_after(
+            &self,
+            bucket: &str,
+            prefix: &str,
+            start_after: Option<&str>,
+            max_keys: usize,
+        ) -> Result<Vec<String>, EventServerError> {
+            self.check()?;
+            self.inner
+                .list_objects_after(bucket, prefix, start_after, max_keys)
+                .await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_breaker_fails_fast_with_retry_after() {
+        let backend = Arc::new(FlakyS3::default());
+        let breaker = CircuitBreakerS3::new(backend.clone(), 2, Duration::from_secs(30));
+
+        for _ in 0..2 {
+            let err = breaker.head_object("bucket", "key").await.unwrap_err();
+            assert!(matches!(err, EventServerError::Storage(_)));
+        }
+
+        // Open: the backend is no longer called
+        let err = breaker.head_object("bucket", "key").await.unwrap_err();
+        assert_eq!(backend.calls.load(Ordering::SeqCst), 2);
+        let EventServerError::ServiceUnavailable {
+            retry_after_seconds: Some(retry_after),
+            ..
+        } = err
+        else {
+            panic!("expected ServiceUnavailable, got {err:?}");
+        };
+        assert!((1..=30).contains(&retry_after));
+
+        let response = breaker
+            .get_object("bucket", "key")
+            .await
+            .unwrap_err()
+            .into_response();
+        assert_eq!(
+            response.status(),
+            axum::http::StatusCode::SERVICE_UNAVAILABLE
+        );
+        let header: u64 = response.headers()[axum::http::header::RETRY_AFTER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((1..=30).contains(&header));
+    }
+
+    #[tokio::test]
+    async fn test_probe_after_cooldown_closes_breaker() {
+        let backend = Arc::new(FlakyS3::default());
+        let breaker = CircuitBreakerS3::new(backend.clone(), 1, Duration::from_millis(20));
+
+        assert!(breaker.head_object("bucket", "key").await.is_err());
+        assert!(matches!(
+            breaker.head_object("bucket", "key").await,
+            Err(EventServerError::ServiceUnavailable { .. })
+        ));
+
+        // A failed probe re-opens the breaker for another cooldown
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(matches!(
+            breaker.head_object("bucket", "key").await,
+            Err(EventServerError::Storage(_))
+        ));
+        assert!(matches!(
+            breaker.head_object("bucket", "key").await,
+            Err(EventServerError::ServiceUnavailable { .. })
+        ));
+
+        // A successful probe closes it
+        backend.healthy.store(true, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(!breaker.head_object("bucket", "key").await.unwrap());
+        assert!(!breaker.head_object("bucket", "key").await.unwrap());
+        assert_eq!(backend.calls.load(Ordering::SeqCst), 4);
+    }
+}
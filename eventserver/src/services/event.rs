@@ -1,39 +1,106 @@
+use std::sync::Arc;
+
 use chrono::Utc;
 use sha2::{Digest, Sha256};
 use tracing::{info, warn};
 
+use crate::config::validation::ValidationConfig;
 use crate::error::EventServerError;
+use crate::services::storage::StoreOutcome;
 use crate::services::StorageService;
 use crate::types::event::{EventPackage, ProcessingResult};
 
+/// Storage operations the event pipeline depends on
+/// Implemented by `StorageService`; lets `EventService` be tested without S3
+#[async_trait::async_trait]
+pub trait Storage: Send + Sync {
+    /// Store the event and return its storage location, and whether this call wrote it
+    async fn store_event(
+        &self,
+        event_package: &EventPackage,
+        event_hash: &str,
+        relay_id: &str,
+    ) -> Result<StoreOutcome, EventServerError>;
+
+    /// Add the stored event to the listing index
+    async fn index_event(
+        &self,
+        event_package: &EventPackage,
+        event_hash: &str,
+        relay_id: &str,
+    ) -> Result<(), EventServerError>;
+
+    /// Check whether an event with this hash is stored
+    async fn event_exists(&self, event_hash: &str) -> Result<bool, EventServerError>;
+}
+
+#[async_trait::async_trait]
+impl Storage for StorageService {
+    async fn store_event(
+        &self,
+        event_package: &EventPackage,
+        event_hash: &str,
+        relay_id: &str,
+    ) -> Result<StoreOutcome, EventServerError> {
+        StorageService::store_event(self, event_package, event_hash, relay_id).await
+    }
+
+    async fn index_event(
+        &self,
+        event_package: &EventPackage,
+        event_hash: &str,
+        relay_id: &str,
+    ) -> Result<(), EventServerError> {
+        StorageService::index_event(self, event_package, event_hash, relay_id).await
+    }
+
+    async fn event_exists(&self, event_hash: &str) -> Result<bool, EventServerError> {
+        StorageService::event_exists(self, event_hash).await
+    }
+}
+
 /// Stateless event processing service
 /// Each request is processed independently without maintaining any state
 #[derive(Clone)]
 pub struct EventService {
-    storage: StorageService,
+    storage: Arc<dyn Storage>,
+    validation: ValidationConfig,
 }
 
 impl EventService {
     /// Create a new EventService instance
-    pub fn new(storage: StorageService) -> Self {
-        Self { storage }
+    pub fn new(storage: impl Storage + 'static, validation: ValidationConfig) -> Self {
+        Self {
+            storage: Arc::new(storage),
+            validation,
+        }
+    }
+
+    /// The same pipeline over other storage, e.g. a tenant's namespace
+    pub fn with_storage(&self, storage: impl Storage + 'static) -> Self {
+        Self {
+            storage: Arc::new(storage),
+            validation: self.validation.clone(),
+        }
     }
 
     /// Process an event package from a relay
-    /// This is completely stateless - each call is independent
+    /// This is completely stateless - each call is independent. The flag is
+    /// true when this call stored the event, false when it was already stored.
     pub async fn process_event(
         &self,
         event_package: EventPackage,
         relay_id: String,
-    ) -> Result<ProcessingResult, EventServerError> {
+    ) -> Result<(ProcessingResult, bool), EventServerError> {
         info!(
             event_id = %event_package.id,
             relay_id = %relay_id,
             "Processing event package"
         );
 
-        // Step 1: Validate the event package
-        let validation = event_package.validate();
+        // Step 1: Upconvert older schema versions, then validate the event package
+        let event_package = event_package.migrate_to_current();
+        let validation = event_package.validate_with(&self.validation);
         if !validation.is_valid {
             warn!(
                 event_id = %event_package.id,
@@ -52,9 +119,12 @@ impl EventService {
         );
 
         // Step 3: Store event in S3-compatible storage
-        let storage_location = self
+        let StoreOutcome {
+            location: storage_location,
+            written,
+        } = self
             .storage
-            .store_event(&event_package, &event_hash)
+            .store_event(&event_package, &event_hash, &relay_id)
             .await?;
         info!(
             event_id = %event_package.id,
@@ -62,6 +132,15 @@ impl EventService {
             "Event stored successfully"
         );
 
+        // The listing index is a convenience for admin views; don't fail the submission over it
+        if let Err(e) = self
+            .storage
+            .index_event(&event_package, &event_hash, &relay_id)
+            .await
+        {
+            warn!(event_id = %event_package.id, error = %e, "Failed to update event index");
+        }
+
         // Step 4: Return processing result
         let result = ProcessingResult {
             event_id: event_package.id,
@@ -75,7 +154,7 @@ impl EventService {
             "Event processing completed successfully"
         );
 
-        Ok(result)
+        Ok((result, written))
     }
 
     /// Verify if an event exists in storage
@@ -96,7 +175,7 @@ impl EventService {
 
     /// Generate a cryptographic hash for the event
     /// Uses SHA-256 for consistency and security
-    fn generate_event_hash(
+    pub fn generate_event_hash(
         &self,
         event_package: &EventPackage,
     ) -> Result<String, EventServerError> {
@@ -111,37 +190,114 @@ impl EventService {
 
         Ok(format!("{result:x}"))
     }
-
-    /// Get event statistics (for monitoring purposes)
-    /// This is stateless - queries external systems for current state
-    pub async fn _get_event_stats(&self) -> Result<EventStats, EventServerError> {
-        // This would typically query storage for current stats
-        // For now, return basic stats structure
-        Ok(EventStats {
-            total_events_processed: 0, // Would query from storage service
-            total_storage_used: 0,     // Would query from storage service
-        })
-    }
-}
-
-/// Event statistics structure
-#[derive(Debug, serde::Serialize)]
-pub struct EventStats {
-    pub total_events_processed: u64,
-    pub total_storage_used: u64, // bytes
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::types::event::{EventAnnotation, EventMetadata, EventSource, FieldValue};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
     use uuid::Uuid;
 
+    /// In-memory `Storage` double keyed by event hash
+    #[derive(Clone, Default)]
+    struct InMemoryStorage {
+        events: Arc<Mutex<HashMap<String, (EventPackage, String)>>>,
+        indexed: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Storage for InMemoryStorage {
+        async fn store_event(
+            &self,
+            event_package: &EventPackage,
+            event_hash: &str,
+            relay_id: &str,
+        ) -> Result<StoreOutcome, EventServerError> {
+            let previous = self.events.lock().unwrap().insert(
+                event_hash.to_string(),
+                (event_package.clone(), relay_id.to_string()),
+            );
+            Ok(StoreOutcome {
+                location: format!("memory://{event_hash}"),
+                written: previous.is_none(),
+            })
+        }
+
+        async fn index_event(
+            &self,
+            _event_package: &EventPackage,
+            event_hash: &str,
+            _relay_id: &str,
+        ) -> Result<(), EventServerError> {
+            self.indexed.lock().unwrap().push(event_hash.to_string());
+            Ok(())
+        }
+
+        async fn event_exists(&self, event_hash: &str) -> Result<bool, EventServerError> {
+            Ok(self.events.lock().unwrap().contains_key(event_hash))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_event_with_in_memory_storage() {
+        let storage = InMemoryStorage::default();
+        let service = EventService::new(storage.clone(), ValidationConfig::default());
+
+        let now = Utc::now();
+        let event_package = EventPackage {
+            id: Uuid::new_v4(),
+            version: "1.0".to_string(),
+            annotations: vec![EventAnnotation {
+                label_id: "test_label".to_string(),
+                value: FieldValue::String("test_value".to_string()),
+                timestamp: now,
+            }],
+            media: None,
+            metadata: EventMetadata {
+                created_at: now,
+                created_by: Some("test_user".to_string()),
+                source: EventSource::Web,
+            },
+        };
+
+        let (result, written) = service
+            .process_event(event_package.clone(), "relay_a".to_string())
+            .await
+            .unwrap();
+        assert!(written);
+
+        assert_eq!(result.event_id, event_package.id);
+        assert_eq!(
+            result.hash,
+            service.generate_event_hash(&event_package).unwrap()
+        );
+        assert_eq!(result.storage_location, format!("memory://{}", result.hash));
+
+        let (stored, relay_id) = storage.events.lock().unwrap()[&result.hash].clone();
+        assert_eq!(stored.id, event_package.id);
+        assert_eq!(relay_id, "relay_a");
+        assert_eq!(*storage.indexed.lock().unwrap(), vec![result.hash.clone()]);
+        assert!(service.verify_event_hash(&result.hash).await.unwrap());
+
+        // Invalid events never reach storage
+        let mut invalid = event_package;
+        invalid.id = Uuid::new_v4();
+        invalid.annotations.clear();
+        let err = service
+            .process_event(invalid, "relay_a".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, EventServerError::Validation(_)));
+        assert_eq!(storage.events.lock().unwrap().len(), 1);
+    }
+
     #[tokio::test]
     async fn test_generate_event_hash() {
         // Create mock services (would use actual mocks in real tests)
         let storage = StorageService::new_mock();
-        let service = EventService::new(storage.await);
+        let service = EventService::new(storage.await, ValidationConfig::default());
 
         let event_package = EventPackage {
             id: Uuid::new_v4(),
@@ -167,7 +323,7 @@ mod tests {
     #[tokio::test]
     async fn test_hash_consistency() {
         let storage = StorageService::new_mock().await;
-        let service = EventService::new(storage);
+        let service = EventService::new(storage, ValidationConfig::default());
 
         let event_package = EventPackage {
             id: Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap(),
@@ -195,4 +351,48 @@ mod tests {
         // Same input should produce same hash (deterministic)
         assert_eq!(hash1, hash2);
     }
+
+    #[tokio::test]
+    async fn test_dedup_hash_ignores_submission_id_and_annotation_order() {
+        let storage = StorageService::new_mock().await;
+        let service = EventService::new(storage, ValidationConfig::default());
+
+        let timestamp = chrono::DateTime::parse_from_rfc3339("2023-01-01T00:00:00.123456Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let annotation = |label: &str, value: &str| EventAnnotation {
+            label_id: label.to_string(),
+            value: FieldValue::String(value.to_string()),
+            timestamp,
+        };
+
+        let first = EventPackage {
+            id: Uuid::new_v4(),
+            version: "1.0".to_string(),
+            annotations: vec![annotation("a", "1"), annotation("b", "2")],
+            media: None,
+            metadata: EventMetadata {
+                created_at: timestamp,
+                created_by: Some("test_user".to_string()),
+                source: EventSource::Web,
+            },
+        };
+        let mut retry = first.clone();
+        retry.id = Uuid::new_v4();
+        retry.annotations.reverse();
+
+        assert_ne!(first.id, retry.id);
+        assert_eq!(
+            service.generate_event_hash(&first).unwrap(),
+            service.generate_event_hash(&retry).unwrap()
+        );
+
+        // Different content still produces a different hash
+        let mut changed = first.clone();
+        changed.annotations[0].value = FieldValue::String("other".to_string());
+        assert_ne!(
+            service.generate_event_hash(&first).unwrap(),
+            service.generate_event_hash(&changed).unwrap()
+        );
+    }
 }
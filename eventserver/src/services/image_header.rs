@@ -0,0 +1,179 @@
+use std::io::{self, Read};
+
+use base64::engine::general_purpose;
+use tracing::warn;
+
+use crate::error::EventServerError;
+use crate::services::zip_packager::ZipPackager;
+use crate::types::event::EventPackage;
+
+/// Reject images whose header declares dimensions beyond `max_width` x `max_height`
+/// Only the header is decoded, streaming through the base64, so a decompression bomb is
+/// caught before any pixel data is touched. Media whose dimensions can't be read is let
+/// through; other checks decide what to do with it
+pub fn check_image_dimensions(
+    event_package: &EventPackage,
+    max_width: u32,
+    max_height: u32,
+) -> Result<(), EventServerError> {
+    let Some(media) = &event_package.media else {
+        return Ok(());
+    };
+
+    let mut encoded = ZipPackager::strip_data_url(&media.data).as_bytes();
+    let decoder = base64::read::DecoderReader::new(&mut encoded, &general_purpose::STANDARD);
+    let Some((width, height)) = image_dimensions(decoder) else {
+        return Ok(());
+    };
+
+    if width > max_width || height > max_height {
+        warn!(
+            event_id = %event_package.id,
+            width,
+            height,
+            max_width,
+            max_height,
+            "Rejecting image with oversized dimensions"
+        );
+        return Err(EventServerError::Validation(format!(
+            "Image dimensions {width}x{height} exceed the {max_width}x{max_height} limit"
+        )));
+    }
+    Ok(())
+}
+
+/// Width and height declared in a PNG, GIF or JPEG header
+pub fn image_dimensions(mut reader: impl Read) -> Option<(u32, u32)> {
+    let mut magic = [0u8; 2];
+    reader.read_exact(&mut magic).ok()?;
+    if magic == [0xFF, 0xD8] {
+        return jpeg_dimensions(reader);
+    }
+
+    // PNG and GIF keep their dimensions within the first 24 bytes
+    let mut header = [0u8; 24];
+    header[..2].copy_from_slice(&magic);
+    let len = 2 + read_up_to(&mut reader, &mut header[2..]).ok()?;
+    let header = &header[..len];
+
+    let u16_le = |at: usize| Some(u16::from_le_bytes(header.get(at..at + 2)?.try_into().ok()?));
+    let u32_be = |at: usize| Some(u32::from_be_bytes(header.get(at..at + 4)?.try_into().ok()?));
+
+    if header.starts_with(b"\x89PNG\r\n\x1a\n") && header.get(12..16)? == b"IHDR" {
+        return Some((u32_be(16)?, u32_be(20)?));
+    }
+    if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
+        return Some((u16_le(6)? as u32, u16_le(8)? as u32));
+    }
+    None
+}
+
+/// Walk JPEG segments up to the first start-of-frame marker, skipping segment bodies
+fn jpeg_dimensions(mut reader: impl Read) -> Option<(u32, u32)> {
+    loop {
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte).ok()?;
+        if byte[0] != 0xFF {
+            return None;
+        }
+        // Skip fill bytes before the marker code
+        while byte[0] == 0xFF {
+            reader.read_exact(&mut byte).ok()?;
+        }
+        let marker = byte[0];
+        // Start of scan or end of image: no frame header found
+        if matches!(marker, 0xDA | 0xD9) {
+            return None;
+        }
+
+        let mut length = [0u8; 2];
+        reader.read_exact(&mut length).ok()?;
+        let length = u16::from_be_bytes(length).checked_sub(2)?;
+
+        // SOF0-SOF15, except DHT (C4), JPG (C8) and DAC (CC)
+        if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let mut frame = [0u8; 5];
+            reader.read_exact(&mut frame).ok()?;
+            let height = u16::from_be_bytes([frame[1], frame[2]]) as u32;
+            let width = u16::from_be_bytes([frame[3], frame[4]]) as u32;
+            return Some((width, height));
+        }
+
+        let skipped = io::copy(&mut (&mut reader).take(length as u64), &mut io::sink()).ok()?;
+        if skipped < length as u64 {
+            return None;
+        }
+    }
+}
+
+/// Fill as much of `buf` as the reader provides, stopping early at end of input
+fn read_up_to(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{sample_event, sample_media};
+    use crate::types::event::MediaType;
+
+    fn with_media(bytes: &[u8]) -> EventPackage {
+        let mut event = sample_event();
+        event.media = Some(sample_media(MediaType::ImagePng, bytes));
+        event
+    }
+
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&[8, 6, 0, 0, 0]);
+        bytes
+    }
+
+    #[test]
+    fn test_huge_png_rejected_from_header() {
+        // Only the header exists, so a full decode could never succeed
+        let event = with_media(&png_header(100_000, 100_000));
+        let err = check_image_dimensions(&event, 16_384, 16_384).unwrap_err();
+        assert!(matches!(err, EventServerError::Validation(_)));
+        assert!(err
+            .to_string()
+            .contains("Image dimensions 100000x100000 exceed the 16384x16384 limit"));
+
+        let event = with_media(&png_header(640, 480));
+        assert!(check_image_dimensions(&event, 16_384, 16_384).is_ok());
+        assert!(check_image_dimensions(&event, 320, 16_384).is_err());
+    }
+
+    #[test]
+    fn test_jpeg_dimensions_after_app_segment() {
+        let mut bytes = vec![0xFF, 0xD8];
+        // APP1 segment with a large body that must be skipped, preceded by a fill byte
+        bytes.extend_from_slice(&[0xFF, 0xFF, 0xE1, 0x10, 0x02]);
+        bytes.extend([0u8; 0x1000]);
+        // SOF0: precision 8, height 50000, width 60000
+        bytes.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 8, 0xC3, 0x50, 0xEA, 0x60]);
+
+        assert_eq!(image_dimensions(bytes.as_slice()), Some((60_000, 50_000)));
+        let event = with_media(&bytes);
+        assert!(check_image_dimensions(&event, 16_384, 16_384).is_err());
+    }
+
+    #[test]
+    fn test_gif_and_unknown_media() {
+        let gif = b"GIF89a\x20\x03\x58\x02\x00\x00";
+        assert_eq!(image_dimensions(&gif[..]), Some((800, 600)));
+
+        // Not an image: let it through
+        let event = with_media(b"\x00\x00\x00\x18ftypmp42");
+        assert!(check_image_dimensions(&event, 1, 1).is_ok());
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// Per-key in-flight locks, so concurrent work on the same key runs one at a time
+/// Entries are removed as soon as the last holder or waiter is gone
+#[derive(Clone, Default)]
+pub struct InFlightLocks {
+    locks: Arc<Mutex<HashMap<String, Arc<AsyncMutex<()>>>>>,
+}
+
+impl InFlightLocks {
+    /// Wait until no one else holds `key`, then hold it until the guard is dropped
+    pub async fn acquire(&self, key: &str) -> InFlightGuard {
+        let lock = self
+            .locks
+            .lock()
+            .unwrap()
+            .entry(key.to_string())
+            .or_default()
+            .clone();
+        let guard = lock.clone().lock_owned().await;
+
+        InFlightGuard {
+            key: key.to_string(),
+            locks: self.locks.clone(),
+            lock,
+            guard: Some(guard),
+        }
+    }
+
+    /// Number of keys currently held or waited on
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.locks.lock().unwrap().len()
+    }
+}
+
+/// Holds an in-flight lock; releases it on drop, including on error paths
+pub struct InFlightGuard {
+    key: String,
+    locks: Arc<Mutex<HashMap<String, Arc<AsyncMutex<()>>>>>,
+    lock: Arc<AsyncMutex<()>>,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.guard.take();
+
+        // Only the map and this guard reference the lock when nobody is waiting on it
+        let mut locks = self.locks.lock().unwrap();
+        if Arc::strong_count(&self.lock) == 2 {
+            locks.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_same_key_is_serialized() {
+        let locks = InFlightLocks::default();
+        let first = locks.acquire("hash").await;
+
+        let waiter = {
+            let locks = locks.clone();
+            tokio::spawn(async move {
+                let _guard = locks.acquire("hash").await;
+            })
+        };
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiter.is_finished());
+
+        drop(first);
+        waiter.await.unwrap();
+        assert_eq!(locks.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_different_keys_do_not_block() {
+        let locks = InFlightLocks::default();
+        let _a = locks.acquire("a").await;
+        let _b = locks.acquire("b").await;
+        assert_eq!(locks.len(), 2);
+    }
+}
@@ -0,0 +1,226 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinSet;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Progress of an event package accepted for background storage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Pending,
+    Stored,
+    Failed,
+}
+
+/// Tracked state of one background storage job
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub status: JobStatus,
+    pub hash: String,
+    pub storage_location: Option<String>,
+    /// Tenant of the submitting certificate
+    pub tenant_id: Option<String>,
+    /// Submitting certificate; only it may poll the job
+    pub certificate_id: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// In-memory table of background storage jobs keyed by event ID
+/// Entries are dropped `retention` after their last update, so the table stays bounded.
+/// Jobs live only on the instance that accepted the submission, so polling must be sticky
+#[derive(Debug, Clone)]
+pub struct JobTable {
+    jobs: Arc<Mutex<HashMap<Uuid, Job>>>,
+    retention: Duration,
+}
+
+impl JobTable {
+    /// Create an empty job table keeping finished entries for `retention`
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+            retention,
+        }
+    }
+
+    /// Record that an event submitted by a certificate of `tenant_id` was accepted and is
+    /// waiting to be stored
+    pub fn pending(
+        &self,
+        event_id: Uuid,
+        hash: &str,
+        tenant_id: Option<&str>,
+        certificate_id: Option<&str>,
+    ) {
+        let mut jobs = self.jobs.lock().unwrap();
+        self.prune(&mut jobs);
+        jobs.insert(
+            event_id,
+            Job {
+                status: JobStatus::Pending,
+                hash: hash.to_string(),
+                storage_location: None,
+                tenant_id: tenant_id.map(str::to_string),
+                certificate_id: certificate_id.map(str::to_string),
+                updated_at: Utc::now(),
+            },
+        );
+    }
+
+    /// Record that an event finished uploading
+    pub fn stored(&self, event_id: Uuid, hash: &str, storage_location: &str) {
+        self.finish(event_id, hash, JobStatus::Stored, Some(storage_location));
+    }
+
+    /// Record that storing an event failed
+    pub fn failed(&self, event_id: Uuid, hash: &str) {
+        self.finish(event_id, hash, JobStatus::Failed, None);
+    }
+
+    /// Current state of a job, if it is known and has not expired
+    pub fn get(&self, event_id: &Uuid) -> Option<Job> {
+        let mut jobs = self.jobs.lock().unwrap();
+        self.prune(&mut jobs);
+        jobs.get(event_id).cloned()
+    }
+
+    /// Move a job to a final state, keeping the tenant and certificate it was submitted by
+    fn finish(
+        &self,
+        event_id: Uuid,
+        hash: &str,
+        status: JobStatus,
+        storage_location: Option<&str>,
+    ) {
+        let mut jobs = self.jobs.lock().unwrap();
+        self.prune(&mut jobs);
+        let (tenant_id, certificate_id) = jobs
+            .get(&event_id)
+            .map(|job| (job.tenant_id.clone(), job.certificate_id.clone()))
+            .unwrap_or_default();
+        jobs.insert(
+            event_id,
+            Job {
+                status,
+                hash: hash.to_string(),
+                storage_location: storage_location.map(str::to_string),
+                tenant_id,
+                certificate_id,
+                updated_at: Utc::now(),
+            },
+        );
+    }
+
+    fn prune(&self, jobs: &mut HashMap<Uuid, Job>) {
+        let cutoff = Utc::now() - self.retention;
+        jobs.retain(|_, job| job.updated_at > cutoff);
+    }
+}
+
+/// Background storage tasks of async-accepted submissions
+/// At most `max_tasks` run at once, and shutdown waits for the running ones to finish
+#[derive(Debug, Clone)]
+pub struct BackgroundStores {
+    tasks: Arc<Mutex<JoinSet<()>>>,
+    slots: Arc<Semaphore>,
+}
+
+/// A reserved place for one background store
+pub struct BackgroundSlot {
+    _permit: OwnedSemaphorePermit,
+}
+
+impl BackgroundStores {
+    pub fn new(max_tasks: usize) -> Self {
+        Self {
+            tasks: Arc::new(Mutex::new(JoinSet::new())),
+            slots: Arc::new(Semaphore::new(max_tasks)),
+        }
+    }
+
+    /// Reserve a slot, or `None` when `max_tasks` stores are already running
+    pub fn try_reserve(&self) -> Option<BackgroundSlot> {
+        let permit = self.slots.clone().try_acquire_owned().ok()?;
+        Some(BackgroundSlot { _permit: permit })
+    }
+
+    /// Run `task` in the background, holding `slot` until it finishes
+    pub fn spawn(&self, slot: BackgroundSlot, task: impl Future<Output = ()> + Send + 'static) {
+        let mut tasks = self.tasks.lock().unwrap();
+        // Reap finished tasks so the set only holds running ones
+        while tasks.try_join_next().is_some() {}
+        tasks.spawn(async move {
+            let _slot = slot;
+            task.await;
+        });
+    }
+
+    /// Wait up to `timeout` for running stores to finish, returning how many didn't
+    pub async fn drain(&self, timeout: std::time::Duration) -> usize {
+        let mut tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        let drained = tokio::time::timeout(timeout, async {
+            while tasks.join_next().await.is_some() {}
+        })
+        .await;
+        match drained {
+            Ok(()) => 0,
+            Err(_) => tasks.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_lifecycle() {
+        let jobs = JobTable::new(Duration::hours(1));
+        let id = Uuid::new_v4();
+        assert!(jobs.get(&id).is_none());
+
+        jobs.pending(id, "hash", Some("acme"), Some("cert-1"));
+        assert_eq!(jobs.get(&id).unwrap().status, JobStatus::Pending);
+
+        jobs.stored(id, "hash", "location");
+        let job = jobs.get(&id).unwrap();
+        assert_eq!(job.status, JobStatus::Stored);
+        assert_eq!(job.storage_location.as_deref(), Some("location"));
+        assert_eq!(job.tenant_id.as_deref(), Some("acme"));
+        assert_eq!(job.certificate_id.as_deref(), Some("cert-1"));
+    }
+
+    #[test]
+    fn test_jobs_expire() {
+        let jobs = JobTable::new(Duration::zero());
+        let id = Uuid::new_v4();
+
+        jobs.failed(id, "hash");
+        assert!(jobs.get(&id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_background_stores_are_capped_and_drained() {
+        let stores = BackgroundStores::new(2);
+        let finished = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        for _ in 0..2 {
+            let slot = stores.try_reserve().unwrap();
+            let finished = finished.clone();
+            stores.spawn(slot, async move {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                finished.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            });
+        }
+        assert!(stores.try_reserve().is_none());
+
+        let unfinished = stores.drain(std::time::Duration::from_secs(5)).await;
+        assert_eq!(unfinished, 0);
+        assert_eq!(finished.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert!(stores.try_reserve().is_some());
+    }
+}
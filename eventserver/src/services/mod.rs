@@ -1,7 +1,19 @@
+pub mod archive_validator;
+pub mod bandwidth;
+pub mod certificate_sync;
+pub mod circuit_breaker;
 pub mod crypto;
 pub mod event;
-mod relay;
+pub mod event_bus;
+pub mod image_header;
+pub mod inflight;
+pub mod jobs;
+pub mod rejection_log;
+pub mod relay;
+pub mod relay_quota;
+pub mod request_dedup;
 pub mod storage;
+pub mod tenant;
 pub mod zip_packager;
 
 pub use event::*;
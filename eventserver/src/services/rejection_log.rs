@@ -0,0 +1,161 @@
+use base64::Engine;
+use tracing::warn;
+
+use crate::error::EventServerError;
+use crate::types::event::EventPackage;
+
+/// What can safely be logged about a rejected event: identifiers, counts and sizes,
+/// never annotation values or media bytes
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RejectedEventSummary {
+    pub event_id: Option<String>,
+    pub annotation_count: Option<usize>,
+    pub media_type: Option<String>,
+    pub media_size: Option<u64>, // As declared by the client
+}
+
+impl RejectedEventSummary {
+    pub fn of(event_package: &EventPackage) -> Self {
+        Self {
+            event_id: Some(event_package.id.to_string()),
+            annotation_count: Some(event_package.annotations.len()),
+            media_type: event_package
+                .media
+                .as_ref()
+                .map(|media| media.media_type.as_str().to_string()),
+            media_size: event_package.media.as_ref().map(|media| media.size),
+        }
+    }
+
+    /// Best-effort summary of an event JWT that failed verification
+    /// The claims are untrusted, so fields that don't parse are left out
+    pub fn from_unverified_jwt(jwt_token: &str) -> Self {
+        let Some(payload) = jwt_token
+            .split('.')
+            .nth(1)
+            .and_then(|claims| {
+                base64::engine::general_purpose::URL_SAFE_NO_PAD
+                    .decode(claims)
+                    .ok()
+            })
+            .and_then(|claims| serde_json::from_slice::<serde_json::Value>(&claims).ok())
+            .map(|mut claims| claims["payload"].take())
+        else {
+            return Self::default();
+        };
+
+        Self {
+            event_id: payload["id"]
+                .as_str()
+                .filter(|id| uuid::Uuid::parse_str(id).is_ok())
+                .map(str::to_string),
+            annotation_count: payload["annotations"].as_array().map(Vec::len),
+            media_type: payload["media"]["type"]
+                .as_str()
+                .filter(|media_type| media_type.len() <= 64)
+                .map(str::to_string),
+            media_size: payload["media"]["size"].as_u64(),
+        }
+    }
+}
+
+/// Log a rejected event with its redacted summary and the rejection reason
+pub fn log_rejected_event(
+    relay_id: Option<&str>,
+    summary: &RejectedEventSummary,
+    reason: &EventServerError,
+) {
+    warn!(
+        relay_id = relay_id.unwrap_or("unknown"),
+        event_id = summary.event_id.as_deref().unwrap_or("unknown"),
+        annotation_count = ?summary.annotation_count,
+        media_type = summary.media_type.as_deref().unwrap_or("none"),
+        media_size = ?summary.media_size,
+        reason = %reason,
+        "Rejected event"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::state::AppState;
+    use crate::test_utils::{
+        issue_token, sample_event, sample_media, signed_package_request, CapturedLogs, DeviceKey,
+    };
+    use crate::types::event::{FieldValue, MediaType};
+    use axum::http::StatusCode;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_unparseable_jwt_summarizes_nothing() {
+        for token in ["", "not-a-jwt", "a.!!!.c", "a.eyJmb28iOjF9.c"] {
+            assert_eq!(
+                RejectedEventSummary::from_unverified_jwt(token),
+                RejectedEventSummary::default()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejection_logs_summary_without_payload() {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let state = AppState::new_mock(AppConfig::default()).await;
+        let device = DeviceKey::generate();
+        let token = issue_token(&state, &device);
+        let mut event = sample_event();
+        event.annotations[0].value = FieldValue::String("secret-annotation-value".to_string());
+        event.media = Some(sample_media(MediaType::ImagePng, b"secret-media-bytes"));
+
+        let app = crate::create_app(state);
+
+        // Signed by a key other than the one bound to the certificate
+        let response = app
+            .clone()
+            .oneshot(signed_package_request(
+                &DeviceKey::generate(),
+                &token,
+                &event,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Verified, but failing event validation
+        event.version = "9.9".to_string();
+        let response = app
+            .oneshot(signed_package_request(&device, &token, &event))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let output = logs.contents();
+        let lines: Vec<&str> = output
+            .lines()
+            .filter(|line| line.contains("Rejected event"))
+            .collect();
+        assert_eq!(lines.len(), 2, "{output}");
+        for line in lines {
+            assert!(line.contains("relay_id=\"test_relay\""), "{line}");
+            assert!(
+                line.contains(&format!("event_id=\"{}\"", event.id)),
+                "{line}"
+            );
+            assert!(line.contains("annotation_count=Some(1)"), "{line}");
+            assert!(line.contains("media_type=\"image/png\""), "{line}");
+            assert!(line.contains("media_size=Some(18)"), "{line}");
+            assert!(line.contains("reason="), "{line}");
+        }
+
+        assert!(!output.contains("secret-annotation-value"));
+        assert!(!output.contains("c2VjcmV0LW1lZGlhLWJ5dGVz"));
+    }
+}
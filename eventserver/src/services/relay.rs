@@ -1,25 +1,84 @@
-use chrono::{DateTime, Utc};
-use tracing::info;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+use tracing::{info, warn};
 use uuid::Uuid;
 
 use crate::config::AppConfig;
+use crate::crypto::{Clock, SystemClock};
 use crate::error::EventServerError;
 use crate::types::relay::{ProvisionRequest, ProvisionResult, RelayInfo, RelayStatus};
 
-/// Stateless relay management service
-/// Handles relay provisioning and management without maintaining local state
+/// Relay management service
+/// Handles relay provisioning and keeps an in-memory registry of relay statuses
 #[derive(Clone)]
-#[allow(dead_code)]
 pub struct RelayService {
     config: AppConfig,
+    statuses: Arc<Mutex<HashMap<String, RelayStatus>>>, // Registered relays by ID
+    draining: Arc<Mutex<HashMap<String, DateTime<Utc>>>>, // Decommissioned relay ID -> end of its drain window
+    drain_window: Duration, // How long a decommissioned relay may keep submitting
+    clock: Arc<dyn Clock>,
     // In a real implementation, this would include cloud provider clients
     // (AWS EC2, Google Compute, Azure, etc.)
 }
-#[allow(dead_code)]
 impl RelayService {
     /// Create a new RelayService instance
     pub fn new(config: AppConfig) -> Self {
-        Self { config }
+        Self {
+            drain_window: Duration::seconds(config.security.relay_drain_seconds as i64),
+            config,
+            statuses: Arc::new(Mutex::new(HashMap::new())),
+            draining: Arc::new(Mutex::new(HashMap::new())),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Use a different time source for drain deadlines
+    #[cfg(test)]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Record the status of a registered relay
+    /// Cancels any drain in progress, e.g. when a decommissioned relay is brought back
+    pub fn set_relay_status(&self, relay_id: &str, status: RelayStatus) {
+        self.draining.lock().unwrap().remove(relay_id);
+        self.statuses
+            .lock()
+            .unwrap()
+            .insert(relay_id.to_string(), status);
+    }
+
+    /// Status of a registered relay, `None` if it is unknown or fully decommissioned
+    pub fn relay_status(&self, relay_id: &str) -> Option<RelayStatus> {
+        self.remove_drained();
+        self.statuses.lock().unwrap().get(relay_id).cloned()
+    }
+
+    /// Reject relays that are not registered as active
+    /// A decommissioned relay is `Inactive` but still accepted until its drain window ends
+    pub fn ensure_active(&self, relay_id: &str) -> Result<(), EventServerError> {
+        match self.relay_status(relay_id) {
+            Some(RelayStatus::Active) => Ok(()),
+            Some(RelayStatus::Inactive) if self.draining.lock().unwrap().contains_key(relay_id) => {
+                info!(relay_id = %relay_id, "Accepting request from draining relay");
+                Ok(())
+            }
+            Some(status) => {
+                warn!(relay_id = %relay_id, status = ?status, "Rejecting request from inactive relay");
+                Err(EventServerError::Forbidden(format!(
+                    "Relay {relay_id} is not active ({status:?})"
+                )))
+            }
+            None => {
+                warn!(relay_id = %relay_id, "Rejecting request from unregistered relay");
+                Err(EventServerError::Forbidden(format!(
+                    "Relay {relay_id} is not registered"
+                )))
+            }
+        }
     }
 
     /// Provision a new relay instance
@@ -45,6 +104,7 @@ impl RelayService {
         // 5. Register the relay in the master list
 
         let relay_info = self.simulate_relay_provisioning(&request).await?;
+        self.set_relay_status(&relay_info.id, relay_info.status.clone());
 
         info!(
             relay_id = %relay_info.id,
@@ -77,16 +137,6 @@ impl RelayService {
         Ok(relays)
     }
 
-    /// Get relay information by ID
-    pub async fn _get_relay(&self, relay_id: &str) -> Result<RelayInfo, EventServerError> {
-        info!(relay_id = %relay_id, "Getting relay information");
-
-        // In a real implementation, this would query the relay registry
-        let relay = self.simulate_get_relay(relay_id).await?;
-
-        Ok(relay)
-    }
-
     /// Check relay health status
     pub async fn check_relay_health(
         &self,
@@ -113,7 +163,9 @@ impl RelayService {
     }
 
     /// Decommission a relay instance
-    pub async fn _decommission_relay(&self, relay_id: &str) -> Result<(), EventServerError> {
+    /// With a drain window the relay is marked `Inactive` and removed once the window ends,
+    /// so submissions already under way can complete
+    pub async fn decommission_relay(&self, relay_id: &str) -> Result<(), EventServerError> {
         info!(relay_id = %relay_id, "Decommissioning relay");
 
         // In a real implementation, this would:
@@ -123,12 +175,38 @@ impl RelayService {
         // 4. Clean up associated resources (security groups, etc.)
 
         self.simulate_relay_decommission(relay_id).await?;
+        if self.drain_window > Duration::zero() {
+            let drain_until = self.clock.now() + self.drain_window;
+            self.set_relay_status(relay_id, RelayStatus::Inactive);
+            self.draining
+                .lock()
+                .unwrap()
+                .insert(relay_id.to_string(), drain_until);
+            info!(relay_id = %relay_id, drain_until = %drain_until, "Relay draining before removal");
+        } else {
+            self.statuses.lock().unwrap().remove(relay_id);
+        }
 
         info!(relay_id = %relay_id, "Relay decommissioned successfully");
 
         Ok(())
     }
 
+    /// Remove decommissioned relays whose drain window has ended
+    fn remove_drained(&self) {
+        let now = self.clock.now();
+        let mut draining = self.draining.lock().unwrap();
+        let mut statuses = self.statuses.lock().unwrap();
+        draining.retain(|relay_id, drain_until| {
+            let draining = now < *drain_until;
+            if !draining {
+                statuses.remove(relay_id);
+                info!(relay_id = %relay_id, "Drain window ended, relay removed");
+            }
+            draining
+        });
+    }
+
     /// Get relay network statistics
     pub async fn get_network_stats(&self) -> Result<RelayNetworkStats, EventServerError> {
         info!("Getting relay network statistics");
@@ -161,8 +239,8 @@ impl RelayService {
         }
 
         // Validate region is supported
-        let supported_regions = vec!["us-east-1", "us-west-2", "eu-west-1", "ap-southeast-1"];
-        if !supported_regions.contains(&request.region.as_str()) {
+        let supported_regions = &self.config.security.supported_relay_regions;
+        if !supported_regions.contains(&request.region) {
             return Err(EventServerError::Validation(format!(
                 "Unsupported region: {}. Supported regions: {:?}",
                 request.region, supported_regions
@@ -222,17 +300,6 @@ impl RelayService {
         Ok(vec![])
     }
 
-    /// Simulate getting relay by ID
-    async fn simulate_get_relay(&self, relay_id: &str) -> Result<RelayInfo, EventServerError> {
-        // Simulate database query delay
-        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
-
-        // For simulation, return error (would be actual relay lookup)
-        Err(EventServerError::NotFound(format!(
-            "Relay not found: {relay_id}"
-        )))
-    }
-
     /// Simulate health check
     async fn simulate_health_check(
         &self,
@@ -264,11 +331,7 @@ impl RelayService {
     /// Create a mock instance for testing
     #[cfg(test)]
     pub fn new_mock() -> Self {
-        use crate::config::*;
-
-        Self {
-            config: AppConfig::default(),
-        }
+        Self::new(AppConfig::default())
     }
 }
 
@@ -297,6 +360,7 @@ pub struct RelayNetworkStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::MockClock;
     use crate::types::relay::ProvisionRequest;
 
     #[tokio::test]
@@ -306,7 +370,6 @@ mod tests {
         let request = ProvisionRequest {
             region: "us-east-1".to_string(),
             instance_type: "t3.medium".to_string(),
-            relay_config: None,
         };
 
         let result = service.provision_relay(request).await;
@@ -325,7 +388,6 @@ mod tests {
         let request = ProvisionRequest {
             region: "invalid-region".to_string(),
             instance_type: "t3.medium".to_string(),
-            relay_config: None,
         };
 
         let result = service.provision_relay(request).await;
@@ -374,6 +436,39 @@ mod tests {
         assert!(stats.network_uptime_percentage > 99.0);
     }
 
+    #[tokio::test]
+    async fn test_decommissioned_relay_drains_then_is_removed() {
+        let clock = MockClock::new();
+        let service = RelayService::new_mock().with_clock(Arc::new(clock.clone()));
+        service.set_relay_status("relay_a", RelayStatus::Active);
+
+        service.decommission_relay("relay_a").await.unwrap();
+        assert!(matches!(
+            service.relay_status("relay_a"),
+            Some(RelayStatus::Inactive)
+        ));
+        assert!(service.ensure_active("relay_a").is_ok());
+
+        clock.advance(Duration::seconds(300));
+        assert!(service.relay_status("relay_a").is_none());
+        assert!(matches!(
+            service.ensure_active("relay_a"),
+            Err(EventServerError::Forbidden(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_decommission_without_drain_window() {
+        let mut config = AppConfig::default();
+        config.security.relay_drain_seconds = 0;
+        let service = RelayService::new(config);
+        service.set_relay_status("relay_a", RelayStatus::Active);
+
+        service.decommission_relay("relay_a").await.unwrap();
+        assert!(service.relay_status("relay_a").is_none());
+        assert!(service.ensure_active("relay_a").is_err());
+    }
+
     #[test]
     fn test_validate_provision_request() {
         let service = RelayService::new_mock();
@@ -381,7 +476,6 @@ mod tests {
         let valid_request = ProvisionRequest {
             region: "us-east-1".to_string(),
             instance_type: "t3.medium".to_string(),
-            relay_config: None,
         };
 
         let result = service.validate_provision_request(&valid_request);
@@ -390,10 +484,37 @@ mod tests {
         let invalid_request = ProvisionRequest {
             region: "".to_string(),
             instance_type: "t3.medium".to_string(),
-            relay_config: None,
         };
 
         let result = service.validate_provision_request(&invalid_request);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_configured_regions_restrict_provisioning() {
+        let mut config = AppConfig::default();
+        config.security.supported_relay_regions =
+            vec!["eu-central-1".to_string(), "eu-west-1".to_string()];
+        let service = RelayService::new(config);
+        let request = |region: &str| ProvisionRequest {
+            region: region.to_string(),
+            instance_type: "t3.medium".to_string(),
+        };
+
+        assert!(service
+            .validate_provision_request(&request("eu-central-1"))
+            .is_ok());
+        assert!(service
+            .validate_provision_request(&request("eu-west-1"))
+            .is_ok());
+
+        // A default region left out of the configured list is refused
+        match service.validate_provision_request(&request("us-east-1")) {
+            Err(EventServerError::Validation(msg)) => {
+                assert!(msg.contains("Unsupported region: us-east-1"), "{msg}");
+                assert!(msg.contains("eu-central-1"), "{msg}");
+            }
+            other => panic!("Expected Validation error, got {other:?}"),
+        }
+    }
 }
@@ -0,0 +1,127 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use crate::error::EventServerError;
+
+/// Bytes stored per relay, checked against an optional per-relay quota
+/// Usage is reserved before an upload and released if it fails, so concurrent uploads
+/// from one relay can't jointly overshoot the quota. The storage service seeds each relay's
+/// usage from its persisted total before the first upload, so it survives restarts
+#[derive(Debug, Clone, Default)]
+pub struct RelayQuota {
+    quota_bytes: Option<u64>, // None disables enforcement; usage is still tracked
+    used: Arc<Mutex<HashMap<String, u64>>>,
+    seeded: Arc<Mutex<HashSet<String>>>, // Relays whose persisted usage has been added
+}
+
+impl RelayQuota {
+    pub fn new(quota_bytes: Option<u64>) -> Self {
+        Self {
+            quota_bytes,
+            used: Arc::default(),
+            seeded: Arc::default(),
+        }
+    }
+
+    /// Whether a quota is configured
+    pub fn is_enforced(&self) -> bool {
+        self.quota_bytes.is_some()
+    }
+
+    /// Whether `relay_id`'s persisted usage still has to be added
+    pub fn needs_seed(&self, relay_id: &str) -> bool {
+        !self.seeded.lock().unwrap().contains(relay_id)
+    }
+
+    /// Add the usage persisted for `relay_id`; only the first call per relay counts
+    pub fn seed(&self, relay_id: &str, bytes: u64) {
+        if self.seeded.lock().unwrap().insert(relay_id.to_string()) {
+            let mut used = self.used.lock().unwrap();
+            let current = used.entry(relay_id.to_string()).or_default();
+            *current = current.saturating_add(bytes);
+        }
+    }
+
+    /// Charge `bytes` to `relay_id`, failing if that would take it past the quota
+    pub fn reserve(&self, relay_id: &str, bytes: u64) -> Result<(), EventServerError> {
+        let mut used = self.used.lock().unwrap();
+        let current = used.get(relay_id).copied().unwrap_or(0);
+        if let Some(quota_bytes) = self.quota_bytes {
+            if current.saturating_add(bytes) > quota_bytes {
+                return Err(EventServerError::QuotaExceeded {
+                    relay_id: relay_id.to_string(),
+                    used_bytes: current,
+                    quota_bytes,
+                });
+            }
+        }
+        used.insert(relay_id.to_string(), current.saturating_add(bytes));
+        Ok(())
+    }
+
+    /// Return bytes reserved for an upload that didn't happen
+    pub fn release(&self, relay_id: &str, bytes: u64) {
+        if let Some(current) = self.used.lock().unwrap().get_mut(relay_id) {
+            *current = current.saturating_sub(bytes);
+        }
+    }
+
+    /// Bytes currently charged to `relay_id`
+    pub fn used(&self, relay_id: &str) -> u64 {
+        self.used
+            .lock()
+            .unwrap()
+            .get(relay_id)
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve_up_to_quota() {
+        let quota = RelayQuota::new(Some(100));
+        quota.reserve("relay_a", 60).unwrap();
+        quota.reserve("relay_a", 40).unwrap();
+
+        let err = quota.reserve("relay_a", 1).unwrap_err();
+        assert!(matches!(
+            err,
+            EventServerError::QuotaExceeded {
+                used_bytes: 100,
+                quota_bytes: 100,
+                ..
+            }
+        ));
+        // Other relays have their own budget
+        quota.reserve("relay_b", 100).unwrap();
+
+        quota.release("relay_a", 40);
+        assert_eq!(quota.used("relay_a"), 60);
+        quota.reserve("relay_a", 40).unwrap();
+    }
+
+    #[test]
+    fn test_seed_counts_once() {
+        let quota = RelayQuota::new(Some(100));
+        quota.reserve("relay_a", 10).unwrap();
+        assert!(quota.needs_seed("relay_a"));
+
+        quota.seed("relay_a", 80);
+        quota.seed("relay_a", 80);
+        assert!(!quota.needs_seed("relay_a"));
+        assert_eq!(quota.used("relay_a"), 90);
+        assert!(quota.reserve("relay_a", 11).is_err());
+    }
+
+    #[test]
+    fn test_unset_quota_only_tracks() {
+        let quota = RelayQuota::new(None);
+        quota.reserve("relay_a", u64::MAX).unwrap();
+        quota.reserve("relay_a", 1).unwrap();
+        assert_eq!(quota.used("relay_a"), u64::MAX);
+    }
+}
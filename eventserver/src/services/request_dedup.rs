@@ -0,0 +1,439 @@
+use axum::body::{Body, Bytes, HttpBody};
+use axum::http::{HeaderMap, HeaderValue, Method, StatusCode};
+use axum::response::Response;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Header marking a response replayed from the dedup cache
+pub const DEDUPLICATED_HEADER: &str = "x-deduplicated";
+
+/// Most responses held at once; the oldest are evicted to make room
+const MAX_DEDUP_ENTRIES: usize = 10_000;
+/// Most response bytes held at once; the oldest are evicted to make room
+const MAX_DEDUP_BYTES: usize = 16 * 1024 * 1024;
+/// Larger response bodies aren't cached
+const MAX_DEDUP_BODY_BYTES: usize = 64 * 1024;
+
+/// Recent successful responses keyed by a hash of the raw request
+/// Catches transport-level retries of byte-identical bodies from clients that can't send
+/// idempotency headers, answering them with the original response before any processing.
+/// A retry arriving while the original is still being processed waits for its response
+#[derive(Debug, Clone)]
+pub struct RequestDedupCache {
+    ttl: Duration, // Zero disables deduplication
+    max_entries: usize,
+    max_bytes: usize,
+    state: Arc<Mutex<DedupState>>,
+}
+
+#[derive(Debug, Default)]
+struct DedupState {
+    entries: HashMap<String, DedupEntry>,
+    expiry: VecDeque<(Instant, String)>, // Cached keys in insertion order, which is expiry order
+    cached_bytes: usize,
+}
+
+#[derive(Debug)]
+enum DedupEntry {
+    /// Being processed; the receiver sees the sender dropped once it's done either way
+    Pending(watch::Receiver<()>),
+    Cached(CachedResponse),
+}
+
+#[derive(Debug, Clone)]
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    expires_at: Instant,
+}
+
+impl CachedResponse {
+    fn size(&self) -> usize {
+        self.body.len()
+            + self
+                .headers
+                .iter()
+                .map(|(name, value)| name.as_str().len() + value.len())
+                .sum::<usize>()
+    }
+
+    fn to_response(&self) -> Response {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response
+            .headers_mut()
+            .insert(DEDUPLICATED_HEADER, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+/// What to do with a request after checking the dedup cache
+pub enum DedupOutcome {
+    /// A byte-identical request was answered recently; send this instead
+    Replay(Response),
+    /// Process the request and pass its response to `RequestDedupCache::remember`
+    Process(Option<InFlightRequest>),
+}
+
+/// Claim on processing a request, letting identical retries wait for its response
+/// Dropping it without a response releases waiting retries to be processed themselves
+pub struct InFlightRequest {
+    cache: RequestDedupCache,
+    key: String,
+    _done: watch::Sender<()>,
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        let mut state = self.cache.state.lock().unwrap();
+        if matches!(state.entries.get(&self.key), Some(DedupEntry::Pending(_))) {
+            state.entries.remove(&self.key);
+        }
+    }
+}
+
+impl DedupState {
+    fn remove_cached(&mut self, key: &str) {
+        if let Some(DedupEntry::Cached(cached)) = self.entries.remove(key) {
+            self.cached_bytes -= cached.size();
+        }
+    }
+
+    /// Drop expired entries, then the oldest ones until `size` more bytes fit the budget
+    fn make_room(&mut self, size: usize, max_entries: usize, max_bytes: usize, now: Instant) {
+        while let Some((expires_at, _)) = self.expiry.front() {
+            let over_budget =
+                self.expiry.len() >= max_entries || self.cached_bytes + size > max_bytes;
+            if *expires_at > now && !over_budget {
+                break;
+            }
+            let (expires_at, key) = self.expiry.pop_front().unwrap();
+            // The key may have been cached again since; only its current entry counts
+            if matches!(
+                self.entries.get(&key),
+                Some(DedupEntry::Cached(cached)) if cached.expires_at == expires_at
+            ) {
+                self.remove_cached(&key);
+            }
+        }
+    }
+}
+
+impl RequestDedupCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_limits(ttl, MAX_DEDUP_ENTRIES, MAX_DEDUP_BYTES)
+    }
+
+    fn with_limits(ttl: Duration, max_entries: usize, max_bytes: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            max_bytes,
+            state: Arc::default(),
+        }
+    }
+
+    /// Cache key of a request from `certificate_id`, or `None` when deduplication is disabled
+    /// The certificate is part of the key so clients never see each other's responses
+    pub fn key(
+        &self,
+        method: &Method,
+        path: &str,
+        certificate_id: &str,
+        body: &[u8],
+    ) -> Option<String> {
+        if self.ttl.is_zero() {
+            return None;
+        }
+        let mut hasher = Sha256::new();
+        for part in [method.as_str(), path, certificate_id] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        hasher.update(body);
+        Some(hex::encode(hasher.finalize()))
+    }
+
+    /// Replay the response previously sent for `key` if it's still within the TTL, waiting
+    /// for it if an identical request is still being processed; otherwise claim processing
+    pub async fn claim(&self, key: Option<String>) -> DedupOutcome {
+        let Some(key) = key else {
+            return DedupOutcome::Process(None);
+        };
+        loop {
+            let mut pending = {
+                let mut state = self.state.lock().unwrap();
+                match state.entries.get(&key) {
+                    Some(DedupEntry::Cached(cached)) if cached.expires_at > Instant::now() => {
+                        return DedupOutcome::Replay(cached.to_response());
+                    }
+                    Some(DedupEntry::Pending(pending)) => pending.clone(),
+                    _ => {
+                        state.remove_cached(&key);
+                        let (done, pending) = watch::channel(());
+                        state
+                            .entries
+                            .insert(key.clone(), DedupEntry::Pending(pending));
+                        return DedupOutcome::Process(Some(InFlightRequest {
+                            cache: self.clone(),
+                            key,
+                            _done: done,
+                        }));
+                    }
+                }
+            };
+            // Resolves once the original request is done, cached or not
+            let _ = pending.changed().await;
+        }
+    }
+
+    /// Remember a successful `response` to a claimed request and hand it back
+    /// Failures aren't cached, so a retry after an error is processed again; neither are
+    /// streamed or large bodies
+    pub async fn remember(
+        &self,
+        in_flight: Option<InFlightRequest>,
+        response: Response,
+    ) -> Response {
+        let cacheable = response.status().is_success()
+            && response
+                .body()
+                .size_hint()
+                .exact()
+                .is_some_and(|len| len <= MAX_DEDUP_BODY_BYTES as u64);
+        let Some(in_flight) = in_flight.filter(|_| cacheable) else {
+            return response;
+        };
+
+        let (parts, body) = response.into_parts();
+        let body = match axum::body::to_bytes(body, MAX_DEDUP_BODY_BYTES).await {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to buffer response for deduplication");
+                return Response::from_parts(parts, Body::empty());
+            }
+        };
+
+        let now = Instant::now();
+        let cached = CachedResponse {
+            status: parts.status,
+            headers: parts.headers.clone(),
+            body: body.clone(),
+            expires_at: now + self.ttl,
+        };
+        let size = cached.size();
+        let mut state = self.state.lock().unwrap();
+        state.make_room(size, self.max_entries, self.max_bytes, now);
+        if size <= self.max_bytes {
+            state.cached_bytes += size;
+            state
+                .expiry
+                .push_back((cached.expires_at, in_flight.key.clone()));
+            state
+                .entries
+                .insert(in_flight.key.clone(), DedupEntry::Cached(cached));
+        }
+        drop(state);
+        drop(in_flight);
+        Response::from_parts(parts, Body::from(body))
+    }
+
+    /// Cached responses and bytes held
+    #[cfg(test)]
+    fn usage(&self) -> (usize, usize) {
+        let state = self.state.lock().unwrap();
+        let cached = state
+            .entries
+            .values()
+            .filter(|entry| matches!(entry, DedupEntry::Cached(_)))
+            .count();
+        (cached, state.cached_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::state::AppState;
+    use crate::test_utils::{issue_token, sample_event, DeviceKey};
+    use crate::types::event::SignedEventPackage;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    /// Submit the same signed body twice, returning both responses
+    async fn submit_twice(ttl_seconds: u64) -> (AppState, [(StatusCode, HeaderMap, Bytes); 2]) {
+        let mut config = AppConfig::default();
+        config.server.body_dedup_ttl_seconds = ttl_seconds;
+        let state = AppState::new_mock(config).await;
+        let device = DeviceKey::generate();
+        let token = issue_token(&state, &device);
+        let body = serde_json::to_vec(&SignedEventPackage {
+            jwt_event_data: device.sign(&sample_event()),
+            signature: None,
+            public_key: None,
+        })
+        .unwrap();
+
+        let app = crate::create_app(state.clone());
+        let mut responses = Vec::new();
+        for _ in 0..2 {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/api/v1/events/package")
+                        .header("Content-Type", "application/json")
+                        .header("Authorization", format!("Bearer {token}"))
+                        .body(Body::from(body.clone()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let (parts, body) = response.into_parts();
+            let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+            responses.push((parts.status, parts.headers, body));
+        }
+        (state, responses.try_into().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_identical_body_is_served_from_dedup_cache() {
+        let (state, [first, second]) = submit_twice(60).await;
+        assert_eq!(first.0, StatusCode::OK);
+        assert!(!first.1.contains_key(DEDUPLICATED_HEADER));
+
+        assert_eq!(second.0, StatusCode::OK);
+        assert_eq!(second.1[DEDUPLICATED_HEADER], "true");
+        assert_eq!(second.2, first.2);
+        // Only the first submission reached the replay cache
+        assert_eq!(state.replay_cache.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_identical_body_without_dedup_is_a_replay() {
+        let (_, [first, second]) = submit_twice(0).await;
+        assert_eq!(first.0, StatusCode::OK);
+        assert_eq!(second.0, StatusCode::CONFLICT);
+    }
+
+    fn ok(body: &'static str) -> Response {
+        Response::new(Body::from(body))
+    }
+
+    async fn replayed_body(outcome: DedupOutcome) -> Bytes {
+        let DedupOutcome::Replay(response) = outcome else {
+            panic!("expected a replayed response");
+        };
+        assert_eq!(response.headers()[DEDUPLICATED_HEADER], "true");
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_retry_waits_for_the_original_response() {
+        let cache = RequestDedupCache::new(Duration::from_secs(60));
+        let key = || Some("request".to_string());
+        let DedupOutcome::Process(Some(original)) = cache.claim(key()).await else {
+            panic!("first request is processed");
+        };
+
+        let retry = tokio::spawn({
+            let cache = cache.clone();
+            async move { cache.claim(key()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!retry.is_finished());
+
+        cache.remember(Some(original), ok("first")).await;
+        assert_eq!(replayed_body(retry.await.unwrap()).await, "first");
+    }
+
+    #[tokio::test]
+    async fn test_failed_original_releases_waiting_retry() {
+        let cache = RequestDedupCache::new(Duration::from_secs(60));
+        let key = || Some("request".to_string());
+        let DedupOutcome::Process(Some(original)) = cache.claim(key()).await else {
+            panic!("first request is processed");
+        };
+
+        let retry = tokio::spawn({
+            let cache = cache.clone();
+            async move { cache.claim(key()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let mut failed = ok("boom");
+        *failed.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+        cache.remember(Some(original), failed).await;
+        // Not cached, so the retry is processed itself
+        assert!(matches!(
+            retry.await.unwrap(),
+            DedupOutcome::Process(Some(_))
+        ));
+        assert_eq!(cache.usage(), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_oldest_responses_are_evicted_past_the_byte_budget() {
+        let body_size = CachedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(b"0123456789"),
+            expires_at: Instant::now(),
+        }
+        .size();
+        let cache = RequestDedupCache::with_limits(Duration::from_secs(60), 100, body_size * 2);
+
+        for key in ["a", "b", "c"] {
+            let DedupOutcome::Process(in_flight) = cache.claim(Some(key.to_string())).await else {
+                panic!("not cached yet");
+            };
+            cache.remember(in_flight, ok("0123456789")).await;
+        }
+        assert_eq!(cache.usage(), (2, body_size * 2));
+        assert!(matches!(
+            cache.claim(Some("a".to_string())).await,
+            DedupOutcome::Process(Some(_))
+        ));
+        assert_eq!(
+            replayed_body(cache.claim(Some("c".to_string())).await).await,
+            "0123456789"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_responses_are_dropped() {
+        let cache = RequestDedupCache::with_limits(Duration::from_millis(10), 100, 1024);
+        let DedupOutcome::Process(in_flight) = cache.claim(Some("a".to_string())).await else {
+            panic!("not cached yet");
+        };
+        cache.remember(in_flight, ok("first")).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let DedupOutcome::Process(in_flight) = cache.claim(Some("b".to_string())).await else {
+            panic!("not cached yet");
+        };
+        cache.remember(in_flight, ok("second")).await;
+        assert_eq!(cache.usage().0, 1);
+    }
+
+    #[test]
+    fn test_key_is_scoped_to_certificate() {
+        let cache = RequestDedupCache::new(Duration::from_secs(60));
+        let key =
+            |certificate_id| cache.key(&Method::POST, "/api/v1/events", certificate_id, b"{}");
+        assert_ne!(key("cert-a"), key("cert-b"));
+        assert_eq!(key("cert-a"), key("cert-a"));
+        assert!(RequestDedupCache::new(Duration::ZERO)
+            .key(&Method::POST, "/", "cert-a", b"{}")
+            .is_none());
+    }
+}
@@ -1,14 +1,28 @@
-use aws_config::{BehaviorVersion, Region};
-use aws_sdk_s3::{primitives::ByteStream, Client as S3Client};
-use chrono::Utc;
-use sha2::Digest;
+use aws_config::{retry::RetryConfig, timeout::TimeoutConfig, BehaviorVersion, Region};
+use aws_sdk_s3::{error::ProvideErrorMetadata, primitives::ByteStream, Client as S3Client};
+use base64::Engine;
+use chrono::{DateTime, NaiveDate, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use futures::StreamExt;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::{Cursor, Read, Write};
 use std::sync::Arc;
-use tracing::info;
+use std::time::Duration;
+use tracing::{info, warn};
 use uuid::Uuid;
+use zip::{result::ZipError, ZipArchive};
 
-use crate::config::storage::StorageConfig;
+use crate::config::storage::{path_segment, StorageConfig, StorageLayout};
+use crate::crypto::{ArchiveSigner, DeviceCertificate, EventReceipt, Revocation, StoredKeyring};
 use crate::error::EventServerError;
-use crate::types::event::EventPackage;
+use crate::services::bandwidth::UploadThrottle;
+use crate::services::circuit_breaker::{CircuitBreakerS3, CircuitBreakerStatus};
+use crate::services::inflight::InFlightLocks;
+use crate::services::relay_quota::RelayQuota;
+use crate::services::zip_packager::ZipPackager;
+use crate::types::event::{EventMedia, EventMetadata, EventPackage, EventSource, MediaType};
 
 /// Trait for S3 operations to enable mocking in tests
 #[async_trait::async_trait]
@@ -19,11 +33,152 @@ pub trait S3Operations: Send + Sync {
         key: &str,
         body: Vec<u8>,
         content_type: &str,
-    ) -> Result<(), EventServerError>;
+    ) -> Result<(), EventServerError> {
+        self.put_object_with_encoding(bucket, key, body, content_type, None)
+            .await
+            .map(|_| ())
+    }
+
+    /// Upload an object, optionally tagging it with a `Content-Encoding` (e.g. `gzip`)
+    /// Returns the ETag the backend reported for the stored object, if any
+    async fn put_object_with_encoding(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+        content_encoding: Option<&str>,
+    ) -> Result<Option<String>, EventServerError>;
+
+    /// Upload an object only if the key doesn't exist yet
+    /// Returns false, without writing, when another writer got there first
+    async fn put_object_if_absent(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+    ) -> Result<bool, EventServerError>;
+
+    /// Replace an object only if it is still stored under `etag`
+    /// Returns false, without writing, when it was changed or removed since `etag` was read
+    async fn put_object_if_match(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+        etag: &str,
+    ) -> Result<bool, EventServerError>;
 
     async fn head_object(&self, bucket: &str, key: &str) -> Result<bool, EventServerError>;
 
-    async fn _get_object(&self, bucket: &str, key: &str) -> Result<Vec<u8>, EventServerError>;
+    async fn get_object(&self, bucket: &str, key: &str) -> Result<Vec<u8>, EventServerError>;
+
+    async fn delete_object(&self, bucket: &str, key: &str) -> Result<(), EventServerError>;
+
+    /// Delete an object only if it is still stored under `etag`
+    /// Returns false, without deleting, when it was changed or removed since `etag` was read
+    async fn delete_object_if_match(
+        &self,
+        bucket: &str,
+        key: &str,
+        etag: &str,
+    ) -> Result<bool, EventServerError>;
+
+    /// Fetch an object, optionally restricted to an HTTP byte range
+    async fn get_object_range(
+        &self,
+        bucket: &str,
+        key: &str,
+        range: Option<&str>,
+    ) -> Result<ObjectDownload, EventServerError>;
+
+    /// List object keys under a prefix, returning at most `max_keys` keys
+    async fn list_objects(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        max_keys: usize,
+    ) -> Result<Vec<String>, EventServerError> {
+        self.list_objects_after(bucket, prefix, None, max_keys)
+            .await
+    }
+
+    /// List object keys under a prefix in key order, starting after `start_after` when set,
+    /// returning at most `max_keys` keys
+    async fn list_objects_after(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        start_after: Option<&str>,
+        max_keys: usize,
+    ) -> Result<Vec<String>, EventServerError>;
+}
+
+/// Object body and metadata returned by a (possibly ranged) download
+#[derive(Debug, Clone)]
+pub struct ObjectDownload {
+    pub body: Vec<u8>,
+    pub content_type: Option<String>,
+    /// `Content-Range` value when a byte range was served
+    pub content_range: Option<String>,
+    /// ETag the object is stored under, for conditional writes
+    pub etag: Option<String>,
+}
+
+/// Attempts per S3 call, including the first one
+const S3_MAX_ATTEMPTS: u32 = 3;
+
+/// Timeouts derived from `upload_timeout`
+/// The whole operation, retries included, must finish within the configured timeout, and each
+/// attempt gets an equal share so a stuck connection still leaves room for a retry
+fn upload_timeout_config(upload_timeout_seconds: u64) -> TimeoutConfig {
+    let operation_timeout = Duration::from_secs(upload_timeout_seconds.max(1));
+    TimeoutConfig::builder()
+        .operation_timeout(operation_timeout)
+        .operation_attempt_timeout(operation_timeout / S3_MAX_ATTEMPTS)
+        .build()
+}
+
+/// Back-off hint for throttling errors the SDK gave up retrying
+const THROTTLED_RETRY_AFTER_SECONDS: u64 = 5;
+/// Back-off hint for hard capacity errors, which won't clear on an immediate retry
+const QUOTA_RETRY_AFTER_SECONDS: u64 = 300;
+
+/// Map an S3 SDK error to an `EventServerError`
+/// Throttling and capacity errors become `ServiceUnavailable` with a `Retry-After` hint;
+/// everything else stays a generic `Storage` error. The SDK's standard retry policy already
+/// retries throttling codes with backoff before we see them, and never retries quota errors.
+fn classify_s3_error<E>(error: &E, context: &str) -> EventServerError
+where
+    E: ProvideErrorMetadata + std::fmt::Display,
+{
+    let retry_after_seconds = match error.code() {
+        Some("SlowDown" | "ServiceUnavailable" | "RequestLimitExceeded" | "Throttling") => {
+            THROTTLED_RETRY_AFTER_SECONDS
+        }
+        Some("QuotaExceeded" | "XMinioStorageFull" | "XMinioAdminBucketQuotaExceeded") => {
+            QUOTA_RETRY_AFTER_SECONDS
+        }
+        _ => return EventServerError::Storage(format!("{context}: {error}")),
+    };
+
+    EventServerError::ServiceUnavailable {
+        message: format!(
+            "Storage backend unavailable ({}), retry later",
+            error.code().unwrap_or_default()
+        ),
+        retry_after_seconds: Some(retry_after_seconds),
+    }
+}
+
+/// Whether a conditional request lost to a concurrent writer, or its object is gone
+fn is_precondition_failure<E: ProvideErrorMetadata>(error: &E) -> bool {
+    matches!(
+        error.code(),
+        Some("PreconditionFailed" | "ConditionalRequestConflict" | "NoSuchKey")
+    )
 }
 
 /// Real S3 client implementation
@@ -33,23 +188,75 @@ pub struct RealS3Client {
 
 #[async_trait::async_trait]
 impl S3Operations for RealS3Client {
-    async fn put_object(
+    async fn put_object_with_encoding(
         &self,
         bucket: &str,
         key: &str,
         body: Vec<u8>,
         content_type: &str,
-    ) -> Result<(), EventServerError> {
-        self.client
+        content_encoding: Option<&str>,
+    ) -> Result<Option<String>, EventServerError> {
+        let response = self
+            .client
             .put_object()
             .bucket(bucket)
             .key(key)
             .body(ByteStream::from(body))
             .content_type(content_type)
+            .set_content_encoding(content_encoding.map(str::to_string))
             .send()
             .await
-            .map_err(|e| EventServerError::Storage(format!("Failed to upload to S3: {e}")))?;
-        Ok(())
+            .map_err(|e| classify_s3_error(&e, "Failed to upload to S3"))?;
+        Ok(response.e_tag().map(str::to_string))
+    }
+
+    async fn put_object_if_absent(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+    ) -> Result<bool, EventServerError> {
+        match self
+            .client
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .body(ByteStream::from(body))
+            .content_type(content_type)
+            .if_none_match("*")
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if is_precondition_failure(&e) => Ok(false),
+            Err(e) => Err(classify_s3_error(&e, "Failed to upload to S3")),
+        }
+    }
+
+    async fn put_object_if_match(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+        etag: &str,
+    ) -> Result<bool, EventServerError> {
+        match self
+            .client
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .body(ByteStream::from(body))
+            .content_type(content_type)
+            .if_match(etag)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if is_precondition_failure(&e) => Ok(false),
+            Err(e) => Err(classify_s3_error(&e, "Failed to upload to S3")),
+        }
     }
 
     async fn head_object(&self, bucket: &str, key: &str) -> Result<bool, EventServerError> {
@@ -66,63 +273,380 @@ impl S3Operations for RealS3Client {
         }
     }
 
-    async fn _get_object(&self, bucket: &str, key: &str) -> Result<Vec<u8>, EventServerError> {
+    async fn get_object(&self, bucket: &str, key: &str) -> Result<Vec<u8>, EventServerError> {
+        Ok(self.get_object_range(bucket, key, None).await?.body)
+    }
+
+    async fn get_object_range(
+        &self,
+        bucket: &str,
+        key: &str,
+        range: Option<&str>,
+    ) -> Result<ObjectDownload, EventServerError> {
         let response = self
             .client
             .get_object()
             .bucket(bucket)
             .key(key)
+            .set_range(range.map(str::to_string))
             .send()
             .await
-            .map_err(|e| EventServerError::Storage(format!("Failed to get object: {e}")))?;
+            .map_err(|e| match e.code() {
+                Some("NoSuchKey") => EventServerError::NotFound(format!("Object not found: {key}")),
+                Some("InvalidRange") => EventServerError::RangeNotSatisfiable(format!(
+                    "Requested range {} is not satisfiable",
+                    range.unwrap_or_default()
+                )),
+                _ => classify_s3_error(&e, "Failed to get object"),
+            })?;
+
+        let content_type = response.content_type().map(str::to_string);
+        let content_range = response.content_range().map(str::to_string);
+        let etag = response.e_tag().map(str::to_string);
 
         let data =
             response.body.collect().await.map_err(|e| {
                 EventServerError::Storage(format!("Failed to read response body: {e}"))
             })?;
 
-        Ok(data.into_bytes().to_vec())
+        Ok(ObjectDownload {
+            body: data.into_bytes().to_vec(),
+            content_type,
+            content_range,
+            etag,
+        })
+    }
+
+    async fn delete_object(&self, bucket: &str, key: &str) -> Result<(), EventServerError> {
+        self.client
+            .delete_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| classify_s3_error(&e, "Failed to delete object"))?;
+        Ok(())
+    }
+
+    async fn delete_object_if_match(
+        &self,
+        bucket: &str,
+        key: &str,
+        etag: &str,
+    ) -> Result<bool, EventServerError> {
+        match self
+            .client
+            .delete_object()
+            .bucket(bucket)
+            .key(key)
+            .if_match(etag)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if is_precondition_failure(&e) => Ok(false),
+            Err(e) => Err(classify_s3_error(&e, "Failed to delete object")),
+        }
+    }
+
+    async fn list_objects_after(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        start_after: Option<&str>,
+        max_keys: usize,
+    ) -> Result<Vec<String>, EventServerError> {
+        let mut keys = Vec::new();
+        let mut continuation_token = None;
+
+        // Follow continuation tokens until we have enough keys or the listing is exhausted
+        loop {
+            let response = self
+                .client
+                .list_objects_v2()
+                .bucket(bucket)
+                .prefix(prefix)
+                .set_start_after(start_after.map(str::to_string))
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(|e| classify_s3_error(&e, "Failed to list objects"))?;
+
+            keys.extend(
+                response
+                    .contents()
+                    .iter()
+                    .filter_map(|object| object.key().map(str::to_string)),
+            );
+
+            if keys.len() >= max_keys {
+                keys.truncate(max_keys);
+                break;
+            }
+
+            match response.next_continuation_token() {
+                Some(token) => continuation_token = Some(token.to_string()),
+                None => break,
+            }
+        }
+
+        Ok(keys)
     }
 }
 
-/// Mock S3 client for testing
+/// In-memory mock S3 client for testing
+#[cfg(test)]
+#[derive(Default)]
+pub struct MockS3Client {
+    objects: std::sync::Mutex<std::collections::BTreeMap<String, MockObject>>,
+    put_log: std::sync::Mutex<Vec<String>>,
+    put_delay: Duration,
+    corrupt_uploads: bool,
+    opaque_etags: bool,
+}
+
+/// Object stored by the mock S3 client
+#[cfg(test)]
+#[derive(Clone)]
+pub struct MockObject {
+    pub body: Vec<u8>,
+    pub content_type: String,
+    pub content_encoding: Option<String>,
+    pub etag: String,
+}
+
 #[cfg(test)]
-pub struct MockS3Client;
+impl MockS3Client {
+    /// Snapshot of a stored object, for test assertions
+    pub fn object(&self, key: &str) -> Option<MockObject> {
+        self.objects.lock().unwrap().get(key).cloned()
+    }
+
+    /// Mock whose uploads take `delay`, to widen race windows in tests
+    pub fn with_put_delay(delay: Duration) -> Self {
+        Self {
+            put_delay: delay,
+            ..Self::default()
+        }
+    }
+
+    /// Mock that flips a bit in every stored body, as silent corruption in transit would
+    pub fn with_corrupt_uploads() -> Self {
+        Self {
+            corrupt_uploads: true,
+            ..Self::default()
+        }
+    }
+
+    /// Mock whose ETags aren't MD5s of the body, as with SSE-KMS or SSE-C encryption
+    pub fn with_opaque_etags() -> Self {
+        Self {
+            opaque_etags: true,
+            ..Self::default()
+        }
+    }
+
+    /// Keys of every upload made so far, in order
+    pub fn put_log(&self) -> Vec<String> {
+        self.put_log.lock().unwrap().clone()
+    }
+
+    /// The object as this mock would store the uploaded `body`
+    fn stored_object(
+        &self,
+        mut body: Vec<u8>,
+        content_type: &str,
+        content_encoding: Option<&str>,
+    ) -> MockObject {
+        if self.corrupt_uploads {
+            if let Some(byte) = body.first_mut() {
+                *byte ^= 0x01;
+            }
+        }
+        // Like S3 for single-part uploads, the ETag is the quoted MD5 of what was stored
+        let digest = if self.opaque_etags {
+            Md5::digest(Uuid::new_v4().as_bytes())
+        } else {
+            Md5::digest(&body)
+        };
+        MockObject {
+            body,
+            content_type: content_type.to_string(),
+            content_encoding: content_encoding.map(str::to_string),
+            etag: format!("\"{}\"", hex::encode(digest)),
+        }
+    }
+}
 
 #[cfg(test)]
 #[async_trait::async_trait]
 impl S3Operations for MockS3Client {
-    async fn put_object(
+    async fn put_object_with_encoding(
         &self,
         _bucket: &str,
-        _key: &str,
-        _body: Vec<u8>,
-        _content_type: &str,
-    ) -> Result<(), EventServerError> {
-        // Simulate successful upload
-        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+        content_encoding: Option<&str>,
+    ) -> Result<Option<String>, EventServerError> {
+        tokio::time::sleep(self.put_delay).await;
+        self.put_log.lock().unwrap().push(key.to_string());
+        let object = self.stored_object(body, content_type, content_encoding);
+        let etag = object.etag.clone();
+        self.objects.lock().unwrap().insert(key.to_string(), object);
+        Ok(Some(etag))
+    }
+
+    async fn put_object_if_absent(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+    ) -> Result<bool, EventServerError> {
+        if self.objects.lock().unwrap().contains_key(key) {
+            return Ok(false);
+        }
+        self.put_object(bucket, key, body, content_type).await?;
+        Ok(true)
+    }
+
+    async fn put_object_if_match(
+        &self,
+        _bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+        etag: &str,
+    ) -> Result<bool, EventServerError> {
+        tokio::time::sleep(self.put_delay).await;
+        // Checked and written under one lock, as S3 applies the condition atomically
+        let mut objects = self.objects.lock().unwrap();
+        if objects.get(key).is_none_or(|object| object.etag != etag) {
+            return Ok(false);
+        }
+        self.put_log.lock().unwrap().push(key.to_string());
+        objects.insert(
+            key.to_string(),
+            self.stored_object(body, content_type, None),
+        );
+        Ok(true)
+    }
+
+    async fn head_object(&self, _bucket: &str, key: &str) -> Result<bool, EventServerError> {
+        Ok(self.objects.lock().unwrap().contains_key(key))
+    }
+
+    async fn get_object(&self, _bucket: &str, key: &str) -> Result<Vec<u8>, EventServerError> {
+        self.object(key)
+            .map(|object| object.body)
+            .ok_or_else(|| EventServerError::NotFound(format!("Object not found: {key}")))
+    }
+
+    async fn get_object_range(
+        &self,
+        _bucket: &str,
+        key: &str,
+        range: Option<&str>,
+    ) -> Result<ObjectDownload, EventServerError> {
+        let object = self
+            .object(key)
+            .ok_or_else(|| EventServerError::NotFound(format!("Object not found: {key}")))?;
+
+        let Some(range) = range else {
+            return Ok(ObjectDownload {
+                body: object.body,
+                content_type: Some(object.content_type),
+                content_range: None,
+                etag: Some(object.etag),
+            });
+        };
+
+        let total = object.body.len();
+        let (start, end) = parse_byte_range(range, total).ok_or_else(|| {
+            EventServerError::RangeNotSatisfiable(format!(
+                "Requested range {range} is not satisfiable"
+            ))
+        })?;
+
+        Ok(ObjectDownload {
+            body: object.body[start..=end].to_vec(),
+            content_type: Some(object.content_type),
+            content_range: Some(format!("bytes {start}-{end}/{total}")),
+            etag: Some(object.etag),
+        })
+    }
+
+    async fn delete_object(&self, _bucket: &str, key: &str) -> Result<(), EventServerError> {
+        self.objects.lock().unwrap().remove(key);
         Ok(())
     }
 
-    async fn head_object(&self, _bucket: &str, _key: &str) -> Result<bool, EventServerError> {
-        // Simulate object exists check
-        tokio::time::sleep(tokio::time::Duration::from_millis(5)).await;
+    async fn delete_object_if_match(
+        &self,
+        _bucket: &str,
+        key: &str,
+        etag: &str,
+    ) -> Result<bool, EventServerError> {
+        let mut objects = self.objects.lock().unwrap();
+        if objects.get(key).is_none_or(|object| object.etag != etag) {
+            return Ok(false);
+        }
+        objects.remove(key);
         Ok(true)
     }
 
-    async fn _get_object(&self, _bucket: &str, _key: &str) -> Result<Vec<u8>, EventServerError> {
-        // Return mock data
-        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-        Ok(b"mock_event_data".to_vec())
+    async fn list_objects_after(
+        &self,
+        _bucket: &str,
+        prefix: &str,
+        start_after: Option<&str>,
+        max_keys: usize,
+    ) -> Result<Vec<String>, EventServerError> {
+        Ok(self
+            .objects
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|key| key.starts_with(prefix))
+            .filter(|key| start_after.is_none_or(|after| key.as_str() > after))
+            .take(max_keys)
+            .cloned()
+            .collect())
     }
 }
 
+/// Resolve a single `bytes=` range against an object size, mirroring S3 semantics
+#[cfg(test)]
+fn parse_byte_range(range: &str, total: usize) -> Option<(usize, usize)> {
+    let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+    let last = total.checked_sub(1)?;
+
+    let (start, end) = match (start.parse::<usize>().ok(), end.parse::<usize>().ok()) {
+        (Some(start), Some(end)) => (start, end.min(last)),
+        (Some(start), None) if end.is_empty() => (start, last),
+        (None, Some(suffix)) if start.is_empty() && suffix > 0 => {
+            (total.saturating_sub(suffix), last)
+        }
+        _ => return None,
+    };
+
+    (start <= end).then_some((start, end))
+}
+
 /// Stateless S3-compatible storage service
 /// Handles event storage without maintaining any local state
 #[derive(Clone)]
 pub struct StorageService {
     config: StorageConfig,
     s3_operations: Arc<dyn S3Operations>,
+    circuit_breaker: Option<Arc<CircuitBreakerS3>>, // Wraps `s3_operations` unless disabled
+    in_flight: InFlightLocks, // Serializes concurrent uploads of the same event hash
+    upload_throttle: UploadThrottle, // Caps aggregate upload bandwidth across all requests
+    archive_signer: Option<ArchiveSigner>, // Signs stored ZIP archives when `sign_archives` is on
+    receipt_signer: Option<ArchiveSigner>, // Signs event receipts when `issue_receipts` is on
+    relay_quota: RelayQuota,  // Bytes stored per relay, capped by `per_relay_quota_bytes`
+    key_prefix: String,       // Namespace of event, media and index keys; empty outside tenants
 }
 
 impl StorageService {
@@ -142,27 +666,86 @@ impl StorageService {
             aws_config = aws_config.to_builder().endpoint_url(endpoint).build();
         }
 
-        // Configure path style for MinIO compatibility
+        // Configure path style for MinIO compatibility, and bound each call by the upload timeout
         let s3_config = aws_sdk_s3::config::Builder::from(&aws_config)
             .force_path_style(config.use_path_style)
+            .retry_config(RetryConfig::standard().with_max_attempts(S3_MAX_ATTEMPTS))
+            .timeout_config(upload_timeout_config(config.upload_timeout))
             .build();
 
         let s3_client = S3Client::from_conf(s3_config);
-        let s3_operations = Arc::new(RealS3Client { client: s3_client });
+        let mut s3_operations: Arc<dyn S3Operations> = Arc::new(RealS3Client { client: s3_client });
+        let mut circuit_breaker = None;
+        if config.circuit_breaker_threshold > 0 {
+            let breaker = Arc::new(CircuitBreakerS3::new(
+                s3_operations,
+                config.circuit_breaker_threshold,
+                Duration::from_secs(config.circuit_breaker_cooldown_seconds),
+            ));
+            s3_operations = breaker.clone();
+            circuit_breaker = Some(breaker);
+        }
+
+        // Archives and receipts are signed with the same key, so one JWKS entry verifies both
+        let server_signer = if config.sign_archives || config.issue_receipts {
+            Some(ArchiveSigner::new(config.archive_signing_key.as_deref())?)
+        } else {
+            None
+        };
+        let archive_signer = server_signer.clone().filter(|_| config.sign_archives);
+        let receipt_signer = server_signer.filter(|_| config.issue_receipts);
 
         Ok(Self {
+            upload_throttle: UploadThrottle::new(config.max_upload_bytes_per_sec),
+            archive_signer,
+            receipt_signer,
+            relay_quota: RelayQuota::new(config.per_relay_quota_bytes),
             config,
             s3_operations,
+            circuit_breaker,
+            in_flight: InFlightLocks::default(),
+            key_prefix: String::new(),
         })
     }
 
+    /// View of this storage whose event, media and index keys live under `tenants/{tenant_id}/`
+    /// `None` is the shared namespace used when tenancy is disabled
+    pub fn for_tenant(&self, tenant_id: Option<&str>) -> Self {
+        let mut scoped = self.clone();
+        scoped.key_prefix = tenant_id
+            .map(|tenant_id| format!("{TENANT_PREFIX}{tenant_id}/"))
+            .unwrap_or_default();
+        scoped
+    }
+
+    /// Key within this service's namespace
+    fn scoped(&self, key: impl std::fmt::Display) -> String {
+        format!("{}{key}", self.key_prefix)
+    }
+
+    /// Probe the bucket with a minimal listing to confirm storage is reachable
+    pub async fn check_health(&self) -> Result<(), EventServerError> {
+        self.s3_operations
+            .list_objects(&self.config.bucket, "", 1)
+            .await
+            .map(|_| ())
+    }
+
+    /// State of the storage circuit breaker, or `None` when it's disabled
+    pub fn circuit_breaker_status(&self) -> Option<CircuitBreakerStatus> {
+        self.circuit_breaker
+            .as_ref()
+            .map(|breaker| breaker.status())
+    }
+
     /// Store an event package in S3-compatible storage
-    /// Returns the storage location URL
+    /// Returns the storage location URL, and whether this call wrote it
     pub async fn store_event(
         &self,
         event_package: &EventPackage,
         event_hash: &str,
-    ) -> Result<String, EventServerError> {
+        relay_id: &str,
+    ) -> Result<StoreOutcome, EventServerError> {
         info!(
             event_id = %event_package.id,
             hash = %event_hash,
@@ -170,16 +753,44 @@ impl StorageService {
             "Storing event in S3-compatible storage"
         );
 
-        // Generate a storage key based on hash and timestamp
-        let storage_key = self.generate_storage_key(event_hash, &event_package.id);
+        // Concurrent retries of the same event wait here, then find the stored object
+        let _in_flight = self.in_flight.acquire(event_hash).await;
+        if let Some(location) = self.existing_location(event_hash).await? {
+            return Ok(StoreOutcome::existing(location));
+        }
 
         // Serialize event package for storage
         let event_data = serde_json::to_vec(event_package)
             .map_err(|e| EventServerError::Validation(format!("Failed to serialize event: {e}")))?;
 
-        // Upload to S3
+        // Annotation JSON is repetitive, so gzip it when configured
+        let (event_data, extension, content_encoding) = if self.config.compress_annotations {
+            (gzip(&event_data)?, "json.gz", Some("gzip"))
+        } else {
+            (event_data, "json", None)
+        };
+
+        // Generate a storage key based on hash and timestamp
+        let storage_key =
+            self.generate_storage_key(event_hash, &event_package.id, relay_id, extension);
+
+        // Upload to S3, charged to the relay's quota
         let storage_location = self
-            .upload_to_s3(&storage_key, &event_data, "application/json")
+            .charged_to_relay(relay_id, event_data.len() as u64, async {
+                let location = self
+                    .upload_to_s3_encoded(
+                        &storage_key,
+                        &event_data,
+                        "application/json",
+                        content_encoding,
+                    )
+                    .await?;
+
+                // Like archives, JSON objects are found through a by-hash marker, so they can be
+                // verified, downloaded and deduplicated by hash
+                self.write_hash_marker(event_hash, &storage_key).await?;
+                Ok(location)
+            })
             .await?;
 
         info!(
@@ -189,26 +800,30 @@ impl StorageService {
             "Event stored successfully"
         );
 
-        Ok(storage_location)
+        Ok(StoreOutcome::written(storage_location))
     }
 
     /// Retrieve an event package from storage by hash
-    pub async fn _retrieve_event(
-        &self,
-        event_hash: &str,
-    ) -> Result<EventPackage, EventServerError> {
+    pub async fn retrieve_event(&self, event_hash: &str) -> Result<EventPackage, EventServerError> {
         info!(hash = %event_hash, "Retrieving event from storage");
 
-        // Generate storage key
-        let storage_key = self.generate_storage_key_from_hash(event_hash);
-
-        // Simulate S3 retrieval
-        let event_data = self._simulate_s3_download(&storage_key).await?;
+        // Resolve the primary object key through the by-hash marker
+        let storage_key = self.resolve_primary_key(event_hash).await?;
+
+        let event_data = if self.config.verify_content_type {
+            let download = self
+                .s3_operations
+                .get_object_range(&self.config.bucket, &storage_key, None)
+                .await?;
+            self.check_content_type(&storage_key, download.content_type.as_deref());
+            download.body
+        } else {
+            self.s3_operations
+                .get_object(&self.config.bucket, &storage_key)
+                .await?
+        };
 
-        // Deserialize event package
-        let event_package: EventPackage = serde_json::from_slice(&event_data).map_err(|e| {
-            EventServerError::Validation(format!("Failed to deserialize event: {e}"))
-        })?;
+        let event_package = decode_event_object(&storage_key, &event_data)?;
 
         info!(
             event_id = %event_package.id,
@@ -219,44 +834,708 @@ impl StorageService {
         Ok(event_package)
     }
 
-    /// Check if an event exists in storage
-    pub async fn event_exists(&self, event_hash: &str) -> Result<bool, EventServerError> {
-        let storage_key = self.generate_storage_key_from_hash(event_hash);
+    /// Delete an event and its by-hash marker from storage
+    #[cfg(all(test, feature = "integration"))]
+    pub async fn delete_event(&self, event_hash: &str) -> Result<(), EventServerError> {
+        let storage_key = self.resolve_primary_key(event_hash).await?;
+        let marker_key = self.generate_storage_key_from_hash(event_hash);
 
-        // Simulate S3 head object operation
-        let exists = self.simulate_s3_exists(&storage_key).await?;
+        self.s3_operations
+            .delete_object(&self.config.bucket, &storage_key)
+            .await?;
+        self.s3_operations
+            .delete_object(&self.config.bucket, &marker_key)
+            .await?;
 
         info!(
             hash = %event_hash,
-            exists = exists,
-            "Checked event existence in storage"
+            key = %storage_key,
+            "Event deleted from storage"
         );
 
-        Ok(exists)
+        Ok(())
     }
 
-    /// Get storage statistics
-    pub async fn _get_storage_stats(&self) -> Result<StorageStats, EventServerError> {
-        // In a real implementation, this would query S3 for bucket statistics
-        Ok(StorageStats {
-            total_objects: 0,
-            total_size_bytes: 0,
-            bucket_name: self.config.bucket.clone(),
-            region: self.config.region.clone(),
-        })
+    /// List the primary event object keys stored between two dates (inclusive)
+    /// Returns at most `limit` keys, in date order
+    pub async fn list_event_keys(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        limit: usize,
+    ) -> Result<Vec<String>, EventServerError> {
+        let mut keys = Vec::new();
+
+        for day in from.iter_days().take_while(|day| *day <= to) {
+            if keys.len() >= limit {
+                break;
+            }
+
+            // Only the date layout encodes the day in the key; otherwise use the daily index
+            if self.config.key_layout != StorageLayout::DateHierarchy {
+                keys.extend(
+                    self.read_index_page(day, 0, limit - keys.len())
+                        .await?
+                        .into_iter()
+                        .map(|entry| entry.key)
+                        .filter(|key| is_event_json(key))
+                        .take(limit - keys.len()),
+                );
+                continue;
+            }
+
+            let prefix = self.scoped(format_args!("events/{}/", day.format("%Y/%m/%d")));
+            let day_keys = self
+                .s3_operations
+                .list_objects(&self.config.bucket, &prefix, limit - keys.len())
+                .await?;
+
+            keys.extend(day_keys.into_iter().filter(|key| is_event_json(key)));
+        }
+
+        info!(
+            from = %from,
+            to = %to,
+            count = keys.len(),
+            "Listed stored event keys"
+        );
+
+        Ok(keys)
+    }
+
+    /// Fetch and deserialize an event package by its primary object key
+    pub async fn get_event_by_key(&self, key: &str) -> Result<EventPackage, EventServerError> {
+        let event_data = self
+            .s3_operations
+            .get_object(&self.config.bucket, key)
+            .await?;
+
+        decode_event_object(key, &event_data)
+    }
+
+    /// Check if an event exists in storage
+    pub async fn event_exists(&self, event_hash: &str) -> Result<bool, EventServerError> {
+        let storage_key = self.generate_storage_key_from_hash(event_hash);
+
+        // Simulate S3 head object operation
+        let exists = self.simulate_s3_exists(&storage_key).await?;
+
+        info!(
+            hash = %event_hash,
+            exists = exists,
+            "Checked event existence in storage"
+        );
+
+        Ok(exists)
+    }
+
+    /// Point the by-hash marker back at the stored object whose recomputed hash is `event_hash`
+    /// The marker's current target and the flat `events/{hash}.*` keys are checked first;
+    /// otherwise event objects whose key could belong to the hash are scanned, under the
+    /// month of `stored_on` when given. `hash_of` is the event pipeline's hash function
+    pub async fn reindex_event(
+        &self,
+        event_hash: &str,
+        stored_on: Option<NaiveDate>,
+        hash_of: impl Fn(&EventPackage) -> Result<String, EventServerError>,
+    ) -> Result<ReindexOutcome, EventServerError> {
+        self.reindex_event_scanning(event_hash, stored_on, REINDEX_SCAN_LIMIT, hash_of)
+            .await
+    }
+
+    async fn reindex_event_scanning(
+        &self,
+        event_hash: &str,
+        stored_on: Option<NaiveDate>,
+        scan_limit: usize,
+        hash_of: impl Fn(&EventPackage) -> Result<String, EventServerError>,
+    ) -> Result<ReindexOutcome, EventServerError> {
+        let _in_flight = self.in_flight.acquire(event_hash).await;
+
+        let current = match self.resolve_primary_key(event_hash).await {
+            Ok(key) => Some(key),
+            Err(EventServerError::NotFound(_) | EventServerError::Storage(_)) => None,
+            Err(e) => return Err(e),
+        };
+        if let Some(key) = current {
+            if self.stored_hash_matches(&key, event_hash, &hash_of).await? {
+                return Ok(ReindexOutcome {
+                    key,
+                    repaired: false,
+                });
+            }
+        }
+
+        // Flat keys are named after the hash, so they're probed without listing
+        for extension in ["zip", "json", "json.gz"] {
+            let key = self.scoped(format_args!("events/{event_hash}.{extension}"));
+            if self.stored_hash_matches(&key, event_hash, &hash_of).await? {
+                return self.rewrite_hash_marker(event_hash, key).await;
+            }
+        }
+
+        let prefix = self.scoped(match (self.config.key_layout, stored_on) {
+            (StorageLayout::DateHierarchy, Some(date)) => {
+                format!("events/{}/", date.format("%Y/%m"))
+            }
+            (StorageLayout::RelayHierarchy, _) => "events/relays/".to_string(),
+            _ => "events/".to_string(),
+        });
+        let marker_prefix = self.scoped("events/by-hash/");
+        let mut scanned = 0;
+        let mut after: Option<String> = None;
+        loop {
+            let requested = REINDEX_PAGE_SIZE.min(scan_limit - scanned);
+            let page = self
+                .s3_operations
+                .list_objects_after(&self.config.bucket, &prefix, after.as_deref(), requested)
+                .await?;
+            scanned += page.len();
+            let exhausted = page.len() < requested;
+            after = page.last().cloned();
+
+            let candidates = page
+                .into_iter()
+                .filter(|key| !key.starts_with(&marker_prefix) && may_hold_hash(key, event_hash));
+            for key in candidates {
+                if self.stored_hash_matches(&key, event_hash, &hash_of).await? {
+                    return self.rewrite_hash_marker(event_hash, key).await;
+                }
+            }
+
+            if exhausted {
+                return Err(EventServerError::NotFound(format!(
+                    "No stored event matches hash {event_hash}"
+                )));
+            }
+            if scanned >= scan_limit {
+                return Err(EventServerError::ScanTruncated { scanned });
+            }
+        }
+    }
+
+    async fn rewrite_hash_marker(
+        &self,
+        event_hash: &str,
+        key: String,
+    ) -> Result<ReindexOutcome, EventServerError> {
+        self.write_hash_marker(event_hash, &key).await?;
+        warn!(hash = %event_hash, key = %key, "Rewrote by-hash marker");
+        Ok(ReindexOutcome {
+            key,
+            repaired: true,
+        })
+    }
+
+    /// Whether the object at `key` decodes to an event with hash `event_hash`
+    async fn stored_hash_matches(
+        &self,
+        key: &str,
+        event_hash: &str,
+        hash_of: impl Fn(&EventPackage) -> Result<String, EventServerError>,
+    ) -> Result<bool, EventServerError> {
+        let data = match self
+            .s3_operations
+            .get_object(&self.config.bucket, key)
+            .await
+        {
+            Ok(data) => data,
+            Err(EventServerError::NotFound(_)) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        match decode_event_object(key, &data) {
+            Ok(event_package) => Ok(hash_of(&event_package)? == event_hash),
+            Err(e) => {
+                warn!(key = %key, error = %e, "Skipping undecodable object during reindex");
+                Ok(false)
+            }
+        }
+    }
+
+    /// Bring event objects stored under an older key scheme into the current one
+    /// Objects without a by-hash marker get one, and objects outside the configured layout are
+    /// copied to it and the old copy removed. Progress is checkpointed in storage, so an
+    /// interrupted run resumes where it stopped and a finished one is a no-op until the layout
+    /// changes again. Covers this service's namespace only; instances sharing it take a lease,
+    /// so only one of them migrates at a time and the others skip
+    pub async fn migrate_legacy_keys(
+        &self,
+        hash_of: impl Fn(&EventPackage) -> Result<String, EventServerError>,
+    ) -> Result<MigrationReport, EventServerError> {
+        let progress_key = self.scoped(MIGRATION_PROGRESS_KEY);
+        if self
+            .load_migration_progress(&progress_key)
+            .await?
+            .completed_layout
+            == Some(self.config.key_layout)
+        {
+            info!(layout = ?self.config.key_layout, "Storage keys already migrated");
+            return Ok(MigrationReport::default());
+        }
+
+        let lease_key = self.scoped(MIGRATION_LEASE_KEY);
+        let Some(lease) = self.acquire_migration_lease(&lease_key).await? else {
+            info!("Storage key migration is running on another instance, skipping it here");
+            return Ok(MigrationReport::default());
+        };
+        // Another instance may have finished between the first look and taking the lease
+        let mut progress = self.load_migration_progress(&progress_key).await?;
+        if progress.completed_layout == Some(self.config.key_layout) {
+            self.release_migration_lease(&lease_key, &lease).await;
+            return Ok(MigrationReport::default());
+        }
+        info!(resumed_after = ?progress.last_key, "Migrating legacy storage keys");
+
+        let result = self
+            .migrate_pages(&progress_key, &mut progress, &lease_key, &lease, &hash_of)
+            .await;
+        // Progress now belongs to whichever instance took the lease over
+        if let Err(EventServerError::Conflict(_)) = &result {
+            return result;
+        }
+        if result.is_ok() {
+            progress.completed_layout = Some(self.config.key_layout);
+            progress.last_key = None;
+        }
+        // Saved on failure too, so the next run resumes where this one stopped
+        let saved = self.save_migration_progress(&progress_key, &progress).await;
+        self.release_migration_lease(&lease_key, &lease).await;
+        let report = result?;
+        saved?;
+        info!(
+            indexed = report.indexed,
+            moved = report.moved,
+            skipped = report.skipped,
+            "Storage key migration complete"
+        );
+        Ok(report)
+    }
+
+    /// Walk the event keys in key order after the last handled one until the listing runs out,
+    /// checkpointing progress and renewing the lease as it goes
+    async fn migrate_pages(
+        &self,
+        progress_key: &str,
+        progress: &mut MigrationProgress,
+        lease_key: &str,
+        lease: &MigrationLease,
+        hash_of: impl Fn(&EventPackage) -> Result<String, EventServerError>,
+    ) -> Result<MigrationReport, EventServerError> {
+        let events_prefix = self.scoped("events/");
+        let marker_prefix = self.scoped("events/by-hash/");
+        let mut report = MigrationReport::default();
+        let mut since_checkpoint = 0;
+        loop {
+            let page = self
+                .s3_operations
+                .list_objects_after(
+                    &self.config.bucket,
+                    &events_prefix,
+                    progress.last_key.as_deref(),
+                    MIGRATION_PAGE_SIZE,
+                )
+                .await?;
+            let exhausted = page.len() < MIGRATION_PAGE_SIZE;
+
+            for key in page {
+                if !key.starts_with(&marker_prefix) && event_extension(&key).is_some() {
+                    self.migrate_object(&key, &hash_of, &mut report).await?;
+                    since_checkpoint += 1;
+                }
+                progress.last_key = Some(key);
+                if since_checkpoint == MIGRATION_CHECKPOINT_INTERVAL {
+                    self.renew_migration_lease(lease_key, lease).await?;
+                    self.save_migration_progress(progress_key, progress).await?;
+                    since_checkpoint = 0;
+                }
+            }
+            if exhausted {
+                return Ok(report);
+            }
+        }
+    }
+
+    /// Index and, if needed, relocate one event object
+    async fn migrate_object(
+        &self,
+        key: &str,
+        hash_of: impl Fn(&EventPackage) -> Result<String, EventServerError>,
+        report: &mut MigrationReport,
+    ) -> Result<(), EventServerError> {
+        let Some(extension) = event_extension(key) else {
+            return Ok(());
+        };
+        let data = self
+            .s3_operations
+            .get_object(&self.config.bucket, key)
+            .await?;
+        let event_package = match decode_event_object(key, &data) {
+            Ok(event_package) => event_package,
+            Err(e) => {
+                warn!(key = %key, error = %e, "Skipping undecodable object during migration");
+                report.skipped += 1;
+                return Ok(());
+            }
+        };
+        let event_hash = hash_of(&event_package)?;
+        let _in_flight = self.in_flight.acquire(&event_hash).await;
+
+        let in_layout = self.fits_layout(key, &event_hash, extension);
+        match self.resolve_primary_key(&event_hash).await {
+            // Already indexed, here or at another copy of the same event
+            Ok(primary) if primary == key && in_layout => return Ok(()),
+            Ok(primary) if primary != key => {
+                if self
+                    .s3_operations
+                    .head_object(&self.config.bucket, &primary)
+                    .await?
+                {
+                    report.skipped += 1;
+                    return Ok(());
+                }
+            }
+            Ok(_) | Err(EventServerError::NotFound(_) | EventServerError::Storage(_)) => {}
+            Err(e) => return Err(e),
+        }
+
+        let target = if in_layout {
+            key.to_string()
+        } else {
+            self.layout_key(key, &event_hash, &event_package, extension)
+        };
+        if target != key {
+            let (content_type, content_encoding) = match extension {
+                "zip" => ("application/zip", None),
+                "json.gz" => ("application/json", Some("gzip")),
+                _ => ("application/json", None),
+            };
+            self.upload_to_s3_encoded(&target, &data, content_type, content_encoding)
+                .await?;
+        }
+
+        self.write_hash_marker(&event_hash, &target).await?;
+        report.indexed += 1;
+
+        if target != key {
+            // A signed archive's detached signature and any receipt move with it
+            for (suffix, content_type) in [
+                (ARCHIVE_SIGNATURE_SUFFIX, "application/octet-stream"),
+                (RECEIPT_SUFFIX, "application/json"),
+            ] {
+                let sidecar_key = format!("{key}{suffix}");
+                match self
+                    .s3_operations
+                    .get_object(&self.config.bucket, &sidecar_key)
+                    .await
+                {
+                    Ok(sidecar) => {
+                        self.upload_to_s3(&format!("{target}{suffix}"), &sidecar, content_type)
+                            .await?;
+                        self.s3_operations
+                            .delete_object(&self.config.bucket, &sidecar_key)
+                            .await?;
+                    }
+                    Err(EventServerError::NotFound(_)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            self.s3_operations
+                .delete_object(&self.config.bucket, key)
+                .await?;
+            report.moved += 1;
+        }
+
+        info!(hash = %event_hash, from = %key, to = %target, "Migrated event object");
+        Ok(())
+    }
+
+    async fn load_migration_progress(
+        &self,
+        progress_key: &str,
+    ) -> Result<MigrationProgress, EventServerError> {
+        match self
+            .s3_operations
+            .get_object(&self.config.bucket, progress_key)
+            .await
+        {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(EventServerError::NotFound(_)) => Ok(MigrationProgress::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn save_migration_progress(
+        &self,
+        progress_key: &str,
+        progress: &MigrationProgress,
+    ) -> Result<(), EventServerError> {
+        let body = serde_json::to_vec(progress)?;
+        self.upload_to_s3(progress_key, &body, "application/json")
+            .await
+            .map(|_| ())
+    }
+
+    /// Take the migration lease, or `None` while another instance holds a live one
+    /// A lease past its expiry was left by an instance that stopped mid-run and is taken over
+    async fn acquire_migration_lease(
+        &self,
+        lease_key: &str,
+    ) -> Result<Option<MigrationLease>, EventServerError> {
+        let lease = MigrationLease::new();
+        let body = serde_json::to_vec(&lease)?;
+        for _ in 0..2 {
+            if self
+                .s3_operations
+                .put_object_if_absent(
+                    &self.config.bucket,
+                    lease_key,
+                    body.clone(),
+                    "application/json",
+                )
+                .await?
+            {
+                return Ok(Some(lease));
+            }
+            // Released in between
+            let Some((held, etag)) = self.read_migration_lease(lease_key).await? else {
+                continue;
+            };
+            if held.expires_at > Utc::now() {
+                return Ok(None);
+            }
+            // Replacing only the expired lease that was read lets exactly one of several
+            // instances taking it over at once succeed; the others find the new live lease
+            if self
+                .s3_operations
+                .put_object_if_match(
+                    &self.config.bucket,
+                    lease_key,
+                    body.clone(),
+                    "application/json",
+                    &etag,
+                )
+                .await?
+            {
+                warn!(holder = %held.holder, "Took over an expired storage key migration lease");
+                return Ok(Some(lease));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The current lease and the ETag it is stored under, `None` when no one holds it
+    async fn read_migration_lease(
+        &self,
+        lease_key: &str,
+    ) -> Result<Option<(MigrationLease, String)>, EventServerError> {
+        let download = match self
+            .s3_operations
+            .get_object_range(&self.config.bucket, lease_key, None)
+            .await
+        {
+            Ok(download) => download,
+            Err(EventServerError::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let etag = download.etag.ok_or_else(|| {
+            EventServerError::Storage("Storage key migration lease has no ETag".to_string())
+        })?;
+        Ok(Some((serde_json::from_slice(&download.body)?, etag)))
+    }
+
+    /// Extend the lease this run holds, failing with `Conflict` once another instance has
+    /// taken it over, so two instances never migrate at the same time
+    async fn renew_migration_lease(
+        &self,
+        lease_key: &str,
+        lease: &MigrationLease,
+    ) -> Result<(), EventServerError> {
+        let renewed = match self.read_migration_lease(lease_key).await? {
+            Some((held, etag)) if held.holder == lease.holder => {
+                self.s3_operations
+                    .put_object_if_match(
+                        &self.config.bucket,
+                        lease_key,
+                        serde_json::to_vec(&lease.renewed())?,
+                        "application/json",
+                        &etag,
+                    )
+                    .await?
+            }
+            _ => false,
+        };
+        if !renewed {
+            warn!(holder = %lease.holder, "Lost the storage key migration lease, stopping");
+            return Err(EventServerError::Conflict(
+                "Storage key migration lease was taken over by another instance".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    async fn write_migration_lease(
+        &self,
+        lease_key: &str,
+        lease: &MigrationLease,
+    ) -> Result<(), EventServerError> {
+        let body = serde_json::to_vec(lease)?;
+        self.upload_to_s3(lease_key, &body, "application/json")
+            .await
+            .map(|_| ())
+    }
+
+    /// Drop the lease unless another instance has taken it over; if that fails it lapses on
+    /// its own at expiry
+    async fn release_migration_lease(&self, lease_key: &str, lease: &MigrationLease) {
+        let released = match self.read_migration_lease(lease_key).await {
+            Ok(Some((held, etag))) if held.holder == lease.holder => self
+                .s3_operations
+                .delete_object_if_match(&self.config.bucket, lease_key, &etag)
+                .await
+                .map(|_| ()),
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = released {
+            warn!(error = %e, "Failed to release the storage key migration lease");
+        }
+    }
+
+    /// Whether `key` is where the configured layout would put this event's object
+    fn fits_layout(&self, key: &str, event_hash: &str, extension: &str) -> bool {
+        let Some(rest) = key.strip_prefix(&self.scoped("events/")) else {
+            return false;
+        };
+        let segments: Vec<&str> = rest.split('/').collect();
+        let hashed_name = format!("{event_hash}.{extension}");
+        let digits = |segment: &str, len: usize| {
+            segment.len() == len && segment.bytes().all(|b| b.is_ascii_digit())
+        };
+
+        match self.config.key_layout {
+            StorageLayout::Flat => segments == [hashed_name.as_str()],
+            StorageLayout::RelayHierarchy => {
+                segments.len() == 6
+                    && segments[0] == "relays"
+                    && digits(segments[2], 4)
+                    && digits(segments[3], 2)
+                    && digits(segments[4], 2)
+                    && segments[5] == hashed_name
+            }
+            StorageLayout::DateHierarchy if extension == "zip" => {
+                segments.len() == 3
+                    && digits(segments[0], 4)
+                    && digits(segments[1], 2)
+                    && segments[2] == hashed_name
+            }
+            StorageLayout::DateHierarchy => {
+                segments.len() == 5
+                    && digits(segments[0], 4)
+                    && digits(segments[1], 2)
+                    && digits(segments[2], 2)
+                    && Some(segments[3]) == event_hash.get(..8)
+                    && segments[4].ends_with(&format!(".{extension}"))
+            }
+        }
+    }
+
+    /// Key of an event object under the configured layout, dated by the event's creation
+    /// The relay layout keeps the relay of an object already filed under `events/relays/`;
+    /// other legacy objects don't record one and go under `unknown`
+    fn layout_key(
+        &self,
+        key: &str,
+        event_hash: &str,
+        event_package: &EventPackage,
+        extension: &str,
+    ) -> String {
+        let created_at = event_package.metadata.created_at;
+        self.scoped(match self.config.key_layout {
+            StorageLayout::DateHierarchy if extension == "zip" => format!(
+                "events/{}/{event_hash}.{extension}",
+                created_at.format("%Y/%m")
+            ),
+            StorageLayout::DateHierarchy => format!(
+                "events/{}/{}/{}.{extension}",
+                created_at.format("%Y/%m/%d"),
+                &event_hash[..8],
+                event_package.id
+            ),
+            StorageLayout::Flat => format!("events/{event_hash}.{extension}"),
+            StorageLayout::RelayHierarchy => format!(
+                "events/relays/{}/{}/{event_hash}.{extension}",
+                key.strip_prefix(&self.scoped("events/relays/"))
+                    .and_then(|rest| rest.split_once('/'))
+                    .map_or_else(|| path_segment(""), |(relay, _)| path_segment(relay)),
+                created_at.format("%Y/%m/%d")
+            ),
+        })
+    }
+
+    /// Drop the by-hash marker, as an interrupted write would leave it
+    #[cfg(test)]
+    pub async fn remove_hash_marker(&self, event_hash: &str) {
+        let marker_key = self.generate_storage_key_from_hash(event_hash);
+        self.s3_operations
+            .delete_object(&self.config.bucket, &marker_key)
+            .await
+            .unwrap();
     }
 
     /// Generate a storage key for an event
-    fn generate_storage_key(&self, event_hash: &str, event_id: &Uuid) -> String {
+    fn generate_storage_key(
+        &self,
+        event_hash: &str,
+        event_id: &Uuid,
+        relay_id: &str,
+        extension: &str,
+    ) -> String {
+        if self.config.key_layout != StorageLayout::DateHierarchy {
+            return self.scoped(
+                self.config
+                    .generate_event_key(event_hash, relay_id, extension),
+            );
+        }
+
         let date = Utc::now().format("%Y/%m/%d");
-        format!("events/{}/{}/{}.json", date, &event_hash[..8], event_id)
+        self.scoped(format_args!(
+            "events/{}/{}/{}.{}",
+            date,
+            &event_hash[..8],
+            event_id,
+            extension
+        ))
     }
 
     /// Generate a storage key from hash only (for retrieval)
     fn generate_storage_key_from_hash(&self, event_hash: &str) -> String {
-        // In a real implementation, we might need to search or maintain an index
-        // For now, we'll use a simplified approach
-        format!("events/by-hash/{event_hash}.json")
+        // The by-hash object is a marker whose body is the primary object key
+        self.scoped(format_args!("events/by-hash/{event_hash}.json"))
+    }
+
+    /// Point the by-hash marker of `event_hash` at the primary object `primary_key`
+    async fn write_hash_marker(
+        &self,
+        event_hash: &str,
+        primary_key: &str,
+    ) -> Result<(), EventServerError> {
+        let marker_key = self.generate_storage_key_from_hash(event_hash);
+        self.upload_to_s3(&marker_key, primary_key.as_bytes(), "text/plain")
+            .await?;
+        Ok(())
+    }
+
+    /// Read the by-hash marker and return the primary object key it points to
+    async fn resolve_primary_key(&self, event_hash: &str) -> Result<String, EventServerError> {
+        let marker_key = self.generate_storage_key_from_hash(event_hash);
+        let marker = self
+            .s3_operations
+            .get_object(&self.config.bucket, &marker_key)
+            .await?;
+
+        String::from_utf8(marker)
+            .map_err(|e| EventServerError::Storage(format!("Invalid by-hash marker: {e}")))
     }
 
     /// Upload data to S3
@@ -266,9 +1545,32 @@ impl StorageService {
         data: &[u8],
         content_type: &str,
     ) -> Result<String, EventServerError> {
-        self.s3_operations
-            .put_object(&self.config.bucket, key, data.to_vec(), content_type)
+        self.upload_to_s3_encoded(key, data, content_type, None)
+            .await
+    }
+
+    /// Upload data to S3 with an optional `Content-Encoding`
+    async fn upload_to_s3_encoded(
+        &self,
+        key: &str,
+        data: &[u8],
+        content_type: &str,
+        content_encoding: Option<&str>,
+    ) -> Result<String, EventServerError> {
+        self.upload_throttle.acquire(data.len() as u64).await;
+        let etag = self
+            .s3_operations
+            .put_object_with_encoding(
+                &self.config.bucket,
+                key,
+                data.to_vec(),
+                content_type,
+                content_encoding,
+            )
             .await?;
+        if self.config.verify_after_upload {
+            self.verify_upload(key, data, etag.as_deref()).await?;
+        }
 
         info!(
             bucket = %self.config.bucket,
@@ -278,7 +1580,48 @@ impl StorageService {
         );
 
         // Return S3 URL
-        Ok(format!(
+        Ok(self.storage_location(key))
+    }
+
+    /// Confirm a just-uploaded object holds `data`
+    /// A matching MD5 ETag settles it. Anything else — multipart or SSE-KMS/SSE-C ETags, which
+    /// aren't MD5s, or no ETag at all — is settled by fetching the object back and comparing
+    /// SHA-256. A mismatch is an error; the object is left for the caller to overwrite or remove
+    async fn verify_upload(
+        &self,
+        key: &str,
+        data: &[u8],
+        etag: Option<&str>,
+    ) -> Result<(), EventServerError> {
+        let etag = etag.map(|etag| etag.trim_matches('"'));
+        let md5_matches =
+            etag.is_some_and(|etag| etag.eq_ignore_ascii_case(&hex::encode(Md5::digest(data))));
+        if md5_matches {
+            return Ok(());
+        }
+        let stored = self
+            .s3_operations
+            .get_object(&self.config.bucket, key)
+            .await?;
+        if Sha256::digest(&stored) == Sha256::digest(data) {
+            return Ok(());
+        }
+
+        warn!(
+            bucket = %self.config.bucket,
+            key = %key,
+            etag = ?etag,
+            size = data.len(),
+            "Stored object does not match the uploaded bytes"
+        );
+        Err(EventServerError::Storage(format!(
+            "Integrity check failed for {key}: stored object does not match the uploaded bytes"
+        )))
+    }
+
+    /// Storage location reported to clients for an object key
+    fn storage_location(&self, key: &str) -> String {
+        format!(
             "{} {} {} {} {}",
             self.clone()
                 .config
@@ -288,28 +1631,62 @@ impl StorageService {
             self.config.bucket,
             self.config.region,
             key
-        ))
+        )
     }
 
-    /// Upload a ZIP file to S3 and return the storage location
+    /// Location of an already stored event, if one exists for this hash
+    /// Callers must hold the in-flight lock for the hash so the answer can't go stale
+    async fn existing_location(
+        &self,
+        event_hash: &str,
+    ) -> Result<Option<String>, EventServerError> {
+        if !self.event_exists(event_hash).await? {
+            return Ok(None);
+        }
+        let storage_key = self.resolve_primary_key(event_hash).await?;
+        info!(hash = %event_hash, key = %storage_key, "Event already stored, skipping upload");
+        Ok(Some(self.storage_location(&storage_key)))
+    }
+
+    /// Upload a ZIP file to S3 and return the storage location, and whether this call wrote it
+    /// Also writes the by-hash marker so the archive can be verified and downloaded by hash
     pub async fn upload_zip_file(
         &self,
         event_package: &EventPackage,
+        event_hash: &str,
+        relay_id: &str,
         zip_data: &[u8],
-    ) -> Result<String, EventServerError> {
-        // Generate storage key for ZIP file
-        let event_hash = format!(
-            "{:x}",
-            sha2::Sha256::digest(serde_json::to_string(event_package).map_err(|e| {
-                EventServerError::Storage(format!("Failed to serialize for hash: {e}"))
-            })?)
-        );
+    ) -> Result<StoreOutcome, EventServerError> {
+        // Concurrent retries of the same event wait here, then find the stored object
+        let _in_flight = self.in_flight.acquire(event_hash).await;
+        if let Some(location) = self.existing_location(event_hash).await? {
+            return Ok(StoreOutcome::existing(location));
+        }
 
-        let storage_key = self.config.generate_event_key(&event_hash, "zip");
+        // Generate storage key for ZIP file
+        let storage_key = self.scoped(self.config.generate_event_key(event_hash, relay_id, "zip"));
 
-        // Upload ZIP file to S3
+        // Upload ZIP file to S3, charged to the relay's quota
         let storage_location = self
-            .upload_to_s3(&storage_key, zip_data, "application/zip")
+            .charged_to_relay(relay_id, zip_data.len() as u64, async {
+                let location = self
+                    .upload_to_s3(&storage_key, zip_data, "application/zip")
+                    .await?;
+
+                // The signature goes up before the marker, so an archive found by hash always has one
+                if let Some(signer) = &self.archive_signer {
+                    let signature = serde_json::to_vec(&signer.sign(zip_data))?;
+                    self.upload_to_s3(
+                        &format!("{storage_key}{ARCHIVE_SIGNATURE_SUFFIX}"),
+                        &signature,
+                        "application/json",
+                    )
+                    .await?;
+                }
+
+                self.write_hash_marker(event_hash, &storage_key).await?;
+                Ok(location)
+            })
             .await?;
 
         info!(
@@ -319,79 +1696,1176 @@ impl StorageService {
             "Successfully uploaded ZIP file to S3"
         );
 
-        Ok(storage_location)
+        Ok(StoreOutcome::written(storage_location))
     }
 
-    /// Simulate S3 download operation
-    async fn _simulate_s3_download(&self, _key: &str) -> Result<Vec<u8>, EventServerError> {
-        // In a real implementation, this would use AWS SDK:
-        // let result = self.s3_client
-        //     .get_object()
-        //     .bucket(&self.config.bucket)
-        //     .key(key)
-        //     .send()
-        //     .await?;
-
-        // Simulate network delay
-        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+    /// Run an upload of `bytes` on behalf of `relay_id`, refusing it past the relay's quota
+    /// Deduplicated events never get here, so storing the same event twice is charged once.
+    /// With a quota configured, usage is loaded from storage before the relay's first upload
+    /// and written back after each one; instances sharing a bucket may briefly undercount
+    async fn charged_to_relay<T>(
+        &self,
+        relay_id: &str,
+        bytes: u64,
+        upload: impl std::future::Future<Output = Result<T, EventServerError>>,
+    ) -> Result<T, EventServerError> {
+        if self.relay_quota.is_enforced() && self.relay_quota.needs_seed(relay_id) {
+            let stored = self.load_relay_usage(relay_id).await?;
+            self.relay_quota.seed(relay_id, stored);
+        }
+        if let Err(e) = self.relay_quota.reserve(relay_id, bytes) {
+            warn!(relay_id = %relay_id, bytes, error = %e, "Rejecting upload over relay storage quota");
+            return Err(e);
+        }
+        let result = upload.await;
+        if result.is_err() {
+            self.relay_quota.release(relay_id, bytes);
+        } else if self.relay_quota.is_enforced() {
+            if let Err(e) = self.save_relay_usage(relay_id).await {
+                warn!(relay_id = %relay_id, error = %e, "Failed to persist relay storage usage");
+            }
+        }
+        result
+    }
 
-        // For simulation, return empty data (would be actual event data)
-        Err(EventServerError::Storage(
-            "Simulated storage - not implemented".to_string(),
-        ))
+    /// Bytes persisted as stored by `relay_id`; zero if nothing was recorded yet
+    async fn load_relay_usage(&self, relay_id: &str) -> Result<u64, EventServerError> {
+        match self
+            .s3_operations
+            .get_object(&self.config.bucket, &relay_usage_key(relay_id))
+            .await
+        {
+            Ok(body) => Ok(serde_json::from_slice::<RelayUsage>(&body)?.used_bytes),
+            Err(EventServerError::NotFound(_)) => Ok(0),
+            Err(e) => Err(e),
+        }
     }
 
-    /// Check if object exists in S3
-    async fn simulate_s3_exists(&self, key: &str) -> Result<bool, EventServerError> {
+    async fn save_relay_usage(&self, relay_id: &str) -> Result<(), EventServerError> {
+        let usage = RelayUsage {
+            used_bytes: self.relay_quota.used(relay_id),
+        };
         self.s3_operations
-            .head_object(&self.config.bucket, key)
+            .put_object(
+                &self.config.bucket,
+                &relay_usage_key(relay_id),
+                serde_json::to_vec(&usage)?,
+                "application/json",
+            )
             .await
     }
 
-    /// Create a mock instance for testing
-    #[cfg(test)]
-    pub async fn new_mock() -> Self {
-        use crate::config::storage::StorageConfig;
+    /// Write a debug capture of a failed request under `debug/{date}/{correlation_id}.json`
+    /// Returns the storage key
+    pub async fn save_debug_capture<T: serde::Serialize>(
+        &self,
+        correlation_id: &str,
+        capture: &T,
+    ) -> Result<String, EventServerError> {
+        let key = format!(
+            "{DEBUG_CAPTURE_PREFIX}{}/{}.json",
+            Utc::now().format("%Y-%m-%d"),
+            path_segment(correlation_id)
+        );
+        let body = serde_json::to_vec(capture)?;
+        self.s3_operations
+            .put_object(&self.config.bucket, &key, body, "application/json")
+            .await?;
+        Ok(key)
+    }
 
-        let config = StorageConfig {
-            endpoint: None,
-            region: "us-east-1".to_string(),
-            bucket: "test-bucket".to_string(),
-            access_key_id: "test-key".to_string(),
-            secret_access_key: "test-secret".to_string(),
-            use_path_style: false,
-            enable_ssl: true,
-            upload_timeout: 300,
-            max_file_size: 100 * 1024 * 1024,
-            allowed_mime_types: vec![
-                "image/jpeg".to_string(),
-                "image/png".to_string(),
-                "application/json".to_string(),
-            ],
-        };
+    /// Persist a device certificate so other instances and restarts can load it
+    pub async fn save_certificate(
+        &self,
+        certificate: &DeviceCertificate,
+    ) -> Result<(), EventServerError> {
+        let key = certificate_key(&certificate.certificate_id);
+        let body = serde_json::to_vec(certificate)?;
+        self.s3_operations
+            .put_object(&self.config.bucket, &key, body, "application/json")
+            .await
+    }
 
-        let s3_operations = Arc::new(MockS3Client);
+    /// Persist a revocation and delete the revoked certificate, so neither a restart nor
+    /// another instance's warm-up brings it back
+    pub async fn save_revocation(&self, revocation: &Revocation) -> Result<(), EventServerError> {
+        let body = serde_json::to_vec(revocation)?;
+        self.s3_operations
+            .put_object(
+                &self.config.bucket,
+                &revocation_key(&revocation.certificate_id),
+                body,
+                "application/json",
+            )
+            .await?;
+        self.s3_operations
+            .delete_object(
+                &self.config.bucket,
+                &certificate_key(&revocation.certificate_id),
+            )
+            .await
+    }
 
-        Self {
-            config,
-            s3_operations,
+    /// Every persisted revocation; unreadable entries are skipped
+    pub async fn load_revocations(&self) -> Result<Vec<Revocation>, EventServerError> {
+        let keys = self
+            .s3_operations
+            .list_objects(&self.config.bucket, REVOCATION_PREFIX, usize::MAX)
+            .await?;
+
+        let mut revocations = Vec::with_capacity(keys.len());
+        for key in keys {
+            let loaded = self
+                .s3_operations
+                .get_object(&self.config.bucket, &key)
+                .await
+                .and_then(|body| {
+                    serde_json::from_slice::<Revocation>(&body)
+                        .map_err(|e| EventServerError::Storage(e.to_string()))
+                });
+            match loaded {
+                Ok(revocation) => revocations.push(revocation),
+                Err(e) => warn!(key = %key, error = %e, "Skipping unreadable stored revocation"),
+            }
         }
+        Ok(revocations)
     }
-}
 
-/// Storage statistics
-#[derive(Debug, serde::Serialize)]
-pub struct StorageStats {
-    pub total_objects: u64,
-    pub total_size_bytes: u64,
-    pub bucket_name: String,
-    pub region: String,
-}
-
-#[cfg(test)]
-mod tests {
+    /// Persist the first certificate signing keyring, unless another instance already did
+    /// Returns false when a keyring was already stored, which then wins
+    pub async fn create_certificate_keys(
+        &self,
+        keyring: &StoredKeyring,
+    ) -> Result<bool, EventServerError> {
+        let body = serde_json::to_vec(keyring)?;
+        self.s3_operations
+            .put_object_if_absent(
+                &self.config.bucket,
+                CERTIFICATE_KEYS_KEY,
+                body,
+                "application/json",
+            )
+            .await
+    }
+
+    /// Persist the certificate signing keyring
+    pub async fn save_certificate_keys(
+        &self,
+        keyring: &StoredKeyring,
+    ) -> Result<(), EventServerError> {
+        let body = serde_json::to_vec(keyring)?;
+        self.s3_operations
+            .put_object(
+                &self.config.bucket,
+                CERTIFICATE_KEYS_KEY,
+                body,
+                "application/json",
+            )
+            .await
+    }
+
+    /// The persisted certificate signing keyring, `None` before the first one is saved
+    pub async fn load_certificate_keys(&self) -> Result<Option<StoredKeyring>, EventServerError> {
+        match self
+            .s3_operations
+            .get_object(&self.config.bucket, CERTIFICATE_KEYS_KEY)
+            .await
+        {
+            Ok(body) => Ok(Some(serde_json::from_slice(&body)?)),
+            Err(EventServerError::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// List the IDs of all persisted certificates
+    pub async fn list_certificate_ids(&self) -> Result<HashSet<String>, EventServerError> {
+        let keys = self
+            .s3_operations
+            .list_objects(&self.config.bucket, CERTIFICATE_PREFIX, usize::MAX)
+            .await?;
+
+        Ok(keys
+            .iter()
+            .filter_map(|key| certificate_id_from_key(key))
+            .collect())
+    }
+
+    /// Load up to `limit` persisted certificates
+    /// Unreadable entries are skipped so one bad object cannot block startup
+    pub async fn load_certificates(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<DeviceCertificate>, EventServerError> {
+        let keys = self
+            .s3_operations
+            .list_objects(&self.config.bucket, CERTIFICATE_PREFIX, limit)
+            .await?;
+
+        let mut certificates = Vec::with_capacity(keys.len());
+        for key in keys.iter().take(limit) {
+            let loaded = match self
+                .s3_operations
+                .get_object(&self.config.bucket, key)
+                .await
+            {
+                Ok(body) => serde_json::from_slice::<DeviceCertificate>(&body)
+                    .map_err(|e| EventServerError::Storage(e.to_string())),
+                Err(e) => Err(e),
+            };
+            match loaded {
+                Ok(certificate) => certificates.push(certificate),
+                Err(e) => warn!(key = %key, error = %e, "Skipping unreadable stored certificate"),
+            }
+        }
+
+        Ok(certificates)
+    }
+
+    /// Signed receipt for a stored event, or `None` unless `issue_receipts` is on
+    /// With `store_receipts` it's also kept next to the event's primary object; failing to
+    /// store it is logged but doesn't withhold the receipt
+    pub async fn issue_receipt(
+        &self,
+        event_id: Uuid,
+        event_hash: &str,
+        storage_location: &str,
+    ) -> Option<EventReceipt> {
+        let receipt =
+            self.receipt_signer
+                .as_ref()?
+                .sign_receipt(event_id, event_hash, storage_location);
+        if self.config.store_receipts {
+            if let Err(e) = self.store_receipt(&receipt).await {
+                warn!(event_id = %event_id, error = %e, "Failed to store event receipt");
+            }
+        }
+        Some(receipt)
+    }
+
+    async fn store_receipt(&self, receipt: &EventReceipt) -> Result<(), EventServerError> {
+        let storage_key = self.resolve_primary_key(&receipt.hash).await?;
+        let body = serde_json::to_vec(receipt)?;
+        self.upload_to_s3(
+            &format!("{storage_key}{RECEIPT_SUFFIX}"),
+            &body,
+            "application/json",
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Record a stored event in the day's listing index so admin views don't need a GET per event
+    /// Each entry is its own object keyed by hash, so concurrent writers on any instance never
+    /// overwrite each other, and an event already listed is not written twice
+    pub async fn index_event(
+        &self,
+        event_package: &EventPackage,
+        event_hash: &str,
+        relay_id: &str,
+    ) -> Result<(), EventServerError> {
+        let key = self.resolve_primary_key(event_hash).await?;
+        let entry = EventIndexEntry {
+            event_id: event_package.id,
+            hash: event_hash.to_string(),
+            relay_id: relay_id.to_string(),
+            created_at: event_package.metadata.created_at,
+            key,
+        };
+
+        self.s3_operations
+            .put_object_if_absent(
+                &self.config.bucket,
+                &self.scoped(index_entry_key(Utc::now().date_naive(), event_hash)),
+                serde_json::to_vec(&entry)?,
+                "application/json",
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Read `limit` entries of the listing index for a day, skipping the first `offset`; a day
+    /// with no stored events has an empty index. Entries from the single-file index written by
+    /// earlier versions come first, in the order they were written, then one entry per event in
+    /// key order. Only the entries on the page are fetched
+    pub async fn read_index_page(
+        &self,
+        date: NaiveDate,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<EventIndexEntry>, EventServerError> {
+        let legacy: Vec<EventIndexEntry> = match self
+            .s3_operations
+            .get_object(&self.config.bucket, &self.scoped(legacy_index_key(date)))
+            .await
+        {
+            Ok(body) => parse_index(&body).collect(),
+            Err(EventServerError::NotFound(_)) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let offset = offset.saturating_sub(legacy.len());
+        let mut entries: Vec<EventIndexEntry> =
+            legacy.into_iter().skip(offset).take(limit).collect();
+        let remaining = limit - entries.len();
+        if remaining == 0 {
+            return Ok(entries);
+        }
+
+        // Only keys are listed up to the page; bodies are fetched for the page alone
+        let entry_keys = self
+            .s3_operations
+            .list_objects(
+                &self.config.bucket,
+                &self.scoped(index_day_prefix(date)),
+                offset.saturating_add(remaining),
+            )
+            .await?;
+        let mut reads = futures::stream::iter(entry_keys.into_iter().skip(offset))
+            .map(|key| async move {
+                match self
+                    .s3_operations
+                    .get_object(&self.config.bucket, &key)
+                    .await
+                {
+                    Ok(body) => Ok(serde_json::from_slice::<EventIndexEntry>(&body).ok()),
+                    // Listed but gone, e.g. the day's index is being cleaned up
+                    Err(EventServerError::NotFound(_)) => Ok(None),
+                    Err(e) => Err(e),
+                }
+            })
+            .buffered(INDEX_READ_CONCURRENCY);
+        while let Some(entry) = reads.next().await {
+            entries.extend(entry?);
+        }
+        Ok(entries)
+    }
+
+    /// Store media under a content-addressed key derived from its SHA-256 digest
+    /// This copy is kept in addition to the one in the event archive. It's uploaded once per
+    /// digest and charged to the relay that uploaded it; later uploads report `already_stored`
+    pub async fn store_media(
+        &self,
+        media_data: &[u8],
+        content_type: &str,
+        relay_id: &str,
+    ) -> Result<StoredMedia, EventServerError> {
+        let digest = hex::encode(Sha256::digest(media_data));
+        let media_key = self.scoped(media_key(&digest));
+
+        if self
+            .s3_operations
+            .head_object(&self.config.bucket, &media_key)
+            .await?
+        {
+            info!(digest = %digest, "Media already stored, skipping upload");
+            return Ok(StoredMedia {
+                digest,
+                already_stored: true,
+            });
+        }
+
+        self.charged_to_relay(
+            relay_id,
+            media_data.len() as u64,
+            self.upload_to_s3(&media_key, media_data, content_type),
+        )
+        .await?;
+
+        Ok(StoredMedia {
+            digest,
+            already_stored: false,
+        })
+    }
+
+    /// Download the stored object for an event hash, optionally restricted to a byte range
+    /// `range` is passed through verbatim as an HTTP `Range` header value (e.g. `bytes=0-99`)
+    pub async fn download_event(
+        &self,
+        event_hash: &str,
+        range: Option<&str>,
+    ) -> Result<EventDownload, EventServerError> {
+        let storage_key = self.resolve_primary_key(event_hash).await?;
+
+        let mut download = self
+            .s3_operations
+            .get_object_range(&self.config.bucket, &storage_key, range)
+            .await?;
+        let content_type_mismatch =
+            self.check_content_type(&storage_key, download.content_type.as_deref());
+        if content_type_mismatch.is_some() {
+            download.content_type = expected_content_type(&storage_key).map(str::to_string);
+        }
+
+        info!(
+            hash = %event_hash,
+            key = %storage_key,
+            range = ?range,
+            size = download.body.len(),
+            "Downloaded stored event object"
+        );
+
+        Ok(EventDownload {
+            download,
+            content_type_mismatch,
+        })
+    }
+
+    /// With `verify_content_type`, compare an event object's stored Content-Type against the
+    /// one its key implies, logging a mismatch and returning the stored type
+    fn check_content_type(&self, key: &str, stored: Option<&str>) -> Option<String> {
+        if !self.config.verify_content_type {
+            return None;
+        }
+        let expected = expected_content_type(key)?;
+        let matches = stored.is_some_and(|stored| {
+            stored
+                .split(';')
+                .next()
+                .is_some_and(|media_type| media_type.trim().eq_ignore_ascii_case(expected))
+        });
+        if matches {
+            return None;
+        }
+
+        let stored = stored.unwrap_or("none").to_string();
+        warn!(
+            key = %key,
+            expected = expected,
+            stored = %stored,
+            "Stored event object has an unexpected Content-Type"
+        );
+        Some(stored)
+    }
+
+    /// Download the media of a stored event, optionally restricted to a byte range
+    /// The event object only provides the media digest and type; the bytes are served from
+    /// the content-addressed media object so ranges are handled by the store
+    pub async fn download_media(
+        &self,
+        event_hash: &str,
+        range: Option<&str>,
+    ) -> Result<MediaDownload, EventServerError> {
+        let storage_key = self.resolve_primary_key(event_hash).await?;
+        let event_object = self
+            .s3_operations
+            .get_object(&self.config.bucket, &storage_key)
+            .await?;
+
+        let (digest, media_type) =
+            stored_media_reference(&storage_key, &event_object, self.config.max_file_size)?
+                .ok_or_else(|| {
+                    EventServerError::NotFound(format!("Event {event_hash} has no media"))
+                })?;
+
+        let download = self
+            .s3_operations
+            .get_object_range(&self.config.bucket, &self.scoped(media_key(&digest)), range)
+            .await?;
+
+        info!(
+            hash = %event_hash,
+            digest = %digest,
+            range = ?range,
+            size = download.body.len(),
+            "Downloaded stored event media"
+        );
+
+        Ok(MediaDownload {
+            media_type,
+            download,
+        })
+    }
+
+    /// Check if object exists in S3
+    async fn simulate_s3_exists(&self, key: &str) -> Result<bool, EventServerError> {
+        self.s3_operations
+            .head_object(&self.config.bucket, key)
+            .await
+    }
+
+    /// Create a mock instance for testing
+    #[cfg(test)]
+    pub async fn new_mock() -> Self {
+        Self::with_mock(Arc::new(MockS3Client::default()))
+    }
+
+    /// Create a mock instance backed by a caller-provided mock client
+    #[cfg(test)]
+    pub fn with_mock(s3_operations: Arc<MockS3Client>) -> Self {
+        use crate::config::storage::StorageConfig;
+
+        let config = StorageConfig {
+            endpoint: None,
+            region: "us-east-1".to_string(),
+            bucket: "test-bucket".to_string(),
+            access_key_id: "test-key".to_string(),
+            secret_access_key: "test-secret".to_string(),
+            use_path_style: false,
+            enable_ssl: true,
+            upload_timeout: 300,
+            max_file_size: 100 * 1024 * 1024,
+            max_image_width: 16384,
+            max_image_height: 16384,
+            circuit_breaker_threshold: 0,
+            circuit_breaker_cooldown_seconds: 30,
+            max_upload_bytes_per_sec: 0,
+            per_relay_quota_bytes: None,
+            sign_archives: false,
+            archive_signing_key: None,
+            compress_annotations: false,
+            key_layout: StorageLayout::DateHierarchy,
+            verify_after_upload: false,
+            verify_content_type: false,
+            issue_receipts: false,
+            store_receipts: false,
+            zip_max_entries: 64,
+            zip_max_concurrent: 1,
+            migrate: false,
+            allowed_mime_types: vec![
+                "image/jpeg".to_string(),
+                "image/png".to_string(),
+                "application/json".to_string(),
+            ],
+        };
+
+        Self {
+            upload_throttle: UploadThrottle::new(config.max_upload_bytes_per_sec),
+            archive_signer: None,
+            receipt_signer: None,
+            relay_quota: RelayQuota::new(config.per_relay_quota_bytes),
+            config,
+            s3_operations,
+            circuit_breaker: None,
+            in_flight: InFlightLocks::default(),
+            key_prefix: String::new(),
+        }
+    }
+
+    /// Signer for stored archives, if archive signing is enabled
+    pub fn archive_signer(&self) -> Option<&ArchiveSigner> {
+        self.archive_signer.as_ref()
+    }
+
+    /// Signer for event receipts, if receipts are enabled
+    pub fn receipt_signer(&self) -> Option<&ArchiveSigner> {
+        self.receipt_signer.as_ref()
+    }
+
+    /// Issue receipts for events stored by this instance, keeping them next to the event
+    /// when `store` is set
+    #[cfg(test)]
+    pub fn with_receipt_signer(mut self, signer: ArchiveSigner, store: bool) -> Self {
+        self.receipt_signer = Some(signer);
+        self.config.issue_receipts = true;
+        self.config.store_receipts = store;
+        self
+    }
+
+    /// Put a circuit breaker in front of this instance's backend, returning it for inspection
+    #[cfg(test)]
+    pub fn with_circuit_breaker(
+        mut self,
+        threshold: u32,
+        cooldown: Duration,
+    ) -> (Self, Arc<CircuitBreakerS3>) {
+        let breaker = Arc::new(CircuitBreakerS3::new(
+            self.s3_operations.clone(),
+            threshold,
+            cooldown,
+        ));
+        self.s3_operations = breaker.clone();
+        self.circuit_breaker = Some(breaker.clone());
+        (self, breaker)
+    }
+
+    /// Sign archives stored by this instance
+    #[cfg(test)]
+    pub fn with_archive_signer(mut self, signer: ArchiveSigner) -> Self {
+        self.archive_signer = Some(signer);
+        self
+    }
+
+    /// Set the per-relay storage quota on a mock instance
+    #[cfg(test)]
+    pub fn set_relay_quota(&mut self, quota_bytes: Option<u64>) {
+        self.relay_quota = RelayQuota::new(quota_bytes);
+    }
+
+    /// Toggle event JSON compression on a mock instance
+    #[cfg(test)]
+    pub fn set_compress_annotations(&mut self, enabled: bool) {
+        self.config.compress_annotations = enabled;
+    }
+}
+
+/// Gzip-compress a stored object body
+fn gzip(data: &[u8]) -> Result<Vec<u8>, EventServerError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(data)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| EventServerError::Storage(format!("Failed to compress object: {e}")))
+}
+
+/// Deserialize a stored event object, transparently decompressing `.json.gz` objects
+fn decode_event_object(key: &str, data: &[u8]) -> Result<EventPackage, EventServerError> {
+    if key.ends_with(".zip") {
+        return decode_event_archive(data);
+    }
+
+    let decompressed;
+    let json = if key.ends_with(".gz") {
+        let mut buffer = Vec::new();
+        GzDecoder::new(data)
+            .read_to_end(&mut buffer)
+            .map_err(|e| EventServerError::Storage(format!("Failed to decompress event: {e}")))?;
+        decompressed = buffer;
+        &decompressed[..]
+    } else {
+        data
+    };
+
+    serde_json::from_slice(json)
+        .map_err(|e| EventServerError::Validation(format!("Failed to deserialize event: {e}")))
+}
+
+/// metadata.json of an archive written by `ZipPackager`
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchiveMetadata {
+    id: Uuid,
+    version: String,
+    created_at: DateTime<Utc>,
+    created_by: Option<String>,
+    source: EventSource,
+}
+
+/// media_metadata.json of an archive written by `ZipPackager`
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchiveMediaMetadata {
+    original_name: String,
+    #[serde(rename = "type")]
+    media_type: MediaType,
+    size: u64,
+    last_modified: Option<DateTime<Utc>>,
+}
+
+/// Rebuild the event package a stored ZIP archive was made from
+/// Media is re-encoded as plain base64, so an event submitted with a data-URL prefix
+/// doesn't reproduce its original hash
+fn decode_event_archive(data: &[u8]) -> Result<EventPackage, EventServerError> {
+    let mut archive = ZipArchive::new(Cursor::new(data))
+        .map_err(|e| EventServerError::Storage(format!("Failed to open stored archive: {e}")))?;
+
+    let metadata: ArchiveMetadata = serde_json::from_slice(
+        &read_archive_entry(&mut archive, "metadata.json")?
+            .ok_or_else(|| EventServerError::Storage("Archive has no metadata.json".to_string()))?,
+    )?;
+    let annotations = serde_json::from_slice(
+        &read_archive_entry(&mut archive, "annotations.json")?.ok_or_else(|| {
+            EventServerError::Storage("Archive has no annotations.json".to_string())
+        })?,
+    )?;
+
+    let media_entry = archive
+        .file_names()
+        .find(|name| name.starts_with("media.") && *name != "media_metadata.json")
+        .map(str::to_string);
+    let media = match media_entry {
+        Some(media_entry) => {
+            let media_metadata: ArchiveMediaMetadata = serde_json::from_slice(
+                &read_archive_entry(&mut archive, "media_metadata.json")?.ok_or_else(|| {
+                    EventServerError::Storage("Archive has no media_metadata.json".to_string())
+                })?,
+            )?;
+            let media_data = read_archive_entry(&mut archive, &media_entry)?.unwrap_or_default();
+            Some(EventMedia {
+                media_type: media_metadata.media_type,
+                data: base64::engine::general_purpose::STANDARD.encode(media_data),
+                name: media_metadata.original_name,
+                size: media_metadata.size,
+                last_modified: media_metadata
+                    .last_modified
+                    .map_or(0, |modified| modified.timestamp_millis() as u64),
+                sha256: None,
+            })
+        }
+        None => None,
+    };
+
+    Ok(EventPackage {
+        id: metadata.id,
+        version: metadata.version,
+        annotations,
+        media,
+        metadata: EventMetadata {
+            created_at: metadata.created_at,
+            created_by: metadata.created_by,
+            source: metadata.source,
+        },
+    })
+}
+
+/// Bytes of an archive entry, `None` when absent
+fn read_archive_entry(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+) -> Result<Option<Vec<u8>>, EventServerError> {
+    let mut file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(ZipError::FileNotFound) => return Ok(None),
+        Err(e) => {
+            return Err(EventServerError::Storage(format!(
+                "Failed to read {name} from archive: {e}"
+            )))
+        }
+    };
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .map_err(|e| EventServerError::Storage(format!("Failed to read {name}: {e}")))?;
+    Ok(Some(bytes))
+}
+
+/// Content-addressed storage key of media with this SHA-256 digest
+fn media_key(digest: &str) -> String {
+    format!("media/{digest}")
+}
+
+/// Digest and type of the media recorded in a stored event object, if it has any
+/// ZIP archives carry them in `media_metadata.json`; JSON objects embed the media itself
+fn stored_media_reference(
+    key: &str,
+    data: &[u8],
+    max_media_bytes: u64,
+) -> Result<Option<(String, MediaType)>, EventServerError> {
+    if !key.ends_with(".zip") {
+        let Some(media) = decode_event_object(key, data)?.media else {
+            return Ok(None);
+        };
+        let media_data = ZipPackager::decode_base64_media(&media.data, max_media_bytes)?;
+        return Ok(Some((
+            hex::encode(Sha256::digest(&media_data)),
+            media.media_type,
+        )));
+    }
+
+    let mut archive = ZipArchive::new(Cursor::new(data))
+        .map_err(|e| EventServerError::Storage(format!("Failed to open stored archive: {e}")))?;
+    let metadata: serde_json::Value = match archive.by_name("media_metadata.json") {
+        Ok(file) => serde_json::from_reader(file)?,
+        Err(ZipError::FileNotFound) => return Ok(None),
+        Err(e) => {
+            return Err(EventServerError::Storage(format!(
+                "Failed to read media metadata: {e}"
+            )))
+        }
+    };
+
+    let digest = metadata["sha256"].as_str().map(str::to_string);
+    let media_type = serde_json::from_value(metadata["type"].clone()).ok();
+    match (digest, media_type) {
+        (Some(digest), Some(media_type)) => Ok(Some((digest, media_type))),
+        _ => Err(EventServerError::Storage(
+            "Stored media metadata is missing its digest or type".to_string(),
+        )),
+    }
+}
+
+/// Key prefix for persisted device certificates
+const CERTIFICATE_PREFIX: &str = "certificates/";
+/// Storage prefix for persisted certificate revocations
+const REVOCATION_PREFIX: &str = "revocations/";
+/// Key of the persisted certificate signing keyring
+const CERTIFICATE_KEYS_KEY: &str = "keys/certificate-signing.json";
+/// Storage prefix for captured bodies of failed requests
+const DEBUG_CAPTURE_PREFIX: &str = "debug/";
+/// Storage prefix for persisted per-relay storage usage
+const RELAY_USAGE_PREFIX: &str = "quota/";
+/// Suffix of the detached signature stored next to a signed archive
+const ARCHIVE_SIGNATURE_SUFFIX: &str = ".sig";
+/// Suffix of the receipt stored next to an event object
+const RECEIPT_SUFFIX: &str = ".receipt.json";
+
+/// Storage key for a certificate; IDs are standard base64, so make them path-safe
+fn certificate_key(certificate_id: &str) -> String {
+    let safe_id = certificate_id.replace('+', "-").replace('/', "_");
+    format!("{CERTIFICATE_PREFIX}{safe_id}.json")
+}
+
+/// Storage key of a certificate's revocation record
+fn revocation_key(certificate_id: &str) -> String {
+    let safe_id = certificate_id.replace('+', "-").replace('/', "_");
+    format!("{REVOCATION_PREFIX}{safe_id}.json")
+}
+
+/// Storage key of a relay's persisted storage usage; shared by all tenants, like the quota
+fn relay_usage_key(relay_id: &str) -> String {
+    format!("{RELAY_USAGE_PREFIX}{}.json", path_segment(relay_id))
+}
+
+/// Persisted storage usage of one relay
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RelayUsage {
+    used_bytes: u64,
+}
+
+/// Recover the certificate ID from a key produced by `certificate_key`
+fn certificate_id_from_key(key: &str) -> Option<String> {
+    let safe_id = key
+        .strip_prefix(CERTIFICATE_PREFIX)?
+        .strip_suffix(".json")?;
+    Some(safe_id.replace('-', "+").replace('_', "/"))
+}
+
+/// Key prefix of per-tenant namespaces
+const TENANT_PREFIX: &str = "tenants/";
+
+/// Key prefix for the daily event listing index
+const INDEX_PREFIX: &str = "index/";
+
+/// Index entries fetched at once when reading a day's index
+const INDEX_READ_CONCURRENCY: usize = 16;
+
+/// Key prefix of the listing index entries for a day
+fn index_day_prefix(date: NaiveDate) -> String {
+    format!("{INDEX_PREFIX}{}/", date.format("%Y-%m-%d"))
+}
+
+/// Storage key of one event's listing index entry
+fn index_entry_key(date: NaiveDate, event_hash: &str) -> String {
+    format!(
+        "{}{}.json",
+        index_day_prefix(date),
+        path_segment(event_hash)
+    )
+}
+
+/// Storage key of the single-file NDJSON index for a day, as written by earlier versions
+fn legacy_index_key(date: NaiveDate) -> String {
+    format!("{INDEX_PREFIX}{}.ndjson", date.format("%Y-%m-%d"))
+}
+
+/// Parse NDJSON index lines, skipping any that are truncated or malformed
+fn parse_index(body: &[u8]) -> impl Iterator<Item = EventIndexEntry> + '_ {
+    body.split(|byte| *byte == b'\n')
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| serde_json::from_slice(line).ok())
+}
+
+/// Most event object keys scanned when reindexing without a usable marker
+const REINDEX_SCAN_LIMIT: usize = 100_000;
+/// Event object keys listed per page while reindexing
+const REINDEX_PAGE_SIZE: usize = 1000;
+
+/// Whether an event object key could hold the event with this hash
+/// Archive keys and non-date-layout JSON keys are named `{hash}.{ext}`; date-layout JSON keys
+/// sit in a directory named after the first 8 hash characters
+fn may_hold_hash(key: &str, event_hash: &str) -> bool {
+    let (directory, file_name) = key.rsplit_once('/').unwrap_or(("", key));
+    let stem = file_name.split('.').next().unwrap_or_default();
+    let short_hash = event_hash.get(..8).unwrap_or(event_hash);
+    (stem == event_hash || directory.ends_with(&format!("/{short_hash}")))
+        && (key.ends_with(".zip") || key.ends_with(".json") || key.ends_with(".json.gz"))
+}
+
+/// Storage key recording the progress of the legacy key migration
+const MIGRATION_PROGRESS_KEY: &str = "migrations/storage-keys.json";
+/// Storage key of the lease held by the instance running the migration
+const MIGRATION_LEASE_KEY: &str = "migrations/storage-keys.lease";
+/// How long a migration lease lasts without renewal
+const MIGRATION_LEASE_SECONDS: i64 = 300;
+/// Event object keys listed per page during migration
+const MIGRATION_PAGE_SIZE: usize = 1000;
+/// Objects migrated between progress checkpoints (and lease renewals)
+const MIGRATION_CHECKPOINT_INTERVAL: usize = 100;
+
+/// Content-Type an event object is stored with, going by its key
+fn expected_content_type(key: &str) -> Option<&'static str> {
+    match event_extension(key)? {
+        "zip" => Some("application/zip"),
+        _ => Some("application/json"),
+    }
+}
+
+/// Extension of a primary event object key, `None` for anything else
+fn event_extension(key: &str) -> Option<&'static str> {
+    if key.ends_with(RECEIPT_SUFFIX) {
+        return None;
+    }
+    ["json.gz", "json", "zip"]
+        .into_iter()
+        .find(|extension| key.ends_with(&format!(".{extension}")))
+}
+
+/// Whether `key` is a JSON event object (plain or gzipped), not an archive or sidecar
+fn is_event_json(key: &str) -> bool {
+    matches!(event_extension(key), Some("json" | "json.gz"))
+}
+
+/// Persisted state of the legacy key migration
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct MigrationProgress {
+    /// Layout the last finished run migrated to
+    completed_layout: Option<StorageLayout>,
+    /// Last key handled by an unfinished run
+    last_key: Option<String>,
+}
+
+/// Lease on the legacy key migration, held by one instance at a time
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct MigrationLease {
+    /// Identifies the run holding the lease
+    holder: Uuid,
+    /// When other instances may take the lease over
+    expires_at: DateTime<Utc>,
+}
+
+impl MigrationLease {
+    fn new() -> Self {
+        Self {
+            holder: Uuid::new_v4(),
+            expires_at: Utc::now() + chrono::Duration::seconds(MIGRATION_LEASE_SECONDS),
+        }
+    }
+
+    fn renewed(&self) -> Self {
+        Self {
+            expires_at: Utc::now() + chrono::Duration::seconds(MIGRATION_LEASE_SECONDS),
+            ..self.clone()
+        }
+    }
+}
+
+/// What a migration run did
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Objects given a by-hash marker
+    pub indexed: usize,
+    /// Objects relocated to the configured layout (also counted in `indexed`)
+    pub moved: usize,
+    /// Undecodable objects and duplicates of already indexed events
+    pub skipped: usize,
+}
+
+/// Result of storing an event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreOutcome {
+    /// Storage location reported to clients
+    pub location: String,
+    /// Whether this call wrote the event, rather than finding it already stored
+    pub written: bool,
+}
+
+impl StoreOutcome {
+    fn written(location: String) -> Self {
+        Self {
+            location,
+            written: true,
+        }
+    }
+
+    fn existing(location: String) -> Self {
+        Self {
+            location,
+            written: false,
+        }
+    }
+}
+
+/// Result of reindexing an event by hash
+#[derive(Debug, Clone)]
+pub struct ReindexOutcome {
+    /// Primary object key the marker now points to
+    pub key: String,
+    /// Whether the marker had to be rewritten
+    pub repaired: bool,
+}
+
+/// One line of the daily event listing index
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct EventIndexEntry {
+    pub event_id: Uuid,
+    pub hash: String,
+    pub relay_id: String,
+    pub created_at: DateTime<Utc>,
+    /// Primary object key of the stored event
+    pub key: String,
+}
+
+/// Result of storing a content-addressed media object
+#[derive(Debug, Clone)]
+pub struct StoredMedia {
+    /// Hex-encoded SHA-256 of the media bytes
+    pub digest: String,
+    /// True if the content-addressed copy already existed, so none was uploaded
+    pub already_stored: bool,
+}
+
+/// Stored object of an event, with the result of the optional Content-Type check
+#[derive(Debug, Clone)]
+pub struct EventDownload {
+    pub download: ObjectDownload,
+    /// Stored Content-Type when `verify_content_type` found it doesn't match the object's key;
+    /// `download` then carries the expected type instead
+    pub content_type_mismatch: Option<String>,
+}
+
+/// Media of a stored event, with the type recorded at submission
+#[derive(Debug, Clone)]
+pub struct MediaDownload {
+    pub media_type: MediaType,
+    pub download: ObjectDownload,
+}
+
+#[cfg(test)]
+mod tests {
     use super::*;
     use crate::types::event::{EventAnnotation, EventMetadata, EventSource, FieldValue};
+    use aws_sdk_s3::error::ErrorMetadata;
+    use aws_sdk_s3::operation::put_object::PutObjectError;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+
+    fn synthesized_put_error(code: &str) -> PutObjectError {
+        PutObjectError::generic(
+            ErrorMetadata::builder()
+                .code(code)
+                .message("synthetic")
+                .build(),
+        )
+    }
+
+    #[test]
+    fn test_classify_s3_throttling_errors() {
+        for code in ["SlowDown", "ServiceUnavailable"] {
+            let error = classify_s3_error(&synthesized_put_error(code), "Failed to upload to S3");
+            assert!(matches!(
+                error,
+                EventServerError::ServiceUnavailable {
+                    retry_after_seconds: Some(THROTTLED_RETRY_AFTER_SECONDS),
+                    ..
+                }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_classify_s3_quota_errors() {
+        let error = classify_s3_error(
+            &synthesized_put_error("QuotaExceeded"),
+            "Failed to upload to S3",
+        );
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            response.headers()[axum::http::header::RETRY_AFTER],
+            QUOTA_RETRY_AFTER_SECONDS.to_string().as_str()
+        );
+    }
+
+    #[test]
+    fn test_classify_other_s3_errors_as_storage() {
+        let error = classify_s3_error(
+            &synthesized_put_error("AccessDenied"),
+            "Failed to upload to S3",
+        );
+        assert!(matches!(error, EventServerError::Storage(_)));
+        assert_eq!(
+            error.into_response().status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn test_upload_timeout_config_splits_budget_across_attempts() {
+        let timeouts = upload_timeout_config(30);
+        assert_eq!(timeouts.operation_timeout(), Some(Duration::from_secs(30)));
+        assert_eq!(
+            timeouts.operation_attempt_timeout(),
+            Some(Duration::from_secs(10))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_aborts_at_configured_timeout() {
+        // An endpoint that accepts connections but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+
+        let config = StorageConfig {
+            endpoint: Some(endpoint),
+            access_key_id: "test-key".to_string(),
+            secret_access_key: "test-secret".to_string(),
+            use_path_style: true,
+            upload_timeout: 1,
+            ..StorageConfig::default()
+        };
+        let service = StorageService::new(config).await.unwrap();
+
+        let started = std::time::Instant::now();
+        let result = service
+            .upload_zip_file(
+                &crate::test_utils::sample_event(),
+                &"a".repeat(64),
+                "test_relay",
+                b"zip",
+            )
+            .await;
+
+        assert!(result.is_err());
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "upload took {:?}",
+            started.elapsed()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_bandwidth_is_paced() {
+        let mut storage = StorageService::new_mock().await;
+        storage.upload_throttle = UploadThrottle::new(40_000);
+
+        // 60 KB against a 40 KB/s cap with a one-second bucket: the last 20 KB wait ~0.5s
+        let started = std::time::Instant::now();
+        let uploads = (0..4).map(|i| {
+            let storage = storage.clone();
+            async move {
+                storage
+                    .upload_to_s3(
+                        &format!("burst/{i}"),
+                        &[0u8; 15_000],
+                        "application/octet-stream",
+                    )
+                    .await
+            }
+        });
+        for result in futures::future::join_all(uploads).await {
+            result.unwrap();
+        }
+        assert!(started.elapsed() >= std::time::Duration::from_millis(450));
+
+        // Unthrottled uploads complete immediately
+        let storage = StorageService::new_mock().await;
+        let started = std::time::Instant::now();
+        for i in 0..4 {
+            storage
+                .upload_to_s3(
+                    &format!("burst/{i}"),
+                    &[0u8; 15_000],
+                    "application/octet-stream",
+                )
+                .await
+                .unwrap();
+        }
+        assert!(started.elapsed() < std::time::Duration::from_millis(200));
+    }
 
     #[tokio::test]
     async fn test_generate_storage_key() {
@@ -399,7 +2873,7 @@ mod tests {
         let event_id = Uuid::new_v4();
         let hash = "abcdef1234567890";
 
-        let key = service.generate_storage_key(hash, &event_id);
+        let key = service.generate_storage_key(hash, &event_id, "test_relay", "json");
 
         // Should include date, hash prefix, and event ID
         assert!(key.contains("events/"));
@@ -408,6 +2882,45 @@ mod tests {
         assert!(key.ends_with(".json"));
     }
 
+    #[tokio::test]
+    async fn test_storage_layouts() {
+        let mut service = StorageService::new_mock().await;
+        let event_id = Uuid::new_v4();
+        let hash = "abcdef1234567890";
+        let date = Utc::now().format("%Y/%m/%d").to_string();
+
+        let mut keys = Vec::new();
+        for layout in [
+            StorageLayout::DateHierarchy,
+            StorageLayout::Flat,
+            StorageLayout::RelayHierarchy,
+        ] {
+            service.config.key_layout = layout;
+            keys.push((
+                service.generate_storage_key(hash, &event_id, "relay/1", "json"),
+                service.config.generate_event_key(hash, "relay/1", "zip"),
+            ));
+            // The by-hash lookup key does not depend on the layout
+            assert_eq!(
+                service.generate_storage_key_from_hash(hash),
+                "events/by-hash/abcdef1234567890.json"
+            );
+        }
+
+        assert_eq!(keys[0].0, format!("events/{date}/abcdef12/{event_id}.json"));
+        assert_eq!(keys[0].1, format!("events/{}/{hash}.zip", &date[..7]));
+        assert_eq!(keys[1].0, "events/abcdef1234567890.json");
+        assert_eq!(keys[1].1, "events/abcdef1234567890.zip");
+        assert_eq!(
+            keys[2].0,
+            format!("events/relays/relay_1/{date}/abcdef1234567890.json")
+        );
+        assert_eq!(
+            keys[2].1,
+            format!("events/relays/relay_1/{date}/abcdef1234567890.zip")
+        );
+    }
+
     #[tokio::test]
     async fn test_generate_storage_key_from_hash() {
         let service = StorageService::new_mock().await;
@@ -439,12 +2952,896 @@ mod tests {
         };
 
         let hash = "test_hash_123";
-        let result = service.store_event(&event_package, hash).await;
+        let result = service
+            .store_event(&event_package, hash, "test_relay")
+            .await;
 
         assert!(result.is_ok());
         println!("{result:?}");
-        let location = result.unwrap();
+        let location = result.unwrap().location;
         assert!(location.contains("s3"));
         assert!(location.contains("test-bucket"));
     }
+
+    #[tokio::test]
+    async fn test_store_event_writes_by_hash_marker() {
+        let mock = Arc::new(MockS3Client::default());
+        let service = StorageService::with_mock(mock.clone());
+
+        let event_package = EventPackage {
+            id: Uuid::new_v4(),
+            version: "1.0".to_string(),
+            annotations: vec![EventAnnotation {
+                label_id: "test_label".to_string(),
+                value: FieldValue::String("test_value".to_string()),
+                timestamp: Utc::now(),
+            }],
+            media: None,
+            metadata: EventMetadata {
+                created_at: Utc::now(),
+                created_by: Some("test_user".to_string()),
+                source: EventSource::Web,
+            },
+        };
+        let hash = "abcdef1234567890";
+
+        service
+            .store_event(&event_package, hash, "test_relay")
+            .await
+            .unwrap();
+
+        let marker = mock.object("events/by-hash/abcdef1234567890.json").unwrap();
+        let primary_key = String::from_utf8(marker.body).unwrap();
+        let primary = mock.object(&primary_key).unwrap();
+        assert_eq!(primary.content_type, "application/json");
+
+        assert!(service.event_exists(hash).await.unwrap());
+        let retrieved = service.retrieve_event(hash).await.unwrap();
+        assert_eq!(retrieved.id, event_package.id);
+    }
+
+    #[tokio::test]
+    async fn test_signed_archive_has_detached_signature() {
+        let mock = Arc::new(MockS3Client::default());
+        let signer = ArchiveSigner::new(None).unwrap();
+        let service = StorageService::with_mock(mock.clone()).with_archive_signer(signer.clone());
+        let hash = "abcdef1234567890";
+        let zip_data = b"PK\x03\x04 archive".to_vec();
+
+        service
+            .upload_zip_file(
+                &crate::test_utils::sample_event(),
+                hash,
+                "test_relay",
+                &zip_data,
+            )
+            .await
+            .unwrap();
+
+        let marker = mock.object("events/by-hash/abcdef1234567890.json").unwrap();
+        let zip_key = String::from_utf8(marker.body).unwrap();
+        let archive = mock.object(&zip_key).unwrap().body;
+        let signature: crate::crypto::ArchiveSignature =
+            serde_json::from_slice(&mock.object(&format!("{zip_key}.sig")).unwrap().body).unwrap();
+        assert!(signer.verify(&archive, &signature));
+
+        let mut tampered = archive;
+        tampered[0] ^= 0xFF;
+        assert!(!signer.verify(&tampered, &signature));
+    }
+
+    #[tokio::test]
+    async fn test_misstored_content_type_is_flagged_on_read() {
+        let mock = Arc::new(MockS3Client::default());
+        let mut service = StorageService::with_mock(mock.clone());
+        let event_package = crate::test_utils::sample_event();
+        let hash = "abcdef1234567890";
+        service
+            .store_event(&event_package, hash, "test_relay")
+            .await
+            .unwrap();
+
+        let primary_key = service.resolve_primary_key(hash).await.unwrap();
+        let body = mock.object(&primary_key).unwrap().body;
+        service
+            .s3_operations
+            .put_object(&service.config.bucket, &primary_key, body, "text/plain")
+            .await
+            .unwrap();
+
+        // Only checked when enabled
+        let download = service.download_event(hash, None).await.unwrap();
+        assert_eq!(download.content_type_mismatch, None);
+
+        service.config.verify_content_type = true;
+        let download = service.download_event(hash, None).await.unwrap();
+        assert_eq!(
+            download.content_type_mismatch.as_deref(),
+            Some("text/plain")
+        );
+        assert_eq!(
+            download.download.content_type.as_deref(),
+            Some("application/json")
+        );
+        // The event itself is still readable
+        let retrieved = service.retrieve_event(hash).await.unwrap();
+        assert_eq!(retrieved.id, event_package.id);
+    }
+
+    #[tokio::test]
+    async fn test_tenants_store_identical_hash_separately() {
+        let mock = Arc::new(MockS3Client::default());
+        let service = StorageService::with_mock(mock.clone());
+        let (acme, globex) = (
+            service.for_tenant(Some("acme")),
+            service.for_tenant(Some("globex")),
+        );
+        let event_package = crate::test_utils::sample_event();
+        let hash = "abcdef1234567890";
+
+        acme.store_event(&event_package, hash, "test_relay")
+            .await
+            .unwrap();
+        assert!(acme.event_exists(hash).await.unwrap());
+        assert!(!globex.event_exists(hash).await.unwrap());
+        assert!(!service.event_exists(hash).await.unwrap());
+        assert!(matches!(
+            globex.download_event(hash, None).await,
+            Err(EventServerError::NotFound(_))
+        ));
+
+        // Not deduplicated against the other tenant's copy
+        globex
+            .store_event(&event_package, hash, "test_relay")
+            .await
+            .unwrap();
+        let acme_key = acme.resolve_primary_key(hash).await.unwrap();
+        let globex_key = globex.resolve_primary_key(hash).await.unwrap();
+        assert!(acme_key.starts_with("tenants/acme/events/"));
+        assert!(globex_key.starts_with("tenants/globex/events/"));
+        assert!(mock.object(&acme_key).is_some() && mock.object(&globex_key).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_store_event_charges_relay_quota() {
+        let mock = Arc::new(MockS3Client::default());
+        let mut service = StorageService::with_mock(mock.clone());
+        service.set_relay_quota(Some(1));
+
+        let err = service
+            .store_event(
+                &crate::test_utils::sample_event(),
+                "abcdef1234567890",
+                "test_relay",
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            EventServerError::QuotaExceeded { quota_bytes: 1, .. }
+        ));
+        assert!(mock.put_log().is_empty());
+        assert_eq!(service.relay_quota.used("test_relay"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_media_copy_is_charged_to_relay_once() {
+        let mock = Arc::new(MockS3Client::default());
+        let mut service = StorageService::with_mock(mock.clone());
+        service.set_relay_quota(Some(1_000_000));
+
+        let first = service
+            .store_media(b"Hello World", "image/jpeg", "test_relay")
+            .await
+            .unwrap();
+        assert!(!first.already_stored);
+        assert_eq!(service.relay_quota.used("test_relay"), 11);
+
+        // The existing copy is reused, so nothing more is charged
+        let second = service
+            .store_media(b"Hello World", "image/jpeg", "other_relay")
+            .await
+            .unwrap();
+        assert!(second.already_stored);
+        assert_eq!(service.relay_quota.used("other_relay"), 0);
+
+        service.set_relay_quota(Some(1));
+        let err = service
+            .store_media(b"Other media", "image/jpeg", "test_relay")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, EventServerError::QuotaExceeded { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_relay_usage_survives_restart() {
+        let mock = Arc::new(MockS3Client::default());
+        let mut service = StorageService::with_mock(mock.clone());
+        service.set_relay_quota(Some(1_000_000));
+        service
+            .store_event(
+                &crate::test_utils::sample_event(),
+                &"a".repeat(64),
+                "test_relay",
+            )
+            .await
+            .unwrap();
+        let used = service.relay_quota.used("test_relay");
+        assert!(used > 0);
+
+        // A fresh instance starts from the persisted usage, not from zero
+        let mut restarted = StorageService::with_mock(mock.clone());
+        restarted.set_relay_quota(Some(used * 3 / 2));
+        let err = restarted
+            .store_event(
+                &crate::test_utils::sample_event(),
+                &"b".repeat(64),
+                "test_relay",
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, EventServerError::QuotaExceeded { used_bytes, .. } if used_bytes == used),
+            "{err:?}"
+        );
+        // Other relays aren't charged for it
+        restarted
+            .store_event(
+                &crate::test_utils::sample_event(),
+                &"c".repeat(64),
+                "other_relay",
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_upload_fails_verification() {
+        let event_package = crate::test_utils::sample_event();
+        let hash = "abcdef1234567890";
+        let zip_data = b"PK\x03\x04 archive bytes";
+
+        // Without verification the corruption goes unnoticed
+        let service = StorageService::with_mock(Arc::new(MockS3Client::with_corrupt_uploads()));
+        assert!(service
+            .upload_zip_file(&event_package, hash, "test_relay", zip_data)
+            .await
+            .is_ok());
+
+        let mock = Arc::new(MockS3Client::with_corrupt_uploads());
+        let mut service = StorageService::with_mock(mock.clone());
+        service.config.verify_after_upload = true;
+        let result = service
+            .upload_zip_file(&event_package, hash, "test_relay", zip_data)
+            .await;
+        assert!(
+            matches!(result, Err(EventServerError::Storage(msg)) if msg.contains("Integrity check failed"))
+        );
+        // The corrupted archive is left for the caller, but never indexed by hash
+        let zip_key = mock.put_log()[0].clone();
+        assert!(mock.object(&zip_key).is_some());
+        assert!(!service.event_exists(hash).await.unwrap());
+
+        // Intact uploads pass, including when the ETag isn't an MD5 (SSE-KMS, SSE-C)
+        for mock in [MockS3Client::default(), MockS3Client::with_opaque_etags()] {
+            let mut service = StorageService::with_mock(Arc::new(mock));
+            service.config.verify_after_upload = true;
+            assert!(service
+                .upload_zip_file(&event_package, hash, "test_relay", zip_data)
+                .await
+                .is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_store_uploads_once() {
+        let mock = Arc::new(MockS3Client::with_put_delay(Duration::from_millis(50)));
+        let service = StorageService::with_mock(mock.clone());
+        let event_package = crate::test_utils::sample_event();
+        let hash = "abcdef1234567890";
+
+        let (first, second) = tokio::join!(
+            service.store_event(&event_package, hash, "test_relay"),
+            service.store_event(&event_package, hash, "test_relay")
+        );
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert_eq!(first.location, second.location);
+        assert!(first.written != second.written);
+
+        let primary_uploads = mock
+            .put_log()
+            .into_iter()
+            .filter(|key| !key.starts_with("events/by-hash/"))
+            .count();
+        assert_eq!(primary_uploads, 1);
+        assert_eq!(service.in_flight.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_index_writes_are_all_listed() {
+        let mock = Arc::new(MockS3Client::with_put_delay(Duration::from_millis(20)));
+        // Two instances sharing a bucket, which the in-flight lock can't coordinate
+        let (first, second) = (
+            StorageService::with_mock(mock.clone()),
+            StorageService::with_mock(mock.clone()),
+        );
+        let events: Vec<_> = (0..4)
+            .map(|i| (crate::test_utils::sample_event(), format!("{i:064}")))
+            .collect();
+        for (event, hash) in &events {
+            first.store_event(event, hash, "test_relay").await.unwrap();
+        }
+
+        let index = |service: &StorageService, (event, hash): &(EventPackage, String)| {
+            let service = service.clone();
+            let (event, hash) = (event.clone(), hash.clone());
+            async move { service.index_event(&event, &hash, "test_relay").await }
+        };
+        let results = futures::future::join_all([
+            index(&first, &events[0]),
+            index(&second, &events[1]),
+            index(&first, &events[2]),
+            index(&second, &events[3]),
+            // Indexing again doesn't list the event twice
+            index(&second, &events[0]),
+        ])
+        .await;
+        assert!(results.into_iter().all(|result| result.is_ok()));
+
+        // Days indexed by earlier versions are still readable
+        let today = Utc::now().date_naive();
+        let legacy = EventIndexEntry {
+            event_id: Uuid::new_v4(),
+            hash: "f".repeat(64),
+            relay_id: "test_relay".to_string(),
+            created_at: Utc::now() - chrono::Duration::hours(1),
+            key: "events/legacy.json".to_string(),
+        };
+        mock.put_object(
+            "test-bucket",
+            &legacy_index_key(today),
+            format!("{}\n", serde_json::to_string(&legacy).unwrap()).into_bytes(),
+            "application/x-ndjson",
+        )
+        .await
+        .unwrap();
+
+        let hashes = |entries: Vec<EventIndexEntry>| -> Vec<String> {
+            entries.into_iter().map(|entry| entry.hash).collect()
+        };
+        let mut expected = vec![legacy.hash.clone()];
+        expected.extend(events.iter().map(|(_, hash)| hash.clone()));
+        let entries = first.read_index_page(today, 0, usize::MAX).await.unwrap();
+        assert_eq!(hashes(entries), expected);
+
+        // A page past the legacy entries only reads its own entries
+        let entries = first.read_index_page(today, 2, 2).await.unwrap();
+        assert_eq!(hashes(entries), expected[2..4]);
+    }
+
+    #[tokio::test]
+    async fn test_store_event_compresses_annotations() {
+        let mock = Arc::new(MockS3Client::default());
+        let mut service = StorageService::with_mock(mock.clone());
+        service.config.compress_annotations = true;
+
+        let event_package = crate::test_utils::sample_event();
+        let hash = "abcdef1234567890";
+        service
+            .store_event(&event_package, hash, "test_relay")
+            .await
+            .unwrap();
+
+        let marker = mock.object("events/by-hash/abcdef1234567890.json").unwrap();
+        let primary_key = String::from_utf8(marker.body).unwrap();
+        assert!(primary_key.ends_with(".json.gz"));
+
+        let primary = mock.object(&primary_key).unwrap();
+        assert_eq!(primary.content_type, "application/json");
+        assert_eq!(primary.content_encoding.as_deref(), Some("gzip"));
+        assert_eq!(&primary.body[..2], &[0x1f, 0x8b]); // gzip magic bytes
+
+        let mut json = Vec::new();
+        GzDecoder::new(&primary.body[..])
+            .read_to_end(&mut json)
+            .unwrap();
+        let stored: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(
+            stored["annotations"],
+            serde_json::to_value(&event_package.annotations).unwrap()
+        );
+
+        // Reads decompress transparently
+        let retrieved = service.retrieve_event(hash).await.unwrap();
+        assert_eq!(retrieved.id, event_package.id);
+        assert_eq!(retrieved.annotations.len(), 1);
+        let by_key = service.get_event_by_key(&primary_key).await.unwrap();
+        assert_eq!(by_key.id, event_package.id);
+    }
+
+    pub(super) fn event_hash(service: &StorageService, event_package: &EventPackage) -> String {
+        crate::services::EventService::new(service.clone(), Default::default())
+            .generate_event_hash(event_package)
+            .unwrap()
+    }
+
+    /// Store events the way the server did before by-hash markers: a date-layout JSON object
+    /// and a ZIP archive, plus an object that isn't an event. Returns the event hashes and
+    /// the JSON object's key
+    pub(super) async fn seed_legacy_events(service: &StorageService) -> (String, String, String) {
+        // Hashes skip the id and keep only milliseconds of the timestamps, so the two
+        // events need distinct annotations to never share a hash
+        let mut json_event = crate::test_utils::sample_event();
+        json_event.annotations[0].value = FieldValue::String("legacy_json".to_string());
+        let json_hash = event_hash(service, &json_event);
+        let json_key = format!(
+            "events/2024/01/02/{}/{}.json",
+            &json_hash[..8],
+            json_event.id
+        );
+        service
+            .s3_operations
+            .put_object(
+                &service.config.bucket,
+                &json_key,
+                serde_json::to_vec(&json_event).unwrap(),
+                "application/json",
+            )
+            .await
+            .unwrap();
+
+        let mut zip_event = crate::test_utils::sample_event();
+        zip_event.annotations[0].value = FieldValue::String("legacy_zip".to_string());
+        let zip_hash = event_hash(service, &zip_event);
+        let zip_data =
+            ZipPackager::create_zip_from_event_package(&zip_event, Default::default()).unwrap();
+        service
+            .s3_operations
+            .put_object(
+                &service.config.bucket,
+                &format!("events/{zip_hash}.zip"),
+                zip_data,
+                "application/zip",
+            )
+            .await
+            .unwrap();
+
+        service
+            .s3_operations
+            .put_object(
+                &service.config.bucket,
+                "events/notes.json",
+                b"not an event".to_vec(),
+                "application/json",
+            )
+            .await
+            .unwrap();
+
+        (json_hash, zip_hash, json_key)
+    }
+
+    #[tokio::test]
+    async fn test_reindex_scan_is_narrowed_by_date_and_reports_truncation() {
+        let mock = Arc::new(MockS3Client::default());
+        let service = StorageService::with_mock(mock.clone());
+        let (json_hash, zip_hash, json_key) = seed_legacy_events(&service).await;
+        // Listed ahead of the seeded events
+        for i in 0..5 {
+            service
+                .s3_operations
+                .put_object(
+                    &service.config.bucket,
+                    &format!("events/2023/12/{i:064}.zip"),
+                    b"filler".to_vec(),
+                    "application/zip",
+                )
+                .await
+                .unwrap();
+        }
+        let hash_of = |event_package: &EventPackage| Ok(event_hash(&service, event_package));
+
+        // Flat keys are found without a scan
+        let outcome = service
+            .reindex_event_scanning(&zip_hash, None, 3, hash_of)
+            .await
+            .unwrap();
+        assert_eq!(outcome.key, format!("events/{zip_hash}.zip"));
+
+        let error = service
+            .reindex_event_scanning(&json_hash, None, 3, hash_of)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            EventServerError::ScanTruncated { scanned: 3 }
+        ));
+
+        let stored_on = NaiveDate::from_ymd_opt(2024, 1, 2);
+        let outcome = service
+            .reindex_event_scanning(&json_hash, stored_on, 3, hash_of)
+            .await
+            .unwrap();
+        assert_eq!(outcome.key, json_key);
+        assert!(outcome.repaired);
+
+        // A scan that runs out of keys is a plain miss
+        let error = service
+            .reindex_event_scanning(&"0".repeat(64), stored_on, 3, hash_of)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, EventServerError::NotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn test_migrate_legacy_keys() {
+        let mock = Arc::new(MockS3Client::default());
+        let mut service = StorageService::with_mock(mock.clone());
+        service.config.key_layout = StorageLayout::Flat;
+        let (json_hash, zip_hash, json_key) = seed_legacy_events(&service).await;
+        assert!(!service.event_exists(&json_hash).await.unwrap());
+        assert!(!service.event_exists(&zip_hash).await.unwrap());
+
+        let hash_of = |event_package: &EventPackage| Ok(event_hash(&service, event_package));
+        let report = service.migrate_legacy_keys(hash_of).await.unwrap();
+        assert_eq!(
+            report,
+            MigrationReport {
+                indexed: 2,
+                moved: 1,
+                skipped: 1,
+            }
+        );
+
+        for hash in [&json_hash, &zip_hash] {
+            assert!(service.event_exists(hash).await.unwrap());
+        }
+        // The date-layout object moved into the flat layout; the archive already fit it
+        assert!(mock.object(&json_key).is_none());
+        assert_eq!(
+            service.resolve_primary_key(&json_hash).await.unwrap(),
+            format!("events/{json_hash}.json")
+        );
+        assert!(service.retrieve_event(&json_hash).await.is_ok());
+        assert_eq!(
+            service.resolve_primary_key(&zip_hash).await.unwrap(),
+            format!("events/{zip_hash}.zip")
+        );
+
+        // Finished runs aren't repeated
+        let uploads = mock.put_log().len();
+        let report = service.migrate_legacy_keys(hash_of).await.unwrap();
+        assert_eq!(report, MigrationReport::default());
+        assert_eq!(mock.put_log().len(), uploads);
+    }
+
+    #[tokio::test]
+    async fn test_migration_resumes_after_checkpoint() {
+        let mock = Arc::new(MockS3Client::default());
+        let service = StorageService::with_mock(mock.clone());
+        let (json_hash, zip_hash, json_key) = seed_legacy_events(&service).await;
+        // File the archive under a relay, which sorts after the date-layout object
+        let zip_key = format!("events/relays/relay-1/2024/01/03/{zip_hash}.zip");
+        let zip_data = mock.object(&format!("events/{zip_hash}.zip")).unwrap().body;
+        service
+            .s3_operations
+            .put_object(
+                &service.config.bucket,
+                &zip_key,
+                zip_data,
+                "application/zip",
+            )
+            .await
+            .unwrap();
+        service
+            .s3_operations
+            .delete_object(&service.config.bucket, &format!("events/{zip_hash}.zip"))
+            .await
+            .unwrap();
+
+        // An earlier run stopped right after the date-layout object
+        let progress = MigrationProgress {
+            completed_layout: None,
+            last_key: Some(json_key.clone()),
+        };
+        service
+            .save_migration_progress(MIGRATION_PROGRESS_KEY, &progress)
+            .await
+            .unwrap();
+
+        let hash_of = |event_package: &EventPackage| Ok(event_hash(&service, event_package));
+        let report = service.migrate_legacy_keys(hash_of).await.unwrap();
+        assert!(!service.event_exists(&json_hash).await.unwrap());
+        assert!(service.event_exists(&zip_hash).await.unwrap());
+        assert_eq!(
+            report,
+            MigrationReport {
+                indexed: 1,
+                moved: 1,
+                skipped: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_migration_pages_through_every_key() {
+        let mock = Arc::new(MockS3Client::default());
+        let service = StorageService::with_mock(mock.clone());
+        // More non-event objects than fit in a page, all sorting before the events
+        for i in 0..=MIGRATION_PAGE_SIZE {
+            service
+                .s3_operations
+                .put_object(
+                    &service.config.bucket,
+                    &format!("events/0000/filler-{i:05}.txt"),
+                    Vec::new(),
+                    "text/plain",
+                )
+                .await
+                .unwrap();
+        }
+        let (json_hash, zip_hash, _) = seed_legacy_events(&service).await;
+
+        let hash_of = |event_package: &EventPackage| Ok(event_hash(&service, event_package));
+        let report = service.migrate_legacy_keys(hash_of).await.unwrap();
+        assert_eq!(report.indexed, 2);
+        for hash in [&json_hash, &zip_hash] {
+            assert!(service.event_exists(hash).await.unwrap());
+        }
+        assert!(mock.object(MIGRATION_LEASE_KEY).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_migration_keeps_relay_of_relay_keys() {
+        let mock = Arc::new(MockS3Client::default());
+        let mut service = StorageService::with_mock(mock.clone());
+        service.config.key_layout = StorageLayout::RelayHierarchy;
+        let event_package = crate::test_utils::sample_event();
+        let hash = event_hash(&service, &event_package);
+        // Filed under a relay, but not in the dated relay layout
+        service
+            .s3_operations
+            .put_object(
+                &service.config.bucket,
+                &format!("events/relays/relay-1/{hash}.json"),
+                serde_json::to_vec(&event_package).unwrap(),
+                "application/json",
+            )
+            .await
+            .unwrap();
+
+        let hash_of = |event_package: &EventPackage| Ok(event_hash(&service, event_package));
+        let report = service.migrate_legacy_keys(hash_of).await.unwrap();
+        assert_eq!(report.moved, 1);
+        assert_eq!(
+            service.resolve_primary_key(&hash).await.unwrap(),
+            format!(
+                "events/relays/relay-1/{}/{hash}.json",
+                event_package.metadata.created_at.format("%Y/%m/%d")
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_migration_waits_for_a_live_lease() {
+        let mock = Arc::new(MockS3Client::default());
+        let service = StorageService::with_mock(mock.clone());
+        let (json_hash, _, _) = seed_legacy_events(&service).await;
+        let hash_of = |event_package: &EventPackage| Ok(event_hash(&service, event_package));
+
+        // Another instance is migrating
+        let held = MigrationLease::new();
+        service
+            .write_migration_lease(MIGRATION_LEASE_KEY, &held)
+            .await
+            .unwrap();
+        let report = service.migrate_legacy_keys(hash_of).await.unwrap();
+        assert_eq!(report, MigrationReport::default());
+        assert!(!service.event_exists(&json_hash).await.unwrap());
+
+        // It stopped without releasing the lease, which then expired
+        let expired = MigrationLease {
+            expires_at: Utc::now() - chrono::Duration::seconds(1),
+            ..held
+        };
+        service
+            .write_migration_lease(MIGRATION_LEASE_KEY, &expired)
+            .await
+            .unwrap();
+        let report = service.migrate_legacy_keys(hash_of).await.unwrap();
+        assert_eq!(report.indexed, 2);
+        assert!(service.event_exists(&json_hash).await.unwrap());
+        assert!(mock.object(MIGRATION_LEASE_KEY).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_expired_lease_is_taken_over_by_one_instance() {
+        // Slow uploads let both instances read the expired lease before either replaces it
+        let mock = Arc::new(MockS3Client::with_put_delay(Duration::from_millis(50)));
+        let first = StorageService::with_mock(mock.clone());
+        let second = StorageService::with_mock(mock.clone());
+        let expired = MigrationLease {
+            expires_at: Utc::now() - chrono::Duration::seconds(1),
+            ..MigrationLease::new()
+        };
+        first
+            .write_migration_lease(MIGRATION_LEASE_KEY, &expired)
+            .await
+            .unwrap();
+
+        let (a, b) = tokio::join!(
+            first.acquire_migration_lease(MIGRATION_LEASE_KEY),
+            second.acquire_migration_lease(MIGRATION_LEASE_KEY)
+        );
+        let winners: Vec<MigrationLease> = [a.unwrap(), b.unwrap()].into_iter().flatten().collect();
+        assert_eq!(winners.len(), 1);
+        let (held, _) = first
+            .read_migration_lease(MIGRATION_LEASE_KEY)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(held.holder, winners[0].holder);
+    }
+
+    #[tokio::test]
+    async fn test_lease_taken_over_is_neither_renewed_nor_released() {
+        let mock = Arc::new(MockS3Client::default());
+        let service = StorageService::with_mock(mock.clone());
+        let lease = service
+            .acquire_migration_lease(MIGRATION_LEASE_KEY)
+            .await
+            .unwrap()
+            .unwrap();
+        service
+            .renew_migration_lease(MIGRATION_LEASE_KEY, &lease)
+            .await
+            .unwrap();
+
+        // This run stalled past expiry and another instance took the lease over
+        let other = MigrationLease::new();
+        service
+            .write_migration_lease(MIGRATION_LEASE_KEY, &other)
+            .await
+            .unwrap();
+
+        let err = service
+            .renew_migration_lease(MIGRATION_LEASE_KEY, &lease)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, EventServerError::Conflict(_)));
+        service
+            .release_migration_lease(MIGRATION_LEASE_KEY, &lease)
+            .await;
+        let (held, _) = service
+            .read_migration_lease(MIGRATION_LEASE_KEY)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(held.holder, other.holder);
+    }
+}
+
+/// End-to-end tests against a real MinIO instance
+/// Run with `cargo test --features integration` (requires Docker)
+#[cfg(all(test, feature = "integration"))]
+mod integration_tests {
+    use super::*;
+    use crate::types::event::{EventAnnotation, EventMetadata, EventSource, FieldValue};
+    use aws_sdk_s3::config::Credentials;
+    use testcontainers_modules::minio::MinIO;
+    use testcontainers_modules::testcontainers::{runners::AsyncRunner, ContainerAsync};
+
+    const MINIO_USER: &str = "minioadmin";
+    const MINIO_PASSWORD: &str = "minioadmin";
+
+    /// Storage service backed by `node`, with its bucket created
+    async fn minio_service(node: &ContainerAsync<MinIO>) -> StorageService {
+        let host = node.get_host().await.unwrap();
+        let port = node.get_host_port_ipv4(9000).await.unwrap();
+
+        let config = StorageConfig {
+            endpoint: Some(format!("http://{host}:{port}")),
+            bucket: "integration-bucket".to_string(),
+            access_key_id: MINIO_USER.to_string(),
+            secret_access_key: MINIO_PASSWORD.to_string(),
+            use_path_style: true,
+            enable_ssl: false,
+            ..StorageConfig::default()
+        };
+
+        // The service resolves credentials through the default provider chain
+        std::env::set_var("AWS_ACCESS_KEY_ID", MINIO_USER);
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", MINIO_PASSWORD);
+        let service = StorageService::new(config.clone()).await.unwrap();
+
+        // Create the bucket with a separately configured client
+        let aws_config = aws_config::defaults(BehaviorVersion::latest())
+            .region(Region::new(config.region.clone()))
+            .endpoint_url(config.endpoint.clone().unwrap())
+            .credentials_provider(Credentials::new(
+                MINIO_USER,
+                MINIO_PASSWORD,
+                None,
+                None,
+                "integration-test",
+            ))
+            .load()
+            .await;
+        let client = S3Client::from_conf(
+            aws_sdk_s3::config::Builder::from(&aws_config)
+                .force_path_style(true)
+                .build(),
+        );
+        client
+            .create_bucket()
+            .bucket(&config.bucket)
+            .send()
+            .await
+            .unwrap();
+        service
+    }
+
+    #[tokio::test]
+    async fn test_minio_store_exists_get_delete() {
+        let node = MinIO::default().start().await.expect("MinIO container");
+        let service = minio_service(&node).await;
+
+        let event_package = EventPackage {
+            id: Uuid::new_v4(),
+            version: "1.0".to_string(),
+            annotations: vec![EventAnnotation {
+                label_id: "test_label".to_string(),
+                value: FieldValue::String("test_value".to_string()),
+                timestamp: Utc::now(),
+            }],
+            media: None,
+            metadata: EventMetadata {
+                created_at: Utc::now(),
+                created_by: Some("test_user".to_string()),
+                source: EventSource::Web,
+            },
+        };
+        let hash = "a".repeat(64);
+
+        assert!(!service.event_exists(&hash).await.unwrap());
+
+        service
+            .store_event(&event_package, &hash, "test_relay")
+            .await
+            .unwrap();
+        assert!(service.event_exists(&hash).await.unwrap());
+
+        let retrieved = service.retrieve_event(&hash).await.unwrap();
+        assert_eq!(retrieved.id, event_package.id);
+        assert_eq!(retrieved.annotations.len(), 1);
+
+        service.delete_event(&hash).await.unwrap();
+        assert!(!service.event_exists(&hash).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_minio_migrates_legacy_keys() {
+        let node = MinIO::default().start().await.expect("MinIO container");
+        let mut service = minio_service(&node).await;
+        service.config.key_layout = StorageLayout::Flat;
+
+        let (json_hash, zip_hash, json_key) = super::tests::seed_legacy_events(&service).await;
+        let hash_of =
+            |event_package: &EventPackage| Ok(super::tests::event_hash(&service, event_package));
+        let report = service.migrate_legacy_keys(hash_of).await.unwrap();
+        assert_eq!(report.indexed, 2);
+
+        for hash in [&json_hash, &zip_hash] {
+            assert!(service.event_exists(hash).await.unwrap());
+            assert!(service.retrieve_event(hash).await.is_ok());
+        }
+        assert!(!service
+            .s3_operations
+            .head_object(&service.config.bucket, &json_key)
+            .await
+            .unwrap());
+
+        // Completed, so a second run does nothing
+        let report = service.migrate_legacy_keys(hash_of).await.unwrap();
+        assert_eq!(report, MigrationReport::default());
+    }
 }
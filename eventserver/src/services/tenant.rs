@@ -0,0 +1,297 @@
+use axum::http::{HeaderMap, HeaderValue};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{SecurityConfig, TenantSource};
+use crate::error::EventServerError;
+
+/// Header the crypto middleware sets to the tenant of an authenticated request
+pub const VALIDATED_TENANT_HEADER: &str = "X-Validated-Tenant-ID";
+
+/// Header carrying an admin-issued enrollment token at `/api/v1/pow/verify`
+pub const TENANT_ENROLLMENT_HEADER: &str = "x-tenant-enrollment";
+
+/// Audience of enrollment tokens, so no other token signed with the server secret passes
+const ENROLLMENT_AUDIENCE: &str = "tenant-enrollment";
+
+/// Claims of a tenant enrollment token
+#[derive(Debug, Serialize, Deserialize)]
+struct EnrollmentClaims {
+    sub: String, // Tenant ID
+    aud: String,
+    iat: i64,
+    exp: i64,
+}
+
+/// Longest accepted tenant ID
+const MAX_TENANT_ID_LEN: usize = 64;
+
+/// Tenant IDs become storage key segments, so only a conservative charset is accepted
+pub fn validate_tenant_id(tenant_id: &str) -> Result<(), EventServerError> {
+    let valid = !tenant_id.is_empty()
+        && tenant_id.len() <= MAX_TENANT_ID_LEN
+        && tenant_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(EventServerError::BadRequest(format!(
+            "Tenant ID must be 1-{MAX_TENANT_ID_LEN} letters, digits, '-' or '_'"
+        )))
+    }
+}
+
+/// Tenant named by the configured tenant header, when tenancy is enabled and the header is set
+pub fn requested_tenant(
+    security: &SecurityConfig,
+    headers: &HeaderMap,
+) -> Result<Option<String>, EventServerError> {
+    if security.tenant_source == TenantSource::Disabled {
+        return Ok(None);
+    }
+    let Some(value) = headers.get(security.tenant_header.as_str()) else {
+        return Ok(None);
+    };
+    let tenant_id = value
+        .to_str()
+        .map_err(|_| {
+            EventServerError::BadRequest(format!("Invalid {} header", security.tenant_header))
+        })?
+        .trim();
+    validate_tenant_id(tenant_id)?;
+    Ok(Some(tenant_id.to_string()))
+}
+
+/// Enrollment token letting devices obtain certificates for `tenant_id` until the returned expiry
+/// Signed with the server secret; only admins can mint them
+pub fn issue_enrollment_token(
+    security: &SecurityConfig,
+    tenant_id: &str,
+    now: DateTime<Utc>,
+) -> Result<(String, DateTime<Utc>), EventServerError> {
+    validate_tenant_id(tenant_id)?;
+    let expires_at = now + Duration::hours(security.tenant_enrollment_ttl_hours as i64);
+    let claims = EnrollmentClaims {
+        sub: tenant_id.to_string(),
+        aud: ENROLLMENT_AUDIENCE.to_string(),
+        iat: now.timestamp(),
+        exp: expires_at.timestamp(),
+    };
+    let token = encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(security.jwt_secret.as_bytes()),
+    )
+    .map_err(|e| EventServerError::Internal(format!("Failed to sign enrollment token: {e}")))?;
+    Ok((token, expires_at))
+}
+
+/// Tenant named by a valid, unexpired enrollment token
+fn verify_enrollment_token(
+    security: &SecurityConfig,
+    token: &str,
+) -> Result<String, EventServerError> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_audience(&[ENROLLMENT_AUDIENCE]);
+    let claims = decode::<EnrollmentClaims>(
+        token,
+        &DecodingKey::from_secret(security.jwt_secret.as_bytes()),
+        &validation,
+    )
+    .map_err(|e| EventServerError::Unauthorized(format!("Invalid tenant enrollment token: {e}")))?
+    .claims;
+    validate_tenant_id(&claims.sub)?;
+    Ok(claims.sub)
+}
+
+/// Tenant to bind into a certificate being issued
+/// `/api/v1/pow/verify` is public, so the tenant only ever comes from an admin-issued
+/// enrollment token; with tenancy enabled every certificate must carry a tenant claim
+pub fn issuance_tenant(
+    security: &SecurityConfig,
+    headers: &HeaderMap,
+) -> Result<Option<String>, EventServerError> {
+    if security.tenant_source == TenantSource::Disabled {
+        return Ok(None);
+    }
+    let token = headers
+        .get(TENANT_ENROLLMENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| {
+            EventServerError::Unauthorized(format!(
+                "Missing {TENANT_ENROLLMENT_HEADER} header; ask an administrator for an enrollment token"
+            ))
+        })?;
+    let tenant_id = verify_enrollment_token(security, token.trim())?;
+
+    if requested_tenant(security, headers)?.is_some_and(|requested| requested != tenant_id) {
+        return Err(EventServerError::Forbidden(
+            "Requested tenant does not match the enrollment token".to_string(),
+        ));
+    }
+    Ok(Some(tenant_id))
+}
+
+/// Tenant of an authenticated request whose certificate carries the tenant claim `claim`
+/// A header naming another tenant than the certificate is a cross-tenant attempt and gets 403,
+/// and with tenancy enabled a certificate without a claim is refused
+pub fn resolve_tenant(
+    security: &SecurityConfig,
+    headers: &HeaderMap,
+    claim: Option<&str>,
+) -> Result<Option<String>, EventServerError> {
+    let requested = requested_tenant(security, headers)?;
+    if let (Some(requested), Some(claim)) = (&requested, claim) {
+        if requested != claim {
+            return Err(EventServerError::Forbidden(
+                "Requested tenant does not match the certificate's tenant".to_string(),
+            ));
+        }
+    }
+
+    if security.tenant_source == TenantSource::Disabled {
+        return Ok(None);
+    }
+    let claim = claim.ok_or_else(|| {
+        EventServerError::Forbidden("Certificate is not bound to a tenant".to_string())
+    })?;
+    if security.tenant_source == TenantSource::Header && requested.is_none() {
+        return Err(EventServerError::BadRequest(format!(
+            "Missing {} header",
+            security.tenant_header
+        )));
+    }
+    Ok(Some(claim.to_string()))
+}
+
+/// Record the resolved tenant on a request, replacing whatever the client sent
+pub fn set_validated_tenant(headers: &mut HeaderMap, tenant_id: Option<&str>) {
+    headers.remove(VALIDATED_TENANT_HEADER);
+    if let Some(value) = tenant_id.and_then(|id| HeaderValue::from_str(id).ok()) {
+        headers.insert(VALIDATED_TENANT_HEADER, value);
+    }
+}
+
+/// Tenant of a request that passed the crypto middleware
+pub fn extract_validated_tenant_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(VALIDATED_TENANT_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+
+    fn security(tenant_source: TenantSource) -> SecurityConfig {
+        let mut security = AppConfig::default().security;
+        security.tenant_source = tenant_source;
+        security
+    }
+
+    fn tenant_header(tenant_id: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-tenant-id", tenant_id.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_resolve_tenant_by_source() {
+        let none = HeaderMap::new();
+        let acme = tenant_header("acme");
+
+        let disabled = security(TenantSource::Disabled);
+        assert_eq!(resolve_tenant(&disabled, &acme, None).unwrap(), None);
+
+        let certificate = security(TenantSource::Certificate);
+        assert_eq!(
+            resolve_tenant(&certificate, &none, Some("acme")).unwrap(),
+            Some("acme".to_string())
+        );
+        assert!(matches!(
+            resolve_tenant(&certificate, &none, None),
+            Err(EventServerError::Forbidden(_))
+        ));
+
+        let header = security(TenantSource::Header);
+        assert_eq!(
+            resolve_tenant(&header, &acme, Some("acme")).unwrap(),
+            Some("acme".to_string())
+        );
+        assert!(matches!(
+            resolve_tenant(&header, &none, Some("acme")),
+            Err(EventServerError::BadRequest(_))
+        ));
+        // The header alone can't pick a tenant for an unbound certificate
+        assert!(matches!(
+            resolve_tenant(&header, &acme, None),
+            Err(EventServerError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn test_issuance_tenant_comes_from_enrollment_token() {
+        let certificate = security(TenantSource::Certificate);
+        let (token, _) = issue_enrollment_token(&certificate, "acme", Utc::now()).unwrap();
+        let enrolled = |token: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(TENANT_ENROLLMENT_HEADER, token.parse().unwrap());
+            headers
+        };
+
+        assert_eq!(
+            issuance_tenant(&certificate, &enrolled(&token)).unwrap(),
+            Some("acme".to_string())
+        );
+
+        // A bare tenant header is not an authenticated source
+        assert!(matches!(
+            issuance_tenant(&certificate, &tenant_header("acme")),
+            Err(EventServerError::Unauthorized(_))
+        ));
+
+        // Nor is a token signed with another secret, or an expired one
+        let mut other = certificate.clone();
+        other.jwt_secret = "another-secret".to_string();
+        let (forged, _) = issue_enrollment_token(&other, "acme", Utc::now()).unwrap();
+        assert!(issuance_tenant(&certificate, &enrolled(&forged)).is_err());
+        let (expired, _) =
+            issue_enrollment_token(&certificate, "acme", Utc::now() - Duration::days(365)).unwrap();
+        assert!(issuance_tenant(&certificate, &enrolled(&expired)).is_err());
+
+        // A header naming another tenant than the token is refused
+        let mut headers = enrolled(&token);
+        headers.insert("x-tenant-id", "globex".parse().unwrap());
+        assert!(matches!(
+            issuance_tenant(&certificate, &headers),
+            Err(EventServerError::Forbidden(_))
+        ));
+
+        assert_eq!(
+            issuance_tenant(&security(TenantSource::Disabled), &HeaderMap::new()).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_header_naming_another_tenant_is_forbidden() {
+        for source in [TenantSource::Certificate, TenantSource::Header] {
+            assert!(matches!(
+                resolve_tenant(&security(source), &tenant_header("globex"), Some("acme")),
+                Err(EventServerError::Forbidden(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_unsafe_tenant_ids_rejected() {
+        for tenant_id in ["", "../acme", "acme/other", &"a".repeat(65)] {
+            assert!(validate_tenant_id(tenant_id).is_err(), "{tenant_id}");
+        }
+        assert!(validate_tenant_id("acme-eu_1").is_ok());
+    }
+}
@@ -1,20 +1,53 @@
 use base64::{engine::general_purpose, Engine as _};
-use chrono::Utc;
+use sha2::{Digest, Sha256};
 use std::io::{Cursor, Write};
-use tracing::{info, warn};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tracing::info;
 use zip::{write::FileOptions, CompressionMethod, ZipWriter};
 
 use crate::error::EventServerError;
 use crate::types::event::{EventMedia, EventPackage};
 
+/// Archive entry holding the event's summary
+pub const METADATA_ENTRY: &str = "metadata.json";
+/// Archive entry holding the annotation list
+pub const ANNOTATIONS_ENTRY: &str = "annotations.json";
+/// Archive entry describing the media file
+pub const MEDIA_METADATA_ENTRY: &str = "media_metadata.json";
+/// Keys `ZipPackager` always writes to the metadata entry
+pub const METADATA_KEYS: [&str; 7] = [
+    "id",
+    "version",
+    "createdAt",
+    "createdBy",
+    "source",
+    "annotationCount",
+    "hasMedia",
+];
+
 /// Service for creating ZIP packages from EventPackage objects
 pub struct ZipPackager;
 
 impl ZipPackager {
+    /// Creates a ZIP archive containing the event package data, decoding its media first
+    #[cfg(test)]
+    pub fn create_zip_from_event_package(
+        event_package: &EventPackage,
+        options: ZipPackageOptions,
+    ) -> Result<Vec<u8>, EventServerError> {
+        let max_media_bytes = crate::config::storage::StorageConfig::default().max_file_size;
+        let media_data = Self::decode_event_media(event_package, max_media_bytes)?;
+        Self::create_zip_with_media(event_package, media_data.as_deref(), options)
+    }
+
     /// Creates a ZIP archive containing the event package data
-    /// Replicates the frontend zip-exporter.ts functionality
-    pub async fn create_zip_from_event_package(
+    /// Replicates the frontend zip-exporter.ts functionality. `media_data` is the package's
+    /// media as returned by `decode_event_media`. This is CPU-bound; request handlers go
+    /// through `ZipPackagingLimiter` instead of calling it directly
+    pub fn create_zip_with_media(
         event_package: &EventPackage,
+        media_data: Option<&[u8]>,
         options: ZipPackageOptions,
     ) -> Result<Vec<u8>, EventServerError> {
         let mut zip_buffer = Vec::new();
@@ -26,22 +59,26 @@ impl ZipPackager {
         let file_options = FileOptions::default()
             .compression_method(CompressionMethod::Deflated)
             .unix_permissions(0o644);
+        let mut entries = EntryBudget::new(options.max_entries);
 
         // Add metadata file if requested
         if options.include_metadata {
-            let metadata = serde_json::json!({
-                "id": event_package.id,
-                "version": event_package.version,
-                "createdAt": event_package.metadata.created_at,
-                "createdBy": event_package.metadata.created_by,
-                "source": event_package.metadata.source,
-                "annotationCount": event_package.annotations.len(),
-                "hasMedia": event_package.media.is_some()
-            });
-
-            zip.start_file("metadata.json", file_options).map_err(|e| {
-                EventServerError::Storage(format!("Failed to create metadata.json: {e}"))
-            })?;
+            let values = [
+                serde_json::json!(event_package.id),
+                serde_json::json!(event_package.version),
+                serde_json::json!(event_package.metadata.created_at),
+                serde_json::json!(event_package.metadata.created_by),
+                serde_json::json!(event_package.metadata.source),
+                serde_json::json!(event_package.annotations.len()),
+                serde_json::json!(event_package.media.is_some()),
+            ];
+            let metadata: serde_json::Map<String, serde_json::Value> = METADATA_KEYS
+                .into_iter()
+                .map(str::to_string)
+                .zip(values)
+                .collect();
+
+            entries.start(&mut zip, METADATA_ENTRY, file_options)?;
 
             zip.write_all(
                 serde_json::to_string_pretty(&metadata)
@@ -54,10 +91,7 @@ impl ZipPackager {
         }
 
         // Add annotations as JSON file
-        zip.start_file("annotations.json", file_options)
-            .map_err(|e| {
-                EventServerError::Storage(format!("Failed to create annotations.json: {e}"))
-            })?;
+        entries.start(&mut zip, ANNOTATIONS_ENTRY, file_options)?;
 
         zip.write_all(
             serde_json::to_string_pretty(&event_package.annotations)
@@ -70,21 +104,16 @@ impl ZipPackager {
 
         // Add media file if available and requested
         if options.include_media {
-            if let Some(media) = &event_package.media {
-                match Self::add_media_to_zip(
+            if let (Some(media), Some(media_data)) = (&event_package.media, media_data) {
+                Self::add_media_to_zip(
                     &mut zip,
+                    &mut entries,
                     media,
+                    media_data,
                     file_options,
                     options.include_metadata,
-                )
-                .await
-                {
-                    Ok(_) => info!("Successfully added media to ZIP"),
-                    Err(e) => {
-                        warn!("Failed to add media to ZIP: {}", e);
-                        // Continue without failing, just log the error (matches frontend behavior)
-                    }
-                }
+                )?;
+                info!("Successfully added media to ZIP");
             }
         }
 
@@ -106,24 +135,22 @@ impl ZipPackager {
     }
 
     /// Add media file to the ZIP archive
-    async fn add_media_to_zip(
+    fn add_media_to_zip(
         zip: &mut ZipWriter<Cursor<&mut Vec<u8>>>,
+        entries: &mut EntryBudget,
         media: &EventMedia,
+        media_data: &[u8],
         file_options: FileOptions,
         include_metadata: bool,
     ) -> Result<(), EventServerError> {
-        // Decode base64 media data
-        let media_data = Self::decode_base64_media(&media.data)?;
-
         // Get file extension from media type
         let extension = Self::get_file_extension(media.media_type.as_str());
         let filename = format!("media.{extension}");
 
         // Add the media file
-        zip.start_file(&filename, file_options)
-            .map_err(|e| EventServerError::Storage(format!("Failed to create media file: {e}")))?;
+        entries.start(zip, &filename, file_options)?;
 
-        zip.write_all(&media_data)
+        zip.write_all(media_data)
             .map_err(|e| EventServerError::Storage(format!("Failed to write media data: {e}")))?;
 
         // Add media metadata if requested
@@ -132,15 +159,12 @@ impl ZipPackager {
                 "originalName": media.name,
                 "type": media.media_type.as_str(),
                 "size": media.size,
+                "sha256": Self::media_digest(media_data),
                 "lastModified": chrono::DateTime::from_timestamp_millis(media.last_modified as i64)
-                    .unwrap_or_else(Utc::now)
-                    .to_rfc3339()
+                    .map(|modified| modified.to_rfc3339())
             });
 
-            zip.start_file("media_metadata.json", file_options)
-                .map_err(|e| {
-                    EventServerError::Storage(format!("Failed to create media_metadata.json: {e}"))
-                })?;
+            entries.start(zip, MEDIA_METADATA_ENTRY, file_options)?;
 
             zip.write_all(
                 serde_json::to_string_pretty(&media_metadata)
@@ -159,22 +183,87 @@ impl ZipPackager {
         Ok(())
     }
 
-    /// Decode base64 media data, handling data URL prefixes
-    fn decode_base64_media(base64_data: &str) -> Result<Vec<u8>, EventServerError> {
-        // Remove data URL prefix if present (e.g., "data:image/jpeg;base64,")
-        let clean_base64 = if base64_data.contains("base64,") {
-            base64_data.split("base64,").nth(1).unwrap_or(base64_data)
-        } else {
-            base64_data
+    /// Decode the event's media, if any, rejecting media that isn't valid base64, decodes
+    /// past `max_media_bytes`, or doesn't match its declared digest
+    /// Handlers call this once while validating and hand the bytes on to packaging and storage
+    pub fn decode_event_media(
+        event_package: &EventPackage,
+        max_media_bytes: u64,
+    ) -> Result<Option<Vec<u8>>, EventServerError> {
+        let Some(media) = &event_package.media else {
+            return Ok(None);
         };
+        // Undecodable media is the client's fault, not a storage failure
+        let media_data =
+            Self::decode_base64_media(&media.data, max_media_bytes).map_err(|e| match e {
+                EventServerError::Storage(message) => EventServerError::Validation(message),
+                e => e,
+            })?;
+
+        if let Some(expected) = &media.sha256 {
+            let actual = Self::media_digest(&media_data);
+            if !actual.eq_ignore_ascii_case(expected) {
+                return Err(EventServerError::Validation(format!(
+                    "Media sha256 mismatch: expected {expected}, got {actual}"
+                )));
+            }
+        }
+        Ok(Some(media_data))
+    }
+
+    /// Hex-encoded SHA-256 of decoded media bytes
+    fn media_digest(media_data: &[u8]) -> String {
+        hex::encode(Sha256::digest(media_data))
+    }
+
+    /// Decode base64 media data, handling data URL prefixes
+    /// Fails with `PayloadTooLarge` before allocating when the data would decode past `max_media_bytes`
+    pub fn decode_base64_media(
+        base64_data: &str,
+        max_media_bytes: u64,
+    ) -> Result<Vec<u8>, EventServerError> {
+        let clean_base64 = Self::strip_data_url(base64_data);
+        Self::check_decoded_size(clean_base64, max_media_bytes)?;
 
         general_purpose::STANDARD
             .decode(clean_base64)
             .map_err(|e| EventServerError::Storage(format!("Failed to decode base64 media: {e}")))
     }
 
+    /// Remove a data URL prefix if present (e.g., "data:image/jpeg;base64,")
+    pub(crate) fn strip_data_url(base64_data: &str) -> &str {
+        match base64_data.split_once("base64,") {
+            Some((_, data)) => data,
+            None => base64_data,
+        }
+    }
+
+    /// Bytes that padded base64 of this length decodes to, an upper bound for malformed input
+    fn decoded_len(clean_base64: &str) -> u64 {
+        let padding = clean_base64
+            .bytes()
+            .rev()
+            .take(2)
+            .take_while(|&b| b == b'=')
+            .count() as u64;
+        (clean_base64.len() as u64).div_ceil(4) * 3 - padding.min(2)
+    }
+
+    fn check_decoded_size(
+        clean_base64: &str,
+        max_media_bytes: u64,
+    ) -> Result<(), EventServerError> {
+        let decoded_len = Self::decoded_len(clean_base64);
+        if decoded_len > max_media_bytes {
+            return Err(EventServerError::PayloadTooLarge(format!(
+                "Media decodes to {decoded_len} bytes, exceeding the {max_media_bytes}-byte limit"
+            )));
+        }
+        Ok(())
+    }
+
     /// Extract file extension from MIME type
-    fn get_file_extension(mime_type: &str) -> &str {
+    pub fn get_file_extension(mime_type: &str) -> &str {
         match mime_type {
             "image/jpeg" => "jpg",
             "image/png" => "png",
@@ -188,6 +277,88 @@ impl ZipPackager {
     }
 }
 
+/// Runs ZIP packaging on the blocking thread pool, at most `max_concurrent` jobs at a time
+/// Excess jobs queue for a slot, so a burst of media-heavy events can't take every core
+/// away from request handling
+#[derive(Debug, Clone)]
+pub struct ZipPackagingLimiter {
+    permits: Arc<Semaphore>,
+}
+
+impl ZipPackagingLimiter {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_concurrent.max(1))),
+        }
+    }
+
+    /// Package `event_package` with its decoded `media_data` once a slot is free
+    pub async fn package(
+        &self,
+        event_package: &EventPackage,
+        media_data: Option<Arc<[u8]>>,
+        options: ZipPackageOptions,
+    ) -> Result<Vec<u8>, EventServerError> {
+        let event_package = event_package.clone();
+        self.run(move || {
+            ZipPackager::create_zip_with_media(&event_package, media_data.as_deref(), options)
+        })
+        .await?
+    }
+
+    /// Run `job` on the blocking pool once a slot is free
+    /// The slot is held by the job itself, so it stays taken until the work is done even
+    /// if the caller stops waiting. Other CPU-bound archive work shares these slots
+    pub async fn run<T: Send + 'static>(
+        &self,
+        job: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<T, EventServerError> {
+        let permit =
+            self.permits.clone().acquire_owned().await.map_err(|_| {
+                EventServerError::Internal("ZIP packaging is shut down".to_string())
+            })?;
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            job()
+        })
+        .await
+        .map_err(|e| EventServerError::Internal(format!("ZIP packaging task failed: {e}")))
+    }
+}
+
+/// Counts archive entries as they are started, refusing to go past the cap
+struct EntryBudget {
+    started: usize,
+    max_entries: usize,
+}
+
+impl EntryBudget {
+    fn new(max_entries: usize) -> Self {
+        Self {
+            started: 0,
+            max_entries,
+        }
+    }
+
+    fn start(
+        &mut self,
+        zip: &mut ZipWriter<Cursor<&mut Vec<u8>>>,
+        name: &str,
+        file_options: FileOptions,
+    ) -> Result<(), EventServerError> {
+        if self.started >= self.max_entries {
+            return Err(EventServerError::Validation(format!(
+                "Archive would exceed the {}-entry limit",
+                self.max_entries
+            )));
+        }
+        zip.start_file(name, file_options)
+            .map_err(|e| EventServerError::Storage(format!("Failed to create {name}: {e}")))?;
+        self.started += 1;
+        Ok(())
+    }
+}
+
 /// Options for ZIP package creation
 #[derive(Debug, Clone)]
 pub struct ZipPackageOptions {
@@ -195,6 +366,8 @@ pub struct ZipPackageOptions {
     pub include_metadata: bool,
     /// Include media file in the ZIP (default: true)
     pub include_media: bool,
+    /// Most entries the archive may hold (default: the storage default entry cap)
+    pub max_entries: usize,
 }
 
 impl Default for ZipPackageOptions {
@@ -202,6 +375,7 @@ impl Default for ZipPackageOptions {
         Self {
             include_metadata: true,
             include_media: true,
+            max_entries: crate::config::storage::StorageConfig::default().zip_max_entries,
         }
     }
 }
@@ -209,7 +383,9 @@ impl Default for ZipPackageOptions {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::event::{EventAnnotation, EventMetadata, EventSource, FieldValue};
+    use crate::test_utils::sample_media;
+    use crate::types::event::{EventAnnotation, EventMetadata, EventSource, FieldValue, MediaType};
+    use chrono::Utc;
     use uuid::Uuid;
 
     #[tokio::test]
@@ -231,13 +407,142 @@ mod tests {
         };
 
         let options = ZipPackageOptions::default();
-        let zip_data = ZipPackager::create_zip_from_event_package(&event_package, options).await;
+        let zip_data = ZipPackager::create_zip_from_event_package(&event_package, options);
 
         assert!(zip_data.is_ok());
         let zip_bytes = zip_data.unwrap();
         assert!(!zip_bytes.is_empty());
     }
 
+    fn event_with_media(sha256: Option<&str>) -> EventPackage {
+        EventPackage {
+            id: Uuid::new_v4(),
+            version: "1.0".to_string(),
+            annotations: vec![EventAnnotation {
+                label_id: "test_label".to_string(),
+                value: FieldValue::String("test_value".to_string()),
+                timestamp: Utc::now(),
+            }],
+            media: Some(EventMedia {
+                sha256: sha256.map(str::to_string),
+                ..sample_media(MediaType::ImageJpeg, b"Hello World")
+            }),
+            metadata: EventMetadata {
+                created_at: Utc::now(),
+                created_by: Some("test_user".to_string()),
+                source: EventSource::Web,
+            },
+        }
+    }
+
+    fn recorded_media_digest(zip_bytes: Vec<u8>) -> String {
+        let mut archive = zip::ZipArchive::new(Cursor::new(zip_bytes)).unwrap();
+        let file = archive.by_name("media_metadata.json").unwrap();
+        let metadata: serde_json::Value = serde_json::from_reader(file).unwrap();
+        metadata["sha256"].as_str().unwrap().to_string()
+    }
+
+    const HELLO_WORLD_SHA256: &str =
+        "a591a6d40bf420404a011733cfb7b190d62c65bf0bcda32b57b277d9ad9f146e";
+
+    #[test]
+    fn test_archive_entry_cap() {
+        // metadata.json, annotations.json, the media and media_metadata.json
+        let event_package = event_with_media(None);
+        let options = |max_entries| ZipPackageOptions {
+            max_entries,
+            ..ZipPackageOptions::default()
+        };
+
+        let result = ZipPackager::create_zip_from_event_package(&event_package, options(3));
+        assert!(matches!(
+            result,
+            Err(EventServerError::Validation(msg)) if msg.contains("3-entry limit")
+        ));
+
+        let zip_bytes =
+            ZipPackager::create_zip_from_event_package(&event_package, options(4)).unwrap();
+        assert_eq!(
+            zip::ZipArchive::new(Cursor::new(zip_bytes)).unwrap().len(),
+            4
+        );
+    }
+
+    #[tokio::test]
+    async fn test_matching_media_digest() {
+        let event_package = event_with_media(Some(&HELLO_WORLD_SHA256.to_uppercase()));
+        let zip_bytes =
+            ZipPackager::create_zip_from_event_package(&event_package, Default::default()).unwrap();
+
+        assert_eq!(recorded_media_digest(zip_bytes), HELLO_WORLD_SHA256);
+    }
+
+    #[tokio::test]
+    async fn test_mismatching_media_digest_is_rejected() {
+        let event_package = event_with_media(Some(&"0".repeat(64)));
+        let result = ZipPackager::create_zip_from_event_package(&event_package, Default::default());
+
+        assert!(
+            matches!(result, Err(EventServerError::Validation(msg)) if msg.contains("mismatch"))
+        );
+    }
+
+    #[test]
+    fn test_undecodable_media_is_rejected() {
+        let mut event_package = event_with_media(None);
+        event_package.media.as_mut().unwrap().data = "not base64!".to_string();
+        let result = ZipPackager::create_zip_from_event_package(&event_package, Default::default());
+
+        assert!(matches!(result, Err(EventServerError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_absent_media_digest_is_computed() {
+        let event_package = event_with_media(None);
+        let zip_bytes =
+            ZipPackager::create_zip_from_event_package(&event_package, Default::default()).unwrap();
+
+        assert_eq!(recorded_media_digest(zip_bytes), HELLO_WORLD_SHA256);
+    }
+
+    /// Most jobs seen running at once when two are started together
+    async fn peak_concurrency(max_concurrent: usize) -> usize {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let limiter = ZipPackagingLimiter::new(max_concurrent);
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let job = || {
+            let (running, peak) = (running.clone(), peak.clone());
+            move || {
+                peak.fetch_max(running.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                running.fetch_sub(1, Ordering::SeqCst);
+            }
+        };
+        let (first, second) = tokio::join!(limiter.run(job()), limiter.run(job()));
+        first.unwrap();
+        second.unwrap();
+        peak.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn test_packaging_concurrency_is_bounded() {
+        assert_eq!(peak_concurrency(1).await, 1);
+        assert_eq!(peak_concurrency(2).await, 2);
+
+        let limiter = ZipPackagingLimiter::new(1);
+        let event_package = event_with_media(None);
+        let media_data = ZipPackager::decode_event_media(&event_package, 1024)
+            .unwrap()
+            .map(Arc::from);
+        let zip_bytes = limiter
+            .package(&event_package, media_data, Default::default())
+            .await
+            .unwrap();
+        assert_eq!(recorded_media_digest(zip_bytes), HELLO_WORLD_SHA256);
+    }
+
     #[test]
     fn test_get_file_extension() {
         assert_eq!(ZipPackager::get_file_extension("image/jpeg"), "jpg");
@@ -251,14 +556,31 @@ mod tests {
     fn test_decode_base64_media() {
         // Test with data URL prefix
         let data_url = "data:image/jpeg;base64,SGVsbG8gV29ybGQ=";
-        let result = ZipPackager::decode_base64_media(data_url);
+        let result = ZipPackager::decode_base64_media(data_url, 1024);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), b"Hello World");
 
         // Test without prefix
         let plain_base64 = "SGVsbG8gV29ybGQ=";
-        let result = ZipPackager::decode_base64_media(plain_base64);
+        let result = ZipPackager::decode_base64_media(plain_base64, 1024);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), b"Hello World");
     }
+
+    #[test]
+    fn test_decode_base64_media_over_limit() {
+        // "Hello World" is exactly 11 bytes, so the limit is inclusive
+        assert!(ZipPackager::decode_base64_media("SGVsbG8gV29ybGQ=", 11).is_ok());
+        assert!(matches!(
+            ZipPackager::decode_base64_media("SGVsbG8gV29ybGQ=", 10),
+            Err(EventServerError::PayloadTooLarge(_))
+        ));
+
+        // Rejected from the length alone, even though this would not decode at all
+        let huge = "!".repeat(4 * 1024 * 1024);
+        let result = ZipPackager::decode_base64_media(&huge, 1024 * 1024);
+        assert!(
+            matches!(result, Err(EventServerError::PayloadTooLarge(msg)) if msg.contains("3145728 bytes"))
+        );
+    }
 }
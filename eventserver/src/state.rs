@@ -1,4 +1,14 @@
-use crate::crypto::{CertificateService, PowService};
+use std::sync::Arc;
+
+use crate::config::AppConfig;
+use crate::controllers::health::HealthTracker;
+use crate::crypto::{CertificateService, PowService, ReplayCache};
+use crate::middleware::rate_limit::RateLimiter;
+use crate::services::event_bus::EventBus;
+use crate::services::jobs::{BackgroundStores, JobTable};
+use crate::services::relay::RelayService;
+use crate::services::request_dedup::RequestDedupCache;
+use crate::services::zip_packager::ZipPackagingLimiter;
 use crate::services::{EventService, StorageService};
 
 /// Unified application state containing all services
@@ -9,6 +19,17 @@ pub struct AppState {
     pub storage_service: StorageService,
     pub pow_service: PowService,
     pub certificate_service: CertificateService,
+    pub replay_cache: ReplayCache,
+    pub request_dedup: RequestDedupCache, // Recent responses to byte-identical authenticated requests
+    pub jobs: JobTable,                   // Background storage jobs from async-accepted submissions
+    pub background_stores: BackgroundStores, // Running background stores, drained on shutdown
+    pub health: HealthTracker,            // Process uptime and last successful storage probe
+    pub rate_limiter: RateLimiter, // Per-relay or per-certificate request budget for protected routes
+    pub capture_limiter: RateLimiter, // Shared budget for debug captures of failed requests
+    pub relay_service: RelayService, // Relay registry consulted when relay status is enforced
+    pub zip_packaging: ZipPackagingLimiter, // Bounds concurrent CPU-bound ZIP packaging
+    pub event_bus: EventBus,       // Publishes stored events to the configured message queue
+    pub config: Arc<AppConfig>,
 }
 
 impl AppState {
@@ -18,12 +39,47 @@ impl AppState {
         storage_service: StorageService,
         pow_service: PowService,
         certificate_service: CertificateService,
+        config: AppConfig,
     ) -> Self {
         Self {
             event_service,
             storage_service,
             pow_service,
             certificate_service,
+            replay_cache: ReplayCache::default(),
+            request_dedup: RequestDedupCache::new(std::time::Duration::from_secs(
+                config.server.body_dedup_ttl_seconds,
+            )),
+            jobs: JobTable::new(chrono::Duration::seconds(
+                config.server.job_retention_seconds as i64,
+            )),
+            background_stores: BackgroundStores::new(config.server.max_background_stores),
+            health: HealthTracker::default(),
+            rate_limiter: RateLimiter::new(
+                config.security.rate_limit_per_minute,
+                config.security.rate_limit_key,
+            ),
+            capture_limiter: RateLimiter::new(
+                config.security.capture_max_per_minute,
+                config.security.rate_limit_key,
+            ),
+            relay_service: RelayService::new(config.clone()),
+            zip_packaging: ZipPackagingLimiter::new(config.storage.zip_max_concurrent),
+            event_bus: EventBus::from_config(&config.event_bus),
+            config: Arc::new(config),
         }
     }
+
+    /// Create a state backed by mock storage for testing
+    #[cfg(test)]
+    pub async fn new_mock(config: AppConfig) -> Self {
+        let storage_service = StorageService::new_mock().await;
+        Self::new(
+            EventService::new(storage_service.clone(), config.validation.clone()),
+            storage_service,
+            PowService::new(),
+            CertificateService::default(),
+            config,
+        )
+    }
 }
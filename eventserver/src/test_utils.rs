@@ -0,0 +1,157 @@
+//! Shared helpers for router-level tests that need signed event submissions
+
+use axum::body::Body;
+use axum::http::Request;
+use base64::Engine;
+use chrono::Utc;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use p256::elliptic_curve::rand_core::OsRng;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::pkcs8::EncodePrivateKey;
+use p256::SecretKey;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+use crate::crypto::{CertificateRequest, SeedCipher};
+use crate::services::zip_packager::ZipPackager;
+use crate::state::AppState;
+use crate::types::event::{
+    EventAnnotation, EventMedia, EventMetadata, EventPackage, EventSource, FieldValue, MediaType,
+    SignedEventPackage,
+};
+
+/// A minimal event package that passes validation
+pub fn sample_event() -> EventPackage {
+    EventPackage {
+        id: Uuid::new_v4(),
+        version: "1.0".to_string(),
+        annotations: vec![EventAnnotation {
+            label_id: "test_label".to_string(),
+            value: FieldValue::String("test_value".to_string()),
+            timestamp: Utc::now(),
+        }],
+        media: None,
+        metadata: EventMetadata {
+            created_at: Utc::now(),
+            created_by: Some("test_user".to_string()),
+            source: EventSource::Web,
+        },
+    }
+}
+
+/// Media attachment carrying `bytes`, with a matching declared size and no digest
+pub fn sample_media(media_type: MediaType, bytes: &[u8]) -> EventMedia {
+    EventMedia {
+        name: format!(
+            "photo.{}",
+            ZipPackager::get_file_extension(media_type.as_str())
+        ),
+        media_type,
+        data: base64::engine::general_purpose::STANDARD.encode(bytes),
+        size: bytes.len() as u64,
+        last_modified: Utc::now().timestamp_millis() as u64,
+        sha256: None,
+    }
+}
+
+/// A device P-256 keypair, as held by a relay
+pub struct DeviceKey {
+    secret: SecretKey,
+}
+
+impl DeviceKey {
+    pub fn generate() -> Self {
+        Self {
+            secret: SecretKey::random(&mut OsRng),
+        }
+    }
+
+    /// Base64-encoded JWK, the format devices register with their certificate
+    pub fn public_key(&self) -> String {
+        let point = self.secret.public_key().to_encoded_point(false);
+        let jwk = serde_json::json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "x": base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(point.x().unwrap()),
+            "y": base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(point.y().unwrap()),
+        });
+        base64::engine::general_purpose::STANDARD.encode(jwk.to_string())
+    }
+
+    /// Sign an event package as an ES256 JWT the crypto middleware accepts
+    pub fn sign(&self, event_package: &EventPackage) -> String {
+        self.sign_claims(serde_json::json!({ "payload": event_package }))
+    }
+
+    /// Sign arbitrary claims as an ES256 JWT, adding the audience and a short expiry
+    pub fn sign_claims(&self, mut claims: serde_json::Value) -> String {
+        claims["aud"] = "event_server".into();
+        claims["exp"] = (Utc::now() + chrono::Duration::minutes(5))
+            .timestamp()
+            .into();
+        let der = self.secret.to_pkcs8_der().unwrap();
+        let key = EncodingKey::from_ec_der(der.as_bytes());
+        encode(&Header::new(Algorithm::ES256), &claims, &key).unwrap()
+    }
+}
+
+/// Cipher sealing certificate signing keys, with a fixed test key
+pub fn seed_cipher() -> SeedCipher {
+    SeedCipher::new(&base64::engine::general_purpose::STANDARD.encode([7u8; 32])).unwrap()
+}
+
+/// Issue a certificate bound to the device key and return its bearer token
+pub fn issue_token(state: &AppState, device: &DeviceKey) -> String {
+    state
+        .certificate_service
+        .issue_certificate(&CertificateRequest {
+            relay_id: "test_relay".to_string(),
+            public_key: device.public_key(),
+            ed25519_public_key: None,
+            tenant_id: None,
+        })
+        .unwrap()
+        .cert_token
+}
+
+/// Build a signed `POST /api/v1/events/package` request
+pub fn signed_package_request(
+    device: &DeviceKey,
+    token: &str,
+    event_package: &EventPackage,
+) -> Request<Body> {
+    let body = SignedEventPackage {
+        jwt_event_data: device.sign(event_package),
+        signature: None,
+        public_key: None,
+    };
+    Request::builder()
+        .method("POST")
+        .uri("/api/v1/events/package")
+        .header("Content-Type", "application/json")
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::from(serde_json::to_vec(&body).unwrap()))
+        .unwrap()
+}
+
+/// Log output written by a test subscriber, e.g. via `.with_writer(move || logs.clone())`
+#[derive(Clone, Default)]
+pub struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+impl CapturedLogs {
+    /// Everything logged so far
+    pub fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,384 @@
+use std::sync::Arc;
+
+use rustls::crypto::ring;
+use rustls::pki_types::{
+    CertificateDer, PrivateKeyDer, PrivatePkcs1KeyDer, PrivatePkcs8KeyDer, PrivateSec1KeyDer,
+};
+use rustls::version::{TLS12, TLS13};
+use rustls::SupportedProtocolVersion;
+
+use crate::config::ServerConfig;
+use crate::error::AppError;
+
+/// Build the rustls config for `server.tls_cert_path`/`tls_key_path`, or `None` to serve plain HTTP
+/// Fails when only one of the paths is set, or when the TLS policy can't be satisfied
+pub fn load_tls_config(
+    server: &ServerConfig,
+) -> Result<Option<Arc<rustls::ServerConfig>>, AppError> {
+    let (cert_path, key_path) = match (&server.tls_cert_path, &server.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => (cert_path, key_path),
+        (None, None) => return Ok(None),
+        _ => {
+            return Err(AppError::Config(
+                "server.tls_cert_path and server.tls_key_path must be set together".to_string(),
+            ))
+        }
+    };
+
+    let cert_chain = read_pem(cert_path)?
+        .into_iter()
+        .filter(|block| block.tag() == "CERTIFICATE")
+        .map(|block| CertificateDer::from(block.into_contents()))
+        .collect::<Vec<_>>();
+    if cert_chain.is_empty() {
+        return Err(AppError::Config(format!(
+            "No certificates found in {cert_path}"
+        )));
+    }
+
+    let key = read_pem(key_path)?
+        .into_iter()
+        .find_map(|block| match block.tag() {
+            "PRIVATE KEY" => Some(PrivatePkcs8KeyDer::from(block.into_contents()).into()),
+            "RSA PRIVATE KEY" => Some(PrivatePkcs1KeyDer::from(block.into_contents()).into()),
+            "EC PRIVATE KEY" => Some(PrivateSec1KeyDer::from(block.into_contents()).into()),
+            _ => None,
+        })
+        .ok_or_else(|| AppError::Config(format!("No private key found in {key_path}")))?;
+
+    build_tls_config(
+        &server.tls_min_version,
+        &server.tls_cipher_suites,
+        cert_chain,
+        key,
+    )
+    .map(Some)
+}
+
+/// Server config offering only TLS `min_version` and up, restricted to `cipher_suites` if any
+pub fn build_tls_config(
+    min_version: &str,
+    cipher_suites: &[String],
+    cert_chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+) -> Result<Arc<rustls::ServerConfig>, AppError> {
+    let versions = protocol_versions(min_version)?;
+    let mut provider = ring::default_provider();
+
+    if !cipher_suites.is_empty() {
+        if let Some(unknown) = cipher_suites.iter().find(|name| {
+            !provider
+                .cipher_suites
+                .iter()
+                .any(|s| suite_name(s) == **name)
+        }) {
+            let supported: Vec<String> = provider.cipher_suites.iter().map(suite_name).collect();
+            return Err(AppError::Config(format!(
+                "Unsupported cipher suite {unknown}; supported: {}",
+                supported.join(", ")
+            )));
+        }
+        provider
+            .cipher_suites
+            .retain(|suite| cipher_suites.contains(&suite_name(suite)));
+    }
+
+    // rustls would otherwise only notice at the first handshake
+    provider
+        .cipher_suites
+        .retain(|suite| versions.contains(&suite.version()));
+    if provider.cipher_suites.is_empty() {
+        return Err(AppError::Config(format!(
+            "No allowed cipher suite is usable with TLS {min_version} or later"
+        )));
+    }
+
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(provider))
+        .with_protocol_versions(versions)
+        .and_then(|builder| {
+            builder
+                .with_no_client_auth()
+                .with_single_cert(cert_chain, key)
+        })
+        .map_err(|e| AppError::Config(format!("Invalid TLS configuration: {e}")))?;
+    Ok(Arc::new(config))
+}
+
+static TLS12_AND_UP: &[&SupportedProtocolVersion] = &[&TLS13, &TLS12];
+static TLS13_ONLY: &[&SupportedProtocolVersion] = &[&TLS13];
+
+/// Protocol versions at or above a configured minimum
+fn protocol_versions(
+    min_version: &str,
+) -> Result<&'static [&'static SupportedProtocolVersion], AppError> {
+    match min_version {
+        "1.2" => Ok(TLS12_AND_UP),
+        "1.3" => Ok(TLS13_ONLY),
+        other => Err(AppError::Config(format!(
+            "server.tls_min_version must be 1.2 or 1.3, got {other:?}"
+        ))),
+    }
+}
+
+/// IANA name of a cipher suite, e.g. `TLS13_AES_256_GCM_SHA384`
+fn suite_name(suite: &rustls::SupportedCipherSuite) -> String {
+    format!("{:?}", suite.suite())
+}
+
+fn read_pem(path: &str) -> Result<Vec<pem::Pem>, AppError> {
+    let contents =
+        std::fs::read(path).map_err(|e| AppError::Config(format!("Failed to read {path}: {e}")))?;
+    pem::parse_many(contents).map_err(|e| AppError::Config(format!("Invalid PEM in {path}: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{serve, ConnectionLimits};
+    use axum::extract::ConnectInfo;
+    use axum::Router;
+    use rustls::pki_types::ServerName;
+    use rustls::{ClientConfig, ClientConnection, RootCertStore, ServerConnection};
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    fn self_signed() -> (CertificateDer<'static>, PrivateKeyDer<'static>) {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let key = PrivatePkcs8KeyDer::from(certified.signing_key.serialize_der()).into();
+        (certified.cert.der().clone(), key)
+    }
+
+    fn server_config(
+        min_version: &str,
+        cipher_suites: &[&str],
+    ) -> Result<Arc<rustls::ServerConfig>, AppError> {
+        let (cert, key) = self_signed();
+        let suites: Vec<String> = cipher_suites.iter().map(|s| s.to_string()).collect();
+        build_tls_config(min_version, &suites, vec![cert], key)
+    }
+
+    /// A TLS 1.1 ClientHello offering TLS_RSA_WITH_AES_128_CBC_SHA, with no extensions
+    fn tls11_client_hello() -> Vec<u8> {
+        let mut hello = vec![0x03, 0x02]; // client_version
+        hello.extend([0u8; 32]); // random
+        hello.push(0); // session id
+        hello.extend([0x00, 0x02, 0x00, 0x2F]); // cipher suites
+        hello.extend([0x01, 0x00]); // compression methods
+
+        let mut handshake = vec![0x01, 0x00];
+        handshake.extend((hello.len() as u16).to_be_bytes());
+        handshake.extend(hello);
+
+        let mut record = vec![0x16, 0x03, 0x01];
+        record.extend((handshake.len() as u16).to_be_bytes());
+        record.extend(handshake);
+        record
+    }
+
+    /// Run a handshake between in-memory client and server connections
+    fn handshake(
+        client: &mut ClientConnection,
+        server: &mut ServerConnection,
+    ) -> Result<(), rustls::Error> {
+        while client.is_handshaking() || server.is_handshaking() {
+            let mut buffer = Vec::new();
+            while client.wants_write() {
+                client.write_tls(&mut buffer).unwrap();
+            }
+            server.read_tls(&mut buffer.as_slice()).unwrap();
+            server.process_new_packets()?;
+
+            let mut buffer = Vec::new();
+            while server.wants_write() {
+                server.write_tls(&mut buffer).unwrap();
+            }
+            client.read_tls(&mut buffer.as_slice()).unwrap();
+            client.process_new_packets()?;
+        }
+        Ok(())
+    }
+
+    fn client_config(
+        server_cert: &CertificateDer<'static>,
+        versions: &[&'static SupportedProtocolVersion],
+    ) -> Arc<ClientConfig> {
+        let mut roots = RootCertStore::empty();
+        roots.add(server_cert.clone()).unwrap();
+        let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_protocol_versions(versions)
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Arc::new(config)
+    }
+
+    fn tls12_client(server_cert: &CertificateDer<'static>) -> ClientConnection {
+        ClientConnection::new(
+            client_config(server_cert, &[&TLS12]),
+            ServerName::try_from("localhost").unwrap(),
+        )
+        .unwrap()
+    }
+
+    /// Serve `app` over TLS on a local port, returning its address and certificate
+    async fn spawn_tls_server(app: Router) -> (SocketAddr, CertificateDer<'static>) {
+        let (cert, key) = self_signed();
+        let config = build_tls_config("1.2", &[], vec![cert.clone()], key).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let limits = ConnectionLimits {
+            handshake_timeout: Duration::from_secs(5),
+            max_connections: 16,
+            max_headers: None,
+            max_head_bytes: None,
+        };
+        tokio::spawn(serve(
+            listener,
+            app,
+            Some(config),
+            limits,
+            std::future::pending(),
+        ));
+        (address, cert)
+    }
+
+    /// Send a raw HTTP/1.1 request over TLS and return the whole response
+    async fn tls_request(
+        address: SocketAddr,
+        cert: &CertificateDer<'static>,
+        request: &str,
+    ) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let connector = tokio_rustls::TlsConnector::from(client_config(cert, &[&TLS13, &TLS12]));
+        let stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        let mut stream = connector
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await
+            .unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = Vec::new();
+        let _ = stream.read_to_end(&mut response).await;
+        String::from_utf8_lossy(&response).into_owned()
+    }
+
+    #[test]
+    fn test_tls11_client_hello_is_refused() {
+        let config = server_config("1.2", &[]).unwrap();
+        let mut server = ServerConnection::new(config).unwrap();
+
+        server
+            .read_tls(&mut tls11_client_hello().as_slice())
+            .unwrap();
+        assert!(server.process_new_packets().is_err());
+
+        // The client is told why with a fatal alert
+        let mut alert = Vec::new();
+        server.write_tls(&mut alert).unwrap();
+        assert_eq!(alert.first(), Some(&0x15));
+    }
+
+    #[test]
+    fn test_tls12_allowed_only_when_minimum_permits() {
+        let (cert, key) = self_signed();
+
+        let config = build_tls_config("1.2", &[], vec![cert.clone()], key.clone_key()).unwrap();
+        let mut server = ServerConnection::new(config).unwrap();
+        handshake(&mut tls12_client(&cert), &mut server).unwrap();
+        assert_eq!(
+            server.protocol_version(),
+            Some(rustls::ProtocolVersion::TLSv1_2)
+        );
+
+        let config = build_tls_config("1.3", &[], vec![cert.clone()], key).unwrap();
+        let mut server = ServerConnection::new(config).unwrap();
+        assert!(handshake(&mut tls12_client(&cert), &mut server).is_err());
+    }
+
+    #[test]
+    fn test_unsatisfiable_policy_rejected() {
+        assert!(matches!(
+            server_config("1.1", &[]),
+            Err(AppError::Config(_))
+        ));
+
+        let err = server_config("1.2", &["TLS_RSA_WITH_RC4_128_SHA"]).unwrap_err();
+        assert!(err.to_string().contains("Unsupported cipher suite"));
+
+        // TLS 1.2-only suites can't serve a TLS 1.3 minimum
+        let err = server_config("1.3", &["TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384"]).unwrap_err();
+        assert!(err.to_string().contains("No allowed cipher suite"));
+
+        assert!(server_config("1.3", &["TLS13_AES_256_GCM_SHA384"]).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_handlers_see_the_peer_address() {
+        let app = Router::new().route(
+            "/peer",
+            axum::routing::get(|ConnectInfo(peer): ConnectInfo<SocketAddr>| async move {
+                peer.ip().to_string()
+            }),
+        );
+        let (address, cert) = spawn_tls_server(app).await;
+
+        let response = tls_request(
+            address,
+            &cert,
+            "GET /peer HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.ends_with("127.0.0.1"), "{response}");
+    }
+
+    #[tokio::test]
+    async fn test_idle_handshakes_time_out_and_connections_are_capped() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpStream;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let limits = ConnectionLimits {
+            handshake_timeout: Duration::from_millis(300),
+            max_connections: 1,
+            max_headers: None,
+            max_head_bytes: None,
+        };
+        tokio::spawn(serve(
+            listener,
+            Router::new(),
+            Some(server_config("1.2", &[]).unwrap()),
+            limits,
+            std::future::pending(),
+        ));
+
+        // Connects but never starts the handshake
+        let mut idle = TcpStream::connect(address).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // At the cap, a second client isn't served until the idle one is dropped
+        let mut waiting = TcpStream::connect(address).await.unwrap();
+        waiting.write_all(&tls11_client_hello()).await.unwrap();
+        let mut buffer = [0u8; 16];
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), waiting.read(&mut buffer))
+                .await
+                .is_err()
+        );
+
+        let read = tokio::time::timeout(Duration::from_secs(2), idle.read(&mut buffer))
+            .await
+            .expect("idle handshake times out");
+        assert!(matches!(read, Ok(0) | Err(_)), "{read:?}");
+
+        // Now accepted, the outdated hello is refused with an alert
+        let read = tokio::time::timeout(Duration::from_secs(2), waiting.read(&mut buffer))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(read > 0);
+        assert_eq!(buffer[0], 0x15);
+    }
+}
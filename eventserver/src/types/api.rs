@@ -3,55 +3,179 @@ use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-/// Standard API response wrapper
-#[derive(Debug, Serialize)]
-#[allow(dead_code)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub error: Option<String>,
-    pub timestamp: DateTime<Utc>,
+use crate::config::RateLimitKey;
+use crate::services::circuit_breaker::CircuitBreakerStatus;
+use crate::services::jobs::JobStatus;
+
+/// Status of the certificate presented in the Authorization header
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CertificateStatusResponse {
+    pub valid: bool,
+    pub relay_id: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub seconds_remaining: i64,
+    /// Why the certificate is not valid (e.g. CERT_EXPIRED, CERT_REVOKED)
+    pub reason: Option<String>,
 }
 
-#[allow(dead_code)]
-impl<T> ApiResponse<T> {
-    pub fn success(data: T) -> Self {
-        Self {
-            success: true,
-            data: Some(data),
-            error: None,
-            timestamp: Utc::now(),
-        }
-    }
+/// Introspection result for one token of a batch
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenIntrospection {
+    /// Whether the token names an unexpired, unrevoked certificate issued by this server
+    pub active: bool,
+    pub relay_id: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
 
-    pub fn error(message: String) -> Self {
-        Self {
-            success: false,
-            data: None,
-            error: Some(message),
-            timestamp: Utc::now(),
-        }
-    }
+/// Batch token introspection results, in request order
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IntrospectBatchResponse {
+    pub results: Vec<TokenIntrospection>,
+}
+
+/// Certificate revocation list
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CrlResponse {
+    /// Revoked certificates that haven't expired yet, sorted
+    pub revoked_certificate_ids: Vec<String>,
+    /// When the list last changed
+    pub updated_at: DateTime<Utc>,
 }
 
-/// Response for successful event processing
-#[derive(Debug, Serialize)]
-#[allow(dead_code)]
+/// Result of rotating the device key bound to a certificate
+#[derive(Debug, Serialize, ToSchema)]
+pub struct KeyRotationResponse {
+    pub relay_id: String,
+    /// Events signed with the previous key are accepted until this time
+    pub previous_key_valid_until: DateTime<Utc>,
+}
+
+/// Storage progress of an event accepted in async mode
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct EventResponse {
+pub struct EventStatusResponse {
     pub event_id: Uuid,
+    pub status: JobStatus,
     pub hash: String,
-    pub processed_at: DateTime<Utc>,
+    /// Set once the event is stored
+    pub storage_location: Option<String>,
+    pub updated_at: DateTime<Utc>,
 }
 
-/// Response for event hash verification
+/// Server capabilities, so clients don't hardcode what the server accepts
 #[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct HashVerificationResponse {
+pub struct CapabilitiesResponse {
+    /// Media types accepted in `EventMedia.type`
+    pub media_types: Vec<String>,
+    /// JSON kinds accepted as annotation values
+    pub field_value_kinds: Vec<String>,
+    /// Maximum accepted file size in bytes
+    pub max_file_size: u64,
+    /// MIME types allowed by the storage configuration
+    pub allowed_mime_types: Vec<String>,
+    /// Event schema versions accepted for submission
+    pub supported_event_versions: Vec<String>,
+}
+
+/// Current state of the replay-protection cache
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReplayStatsResponse {
+    /// Number of event JWTs currently remembered
+    pub entries: usize,
+}
+
+/// Delivery counters of the event bus since startup
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EventBusStatsResponse {
+    /// Whether a backend is configured
+    pub enabled: bool,
+    /// Notifications delivered to the queue
+    pub published: u64,
+    /// Notifications the backend failed to accept
+    pub failed: u64,
+    /// Notifications dropped because the buffer was full
+    pub dropped: u64,
+}
+
+/// Result of flushing the replay-protection cache
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReplayFlushResponse {
+    /// Number of entries removed
+    pub flushed: usize,
+}
+
+/// Result of reindexing a stored event by hash
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReindexResponse {
     pub hash: String,
-    pub exists: bool,
-    pub block_number: Option<u64>,
-    pub timestamp: Option<DateTime<Utc>>,
+    /// Primary object key the by-hash marker points to
+    pub key: String,
+    /// Whether the marker was missing or stale and had to be rewritten
+    pub repaired: bool,
+}
+
+/// Enrollment token letting devices obtain certificates bound to a tenant
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TenantEnrollmentResponse {
+    pub tenant_id: String,
+    /// Sent by devices in the `x-tenant-enrollment` header at `/api/v1/pow/verify`
+    pub enrollment_token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Result of rotating the server's certificate signing key
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningKeyRotationResponse {
+    /// `kid` of the key signing new certificate tokens
+    pub key_id: String,
+    /// `kid` of the replaced key, still verifying outstanding certificates until they expire
+    pub previous_key_id: String,
+    /// Every `kid` currently published in the JWKS for certificate tokens
+    pub verification_key_ids: Vec<String>,
+}
+
+/// Outcome of a batch certificate revocation
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RevokeBatchResponse {
+    /// Total number of certificates revoked
+    pub revoked: usize,
+    /// One entry per requested certificate or relay ID, in request order
+    pub results: Vec<RevocationResult>,
+}
+
+/// Revocation outcome for a single requested ID
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RevocationResult {
+    /// Requested certificate or relay ID
+    pub id: String,
+    /// Whether at least one certificate was revoked for this ID
+    pub revoked: bool,
+    /// Certificates revoked for this ID
+    pub certificate_ids: Vec<String>,
+}
+
+/// Outcome of checking a client-built archive against the server's packaging layout
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveValidationReport {
+    /// Whether the archive matches the layout with no discrepancies
+    pub conformant: bool,
+    /// Entry names found in the archive, in archive order
+    pub entries: Vec<String>,
+    pub discrepancies: Vec<ArchiveDiscrepancy>,
+}
+
+/// A single difference from the expected archive layout
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ArchiveDiscrepancy {
+    /// Entry the problem concerns
+    pub entry: String,
+    pub problem: String,
 }
 
 /// Health check response
@@ -61,6 +185,10 @@ pub struct HealthResponse {
     pub timestamp: DateTime<Utc>,
     pub services: ServiceHealthStatus,
     pub version: String,
+    /// Seconds since the process started
+    pub uptime_seconds: f64,
+    /// When the storage backend last answered a health probe
+    pub last_storage_ok_at: Option<DateTime<Utc>>,
 }
 
 /// Service health status breakdown
@@ -69,101 +197,91 @@ pub struct ServiceHealthStatus {
     pub storage: bool,
 }
 
-/// Error response details
-#[derive(Debug, Serialize)]
-pub struct ErrorResponse {
-    pub error: String,
-    pub code: String,
-    pub details: Option<serde_json::Value>,
-    pub timestamp: DateTime<Utc>,
-}
-
-/// Request validation error details
-#[derive(Debug, Serialize)]
-pub struct ValidationError {
-    pub field: String,
-    pub message: String,
-}
-
-/// Batch validation errors
-#[derive(Debug, Serialize)]
-pub struct ValidationErrorResponse {
-    pub message: String,
-    pub errors: Vec<ValidationError>,
-    pub timestamp: DateTime<Utc>,
+/// Readiness of the instance to take traffic
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessResponse {
+    pub ready: bool,
+    /// Storage circuit breaker, when enabled; readiness follows it instead of a probe
+    pub circuit_breaker: Option<CircuitBreakerStatus>,
 }
 
 /// Pagination parameters for list endpoints
-#[derive(Debug, Deserialize)]
-#[allow(dead_code)]
+/// Resolve with `normalize` rather than reading the fields, so every endpoint applies the
+/// same defaults and bounds
+#[derive(Debug, Default, Deserialize, utoipa::IntoParams)]
 pub struct PaginationParams {
+    /// 1-based page number (default 1)
     pub page: Option<u32>,
+    /// Items per page; 0 or absent uses the server default, larger values are capped
     pub limit: Option<u32>,
 }
 
-impl Default for PaginationParams {
-    fn default() -> Self {
-        Self {
-            page: Some(1),
-            limit: Some(50),
+impl PaginationParams {
+    /// Apply the default page size and clamp the limit to `max_limit`
+    pub fn normalize(&self, default_limit: u32, max_limit: u32) -> Page {
+        let max_limit = max_limit.max(1);
+        let limit = match self.limit {
+            None | Some(0) => default_limit,
+            Some(limit) => limit,
+        };
+        Page {
+            page: self.page.unwrap_or(1).max(1),
+            limit: limit.clamp(1, max_limit),
         }
     }
 }
 
-/// Paginated response wrapper
-#[derive(Debug, Serialize)]
-pub struct PaginatedResponse<T> {
-    pub data: Vec<T>,
-    pub pagination: PaginationInfo,
-}
-
-/// Pagination metadata
-#[derive(Debug, Serialize)]
-pub struct PaginationInfo {
+/// A page request after defaults and bounds have been applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
     pub page: u32,
     pub limit: u32,
-    pub total: u64,
-    pub pages: u32,
+}
+
+impl Page {
+    /// Number of items on the pages before this one
+    pub fn offset(self) -> usize {
+        (self.page as usize - 1).saturating_mul(self.limit as usize)
+    }
 }
 
 /// Rate limiting information
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RateLimitInfo {
     pub requests_remaining: u32,
     pub reset_time: DateTime<Utc>,
     pub limit_per_minute: u32,
+    pub scope: RateLimitKey, // What the budget is keyed on
 }
 
-/// Authentication challenge for PoW
-#[derive(Debug, Serialize)]
-pub struct AuthChallenge {
-    pub challenge: String,
-    pub difficulty: u32,
-    pub expires_at: DateTime<Utc>,
-}
-
-/// PoW solution submission
-#[derive(Debug, Deserialize)]
-#[allow(dead_code)]
-pub struct PowSolution {
-    pub challenge: String,
-    pub nonce: u64,
-    pub public_key: String,
-}
+impl RateLimitInfo {
+    /// Write `X-RateLimit-Limit`, `X-RateLimit-Remaining`, `X-RateLimit-Reset` (unix seconds)
+    /// and `X-RateLimit-Scope`
+    pub fn insert_headers(&self, headers: &mut axum::http::HeaderMap) {
+        headers.insert("x-ratelimit-limit", self.limit_per_minute.into());
+        headers.insert("x-ratelimit-remaining", self.requests_remaining.into());
+        headers.insert("x-ratelimit-reset", self.reset_time.timestamp().into());
+        headers.insert(
+            "x-ratelimit-scope",
+            axum::http::HeaderValue::from_static(self.scope.as_str()),
+        );
+    }
 
-/// Certificate issuance response
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct CertificateResponse {
-    pub certificate: String,
-    pub issued_at: DateTime<Utc>,
-    pub expires_at: DateTime<Utc>,
-    pub relay_id: String,
+    /// Seconds until the window resets, rounded up
+    pub fn seconds_until_reset(&self) -> u64 {
+        let millis = (self.reset_time - Utc::now()).num_milliseconds().max(0) as u64;
+        millis.div_ceil(1000)
+    }
 }
 
 impl HealthResponse {
-    pub fn new(services: ServiceHealthStatus) -> Self {
+    pub fn new(
+        services: ServiceHealthStatus,
+        uptime_seconds: f64,
+        last_storage_ok_at: Option<DateTime<Utc>>,
+    ) -> Self {
         let status = if services.storage {
             "healthy"
         } else {
@@ -175,19 +293,53 @@ impl HealthResponse {
             timestamp: Utc::now(),
             services,
             version: env!("CARGO_PKG_VERSION").to_string(),
+            uptime_seconds,
+            last_storage_ok_at,
         }
     }
 }
 
-#[allow(dead_code)]
-impl PaginationInfo {
-    pub fn new(page: u32, limit: u32, total: u64) -> Self {
-        let pages = ((total as f64) / (limit as f64)).ceil() as u32;
-        Self {
-            page,
-            limit,
-            total,
-            pages,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_over_max_limit_is_clamped() {
+        let params = PaginationParams {
+            page: Some(2),
+            limit: Some(1_000_000),
+        };
+        assert_eq!(
+            params.normalize(50, 500),
+            Page {
+                page: 2,
+                limit: 500
+            }
+        );
+    }
+
+    #[test]
+    fn test_zero_or_missing_limit_uses_default() {
+        let zero = PaginationParams {
+            page: Some(0),
+            limit: Some(0),
+        };
+        assert_eq!(zero.normalize(50, 500), Page { page: 1, limit: 50 });
+        assert_eq!(
+            PaginationParams::default().normalize(50, 500),
+            Page { page: 1, limit: 50 }
+        );
+        // A default above the cap is still capped
+        assert_eq!(PaginationParams::default().normalize(1000, 500).limit, 500);
+    }
+
+    #[test]
+    fn test_page_offset() {
+        let page = PaginationParams {
+            page: Some(3),
+            limit: Some(2),
         }
+        .normalize(50, 500);
+        assert_eq!(page.offset(), 4);
     }
 }
@@ -1,8 +1,17 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::config::validation::ValidationConfig;
+
+/// Earliest plausible media `last_modified` (1990-01-01); smaller values are usually seconds, not milliseconds
+const MIN_MEDIA_LAST_MODIFIED_MS: u64 = 631_152_000_000;
+
+/// How far ahead media `last_modified` may be when no clock-skew limit is configured
+const MAX_MEDIA_LAST_MODIFIED_AHEAD_DAYS: i64 = 1;
+
 /// Supported field value types - matches TypeScript FieldValue
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(untagged)]
@@ -11,6 +20,24 @@ pub enum FieldValue {
     Number(f64),
     Boolean(bool),
     Null,
+    /// Catch-all for values not supported yet (arrays, objects), rejected during validation
+    /// so one bad annotation yields a field-level error instead of failing the whole parse
+    Unknown(serde_json::Value),
+}
+
+impl FieldValue {
+    /// JSON kinds accepted as annotation values
+    pub const SUPPORTED_KINDS: [&'static str; 4] = ["string", "number", "boolean", "null"];
+
+    /// JSON type name of an unsupported value, or None if the value is supported
+    fn unsupported_type(&self) -> Option<&'static str> {
+        match self {
+            FieldValue::Unknown(serde_json::Value::Array(_)) => Some("array"),
+            FieldValue::Unknown(serde_json::Value::Object(_)) => Some("object"),
+            FieldValue::Unknown(_) => Some("unknown"),
+            _ => None,
+        }
+    }
 }
 
 /// Supported media types - matches TypeScript MediaType
@@ -27,6 +54,14 @@ pub enum MediaType {
 }
 
 impl MediaType {
+    /// Every supported media type, in declaration order
+    pub const ALL: [MediaType; 4] = [
+        MediaType::ImageJpeg,
+        MediaType::ImagePng,
+        MediaType::ImageGif,
+        MediaType::VideoMp4,
+    ];
+
     pub fn as_str(&self) -> &'static str {
         match self {
             MediaType::ImageJpeg => "image/jpeg",
@@ -55,7 +90,10 @@ pub struct EventMedia {
     pub data: String, // Base64 encoded media data
     pub name: String,
     pub size: u64,
-    pub last_modified: u64, // Unix timestamp
+    pub last_modified: u64, // Unix timestamp in milliseconds
+    /// Hex SHA-256 of the decoded media bytes, checked on packaging when provided
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
 }
 
 /// Event metadata - matches TypeScript structure
@@ -90,6 +128,12 @@ pub struct EventPackage {
 #[serde(rename_all = "camelCase")]
 pub struct SignedEventPackage {
     pub jwt_event_data: String,
+    /// Base64 Ed25519 signature over `jwt_event_data`, checked in dual-signature mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// Base64 Ed25519 public key that produced `signature`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
 }
 
 /// Simple event payload from frontend - file upload notification
@@ -118,23 +162,110 @@ pub struct ValidationResult {
 }
 
 impl EventPackage {
-    /// Validates the event package structure
+    /// Validates the event package structure with the default (lenient) rules
+    #[cfg(test)]
     pub fn validate(&self) -> ValidationResult {
+        self.validate_with(&ValidationConfig::default())
+    }
+
+    /// Validates the event package structure against the configured rules
+    /// Upconvert an older schema version to the current one before validation
+    /// Every supported version currently shares the same shape; add per-version upgrades
+    /// here (e.g. `"0.9" => upgrade_from_0_9(self)`) when the schema changes
+    pub fn migrate_to_current(self) -> Self {
+        self
+    }
+
+    pub fn validate_with(&self, rules: &ValidationConfig) -> ValidationResult {
         let mut errors = Vec::new();
+        let now = Utc::now();
+        let latest_allowed = rules.latest_allowed_timestamp(now);
 
         if self.annotations.is_empty() {
-            errors.push("Event package must contain at least one annotation".to_string());
+            if !rules.allow_media_only_events {
+                errors.push("Event package must contain at least one annotation".to_string());
+            } else if self.media.is_none() {
+                errors.push(
+                    "Event package must contain at least one annotation or media".to_string(),
+                );
+            }
         }
 
         if self.version.is_empty() {
             errors.push("Event package must have a version".to_string());
+        } else if !rules.is_supported_version(&self.version) {
+            errors.push(format!(
+                "Unsupported event version '{}'; supported versions: {}",
+                self.version,
+                rules.supported_event_versions.join(", ")
+            ));
         }
 
         // Validate annotations
+        let mut unknown_labels: Vec<&str> = Vec::new();
+        let mut duplicate_labels: Vec<&str> = Vec::new();
         for (index, annotation) in self.annotations.iter().enumerate() {
+            if rules.reject_duplicate_labels
+                && !annotation.label_id.is_empty()
+                && !duplicate_labels.contains(&annotation.label_id.as_str())
+                && self.annotations[..index]
+                    .iter()
+                    .any(|earlier| earlier.label_id == annotation.label_id)
+            {
+                duplicate_labels.push(&annotation.label_id);
+            }
+            if !annotation.label_id.is_empty()
+                && !rules.is_allowed_label(&annotation.label_id)
+                && !unknown_labels.contains(&annotation.label_id.as_str())
+            {
+                unknown_labels.push(&annotation.label_id);
+            }
             if annotation.label_id.is_empty() {
                 errors.push(format!("Annotation {index} must have a label_id"));
             }
+            if let Some(kind) = annotation.value.unsupported_type() {
+                errors.push(format!(
+                    "Annotation {index} ({}) has unsupported value type '{kind}'",
+                    annotation.label_id
+                ));
+            }
+            if let Some(latest) = latest_allowed {
+                if annotation.timestamp > latest {
+                    errors.push(format!(
+                        "Annotation {index} timestamp {} is too far in the future",
+                        annotation.timestamp.to_rfc3339()
+                    ));
+                }
+            }
+            if let Some(floor) = rules.min_annotation_timestamp {
+                if annotation.timestamp < floor {
+                    errors.push(format!(
+                        "Annotation {index} timestamp {} is before the earliest accepted date {}",
+                        annotation.timestamp.to_rfc3339(),
+                        floor.to_rfc3339()
+                    ));
+                }
+            }
+        }
+
+        if !unknown_labels.is_empty() {
+            errors.push(format!(
+                "Annotation label_ids not in the allowed set: {}",
+                unknown_labels.join(", ")
+            ));
+        }
+        if !duplicate_labels.is_empty() {
+            errors.push(format!(
+                "Annotation label_ids appear more than once: {}",
+                duplicate_labels.join(", ")
+            ));
+        }
+
+        if self.media.is_none() && rules.requires_media(&self.version) {
+            errors.push(format!(
+                "Event version '{}' requires media, but none was provided",
+                self.version
+            ));
         }
 
         // Validate media if present
@@ -148,6 +279,16 @@ impl EventPackage {
             if media.size == 0 {
                 errors.push("Media size must be greater than 0".to_string());
             }
+            let latest_modified =
+                latest_allowed.unwrap_or(now + Duration::days(MAX_MEDIA_LAST_MODIFIED_AHEAD_DAYS));
+            if media.last_modified < MIN_MEDIA_LAST_MODIFIED_MS
+                || media.last_modified > latest_modified.timestamp_millis() as u64
+            {
+                errors.push(format!(
+                    "Media last_modified {} is not a plausible Unix timestamp in milliseconds",
+                    media.last_modified
+                ));
+            }
         }
 
         ValidationResult {
@@ -156,17 +297,39 @@ impl EventPackage {
         }
     }
 
-    /// Creates a hash input string for cryptographic operations
+    /// Canonical form of the event content used for the dedup hash
+    ///
+    /// - the per-submission `id` is excluded, so a retried submission hashes the same
+    /// - annotations are sorted by label, then timestamp, then value
+    /// - all timestamps are normalized to RFC 3339 UTC with millisecond precision
+    /// - media is represented by its type, name, size and the SHA-256 of its data
+    /// - object keys are serialized in sorted order
     pub fn create_hash_input(&self) -> serde_json::Value {
+        let normalize = |ts: &DateTime<Utc>| ts.to_rfc3339_opts(SecondsFormat::Millis, true);
+
+        let mut annotations: Vec<serde_json::Value> = self
+            .annotations
+            .iter()
+            .map(|a| {
+                serde_json::json!({
+                    "labelId": a.label_id,
+                    "value": a.value,
+                    "timestamp": normalize(&a.timestamp),
+                })
+            })
+            .collect();
+        annotations.sort_by_key(|a| a.to_string());
+
         serde_json::json!({
-            "id": self.id,
-            "annotations": self.annotations,
+            "version": self.version,
+            "annotations": annotations,
             "media": self.media.as_ref().map(|m| serde_json::json!({
                 "type": m.media_type.as_str(),
                 "size": m.size,
-                "name": m.name
+                "name": m.name,
+                "sha256": hex::encode(Sha256::digest(m.data.as_bytes())),
             })),
-            "createdAt": self.metadata.created_at
+            "createdAt": normalize(&self.metadata.created_at)
         })
     }
 }
@@ -174,6 +337,7 @@ impl EventPackage {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::sample_media;
 
     #[test]
     fn test_event_package_validation() {
@@ -217,6 +381,150 @@ mod tests {
         assert_eq!(validation.errors.len(), 2);
     }
 
+    fn media_only_package() -> EventPackage {
+        EventPackage {
+            id: Uuid::new_v4(),
+            version: "1.0".to_string(),
+            annotations: vec![],
+            media: Some(sample_media(MediaType::ImageJpeg, b"hello")),
+            metadata: EventMetadata {
+                created_at: Utc::now(),
+                created_by: None,
+                source: EventSource::Mobile,
+            },
+        }
+    }
+
+    #[test]
+    fn test_media_only_event_rejected_by_default() {
+        let validation = media_only_package().validate();
+        assert!(!validation.is_valid);
+        assert_eq!(
+            validation.errors,
+            vec!["Event package must contain at least one annotation"]
+        );
+    }
+
+    #[test]
+    fn test_media_only_event_accepted_when_allowed() {
+        let rules = ValidationConfig {
+            allow_media_only_events: true,
+            ..ValidationConfig::default()
+        };
+        assert!(media_only_package().validate_with(&rules).is_valid);
+
+        // Media is still required when there are no annotations
+        let empty = EventPackage {
+            media: None,
+            ..media_only_package()
+        };
+        let validation = empty.validate_with(&rules);
+        assert!(!validation.is_valid);
+        assert_eq!(
+            validation.errors,
+            vec!["Event package must contain at least one annotation or media"]
+        );
+
+        // ...and the media must itself be valid
+        let mut invalid_media = media_only_package();
+        invalid_media.media.as_mut().unwrap().data.clear();
+        assert!(!invalid_media.validate_with(&rules).is_valid);
+    }
+
+    #[test]
+    fn test_zero_media_last_modified_rejected() {
+        let rules = ValidationConfig {
+            allow_media_only_events: true,
+            ..ValidationConfig::default()
+        };
+        let mut event_package = media_only_package();
+        event_package.media.as_mut().unwrap().last_modified = 0;
+
+        let validation = event_package.validate_with(&rules);
+        assert!(!validation.is_valid);
+        assert_eq!(
+            validation.errors,
+            vec!["Media last_modified 0 is not a plausible Unix timestamp in milliseconds"]
+        );
+    }
+
+    #[test]
+    fn test_far_future_media_last_modified_rejected() {
+        let rules = ValidationConfig {
+            allow_media_only_events: true,
+            ..ValidationConfig::default()
+        };
+        let mut event_package = media_only_package();
+        let far_future = (Utc::now() + Duration::days(365 * 100)).timestamp_millis() as u64;
+        event_package.media.as_mut().unwrap().last_modified = far_future;
+
+        let validation = event_package.validate_with(&rules);
+        assert!(!validation.is_valid);
+        assert_eq!(validation.errors.len(), 1);
+        assert!(validation.errors[0].contains("last_modified"));
+
+        // A timestamp sent in seconds is far too small as milliseconds
+        event_package.media.as_mut().unwrap().last_modified = Utc::now().timestamp() as u64;
+        assert!(!event_package.validate_with(&rules).is_valid);
+    }
+
+    fn package_with_annotation_at(timestamp: DateTime<Utc>) -> EventPackage {
+        EventPackage {
+            id: Uuid::new_v4(),
+            version: "1.0".to_string(),
+            annotations: vec![EventAnnotation {
+                label_id: "test_label".to_string(),
+                value: FieldValue::Boolean(true),
+                timestamp,
+            }],
+            media: None,
+            metadata: EventMetadata {
+                created_at: Utc::now(),
+                created_by: None,
+                source: EventSource::Mobile,
+            },
+        }
+    }
+
+    fn timestamp_rules() -> ValidationConfig {
+        ValidationConfig {
+            max_clock_skew_seconds: Some(300),
+            min_annotation_timestamp: Some(
+                DateTime::parse_from_rfc3339("2020-01-01T00:00:00Z")
+                    .unwrap()
+                    .with_timezone(&Utc),
+            ),
+            ..ValidationConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_future_dated_annotation_rejected() {
+        let event_package = package_with_annotation_at(Utc::now() + chrono::Duration::days(1));
+
+        let validation = event_package.validate_with(&timestamp_rules());
+        assert!(!validation.is_valid);
+        assert_eq!(validation.errors.len(), 1);
+        assert!(validation.errors[0].contains("Annotation 0"));
+        assert!(validation.errors[0].contains("future"));
+
+        // Lenient when no window is configured
+        assert!(event_package.validate().is_valid);
+    }
+
+    #[test]
+    fn test_implausibly_old_annotation_rejected() {
+        let event_package = package_with_annotation_at(DateTime::UNIX_EPOCH);
+
+        let validation = event_package.validate_with(&timestamp_rules());
+        assert!(!validation.is_valid);
+        assert!(validation.errors[0].contains("earliest accepted date"));
+
+        // Within the window is accepted
+        let recent = package_with_annotation_at(Utc::now());
+        assert!(recent.validate_with(&timestamp_rules()).is_valid);
+    }
+
     #[test]
     fn test_event_payload_deserialization() {
         // Test with the sample payload from the issue description
@@ -244,4 +552,143 @@ mod tests {
 
         assert_eq!(json, expected);
     }
+
+    #[test]
+    fn test_unsupported_field_value_is_a_field_level_error() {
+        let json = serde_json::json!({
+            "id": Uuid::new_v4(),
+            "version": "1.0",
+            "annotations": [
+                { "labelId": "name", "value": "ok", "timestamp": Utc::now() },
+                { "labelId": "tags", "value": ["a", "b"], "timestamp": Utc::now() },
+                { "labelId": "count", "value": 3, "timestamp": Utc::now() }
+            ],
+            "media": null,
+            "metadata": { "createdAt": Utc::now(), "createdBy": null, "source": "web" }
+        });
+
+        // The package still parses; the bad value is preserved rather than failing the whole event
+        let event_package: EventPackage = serde_json::from_value(json).unwrap();
+        assert!(matches!(
+            event_package.annotations[1].value,
+            FieldValue::Unknown(serde_json::Value::Array(_))
+        ));
+
+        let validation = event_package.validate();
+        assert!(!validation.is_valid);
+        assert_eq!(
+            validation.errors,
+            vec!["Annotation 1 (tags) has unsupported value type 'array'".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_supported_version_passes() {
+        let event_package = package_with_annotation_at(Utc::now());
+        let rules = ValidationConfig {
+            supported_event_versions: vec!["1.0".to_string(), "1.1".to_string()],
+            ..ValidationConfig::default()
+        };
+
+        assert!(event_package.validate_with(&rules).is_valid);
+    }
+
+    fn label_rules() -> ValidationConfig {
+        ValidationConfig {
+            allowed_label_ids: vec!["severity".to_string(), "location".to_string()],
+            ..ValidationConfig::default()
+        }
+    }
+
+    fn package_with_labels(labels: &[&str]) -> EventPackage {
+        let mut event_package = package_with_annotation_at(Utc::now());
+        let template = event_package.annotations[0].clone();
+        event_package.annotations = labels
+            .iter()
+            .map(|label| EventAnnotation {
+                label_id: label.to_string(),
+                ..template.clone()
+            })
+            .collect();
+        event_package
+    }
+
+    #[test]
+    fn test_allowed_labels_pass() {
+        let event_package = package_with_labels(&["severity", "location"]);
+        assert!(event_package.validate_with(&label_rules()).is_valid);
+
+        // Without an allowed set any label passes
+        let event_package = package_with_labels(&["anything"]);
+        assert!(event_package.validate().is_valid);
+    }
+
+    #[test]
+    fn test_unknown_labels_are_rejected() {
+        let event_package = package_with_labels(&["severity", "colour", "mood", "colour"]);
+
+        let validation = event_package.validate_with(&label_rules());
+        assert!(!validation.is_valid);
+        assert_eq!(
+            validation.errors,
+            vec!["Annotation label_ids not in the allowed set: colour, mood".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_labels_rejected_when_configured() {
+        let event_package = package_with_labels(&["severity", "location", "severity", "severity"]);
+        let rules = ValidationConfig {
+            reject_duplicate_labels: true,
+            ..ValidationConfig::default()
+        };
+
+        let validation = event_package.validate_with(&rules);
+        assert!(!validation.is_valid);
+        assert_eq!(
+            validation.errors,
+            vec!["Annotation label_ids appear more than once: severity".to_string()]
+        );
+
+        // Multiple values per label are allowed by default
+        assert!(event_package.validate().is_valid);
+    }
+
+    #[test]
+    fn test_media_required_for_configured_versions() {
+        let rules = ValidationConfig {
+            supported_event_versions: vec!["1.0".to_string(), "1.1-photo".to_string()],
+            media_required_versions: vec!["1.1-photo".to_string()],
+            ..ValidationConfig::default()
+        };
+        let mut event_package = package_with_annotation_at(Utc::now());
+        event_package.version = "1.1-photo".to_string();
+
+        let validation = event_package.validate_with(&rules);
+        assert!(!validation.is_valid);
+        assert_eq!(
+            validation.errors,
+            vec!["Event version '1.1-photo' requires media, but none was provided".to_string()]
+        );
+
+        event_package.media = media_only_package().media;
+        assert!(event_package.validate_with(&rules).is_valid);
+
+        // Media stays optional for versions not listed
+        let event_package = package_with_annotation_at(Utc::now());
+        assert!(event_package.validate_with(&rules).is_valid);
+    }
+
+    #[test]
+    fn test_unsupported_version_is_rejected() {
+        let mut event_package = package_with_annotation_at(Utc::now());
+        event_package.version = "2.0-beta".to_string();
+
+        let validation = event_package.validate();
+        assert!(!validation.is_valid);
+        assert_eq!(
+            validation.errors,
+            vec!["Unsupported event version '2.0-beta'; supported versions: 1.0".to_string()]
+        );
+    }
 }
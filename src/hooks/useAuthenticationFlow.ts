@@ -205,7 +205,7 @@ const useAuthenticationFlow = () => {
         }));
 
         const result = await performProofOfWork(
-          challengeRes.challenge_data,
+          challengeRes.challengeData,
           challengeRes.difficulty,
         );
 
@@ -218,7 +218,7 @@ const useAuthenticationFlow = () => {
         const verifyRes = (await verifyMutation.mutateAsync({
           requestBody: {
             solution: {
-              challenge_id: challengeRes.challenge_id,
+              challenge_id: challengeRes.challengeId,
               nonce: result.nonce,
               hash: result.hash,
             },
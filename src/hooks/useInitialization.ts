@@ -54,7 +54,7 @@ const useInitialization = ({
         // Step 2: Perform Proof of Work
         setPowStatus("Computing Proof of Work...");
         const result = await performProofOfWork(
-          challengeRes.challenge_data,
+          challengeRes.challengeData,
           challengeRes.difficulty,
         );
 
@@ -63,7 +63,7 @@ const useInitialization = ({
         const verifyRes = (await verifyMutation.mutateAsync({
           requestBody: {
             solution: {
-              challenge_id: challengeRes.challenge_id,
+              challenge_id: challengeRes.challengeId,
               nonce: result.nonce,
               hash: result.hash,
             },
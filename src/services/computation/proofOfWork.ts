@@ -6,10 +6,10 @@ export interface ProofOfWorkResult {
 }
 
 export interface PowChallenge {
-  challenge_id: string;
-  challenge_data: string;
+  challengeId: string;
+  challengeData: string;
   difficulty: number;
-  expires_at: string;
+  expiresAt: string;
 }
 
 /**
@@ -34,8 +34,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
     }
 
     let challenge_data: Value = challenge_response.json().await?;
-    let challenge_id = challenge_data["challenge_id"].as_str().unwrap();
-    let challenge_data_str = challenge_data["challenge_data"].as_str().unwrap();
+    let challenge_id = challenge_data["challengeId"].as_str().unwrap();
+    let challenge_data_str = challenge_data["challengeData"].as_str().unwrap();
     let difficulty = challenge_data["difficulty"].as_u64().unwrap() as u32;
     
     println!("   ✓ Challenge ID: {}", &challenge_id[..16]);